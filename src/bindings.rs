@@ -0,0 +1,726 @@
+// Which button or axis asks for what. The defaults are a standard pad's face
+// buttons; `[bindings]` in gear_changer.toml (or the `bind` subcommand that
+// writes it) can point them at anything gilrs reports, including the
+// unnamed buttons and axes of wheels and flight sticks:
+//
+// [bindings]
+// upshift = "RightTrigger"   # a named button
+// downshift = "code:300"     # a raw button code, as `bind` reports it
+// query_gear = "+LeftZ"      # an axis pushed past halfway, or "-axis:5"
+// launch_control = "LeftTrigger"  # held, see `Session::engage_launch_control`
+// starter = "RightTrigger"         # held until the engine catches after a stall
+// toggle_mute = "Select+Start"     # a chord: two buttons pressed together
+// save_profile = "Select+North"   # see `profiles`
+//
+// A button that is part of a chord does its own thing when it is let go of
+// instead of when it goes down, unless the chord was pressed. `bind` only
+// takes single inputs; chords are written here by hand.
+
+use crate::session::Action;
+use gilrs::{Axis, Button, EventType};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+// How far an axis has to move before it counts as a press
+pub const AXIS_THRESHOLD: f32 = 0.5;
+
+const BUTTON_NAMES: [(Button, &str); 19] = [
+    (Button::South, "South"),
+    (Button::East, "East"),
+    (Button::North, "North"),
+    (Button::West, "West"),
+    (Button::C, "C"),
+    (Button::Z, "Z"),
+    (Button::LeftTrigger, "LeftTrigger"),
+    (Button::LeftTrigger2, "LeftTrigger2"),
+    (Button::RightTrigger, "RightTrigger"),
+    (Button::RightTrigger2, "RightTrigger2"),
+    (Button::Select, "Select"),
+    (Button::Start, "Start"),
+    (Button::Mode, "Mode"),
+    (Button::LeftThumb, "LeftThumb"),
+    (Button::RightThumb, "RightThumb"),
+    (Button::DPadUp, "DPadUp"),
+    (Button::DPadDown, "DPadDown"),
+    (Button::DPadLeft, "DPadLeft"),
+    (Button::DPadRight, "DPadRight"),
+];
+
+const AXIS_NAMES: [(Axis, &str); 8] = [
+    (Axis::LeftStickX, "LeftStickX"),
+    (Axis::LeftStickY, "LeftStickY"),
+    (Axis::LeftZ, "LeftZ"),
+    (Axis::RightStickX, "RightStickX"),
+    (Axis::RightStickY, "RightStickY"),
+    (Axis::RightZ, "RightZ"),
+    (Axis::DPadX, "DPadX"),
+    (Axis::DPadY, "DPadY"),
+];
+
+/// A physical input. Axes are one direction of travel (`positive`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Button(Button),
+    ButtonCode(u32),
+    Axis(Axis, bool),
+    AxisCode(u32, bool),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = |positive: bool| if positive { '+' } else { '-' };
+        match *self {
+            Input::Button(button) => {
+                let name = BUTTON_NAMES.iter().find(|(b, _)| *b == button);
+                write!(f, "{}", name.map_or("Unknown", |(_, name)| name))
+            }
+            Input::ButtonCode(code) => write!(f, "code:{}", code),
+            Input::Axis(axis, positive) => {
+                let name = AXIS_NAMES.iter().find(|(a, _)| *a == axis);
+                write!(
+                    f,
+                    "{}{}",
+                    sign(positive),
+                    name.map_or("Unknown", |(_, name)| name)
+                )
+            }
+            Input::AxisCode(code, positive) => write!(f, "{}axis:{}", sign(positive), code),
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let code = |digits: &str| {
+            digits
+                .parse::<u32>()
+                .map_err(|_| format!("'{}' is not a code number", digits))
+        };
+        if let Some(digits) = s.strip_prefix("code:") {
+            return Ok(Input::ButtonCode(code(digits)?));
+        }
+        let axis = s
+            .strip_prefix('+')
+            .map(|rest| (rest, true))
+            .or_else(|| s.strip_prefix('-').map(|rest| (rest, false)));
+        if let Some((name, positive)) = axis {
+            if let Some(digits) = name.strip_prefix("axis:") {
+                return Ok(Input::AxisCode(code(digits)?, positive));
+            }
+            return AXIS_NAMES
+                .iter()
+                .find(|(_, n)| n.eq_ignore_ascii_case(name))
+                .map(|&(axis, _)| Input::Axis(axis, positive))
+                .ok_or_else(|| format!("unknown axis '{}'", name));
+        }
+        BUTTON_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(s))
+            .map(|&(button, _)| Input::Button(button))
+            .ok_or_else(|| format!("unknown button '{}'", s))
+    }
+}
+
+impl Input {
+    fn is_any_button(self) -> bool {
+        matches!(self, Input::Button(_) | Input::ButtonCode(_))
+    }
+
+    /// Whether this is the button gilrs reported as `button` with `code`.
+    pub fn is_button(self, button: Button, code: u32) -> bool {
+        match self {
+            Input::Button(b) => b == button && button != Button::Unknown,
+            Input::ButtonCode(c) => c == code,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a direction of the axis gilrs reported as `axis`
+    /// with `code`.
+    pub fn is_axis(self, axis: Axis, code: u32) -> bool {
+        match self {
+            Input::Axis(a, _) => a == axis && axis != Axis::Unknown,
+            Input::AxisCode(c, _) => c == code,
+            _ => false,
+        }
+    }
+}
+
+/// What sets a binding off: one input, or a chord of two buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Single(Input),
+    Chord(Input, Input),
+}
+
+impl From<Input> for Binding {
+    fn from(input: Input) -> Self {
+        Binding::Single(input)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Single(input) => write!(f, "{}", input),
+            Binding::Chord(first, second) => write!(f, "{}+{}", first, second),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        // Past the first character, so "+LeftZ" stays an axis
+        let Some(at) = s.get(1..).and_then(|rest| rest.find('+')) else {
+            return s.parse().map(Binding::Single);
+        };
+        let (first, second): (Input, Input) = (s[..=at].parse()?, s[at + 2..].parse()?);
+        if !first.is_any_button() || !second.is_any_button() || first == second {
+            return Err(format!("'{}': a chord is two different buttons", s));
+        }
+        Ok(Binding::Chord(first, second))
+    }
+}
+
+impl Binding {
+    fn has_button(self, button: Button, code: u32) -> bool {
+        match self {
+            Binding::Single(input) => input.is_button(button, code),
+            Binding::Chord(first, second) => {
+                first.is_button(button, code) || second.is_button(button, code)
+            }
+        }
+    }
+}
+
+/// What an input can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Action(Action),
+    /// Engaged for as long as the input is held.
+    LaunchControl,
+    /// Cranks the engine for as long as the input is held.
+    Starter,
+    Exit,
+}
+
+impl Bound {
+    /// Whether letting go of the input matters as well as pressing it.
+    pub fn is_held(self) -> bool {
+        matches!(self, Bound::LaunchControl | Bound::Starter)
+    }
+}
+
+/// A bound input going down, or coming back up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Press {
+    Down(Bound),
+    Up(Bound),
+}
+
+/// Everything bindable with its key in `[bindings]`, in the order `bind`
+/// asks for them.
+pub const BINDABLE: [(Bound, &str); 13] = [
+    (Bound::Action(Action::Upshift), "upshift"),
+    (Bound::Action(Action::Downshift), "downshift"),
+    (Bound::Action(Action::ReplaySlowmo), "replay_slowmo"),
+    (Bound::Action(Action::QueryGear), "query_gear"),
+    (Bound::Action(Action::ToggleAutomatic), "toggle_automatic"),
+    (Bound::Action(Action::ToggleMute), "toggle_mute"),
+    (Bound::Action(Action::NextProfile), "next_profile"),
+    (Bound::Action(Action::SaveProfile), "save_profile"),
+    (Bound::Action(Action::Neutral), "neutral"),
+    (Bound::Action(Action::Reverse), "reverse"),
+    (Bound::LaunchControl, "launch_control"),
+    (Bound::Starter, "starter"),
+    (Bound::Exit, "exit"),
+];
+
+/// Input to action table, plus which axes are currently held past the
+/// threshold so an axis fires once per push, and which buttons are down
+/// for the chords.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    inputs: Vec<(Binding, Bound)>,
+    held: Vec<Input>,
+    down: Vec<(Button, u32)>,
+    deferred: Vec<(Button, u32)>, // Down, acting when let go of
+    chorded: Vec<(Button, u32)>,  // Down, and spent on a chord
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            inputs: vec![
+                (
+                    Input::Button(Button::West),
+                    Bound::Action(Action::Downshift),
+                ), // X button
+                (Input::Button(Button::East), Bound::Action(Action::Upshift)), // B button
+                (
+                    Input::Button(Button::North),
+                    Bound::Action(Action::ReplaySlowmo),
+                ), // Y button
+                (
+                    Input::Button(Button::South),
+                    Bound::Action(Action::QueryGear),
+                ), // A button
+                (
+                    Input::Button(Button::Select),
+                    Bound::Action(Action::ToggleAutomatic),
+                ),
+                (
+                    Input::Button(Button::DPadUp),
+                    Bound::Action(Action::Neutral),
+                ),
+                (
+                    Input::Button(Button::DPadDown),
+                    Bound::Action(Action::Reverse),
+                ),
+                (
+                    Input::Button(Button::DPadRight),
+                    Bound::Action(Action::NextProfile),
+                ),
+                (Input::Button(Button::LeftTrigger), Bound::LaunchControl), // Left bumper
+                (Input::Button(Button::RightTrigger), Bound::Starter),      // Right bumper
+                (Input::Button(Button::Start), Bound::Exit),
+            ]
+            .into_iter()
+            .map(|(input, bound)| (input.into(), bound))
+            .chain([
+                (
+                    Binding::Chord(Input::Button(Button::Select), Input::Button(Button::Start)),
+                    Bound::Action(Action::ToggleMute),
+                ),
+                (
+                    Binding::Chord(Input::Button(Button::Select), Input::Button(Button::North)),
+                    Bound::Action(Action::SaveProfile),
+                ),
+            ])
+            .collect(),
+            held: Vec::new(),
+            down: Vec::new(),
+            deferred: Vec::new(),
+            chorded: Vec::new(),
+        }
+    }
+}
+
+impl Bindings {
+    /// The defaults with `[bindings]` entries (key → input) applied.
+    pub fn from_config(entries: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut bindings = Self::default();
+        for (key, input) in entries {
+            let &(bound, _) = BINDABLE
+                .iter()
+                .find(|(_, name)| name == key)
+                .ok_or_else(|| format!("[bindings]: unknown action '{}'", key))?;
+            let input: Binding = input
+                .parse()
+                .map_err(|e| format!("[bindings] {}: {}", key, e))?;
+            bindings.set(bound, input);
+        }
+        Ok(bindings)
+    }
+
+    pub fn input_for(&self, bound: Bound) -> Option<Binding> {
+        self.inputs
+            .iter()
+            .find(|(_, b)| *b == bound)
+            .map(|&(binding, _)| binding)
+    }
+
+    /// Binds `binding` to `bound`, replacing its old one. An input or chord
+    /// does one thing only, so whatever else it was bound to loses it.
+    pub fn set(&mut self, bound: Bound, binding: impl Into<Binding>) {
+        let binding = binding.into();
+        self.inputs.retain(|&(i, b)| b != bound && i != binding);
+        self.inputs.push((binding, bound));
+    }
+
+    /// `[bindings]` entries for the current table.
+    pub fn entries(&self) -> BTreeMap<String, String> {
+        BINDABLE
+            .iter()
+            .filter_map(|&(bound, key)| Some((key.to_string(), self.input_for(bound)?.to_string())))
+            .collect()
+    }
+
+    /// What a gamepad event presses or lets go of, if anything.
+    pub fn resolve(&mut self, event: &EventType) -> Option<Press> {
+        match *event {
+            EventType::ButtonPressed(button, code) => self.button_down(button, code.into_u32()),
+            EventType::ButtonReleased(button, code) => self.button_up(button, code.into_u32()),
+            EventType::AxisChanged(axis, value, code) => {
+                self.axis_changed(axis, value, code.into_u32())
+            }
+            _ => None,
+        }
+    }
+
+    /// What `button` is bound to on its own.
+    fn button_pressed(&self, button: Button, code: u32) -> Option<Bound> {
+        self.inputs
+            .iter()
+            .find_map(|&(binding, bound)| match binding {
+                Binding::Single(input) => input.is_button(button, code).then_some(bound),
+                Binding::Chord(..) => None,
+            })
+    }
+
+    /// The chord `button` completes with a button already down, if any.
+    fn chord_with(&self, button: Button, code: u32) -> Option<(Bound, (Button, u32))> {
+        self.inputs.iter().find_map(|&(binding, bound)| {
+            let Binding::Chord(first, second) = binding else {
+                return None;
+            };
+            let other = if first.is_button(button, code) {
+                second
+            } else if second.is_button(button, code) {
+                first
+            } else {
+                return None;
+            };
+            let &held = self.down.iter().find(|&&(b, c)| other.is_button(b, c))?;
+            Some((bound, held))
+        })
+    }
+
+    fn button_down(&mut self, button: Button, code: u32) -> Option<Press> {
+        self.down.push((button, code));
+        if let Some((bound, held)) = self.chord_with(button, code) {
+            self.deferred.retain(|&pressed| pressed != held);
+            self.chorded.extend([held, (button, code)]);
+            return Some(Press::Down(bound));
+        }
+        let bound = self.button_pressed(button, code)?;
+        let in_chord = self.inputs.iter().any(|&(binding, _)| {
+            matches!(binding, Binding::Chord(..)) && binding.has_button(button, code)
+        });
+        // Held inputs can't wait for the release
+        if in_chord && !bound.is_held() {
+            self.deferred.push((button, code));
+            return None;
+        }
+        Some(Press::Down(bound))
+    }
+
+    fn button_up(&mut self, button: Button, code: u32) -> Option<Press> {
+        let pressed = (button, code);
+        self.down.retain(|&down| down != pressed);
+        if self.chorded.contains(&pressed) {
+            self.chorded.retain(|&chorded| chorded != pressed);
+            return None;
+        }
+        let bound = self.button_pressed(button, code)?;
+        if self.deferred.contains(&pressed) {
+            self.deferred.retain(|&deferred| deferred != pressed);
+            return Some(Press::Down(bound));
+        }
+        Some(Press::Up(bound))
+    }
+
+    fn axis_changed(&mut self, axis: Axis, value: f32, code: u32) -> Option<Press> {
+        let mut pushed = None;
+        for &(binding, bound) in &self.inputs {
+            let Binding::Single(input @ (Input::Axis(_, positive) | Input::AxisCode(_, positive))) =
+                binding
+            else {
+                continue;
+            };
+            if !input.is_axis(axis, code) {
+                continue;
+            }
+            let past = if positive {
+                value >= AXIS_THRESHOLD
+            } else {
+                value <= -AXIS_THRESHOLD
+            };
+            let was_held = self.held.contains(&input);
+            if past && !was_held {
+                self.held.push(input);
+                pushed = pushed.or(Some(Press::Down(bound)));
+            } else if !past && was_held {
+                self.held.retain(|&held| held != input);
+                pushed = pushed.or(Some(Press::Up(bound)));
+            }
+        }
+        pushed
+    }
+}
+
+/// The input an event would bind in capture mode: a button press, or an
+/// axis pushed past the threshold. Named inputs are preferred over codes.
+pub fn captured(event: &EventType) -> Option<Input> {
+    match *event {
+        EventType::ButtonPressed(Button::Unknown, code) => Some(Input::ButtonCode(code.into_u32())),
+        EventType::ButtonPressed(button, _) => Some(Input::Button(button)),
+        EventType::AxisChanged(axis, value, code) if value.abs() >= AXIS_THRESHOLD => {
+            let positive = value > 0.0;
+            Some(if axis == Axis::Unknown {
+                Input::AxisCode(code.into_u32(), positive)
+            } else {
+                Input::Axis(axis, positive)
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn bound(bindings: &Bindings, input: &str) -> Option<Bound> {
+        let binding: Binding = input.parse().unwrap();
+        bindings
+            .inputs
+            .iter()
+            .find(|&&(b, _)| b == binding)
+            .map(|&(_, bound)| bound)
+    }
+
+    #[test]
+    fn default_bindings_are_the_face_buttons() {
+        let bindings = Bindings::default();
+        assert_eq!(
+            bound(&bindings, "West"),
+            Some(Bound::Action(Action::Downshift))
+        );
+        assert_eq!(
+            bound(&bindings, "East"),
+            Some(Bound::Action(Action::Upshift))
+        );
+        assert_eq!(
+            bound(&bindings, "North"),
+            Some(Bound::Action(Action::ReplaySlowmo))
+        );
+        assert_eq!(
+            bound(&bindings, "South"),
+            Some(Bound::Action(Action::QueryGear))
+        );
+        assert_eq!(
+            bound(&bindings, "Select"),
+            Some(Bound::Action(Action::ToggleAutomatic))
+        );
+        assert_eq!(bound(&bindings, "LeftTrigger"), Some(Bound::LaunchControl));
+        assert_eq!(bound(&bindings, "Start"), Some(Bound::Exit));
+        assert_eq!(bound(&bindings, "RightTrigger"), Some(Bound::Starter));
+        assert_eq!(
+            bound(&bindings, "Select+Start"),
+            Some(Bound::Action(Action::ToggleMute))
+        );
+        assert_eq!(
+            bound(&bindings, "DPadRight"),
+            Some(Bound::Action(Action::NextProfile))
+        );
+        assert_eq!(
+            bound(&bindings, "Select+North"),
+            Some(Bound::Action(Action::SaveProfile))
+        );
+        assert_eq!(bound(&bindings, "DPadLeft"), None);
+    }
+
+    #[test]
+    fn inputs_round_trip_through_text() {
+        for text in [
+            "East",
+            "DPadUp",
+            "code:300",
+            "+LeftZ",
+            "-RightStickY",
+            "-axis:5",
+        ] {
+            let input: Input = text.parse().unwrap();
+            assert_eq!(input.to_string(), text);
+        }
+        assert_eq!("east".parse::<Input>(), Ok(Input::Button(Button::East)));
+        assert!("Turbo".parse::<Input>().is_err());
+        assert!("+Throttle".parse::<Input>().is_err());
+        assert!("code:x".parse::<Input>().is_err());
+
+        for text in ["Select+Start", "LeftTrigger+code:300", "+LeftZ", "East"] {
+            let binding: Binding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+        assert!("Select+".parse::<Binding>().is_err());
+        assert!("Select+Select".parse::<Binding>().is_err());
+        assert!("Select++LeftZ".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn a_chord_takes_over_from_its_buttons() {
+        let mut bindings = Bindings::default();
+        let mute = Some(Press::Down(Bound::Action(Action::ToggleMute)));
+        // Select waits to see whether Start joins it
+        assert_eq!(bindings.button_down(Button::Select, 0), None);
+        assert_eq!(bindings.button_down(Button::Start, 0), mute);
+        assert_eq!(bindings.button_up(Button::Start, 0), None);
+        assert_eq!(bindings.button_up(Button::Select, 0), None);
+        // Either way round
+        assert_eq!(bindings.button_down(Button::Start, 0), None);
+        assert_eq!(bindings.button_down(Button::Select, 0), mute);
+        assert_eq!(bindings.button_up(Button::Select, 0), None);
+        assert_eq!(bindings.button_up(Button::Start, 0), None);
+
+        // Alone, each does its own thing once let go of
+        assert_eq!(bindings.button_down(Button::Select, 0), None);
+        assert_eq!(
+            bindings.button_up(Button::Select, 0),
+            Some(Press::Down(Bound::Action(Action::ToggleAutomatic)))
+        );
+        assert_eq!(bindings.button_down(Button::Start, 0), None);
+        assert_eq!(
+            bindings.button_up(Button::Start, 0),
+            Some(Press::Down(Bound::Exit))
+        );
+        // Buttons outside any chord act at once
+        assert_eq!(
+            bindings.button_down(Button::East, 0),
+            Some(Press::Down(Bound::Action(Action::Upshift)))
+        );
+        assert_eq!(
+            bindings.button_up(Button::East, 0),
+            Some(Press::Up(Bound::Action(Action::Upshift)))
+        );
+
+        // A held input in a chord still goes down with the button
+        bindings.set(
+            Bound::Action(Action::ToggleMute),
+            "LeftTrigger+East".parse::<Binding>().unwrap(),
+        );
+        assert_eq!(
+            bindings.button_down(Button::LeftTrigger, 0),
+            Some(Press::Down(Bound::LaunchControl))
+        );
+        assert_eq!(bindings.button_down(Button::East, 0), mute);
+        assert_eq!(bindings.button_up(Button::East, 0), None);
+    }
+
+    #[test]
+    fn buttons_by_name_and_code() {
+        let mut bindings = Bindings::default();
+        bindings.set(Bound::Action(Action::Upshift), Input::ButtonCode(300));
+        assert_eq!(
+            bindings.button_pressed(Button::Unknown, 300),
+            Some(Bound::Action(Action::Upshift))
+        );
+        assert_eq!(
+            bindings.button_pressed(Button::West, 0),
+            Some(Bound::Action(Action::Downshift))
+        );
+        assert_eq!(bindings.button_pressed(Button::East, 1), None);
+        assert_eq!(bindings.button_pressed(Button::Unknown, 301), None);
+    }
+
+    #[test]
+    fn axes_fire_once_per_push() {
+        let mut bindings = Bindings::default();
+        bindings.set(
+            Bound::Action(Action::Upshift),
+            Input::Axis(Axis::RightStickY, false),
+        );
+        bindings.set(Bound::Action(Action::Downshift), Input::AxisCode(5, true));
+
+        let upshift = Bound::Action(Action::Upshift);
+        assert_eq!(bindings.axis_changed(Axis::RightStickY, -0.3, 0), None);
+        assert_eq!(
+            bindings.axis_changed(Axis::RightStickY, -0.6, 0),
+            Some(Press::Down(upshift))
+        );
+        assert_eq!(bindings.axis_changed(Axis::RightStickY, -1.0, 0), None);
+        assert_eq!(
+            bindings.axis_changed(Axis::RightStickY, 0.9, 0),
+            Some(Press::Up(upshift))
+        );
+        assert_eq!(
+            bindings.axis_changed(Axis::RightStickY, -0.8, 0),
+            Some(Press::Down(upshift))
+        );
+
+        assert_eq!(
+            bindings.axis_changed(Axis::Unknown, 0.7, 5),
+            Some(Press::Down(Bound::Action(Action::Downshift)))
+        );
+        assert_eq!(bindings.axis_changed(Axis::Unknown, 0.7, 6), None);
+    }
+
+    #[test]
+    fn config_entries_override_defaults() {
+        let entries: BTreeMap<String, String> = [
+            ("upshift".to_string(), "RightTrigger".to_string()),
+            ("downshift".to_string(), "East".to_string()),
+        ]
+        .into();
+        let bindings = Bindings::from_config(&entries).unwrap();
+        assert_eq!(
+            bound(&bindings, "RightTrigger"),
+            Some(Bound::Action(Action::Upshift))
+        );
+        // East moved to downshift, and West is free now
+        assert_eq!(
+            bound(&bindings, "East"),
+            Some(Bound::Action(Action::Downshift))
+        );
+        assert_eq!(bound(&bindings, "West"), None);
+        assert_eq!(bindings.entries()["upshift"], "RightTrigger");
+        assert_eq!(
+            Bindings::from_config(&bindings.entries())
+                .unwrap()
+                .entries(),
+            bindings.entries()
+        );
+
+        let bad = |key: &str, value: &str| {
+            Bindings::from_config(&[(key.to_string(), value.to_string())].into()).is_err()
+        };
+        assert!(bad("turbo", "East"));
+        assert!(bad("upshift", "Turbo"));
+    }
+
+    fn input() -> impl Strategy<Value = Input> {
+        prop_oneof![
+            prop::sample::select(&BUTTON_NAMES[..]).prop_map(|(button, _)| Input::Button(button)),
+            any::<u32>().prop_map(Input::ButtonCode),
+            (prop::sample::select(&AXIS_NAMES[..]), any::<bool>())
+                .prop_map(|((axis, _), positive)| Input::Axis(axis, positive)),
+            (any::<u32>(), any::<bool>())
+                .prop_map(|(code, positive)| Input::AxisCode(code, positive)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn any_text_is_a_binding_or_an_error(text in "\\PC*") {
+            let _ = text.parse::<Binding>();
+        }
+
+        #[test]
+        fn look_alike_text_is_a_binding_or_an_error(
+            text in "[+-]?(code:|axis:|DPad|Left|Right|[0-9]|[+-]){0,8}",
+        ) {
+            if let Ok(binding) = text.parse::<Binding>() {
+                prop_assert_eq!(binding.to_string().parse::<Binding>(), Ok(binding));
+            }
+        }
+
+        #[test]
+        fn bindings_read_back_as_written(first in input(), second in input(), chord: bool) {
+            let buttons = first.is_any_button() && second.is_any_button();
+            let binding = if chord && buttons && first != second {
+                Binding::Chord(first, second)
+            } else {
+                Binding::Single(first)
+            };
+            prop_assert_eq!(binding.to_string().parse::<Binding>(), Ok(binding));
+        }
+    }
+}
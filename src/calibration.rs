@@ -0,0 +1,162 @@
+// Makes different pads feel the same. `gear_changer calibrate` finds the
+// faintest rumble the driver can feel on each motor of a pad, and from then
+// on that pad's motors are scaled so it comes in where it does on a typical
+// pad. Kept in gear_changer.toml by the name gilrs reports for the pad:
+//
+// [calibration."Xbox Wireless Controller"]
+// strong = 1.5
+// weak = 0.75
+
+use crate::haptics::{RumbleCommand, RumblePattern};
+use serde::Deserialize;
+
+/// The levels `calibrate` steps through, as fractions of full power.
+pub const STEPS: [f32; 10] = [0.05, 0.1, 0.15, 0.2, 0.3, 0.4, 0.5, 0.65, 0.8, 1.0];
+
+// Where a typical pad's motors are first felt
+const REFERENCE_THRESHOLD: f32 = 0.15;
+// The most a motor is scaled either way; a motor never felt gets the most
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 3.0;
+
+/// Which motors a calibration step plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Motors {
+    Strong,
+    Weak,
+    Both,
+}
+
+impl Motors {
+    pub fn name(self) -> &'static str {
+        match self {
+            Motors::Strong => "strong motor only",
+            Motors::Weak => "weak motor only",
+            Motors::Both => "both motors",
+        }
+    }
+
+    /// A step at `level`, 0 to 1, on these motors.
+    pub fn command(self, level: f32, duration_ms: u32) -> RumbleCommand {
+        let magnitude = (level.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let (strong, weak) = match self {
+            Motors::Strong => (magnitude, 0),
+            Motors::Weak => (0, magnitude),
+            Motors::Both => (magnitude, magnitude),
+        };
+        RumbleCommand {
+            strong_magnitude: strong,
+            weak_magnitude: weak,
+            duration_ms,
+        }
+    }
+}
+
+/// How much one pad's motors are scaled.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Calibration {
+    pub strong: f32,
+    pub weak: f32,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            strong: 1.0,
+            weak: 1.0,
+        }
+    }
+}
+
+impl Calibration {
+    /// The scaling for a pad whose motors were first felt at these levels,
+    /// `None` for one that never was.
+    pub fn from_thresholds(strong: Option<f32>, weak: Option<f32>) -> Self {
+        let scale = |felt: Option<f32>| {
+            felt.map_or(MAX_SCALE, |felt| {
+                (REFERENCE_THRESHOLD / felt).clamp(MIN_SCALE, MAX_SCALE)
+            })
+        };
+        Self {
+            strong: scale(strong),
+            weak: scale(weak),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (motor, scale) in [("strong", self.strong), ("weak", self.weak)] {
+            if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+                return Err(format!(
+                    "{}: {} is outside {} to {}",
+                    motor, scale, MIN_SCALE, MAX_SCALE
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn strong(&self, magnitude: u16) -> u16 {
+        scale(magnitude, self.strong)
+    }
+
+    pub fn weak(&self, magnitude: u16) -> u16 {
+        scale(magnitude, self.weak)
+    }
+
+    pub fn command(&self, command: RumbleCommand) -> RumbleCommand {
+        RumbleCommand {
+            strong_magnitude: self.strong(command.strong_magnitude),
+            weak_magnitude: self.weak(command.weak_magnitude),
+            duration_ms: command.duration_ms,
+        }
+    }
+
+    pub fn pattern(&self, pattern: &RumblePattern) -> RumblePattern {
+        pattern.map(|stage| self.command(stage))
+    }
+}
+
+fn scale(magnitude: u16, by: f32) -> u16 {
+    (magnitude as f32 * by).min(u16::MAX as f32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motors_felt_late_are_scaled_up_and_early_ones_down() {
+        let calibration = Calibration::from_thresholds(Some(0.3), Some(0.1));
+        assert_eq!(calibration.strong, 0.5);
+        assert!((calibration.weak - 1.5).abs() < 1e-6);
+        assert_eq!(
+            Calibration::from_thresholds(Some(REFERENCE_THRESHOLD), None),
+            Calibration {
+                strong: 1.0,
+                weak: MAX_SCALE,
+            }
+        );
+        assert_eq!(
+            Calibration::from_thresholds(Some(0.01), None).strong,
+            MAX_SCALE
+        );
+
+        let command = Motors::Both.command(0.5, 100);
+        let scaled = calibration.command(command);
+        assert_eq!(scaled.strong_magnitude, command.strong_magnitude / 2);
+        assert!(scaled.weak_magnitude.abs_diff(49151) <= 1);
+        assert_eq!(calibration.weak(60000), u16::MAX);
+        assert_eq!(Motors::Weak.command(1.0, 100).strong_magnitude, 0);
+
+        assert!(calibration.validate().is_ok());
+        let wild = Calibration {
+            strong: 5.0,
+            weak: 1.0,
+        };
+        assert_eq!(
+            wild.validate(),
+            Err("strong: 5 is outside 0.5 to 3".to_string())
+        );
+    }
+}
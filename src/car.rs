@@ -0,0 +1,1155 @@
+// The simulated car: specs, current gear and how hard a shift should rumble.
+
+use crate::engine::{CRUISE_RPM, DEFAULT_IDLE_RPM, DEFAULT_REDLINE_RPM, Engine, TorqueCurve};
+use crate::haptics::RumbleCommand;
+use crate::output;
+use crate::say;
+use crate::transmission::Transmission;
+use crate::turbo::Turbo;
+use crate::units::{AngularSpeed, Power, Speed, Torque, UnitSystem};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Ratios of a typical six-speed box, first gear first.
+pub const DEFAULT_GEAR_RATIOS: [f32; 6] = [3.36, 2.10, 1.49, 1.20, 1.00, 0.84];
+pub const DEFAULT_FINAL_DRIVE: f32 = 3.42;
+
+// Rolling radius of a typical road tyre
+pub(crate) const WHEEL_RADIUS_M: f32 = 0.33;
+
+/// What the driving simulation pushes around, in kg.
+pub const MASS_KG: f32 = 1500.0;
+pub(crate) const DRAG_AREA_M2: f32 = 0.7; // Drag coefficient × frontal area
+pub(crate) const AIR_DENSITY_KG_M3: f32 = 1.2;
+pub(crate) const ROLLING_RESISTANCE_N: f32 = 150.0;
+const ENGINE_BRAKING: f32 = 0.2; // Fraction of peak torque, off the throttle
+const MAX_BRAKING_M_PER_S2: f32 = 9.0;
+
+// Gear a freshly started session is in, if the box has that many
+const START_GEAR: u8 = 3;
+
+// Longest a gear's shift rumble may be next to the others; the rumble also
+// holds off the next shift
+const MAX_FEEL_DURATION: f32 = 10.0;
+
+/// Parses `--gears` syntax: ratios separated by commas, first gear first,
+/// e.g. `3.36,2.10,1.49,1.20,1.00,0.84`.
+pub fn parse_gear_ratios(spec: &str) -> Result<Vec<f32>, String> {
+    spec.split(',')
+        .map(|ratio| {
+            ratio
+                .trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid gear ratio '{}'", ratio))
+        })
+        .collect()
+}
+
+/// Where the gearbox is. The `Car` itself is always in a numbered gear;
+/// neutral and reverse come from outside it, from a game, an H-pattern
+/// shifter or their buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GearPosition {
+    Gear(u8),
+    Neutral,
+    Reverse,
+}
+
+/// The gear number, `N` or `R`.
+impl fmt::Display for GearPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GearPosition::Gear(gear) => write!(f, "{}", gear),
+            GearPosition::Neutral => f.write_str("N"),
+            GearPosition::Reverse => f.write_str("R"),
+        }
+    }
+}
+
+/// Gains applied to the strong and weak motors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotorMix {
+    pub strong: f32,
+    pub weak: f32,
+}
+
+/// Per-gear motor balance keyed by destination gear. Gears between two rows
+/// are linearly interpolated, gears outside the table use the nearest row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GearMotorMix {
+    rows: Vec<(u8, MotorMix)>, // Sorted by gear, never empty
+}
+
+impl GearMotorMix {
+    pub fn new(mut rows: Vec<(u8, MotorMix)>, gear_count: u8) -> Result<Self, String> {
+        if rows.is_empty() {
+            return Err("motor mix table is empty".to_string());
+        }
+        rows.sort_by_key(|(gear, _)| *gear);
+        for (gear, mix) in &rows {
+            if *gear < 1 || *gear > gear_count {
+                return Err(format!(
+                    "gear {} is outside 1..={} for this car",
+                    gear, gear_count
+                ));
+            }
+            for gain in [mix.strong, mix.weak] {
+                if !gain.is_finite() || gain < 0.0 {
+                    return Err(format!("gear {} has an invalid gain {}", gear, gain));
+                }
+            }
+        }
+        if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("gear {} is listed twice", pair[0].0));
+        }
+        Ok(Self { rows })
+    }
+
+    /// Parses `--gear-mix` syntax: `gear=strong:weak` rows separated by commas,
+    /// e.g. `1=1.2:0.4,6=0.3:1.0`.
+    pub fn parse(spec: &str, gear_count: u8) -> Result<Self, String> {
+        let rows = spec
+            .split(',')
+            .map(|row| {
+                let (gear, gains) = row
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected gear=strong:weak, got '{}'", row))?;
+                let (strong, weak) = gains
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected strong:weak gains, got '{}'", gains))?;
+                let number = |s: &str| {
+                    s.trim()
+                        .parse::<f32>()
+                        .map_err(|_| format!("invalid gain '{}'", s))
+                };
+                let gear = gear
+                    .trim()
+                    .parse::<u8>()
+                    .map_err(|_| format!("invalid gear '{}'", gear))?;
+                Ok((
+                    gear,
+                    MotorMix {
+                        strong: number(strong)?,
+                        weak: number(weak)?,
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Self::new(rows, gear_count)
+    }
+
+    /// Returns the mix for `gear` and whether it was interpolated.
+    pub fn mix_for(&self, gear: u8) -> (MotorMix, bool) {
+        let (first_gear, first) = self.rows[0];
+        let (last_gear, last) = self.rows[self.rows.len() - 1];
+        if gear <= first_gear {
+            return (first, gear != first_gear);
+        }
+        if gear >= last_gear {
+            return (last, gear != last_gear);
+        }
+
+        for pair in self.rows.windows(2) {
+            let ((low_gear, low), (high_gear, high)) = (pair[0], pair[1]);
+            if gear == low_gear {
+                return (low, false);
+            }
+            if gear < high_gear {
+                let t = (gear - low_gear) as f32 / (high_gear - low_gear) as f32;
+                let mix = MotorMix {
+                    strong: low.strong + (high.strong - low.strong) * t,
+                    weak: low.weak + (high.weak - low.weak) * t,
+                };
+                return (mix, true);
+            }
+        }
+        (last, false)
+    }
+}
+
+/// How one gear's engagement feels next to the others: multipliers on the
+/// rumble's intensity and on its length.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GearFeel {
+    pub intensity: f32,
+    pub duration: f32,
+}
+
+impl Default for GearFeel {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            duration: 1.0,
+        }
+    }
+}
+
+/// Per-gear feel keyed by destination gear, from `[gear_feel]`. Unlike the
+/// motor mix a gear that isn't listed feels like any other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GearFeelTable {
+    gears: BTreeMap<u8, GearFeel>,
+}
+
+impl GearFeelTable {
+    /// Reads `[gear_feel]` entries, gear number → feel, for a car with
+    /// `gear_count` gears.
+    pub fn from_config(
+        entries: &BTreeMap<String, GearFeel>,
+        gear_count: u8,
+    ) -> Result<Self, String> {
+        let mut gears = BTreeMap::new();
+        for (key, &feel) in entries {
+            let gear = match key.parse::<u8>() {
+                Ok(n) if (1..=gear_count).contains(&n) => n,
+                _ => {
+                    return Err(format!(
+                        "[gear_feel]: '{}' is not a gear in 1..={}",
+                        key, gear_count
+                    ));
+                }
+            };
+            if !feel.intensity.is_finite() || feel.intensity < 0.0 {
+                return Err(format!(
+                    "[gear_feel] {}: invalid intensity {}",
+                    key, feel.intensity
+                ));
+            }
+            // A gear that shouldn't rumble wants an intensity of 0 instead
+            if !feel.duration.is_finite() || feel.duration <= 0.0 {
+                return Err(format!(
+                    "[gear_feel] {}: invalid duration {}",
+                    key, feel.duration
+                ));
+            }
+            if feel.duration > MAX_FEEL_DURATION {
+                return Err(format!(
+                    "[gear_feel] {}: duration {} is over {}",
+                    key, feel.duration, MAX_FEEL_DURATION
+                ));
+            }
+            gears.insert(gear, feel);
+        }
+        Ok(Self { gears })
+    }
+
+    /// The feel for `gear`, `None` if it isn't listed.
+    pub fn feel_for(&self, gear: u8) -> Option<GearFeel> {
+        self.gears.get(&gear).copied()
+    }
+}
+
+pub struct Car {
+    torque: Torque, // Peak
+    power: Power,
+    engine: Engine,
+    gear_ratios: Vec<f32>, // First gear first
+    final_drive: f32,
+    current_gear: u8,
+    max_torque: Torque,               // Maximum possible torque for calculations
+    rumble_scale: f32,                // Multiplies every shift rumble
+    motor_mix: Option<GearMotorMix>,  // Per-gear strong/weak balance
+    gear_feel: Option<GearFeelTable>, // Per-gear intensity and length
+    money_shifts: u32,                // Downshifts that over-revved the engine
+    auto_blip: bool,                  // Downshifts blip the throttle to match revs
+    transmission: Option<Transmission>,
+    turbo: Option<Turbo>,
+    units: UnitSystem, // Shown in, not worked out in
+}
+
+impl Car {
+    pub fn new(
+        torque: Torque,
+        power: Power,
+        gear_ratios: Vec<f32>,
+        final_drive: f32,
+    ) -> Result<Self, String> {
+        if gear_ratios.is_empty() {
+            return Err("the gearbox needs at least one gear".to_string());
+        }
+        if gear_ratios.len() > u8::MAX as usize {
+            return Err(format!("{} gears is too many", gear_ratios.len()));
+        }
+        for (i, ratio) in gear_ratios.iter().enumerate() {
+            if !ratio.is_finite() || *ratio <= 0.0 {
+                return Err(format!("gear {} has an invalid ratio {}", i + 1, ratio));
+            }
+        }
+        if !final_drive.is_finite() || final_drive <= 0.0 {
+            return Err(format!("invalid final drive {}", final_drive));
+        }
+
+        let mut engine = Engine::new(
+            AngularSpeed::from_rpm(DEFAULT_IDLE_RPM),
+            AngularSpeed::from_rpm(DEFAULT_REDLINE_RPM),
+            TorqueCurve::scaled_to(torque),
+        );
+        engine.set_speed(AngularSpeed::from_rpm(CRUISE_RPM));
+
+        Ok(Self {
+            torque,
+            power,
+            engine,
+            current_gear: START_GEAR.min(gear_ratios.len() as u8),
+            gear_ratios,
+            final_drive,
+            max_torque: Torque::from_lb_ft(1000.0), // Assuming max 1000 lb-ft for scaling
+            rumble_scale: 1.0,
+            motor_mix: None,
+            gear_feel: None,
+            money_shifts: 0,
+            auto_blip: true,
+            transmission: None,
+            turbo: None,
+            units: UnitSystem::Imperial,
+        })
+    }
+
+    pub fn torque(&self) -> Torque {
+        self.torque
+    }
+
+    pub fn power(&self) -> Power {
+        self.power
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    /// Swaps in `engine`, e.g. with a preset's own curve and redline, at
+    /// the speed a session starts at.
+    pub fn set_engine(&mut self, mut engine: Engine) {
+        engine.set_speed(AngularSpeed::from_rpm(CRUISE_RPM));
+        self.engine = engine;
+    }
+
+    /// Swaps in a measured torque `curve`, e.g. from a dyno sheet, which
+    /// then also sets the peak torque.
+    pub fn set_torque_curve(&mut self, curve: TorqueCurve) {
+        self.torque = curve.peak();
+        let (idle, redline) = (self.engine.idle(), self.engine.redline());
+        self.set_engine(Engine::new(idle, redline, curve));
+    }
+
+    pub fn current_gear(&self) -> u8 {
+        self.current_gear
+    }
+
+    pub fn gear_count(&self) -> u8 {
+        self.gear_ratios.len() as u8
+    }
+
+    pub fn gear_ratios(&self) -> &[f32] {
+        &self.gear_ratios
+    }
+
+    pub fn final_drive(&self) -> f32 {
+        self.final_drive
+    }
+
+    fn gear_ratio(&self, gear: u8) -> f32 {
+        self.gear_ratios[gear as usize - 1]
+    }
+
+    /// Road speed at the current engine speed and gear.
+    pub fn road_speed(&self) -> Speed {
+        let wheel = self.engine.speed().rad_per_sec()
+            / (self.gear_ratio(self.current_gear) * self.final_drive);
+        Speed::from_m_per_s(wheel * WHEEL_RADIUS_M)
+    }
+
+    /// Drives on for `dt` with the throttle and brake pedals at 0 (released)
+    /// to 1 (floored). The engine stays between idle and redline, as if the
+    /// clutch slipped at either end.
+    pub fn drive(&mut self, throttle: f32, brake: f32, dt: Duration) {
+        let throttle = throttle.clamp(0.0, 1.0);
+        let torque = self.engine.torque().nm() * throttle
+            - self.torque.nm() * ENGINE_BRAKING * (1.0 - throttle);
+        let force = torque * self.gear_ratio(self.current_gear) * self.final_drive / WHEEL_RADIUS_M;
+        self.move_on(force, brake, dt);
+    }
+
+    /// Rolls on for `dt` with the engine out of the loop, e.g. in neutral.
+    pub fn coast(&mut self, brake: f32, dt: Duration) {
+        self.move_on(0.0, brake, dt);
+    }
+
+    fn move_on(&mut self, engine_force_n: f32, brake: f32, dt: Duration) {
+        let speed = self.road_speed().m_per_s();
+        let drag = 0.5 * AIR_DENSITY_KG_M3 * DRAG_AREA_M2 * speed * speed;
+        let acceleration = (engine_force_n - drag - ROLLING_RESISTANCE_N) / MASS_KG
+            - brake.clamp(0.0, 1.0) * MAX_BRAKING_M_PER_S2;
+        let speed = speed + acceleration * dt.as_secs_f32();
+        if !speed.is_finite() {
+            return;
+        }
+        let wheel = speed.max(0.0) / WHEEL_RADIUS_M;
+        self.engine.set_speed(AngularSpeed::from_rad_per_sec(
+            wheel * self.gear_ratio(self.current_gear) * self.final_drive,
+        ));
+    }
+
+    pub fn rumble_scale(&self) -> f32 {
+        self.rumble_scale
+    }
+
+    pub fn set_rumble_scale(&mut self, scale: f32) -> Result<(), String> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(format!("invalid rumble scale {}", scale));
+        }
+        self.rumble_scale = scale;
+        Ok(())
+    }
+
+    /// Whether downshifts blip the throttle to match the revs of the lower
+    /// gear, as heel-and-toe or an auto-blip box would.
+    pub fn auto_blip(&self) -> bool {
+        self.auto_blip
+    }
+
+    pub fn set_auto_blip(&mut self, on: bool) {
+        self.auto_blip = on;
+    }
+
+    /// The kind of gearbox, `None` for the plain shift rumbles.
+    pub fn transmission(&self) -> Option<Transmission> {
+        self.transmission
+    }
+
+    pub fn set_transmission(&mut self, transmission: Option<Transmission>) {
+        self.transmission = transmission;
+    }
+
+    pub fn turbo(&self) -> Option<&Turbo> {
+        self.turbo.as_ref()
+    }
+
+    pub fn turbo_mut(&mut self) -> Option<&mut Turbo> {
+        self.turbo.as_mut()
+    }
+
+    pub fn units(&self) -> UnitSystem {
+        self.units
+    }
+
+    pub fn set_units(&mut self, units: UnitSystem) {
+        self.units = units;
+    }
+
+    /// Fits a turbocharger, with no boost yet, or takes it off.
+    pub fn set_turbo(&mut self, on: bool) {
+        self.turbo = on.then(Turbo::new);
+    }
+
+    pub fn motor_mix(&self) -> Option<&GearMotorMix> {
+        self.motor_mix.as_ref()
+    }
+
+    pub fn set_motor_mix(&mut self, motor_mix: Option<GearMotorMix>) {
+        self.motor_mix = motor_mix;
+    }
+
+    pub fn gear_feel(&self) -> Option<&GearFeelTable> {
+        self.gear_feel.as_ref()
+    }
+
+    pub fn set_gear_feel(&mut self, gear_feel: Option<GearFeelTable>) {
+        self.gear_feel = gear_feel;
+    }
+
+    /// How the current gear feels, plain if it has nothing of its own.
+    fn current_feel(&self) -> GearFeel {
+        self.gear_feel
+            .as_ref()
+            .and_then(|table| table.feel_for(self.current_gear))
+            .unwrap_or_default()
+    }
+
+    pub fn calculate_rumble_intensity(&self, is_downshift: bool) -> f32 {
+        self.rumble_intensity_at(self.engine.torque(), is_downshift)
+    }
+
+    /// Rumble intensity for a shift made while the engine delivers `torque`.
+    pub fn rumble_intensity_at(&self, torque: Torque, is_downshift: bool) -> f32 {
+        // Base intensity from torque (0.0 to 1.0)
+        let mut intensity = torque / self.max_torque;
+
+        // Downshifts are 30% stronger, upshifts are 20% lighter
+        if is_downshift {
+            intensity *= 1.3;
+        } else {
+            intensity *= 0.8;
+        }
+        intensity *= self.rumble_scale;
+        // The gear just landed in may be heavier or lighter than the rest
+        intensity *= self.current_feel().intensity;
+
+        // A NaN torque must never reach the motors
+        if intensity.is_nan() {
+            return 0.0;
+        }
+
+        // Clamp between 0.0 and 1.0
+        intensity.clamp(0.0, 1.0)
+    }
+
+    pub fn rumble_duration_ms(&self, is_downshift: bool) -> u32 {
+        let duration_ms = match self.transmission {
+            Some(transmission) => transmission.shift_ms(is_downshift),
+            None if is_downshift => 200,
+            None => 150,
+        };
+        // However short the gear's feel, the shift is still felt
+        ((duration_ms as f32 * self.current_feel().duration).round() as u32).max(1)
+    }
+
+    /// Moves up one gear. Returns false if already in the highest gear.
+    pub fn upshift(&mut self) -> bool {
+        if self.current_gear < self.gear_count() {
+            self.shift_to(self.current_gear + 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves down one gear. Returns false if already in first.
+    pub fn downshift(&mut self) -> bool {
+        if self.current_gear > 1 {
+            self.shift_to(self.current_gear - 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Jumps straight to `gear`, e.g. to follow a game's gearbox. The engine
+    /// speed is left alone. Returns false if the car has no such gear.
+    pub fn set_gear(&mut self, gear: u8) -> bool {
+        if gear < 1 || gear > self.gear_count() {
+            return false;
+        }
+        self.current_gear = gear;
+        true
+    }
+
+    /// Moves into `gear` from wherever the box is, with the engine
+    /// following at constant road speed. Returns false if the car has no
+    /// such gear.
+    pub fn shift_into(&mut self, gear: u8) -> bool {
+        if gear < 1 || gear > self.gear_count() {
+            return false;
+        }
+        self.shift_to(gear);
+        true
+    }
+
+    /// Engine speed in `gear` at the current road speed, redline or not.
+    pub fn engine_speed_in(&self, gear: u8) -> AngularSpeed {
+        AngularSpeed::from_rpm(
+            self.engine.speed().rpm() * self.gear_ratio(gear) / self.gear_ratio(self.current_gear),
+        )
+    }
+
+    /// Whether dropping into `gear` would spin the engine past the redline.
+    pub fn is_money_shift(&self, gear: u8) -> bool {
+        self.engine_speed_in(gear) > self.engine.redline()
+    }
+
+    /// Forces the box into `gear` however fast that spins the engine, which
+    /// pays for it in torque. Returns the fraction of torque just lost, or
+    /// `None` if the car has no such gear.
+    pub fn money_shift(&mut self, gear: u8) -> Option<f32> {
+        if gear < 1 || gear > self.gear_count() {
+            return None;
+        }
+        let speed = self.engine_speed_in(gear);
+        self.shift_to(gear);
+        let lost = self.engine.over_rev(speed);
+        if lost > 0.0 {
+            self.money_shifts += 1;
+        }
+        Some(lost)
+    }
+
+    pub fn money_shifts(&self) -> u32 {
+        self.money_shifts
+    }
+
+    fn shift_to(&mut self, gear: u8) {
+        self.engine
+            .shift(self.gear_ratio(self.current_gear), self.gear_ratio(gear));
+        self.current_gear = gear;
+    }
+
+    /// The rumble for a shift that just landed in the current gear.
+    pub fn shift_rumble_command(&self, intensity: f32, duration_ms: u32) -> RumbleCommand {
+        let mut strong = intensity;
+        let mut weak = intensity * 0.7;
+
+        // Per-gear balance for the gear we just landed in
+        if let Some(table) = &self.motor_mix {
+            let (mix, _) = table.mix_for(self.current_gear);
+            strong *= mix.strong;
+            weak *= mix.weak;
+        }
+
+        RumbleCommand {
+            strong_magnitude: (strong.clamp(0.0, 1.0) * 65535.0) as u16,
+            weak_magnitude: (weak.clamp(0.0, 1.0) * 65535.0) as u16,
+            duration_ms,
+        }
+    }
+
+    /// Prints the car's state with the box at `position`.
+    pub fn display_status(&self, position: GearPosition) {
+        // The dashboard shows all of this live
+        if output::is_captured() {
+            return;
+        }
+        say!("\n┌─────────────────────────────────┐");
+        say!("│      CURRENT STATUS             │");
+        say!("├─────────────────────────────────┤");
+        say!("│ Gear:       {}                   │", position);
+        say!(
+            "│ RPM:        {:.0}                │",
+            self.engine.speed().rpm()
+        );
+        say!(
+            "│ Speed:      {:.0} mph             │",
+            self.road_speed().mph()
+        );
+        say!(
+            "│ Torque:     {:<20}│",
+            self.units.format_torque(self.torque)
+        );
+        say!("│ Power:      {:<20}│", self.units.format_power(self.power));
+        if let Some(transmission) = self.transmission {
+            say!("│ Gearbox:    {:<20}│", transmission.name());
+        }
+        if self.turbo.is_some() {
+            say!("│ Turbo:      yes                 │");
+        }
+        if self.money_shifts > 0 {
+            say!(
+                "│ Damage:     -{:.0}% torque          │",
+                self.engine.damage() * 100.0
+            );
+            say!("│ Money shifts: {}                 │", self.money_shifts);
+        }
+        say!("└─────────────────────────────────┘");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::haptics::RumblePattern;
+    use proptest::prelude::*;
+
+    fn car_with(torque: f32, gear: u8) -> Car {
+        let mut car = Car::new(
+            Torque::from_lb_ft(torque),
+            Power::from_hp(400.0),
+            DEFAULT_GEAR_RATIOS.to_vec(),
+            DEFAULT_FINAL_DRIVE,
+        )
+        .unwrap();
+        car.current_gear = gear;
+        car
+    }
+
+    #[test]
+    fn nan_torque_gives_zero_intensity() {
+        let car = car_with(f32::NAN, 3);
+        assert_eq!(car.calculate_rumble_intensity(true), 0.0);
+        assert_eq!(car.calculate_rumble_intensity(false), 0.0);
+    }
+
+    #[test]
+    fn infinite_torque_saturates() {
+        assert_eq!(
+            car_with(f32::INFINITY, 3).calculate_rumble_intensity(true),
+            1.0
+        );
+        assert_eq!(
+            car_with(f32::NEG_INFINITY, 3).calculate_rumble_intensity(true),
+            0.0
+        );
+    }
+
+    #[test]
+    fn shifting_stops_at_the_ends_of_the_box() {
+        let mut car = car_with(300.0, 6);
+        assert!(!car.upshift());
+        assert_eq!(car.current_gear(), 6);
+
+        let mut car = car_with(300.0, 1);
+        assert!(!car.downshift());
+        assert!(car.upshift());
+        assert_eq!(car.current_gear(), 2);
+    }
+
+    #[test]
+    fn shifts_move_the_engine_through_the_rev_range() {
+        let mut car = car_with(300.0, 3);
+        let cruise = car.engine().speed().rpm();
+        assert!(car.upshift());
+        let after_upshift = car.engine().speed().rpm();
+        assert!((after_upshift - cruise * 1.20 / 1.49).abs() < 0.1);
+
+        assert!(car.downshift());
+        assert!(car.downshift());
+        assert!(car.engine().speed().rpm() > cruise);
+    }
+
+    fn car_with_box(ratios: &[f32]) -> Car {
+        Car::new(
+            Torque::from_lb_ft(300.0),
+            Power::from_hp(400.0),
+            ratios.to_vec(),
+            DEFAULT_FINAL_DRIVE,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn gear_count_follows_the_ratios() {
+        let mut eight_speed = car_with_box(&[5.0, 3.2, 2.1, 1.7, 1.3, 1.0, 0.8, 0.64]);
+        assert_eq!(eight_speed.gear_count(), 8);
+        while eight_speed.upshift() {}
+        assert_eq!(eight_speed.current_gear(), 8);
+
+        let mut four_speed = car_with_box(&[2.5, 1.5, 1.0, 0.7]);
+        while four_speed.upshift() {}
+        assert_eq!(four_speed.current_gear(), 4);
+
+        // Starts in the highest gear a short box has
+        assert_eq!(car_with_box(&[1.0, 0.6]).current_gear(), 2);
+    }
+
+    #[test]
+    fn gearbox_validation() {
+        let new = |ratios: Vec<f32>, final_drive: f32| {
+            Car::new(
+                Torque::from_lb_ft(300.0),
+                Power::from_hp(400.0),
+                ratios,
+                final_drive,
+            )
+        };
+        assert!(new(vec![], 3.0).is_err());
+        assert!(new(vec![2.0, 0.0], 3.0).is_err());
+        assert!(new(vec![2.0, f32::NAN], 3.0).is_err());
+        assert!(new(vec![2.0, 1.0], -1.0).is_err());
+        assert!(new(vec![1.0; 256], 3.0).is_err());
+        assert!(new(vec![2.0, 1.0], 3.0).is_ok());
+    }
+
+    #[test]
+    fn road_speed_from_engine_speed_and_ratios() {
+        let mut car = car_with_box(&[1.0]);
+        car.engine_mut().set_speed(AngularSpeed::from_rpm(3000.0));
+        // 3000 rpm / 3.42 through a 0.33 m wheel
+        let expected = 3000.0 / 60.0 * std::f32::consts::TAU / 3.42 * 0.33;
+        assert!((car.road_speed().m_per_s() - expected).abs() < 1e-3);
+
+        // Same road speed before and after a shift that stays inside the rev range
+        let mut car = car_with(300.0, 3);
+        let before = car.road_speed();
+        car.upshift();
+        assert!((car.road_speed().m_per_s() - before.m_per_s()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rumble_scale_multiplies_intensity() {
+        let mut car = car_with(300.0, 3);
+        let unscaled = car.calculate_rumble_intensity(false);
+        car.set_rumble_scale(0.5).unwrap();
+        assert!((car.calculate_rumble_intensity(false) - unscaled * 0.5).abs() < 1e-6);
+        assert!(car.set_rumble_scale(f32::NAN).is_err());
+        assert_eq!(car.rumble_scale(), 0.5);
+    }
+
+    #[test]
+    fn set_gear_stays_inside_the_box() {
+        let mut car = car_with(300.0, 3);
+        let rpm = car.engine().speed();
+        assert!(car.set_gear(6));
+        assert_eq!(car.current_gear(), 6);
+        assert_eq!(car.engine().speed(), rpm);
+        assert!(!car.set_gear(7));
+        assert!(!car.set_gear(0));
+        assert_eq!(car.current_gear(), 6);
+    }
+
+    #[test]
+    fn shifting_into_any_gear() {
+        let mut car = car_with(300.0, 3);
+        let cruise = car.engine().speed().rpm();
+        let expected = cruise * 3.36 / 1.49;
+        assert!((car.engine_speed_in(1).rpm() - expected).abs() < 0.1);
+        assert!(car.engine_speed_in(1).rpm() > DEFAULT_REDLINE_RPM);
+
+        assert!(car.shift_into(5));
+        assert_eq!(car.current_gear(), 5);
+        assert!((car.engine().speed().rpm() - cruise * 1.00 / 1.49).abs() < 0.1);
+        assert!(!car.shift_into(7));
+        assert!(!car.shift_into(0));
+    }
+
+    #[test]
+    fn money_shifts_over_rev_and_damage_the_engine() {
+        let mut car = car_with(300.0, 3);
+        assert!(!car.is_money_shift(2));
+        assert!(car.is_money_shift(1));
+        let torque = car.engine().torque();
+
+        let lost = car.money_shift(1).unwrap();
+        assert!(lost > 0.0);
+        assert_eq!(car.current_gear(), 1);
+        assert_eq!(car.money_shifts(), 1);
+        assert_eq!(car.engine().speed(), car.engine().redline());
+        assert!(car.engine().torque().nm() < torque.nm());
+        assert_eq!(car.money_shift(9), None);
+    }
+
+    #[test]
+    fn throttle_and_brake_move_the_engine() {
+        let second = Duration::from_secs(1);
+        let mut car = car_with(300.0, 3);
+        let cruise = car.engine().speed().rpm();
+        car.drive(1.0, 0.0, second);
+        let flat_out = car.engine().speed().rpm();
+        assert!(flat_out > cruise);
+
+        car.drive(0.0, 0.0, second);
+        let lifted = car.engine().speed().rpm();
+        assert!(lifted < flat_out);
+
+        car.drive(0.0, 1.0, second);
+        assert!(car.engine().speed().rpm() < lifted - 1000.0);
+
+        // First pulls harder than top
+        let mut first = car_with(300.0, 1);
+        let mut top = car_with(300.0, 6);
+        first.drive(1.0, 0.0, second);
+        top.drive(1.0, 0.0, second);
+        assert!(first.engine().speed().rpm() - cruise > top.engine().speed().rpm() - cruise);
+    }
+
+    #[test]
+    fn coasting_slows_down_without_engine_braking() {
+        let second = Duration::from_secs(1);
+        let mut coasting = car_with(300.0, 3);
+        let mut lifted = car_with(300.0, 3);
+        coasting.coast(0.0, second);
+        lifted.drive(0.0, 0.0, second);
+        let cruise = car_with(300.0, 3).engine().speed().rpm();
+        assert!(coasting.engine().speed().rpm() < cruise);
+        assert!(coasting.engine().speed().rpm() > lifted.engine().speed().rpm());
+
+        // Never below idle, and never NaN
+        coasting.coast(1.0, Duration::from_secs(60));
+        assert!((coasting.engine().speed().rpm() - DEFAULT_IDLE_RPM).abs() < 0.1);
+        let mut broken = car_with(f32::NAN, 3);
+        broken.drive(1.0, 0.0, second);
+        assert_eq!(broken.engine().speed().rpm(), cruise);
+    }
+
+    #[test]
+    fn gear_ratio_parsing() {
+        assert_eq!(parse_gear_ratios("3.5, 2,1").unwrap(), vec![3.5, 2.0, 1.0]);
+        assert!(parse_gear_ratios("3.5,,1").is_err());
+        assert!(parse_gear_ratios("fast").is_err());
+    }
+
+    #[test]
+    fn intensity_follows_the_torque_curve() {
+        let mut car = car_with(300.0, 3);
+        car.engine_mut()
+            .set_speed(AngularSpeed::from_rpm(DEFAULT_IDLE_RPM));
+        let at_idle = car.calculate_rumble_intensity(true);
+        car.engine_mut().set_speed(AngularSpeed::from_rpm(4000.0));
+        let at_peak = car.calculate_rumble_intensity(true);
+        assert!(at_idle < at_peak);
+        assert!((at_peak - 300.0 / 1000.0 * 1.3).abs() < 1e-4);
+    }
+
+    fn mix(strong: f32, weak: f32) -> MotorMix {
+        MotorMix { strong, weak }
+    }
+
+    #[test]
+    fn motor_mix_interpolates_between_rows() {
+        let table = GearMotorMix::parse("1=1.2:0.4,5=0.4:1.2", 6).unwrap();
+        assert_eq!(table.mix_for(1), (mix(1.2, 0.4), false));
+        assert_eq!(table.mix_for(5), (mix(0.4, 1.2), false));
+
+        let (mid, interpolated) = table.mix_for(3);
+        assert!(interpolated);
+        assert!((mid.strong - 0.8).abs() < 1e-6);
+        assert!((mid.weak - 0.8).abs() < 1e-6);
+
+        // Outside the table the nearest row applies
+        assert_eq!(table.mix_for(6), (mix(0.4, 1.2), true));
+    }
+
+    #[test]
+    fn motor_mix_rows_can_be_listed_in_any_order() {
+        let table = GearMotorMix::parse("6=0.3:1.0, 1=1.2:0.4, 3=1:1", 6).unwrap();
+        assert_eq!(table.mix_for(3), (mix(1.0, 1.0), false));
+        let (mix2, _) = table.mix_for(2);
+        assert!((mix2.strong - 1.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn motor_mix_validation() {
+        assert!(GearMotorMix::parse("7=1:1", 6).is_err());
+        assert!(GearMotorMix::parse("0=1:1", 6).is_err());
+        assert!(GearMotorMix::parse("2=1:1,2=0.5:0.5", 6).is_err());
+        assert!(GearMotorMix::parse("2=-1:1", 6).is_err());
+        assert!(GearMotorMix::parse("2=1", 6).is_err());
+        assert!(GearMotorMix::parse("", 6).is_err());
+    }
+
+    #[test]
+    fn no_motor_mix_keeps_current_balance() {
+        let car = car_with(500.0, 4);
+        let intensity = car.calculate_rumble_intensity(true);
+        let command = car.shift_rumble_command(intensity, 200);
+        assert_eq!(command.strong_magnitude, (intensity * 65535.0) as u16);
+        assert_eq!(command.weak_magnitude, (intensity * 0.7 * 65535.0) as u16);
+        assert_eq!(command.duration_ms, 200);
+    }
+
+    #[test]
+    fn gear_feel_weighs_each_gear_before_the_clamp() {
+        let feel = |intensity, duration| GearFeel {
+            intensity,
+            duration,
+        };
+        let entries = BTreeMap::from([
+            ("1".to_string(), feel(1.6, 1.5)),
+            ("6".to_string(), feel(0.1, 1.0)),
+        ]);
+        let table = GearFeelTable::from_config(&entries, 6).unwrap();
+        let plain = car_with(300.0, 1);
+        let mut car = car_with(300.0, 1);
+        car.set_gear_feel(Some(table));
+
+        let heavy = car.calculate_rumble_intensity(false);
+        assert!((heavy - plain.calculate_rumble_intensity(false) * 1.6).abs() < 1e-5);
+        assert_eq!(car.rumble_duration_ms(false), 225);
+        // Heavy enough to reach the clamp, and no further
+        let mut strong = car_with(900.0, 1);
+        strong.set_gear_feel(car.gear_feel().cloned());
+        assert_eq!(strong.calculate_rumble_intensity(true), 1.0);
+
+        car.set_gear(6);
+        assert!(car.calculate_rumble_intensity(false) < 0.05);
+        assert_eq!(car.rumble_duration_ms(false), 150);
+        // Gears not listed feel like any other
+        car.set_gear(3);
+        assert_eq!(car.rumble_duration_ms(true), 200);
+
+        let bad = |key: &str, feel| {
+            GearFeelTable::from_config(&BTreeMap::from([(key.to_string(), feel)]), 6).unwrap_err()
+        };
+        assert_eq!(
+            bad("7", feel(1.0, 1.0)),
+            "[gear_feel]: '7' is not a gear in 1..=6"
+        );
+        assert_eq!(
+            bad("R", feel(1.0, 1.0)),
+            "[gear_feel]: 'R' is not a gear in 1..=6"
+        );
+        assert_eq!(
+            bad("2", feel(-1.0, 1.0)),
+            "[gear_feel] 2: invalid intensity -1"
+        );
+        // Found by duration_is_positive_and_downshift_longer and
+        // the_shift_rumble_lasts_as_long_as_the_shift
+        assert_eq!(
+            bad("3", feel(1.0, 0.0)),
+            "[gear_feel] 3: invalid duration 0"
+        );
+        assert_eq!(
+            bad("3", feel(0.0, 4.417956e24)),
+            "[gear_feel] 3: duration 4417956000000000000000000 is over 10"
+        );
+        let mut short = car_with(300.0, 1);
+        short.set_gear_feel(Some(
+            GearFeelTable::from_config(&BTreeMap::from([("1".to_string(), feel(1.0, 1e-6))]), 6)
+                .unwrap(),
+        ));
+        assert_eq!(short.rumble_duration_ms(false), 1);
+    }
+
+    #[test]
+    fn a_dyno_curve_sets_where_the_rumble_peaks() {
+        let mut car = car_with(300.0, 3);
+        let sheet = "rpm,lb-ft\n1000,80\n3000,200\n5500,420\n7000,350";
+        car.set_torque_curve(TorqueCurve::from_csv(sheet, UnitSystem::Imperial).unwrap());
+        assert!((car.torque().lb_ft() - 420.0).abs() < 0.1);
+        assert_eq!(car.engine().redline().rpm().round(), DEFAULT_REDLINE_RPM);
+
+        let intensity_at = |car: &mut Car, rpm: f32| {
+            car.engine_mut().set_speed(AngularSpeed::from_rpm(rpm));
+            car.calculate_rumble_intensity(false)
+        };
+        let low = intensity_at(&mut car, 1500.0);
+        let peak = intensity_at(&mut car, 5500.0);
+        assert!(low < peak / 3.0);
+        assert!(intensity_at(&mut car, 6500.0) < peak);
+    }
+
+    #[test]
+    fn motor_mix_scales_the_destination_gear() {
+        let mut car = car_with(500.0, 6);
+        car.set_motor_mix(Some(GearMotorMix::parse("1=1:0,6=0:1", 6).unwrap()));
+        let command = car.shift_rumble_command(0.5, 150);
+        assert_eq!(command.strong_magnitude, 0);
+        assert_eq!(command.weak_magnitude, (0.5f32 * 0.7 * 65535.0) as u16);
+    }
+
+    /// What a car is fitted with besides its torque, anything the checks on
+    /// each let through.
+    #[derive(Debug, Clone)]
+    struct Fittings {
+        rumble_scale: f32,
+        gear_feel: Option<GearFeelTable>,
+        motor_mix: Option<GearMotorMix>,
+        transmission: Option<Transmission>,
+    }
+
+    impl Fittings {
+        fn car(&self, torque: f32, gear: u8) -> Car {
+            let mut car = car_with(torque, gear);
+            car.set_rumble_scale(self.rumble_scale).unwrap();
+            car.set_gear_feel(self.gear_feel.clone());
+            car.set_motor_mix(self.motor_mix.clone());
+            car.set_transmission(self.transmission);
+            car
+        }
+    }
+
+    /// Finite and not negative, mostly everyday figures but tiny and huge
+    /// ones too.
+    fn gain() -> impl Strategy<Value = f32> {
+        use proptest::num::f32::{NORMAL, POSITIVE, SUBNORMAL, ZERO};
+        prop_oneof![0.0f32..4.0, POSITIVE | NORMAL | SUBNORMAL | ZERO]
+    }
+
+    fn fittings() -> impl Strategy<Value = Fittings> {
+        let duration = prop_oneof![0.0f32..MAX_FEEL_DURATION, Just(MAX_FEEL_DURATION)]
+            .prop_filter("no rumble", |&duration| duration > 0.0);
+        let feel = (gain(), duration).prop_map(|(intensity, duration)| GearFeel {
+            intensity,
+            duration,
+        });
+        let gear_feel = proptest::collection::btree_map(1u8..=6, feel, 0..=6).prop_map(|gears| {
+            let entries = gears
+                .into_iter()
+                .map(|(gear, feel)| (gear.to_string(), feel))
+                .collect();
+            GearFeelTable::from_config(&entries, 6).unwrap()
+        });
+        let mix = (gain(), gain()).prop_map(|(strong, weak)| MotorMix { strong, weak });
+        let motor_mix = proptest::collection::btree_map(1u8..=6, mix, 1..=6)
+            .prop_map(|rows| GearMotorMix::new(rows.into_iter().collect(), 6).unwrap());
+        let transmission = prop_oneof![
+            Just(Transmission::Dct),
+            Just(Transmission::Manual),
+            Just(Transmission::Automatic),
+        ];
+        (
+            gain(),
+            proptest::option::of(gear_feel),
+            proptest::option::of(motor_mix),
+            proptest::option::of(transmission),
+        )
+            .prop_map(
+                |(rumble_scale, gear_feel, motor_mix, transmission)| Fittings {
+                    rumble_scale,
+                    gear_feel,
+                    motor_mix,
+                    transmission,
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn intensity_is_finite_and_in_range(
+            torque in proptest::num::f32::ANY,
+            gear in 1u8..=6,
+            is_downshift in any::<bool>(),
+            fittings in fittings(),
+        ) {
+            let intensity = fittings.car(torque, gear).calculate_rumble_intensity(is_downshift);
+            prop_assert!(intensity.is_finite());
+            prop_assert!((0.0..=1.0).contains(&intensity));
+        }
+
+        #[test]
+        fn intensity_is_monotonic_in_torque(
+            a in -2000.0f32..2000.0,
+            b in -2000.0f32..2000.0,
+            gear in 1u8..=6,
+            is_downshift in any::<bool>(),
+            fittings in fittings(),
+        ) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            let low = fittings.car(low, gear).calculate_rumble_intensity(is_downshift);
+            let high = fittings.car(high, gear).calculate_rumble_intensity(is_downshift);
+            prop_assert!(low <= high);
+        }
+
+        #[test]
+        fn downshift_never_lighter_than_upshift(
+            torque in 0.0f32..2000.0,
+            gear in 1u8..=6,
+            fittings in fittings(),
+        ) {
+            let car = fittings.car(torque, gear);
+            prop_assert!(car.calculate_rumble_intensity(true) >= car.calculate_rumble_intensity(false));
+        }
+
+        #[test]
+        fn duration_is_positive_and_downshift_longer(gear in 1u8..=6, fittings in fittings()) {
+            let car = fittings.car(300.0, gear);
+            let down = car.rumble_duration_ms(true);
+            let up = car.rumble_duration_ms(false);
+            prop_assert!(up > 0);
+            prop_assert!(down >= up);
+        }
+
+        #[test]
+        fn the_shift_rumble_lasts_as_long_as_the_shift(
+            torque in 0.0f32..2000.0,
+            gear in 1u8..=6,
+            is_downshift in any::<bool>(),
+            fittings in fittings(),
+        ) {
+            let car = fittings.car(torque, gear);
+            let command = car.shift_rumble_command(
+                car.calculate_rumble_intensity(is_downshift),
+                car.rumble_duration_ms(is_downshift),
+            );
+            let pattern = match car.transmission() {
+                Some(transmission) => transmission.shift_pattern(command),
+                None => RumblePattern::single(command),
+            };
+            prop_assert_eq!(pattern.duration_ms(), command.duration_ms);
+        }
+    }
+}
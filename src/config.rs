@@ -0,0 +1,554 @@
+// Car presets from `gear_changer.toml`, so specs don't have to be typed in
+// on every launch. A running session picks up edits to it, see `reload`.
+//
+// units = "metric"       # optional: torque and power in Nm and kW, "metric-ps"
+//                       # for Nm and PS, or "imperial" lb-ft and hp by default
+// intensity = 80         # optional, every rumble's strength in percent, 0 to 200
+// haptic_profile = "gentle" # optional, caps the strength and draws rumbles
+//                       # out for sensory sensitivities, see `intensity`
+// slowmo_factor = 3     # optional, how many times slower slow-mo replays
+//                       # play the last shift, above 1; 4 by default
+//
+// [cars.gt3rs]           # or one of the built-in `presets` by name
+// name = "Porsche 911 GT3 RS" # optional, for the menu
+// torque = 346          # lb-ft, or in `units`
+// horsepower = 518      # or power = ..., in `units`
+// units = "imperial"    # optional, what this preset's figures are in
+// redline = 9000        # optional, rpm
+// torque_curve = [[3000, 280], [6000, 346], [9000, 290]] # optional, [rpm, torque]
+// gears = 7             # or gear_ratios = [3.75, 2.38, ...]
+// final_drive = 4.19    # optional
+// rumble_scale = 1.2    # optional, multiplies every shift rumble
+// auto_blip = false     # optional, no rev-match blip on downshifts
+// transmission = "dct"  # optional: "dct", "manual" or "auto", see `transmission`
+// turbo = true          # optional, spool and blow-off rumbles, see `turbo`
+//
+// [json_telemetry]      # optional, for --telemetry json
+// port = 5555
+// gear = "gear"         # field names, dotted for nested objects
+// rpm = "rpm"
+// torque = "torque"
+// torque_unit = "nm"    # or "lb-ft"
+// reverse = -1          # gear values for reverse and neutral
+// neutral = 0
+//
+// [bindings]            # optional, see `bindings` or run `gear_changer bind`
+// upshift = "RightTrigger"
+//
+// [calibration."Xbox Wireless Controller"] # written by `calibrate`, see
+// strong = 1.5          # `calibration`: per-pad motor scaling, by pad name
+// weak = 0.75
+//
+// [gear_feel.1]         # optional, per destination gear: first goes in heavy
+// intensity = 1.6       # multiplies the shift rumble's strength
+// duration = 1.3        # and its length, up to 10 times
+//
+// [gear_feel.6]         # top barely there
+// intensity = 0.2
+//
+// [h_pattern]           # for --h-pattern, see `shifter`
+// 1 = "code:300"        # gate → button
+// reverse = "code:306"
+//
+// [pedals]              # optional, see `pedals`
+// clutch = "+axis:2"
+//
+// [envelopes.downshift] # optional, see `haptics::Envelopes`
+// attack_ms = 20
+// sustain_level = 0.6
+//
+// [drag_best]           # written by --drag: best 0–60 mph times in seconds,
+// gt3rs = 3.42          # by --car preset or "<torque> lb-ft, <hp> hp"
+//
+// [quarter_mile_best]   # written by --drag=quarter-mile: best ETs, the same way
+// gt3rs = 11.204
+
+use crate::calibration::Calibration;
+use crate::car::{Car, DEFAULT_FINAL_DRIVE, DEFAULT_GEAR_RATIOS, GearFeel};
+use crate::engine::{DEFAULT_IDLE_RPM, Engine, TorqueCurve};
+use crate::haptics::Envelopes;
+use crate::intensity::HapticProfile;
+use crate::pedals::PedalConfig;
+use crate::presets;
+use crate::telemetry::json::JsonMapping;
+use crate::transmission::Transmission;
+use crate::units::{AngularSpeed, UnitSystem};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const CONFIG_PATH: &str = "gear_changer.toml";
+
+// First and top gear of the ratios spread out for a preset that only gives a count
+const SPREAD_FIRST_RATIO: f32 = 3.5;
+const SPREAD_TOP_RATIO: f32 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub units: UnitSystem,
+    pub intensity: Option<u16>, // Percent
+    #[serde(default)]
+    pub haptic_profile: HapticProfile,
+    pub slowmo_factor: Option<f32>,
+    #[serde(default)]
+    pub cars: BTreeMap<String, CarPreset>,
+    #[serde(default)]
+    pub json_telemetry: JsonMapping,
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>, // Action → input, see `bindings`
+    #[serde(default)]
+    pub h_pattern: BTreeMap<String, String>, // Gate → button, see `shifter`
+    #[serde(default)]
+    pub calibration: BTreeMap<String, Calibration>, // Gamepad name → scaling
+    #[serde(default)]
+    pub gear_feel: BTreeMap<String, GearFeel>, // Gear → feel, see `car::GearFeelTable`
+    #[serde(default)]
+    pub pedals: PedalConfig,
+    #[serde(default)]
+    pub envelopes: Envelopes,
+    #[serde(default)]
+    pub drag_best: BTreeMap<String, f32>, // Car → seconds, see `drag`
+    #[serde(default)]
+    pub quarter_mile_best: BTreeMap<String, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CarPreset {
+    pub name: Option<String>, // In full, for the menu
+    pub torque: f32,          // In `units`
+    #[serde(alias = "power")]
+    pub horsepower: f32,
+    pub units: Option<UnitSystem>,             // None: the config's
+    pub redline: Option<f32>,                  // rpm
+    pub torque_curve: Option<Vec<(f32, f32)>>, // (rpm, torque in `units`)
+    pub gears: Option<u8>,
+    pub gear_ratios: Option<Vec<f32>>,
+    pub final_drive: Option<f32>,
+    pub rumble_scale: Option<f32>,
+    pub auto_blip: Option<bool>,
+    pub transmission: Option<Transmission>,
+    pub turbo: Option<bool>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// Reads `path`. A missing file is an empty config, not an error.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// The preset called `name`, the driver's own or else a built-in one.
+    pub fn car(&self, name: &str) -> Option<&CarPreset> {
+        self.cars.get(name).or_else(|| presets::builtin().get(name))
+    }
+
+    /// Every preset by name, built-in ones included.
+    pub fn all_cars(&self) -> BTreeMap<&str, &CarPreset> {
+        presets::builtin()
+            .iter()
+            .chain(&self.cars)
+            .map(|(name, preset)| (name.as_str(), preset))
+            .collect()
+    }
+}
+
+impl CarPreset {
+    /// A preset that builds `car` again, its figures in the car's units.
+    pub fn from_car(car: &Car) -> Self {
+        let units = car.units();
+        // Rounded, so unit conversions don't leave 345.99997 in the file
+        let round = |value: f32| (value * 100.0).round() / 100.0;
+        let curve = car
+            .engine()
+            .curve()
+            .points()
+            .iter()
+            .map(|&(speed, torque)| (round(speed.rpm()), round(units.torque_in(torque))))
+            .collect();
+        Self {
+            name: None,
+            torque: round(units.torque_in(car.torque())),
+            horsepower: round(units.power_in(car.power())),
+            units: Some(units),
+            redline: Some(round(car.engine().redline().rpm())),
+            torque_curve: Some(curve),
+            gears: None,
+            gear_ratios: Some(car.gear_ratios().to_vec()),
+            final_drive: Some(car.final_drive()),
+            rumble_scale: Some(car.rumble_scale()),
+            auto_blip: Some(car.auto_blip()),
+            transmission: car.transmission(),
+            turbo: Some(car.turbo().is_some()),
+        }
+    }
+
+    pub fn gear_ratios(&self) -> Result<Vec<f32>, String> {
+        match (&self.gear_ratios, self.gears) {
+            (Some(ratios), Some(gears)) if ratios.len() != gears as usize => Err(format!(
+                "gears = {} but {} gear_ratios are listed",
+                gears,
+                ratios.len()
+            )),
+            (Some(ratios), _) => Ok(ratios.clone()),
+            (None, Some(gears)) => Ok(spread_ratios(gears)),
+            (None, None) => Ok(DEFAULT_GEAR_RATIOS.to_vec()),
+        }
+    }
+
+    /// Builds the car to show in `units`, which its figures are in too
+    /// unless the preset says otherwise.
+    pub fn build(&self, units: UnitSystem) -> Result<Car, String> {
+        let read = self.units.unwrap_or(units);
+        let mut car = Car::new(
+            read.torque(self.torque),
+            read.power(self.horsepower),
+            self.gear_ratios()?,
+            self.final_drive.unwrap_or(DEFAULT_FINAL_DRIVE),
+        )?;
+        if self.redline.is_some() || self.torque_curve.is_some() {
+            let redline = self.redline.unwrap_or(car.engine().redline().rpm());
+            if !redline.is_finite() || redline <= DEFAULT_IDLE_RPM {
+                return Err(format!("redline {} rpm is not above idle", redline));
+            }
+            let curve = match &self.torque_curve {
+                Some(points) => TorqueCurve::new(
+                    points
+                        .iter()
+                        .map(|&(rpm, torque)| (AngularSpeed::from_rpm(rpm), read.torque(torque)))
+                        .collect(),
+                )?,
+                None => car.engine().curve().clone(),
+            };
+            car.set_engine(Engine::new(
+                AngularSpeed::from_rpm(DEFAULT_IDLE_RPM),
+                AngularSpeed::from_rpm(redline),
+                curve,
+            ));
+        }
+        if let Some(scale) = self.rumble_scale {
+            car.set_rumble_scale(scale)?;
+        }
+        if let Some(on) = self.auto_blip {
+            car.set_auto_blip(on);
+        }
+        car.set_transmission(self.transmission);
+        car.set_turbo(self.turbo.unwrap_or(false));
+        car.set_units(units);
+        Ok(car)
+    }
+}
+
+/// Geometric spread of `gears` ratios from a short first to a tall top gear.
+fn spread_ratios(gears: u8) -> Vec<f32> {
+    if gears <= 1 {
+        return vec![1.0; gears as usize];
+    }
+    let step = (SPREAD_TOP_RATIO / SPREAD_FIRST_RATIO).powf(1.0 / (gears - 1) as f32);
+    (0..gears)
+        .map(|i| SPREAD_FIRST_RATIO * step.powi(i as i32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const EXAMPLE: &str = r#"
+        [cars.gt3rs]
+        torque = 346
+        horsepower = 518
+        gears = 7
+        rumble_scale = 1.2
+        transmission = "dct"
+        turbo = true
+
+        [cars.classic]
+        torque = 180
+        horsepower = 150
+        gear_ratios = [3.2, 1.9, 1.3, 1.0]
+        final_drive = 3.9
+        auto_blip = false
+    "#;
+
+    #[test]
+    fn presets_build_cars() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        assert_eq!(config.cars.len(), 2);
+
+        let gt3rs = config.car("gt3rs").unwrap().build(config.units).unwrap();
+        assert_eq!(gt3rs.gear_count(), 7);
+        assert!((gt3rs.torque().lb_ft() - 346.0).abs() < 1e-3);
+        assert_eq!(gt3rs.rumble_scale(), 1.2);
+        assert!(gt3rs.auto_blip());
+        assert_eq!(gt3rs.transmission(), Some(Transmission::Dct));
+        assert!(gt3rs.turbo().is_some());
+
+        let classic = config.car("classic").unwrap().build(config.units).unwrap();
+        assert_eq!(classic.gear_ratios(), &[3.2, 1.9, 1.3, 1.0]);
+        assert_eq!(classic.final_drive(), 3.9);
+        assert_eq!(classic.rumble_scale(), 1.0);
+        assert!(!classic.auto_blip());
+        assert_eq!(classic.transmission(), None);
+        assert!(classic.turbo().is_none());
+
+        assert!(config.car("missing").is_none());
+    }
+
+    #[test]
+    fn a_car_makes_a_preset_that_builds_it_again() {
+        let config = Config::parse(EXAMPLE).unwrap();
+        let mut gt3rs = config.car("gt3rs").unwrap().build(config.units).unwrap();
+        gt3rs.set_units(UnitSystem::Metric);
+        let preset = CarPreset::from_car(&gt3rs);
+        assert_eq!(preset.units, Some(UnitSystem::Metric));
+        assert_eq!(preset.torque, 469.11);
+
+        let again = preset.build(UnitSystem::Imperial).unwrap();
+        assert!((again.torque().lb_ft() - 346.0).abs() < 0.01);
+        assert!((again.power().hp() - 518.0).abs() < 0.01);
+        assert_eq!(again.gear_ratios(), gt3rs.gear_ratios());
+        assert_eq!(again.rumble_scale(), 1.2);
+        assert_eq!(again.transmission(), Some(Transmission::Dct));
+        assert!(again.turbo().is_some());
+        assert_eq!(again.engine().redline(), gt3rs.engine().redline());
+    }
+
+    #[test]
+    fn metric_presets_build_the_same_car() {
+        let config = Config::parse(
+            r#"
+            units = "metric"
+
+            [cars.gt3rs]
+            torque = 469
+            power = 386
+            "#,
+        )
+        .unwrap();
+        let gt3rs = config.car("gt3rs").unwrap().build(config.units).unwrap();
+        assert!((gt3rs.torque().lb_ft() - 346.0).abs() < 0.5);
+        assert!((gt3rs.power().hp() - 518.0).abs() < 0.5);
+        assert_eq!(gt3rs.units(), UnitSystem::Metric);
+        assert!(Config::parse("units = \"furlongs\"").is_err());
+    }
+
+    #[test]
+    fn presets_can_bring_their_own_curve_and_redline() {
+        let config = Config::parse(
+            r#"
+            [cars.miata]
+            name = "My Miata"
+            torque = 150
+            horsepower = 200
+            redline = 8000
+            torque_curve = [[2000, 100], [6000, 150]]
+            "#,
+        )
+        .unwrap();
+        // The driver's own preset wins over the built-in one
+        let miata = config.car("miata").unwrap();
+        assert_eq!(miata.name.as_deref(), Some("My Miata"));
+        let car = miata.build(config.units).unwrap();
+        assert!((car.engine().redline().rpm() - 8000.0).abs() < 0.5);
+        let at_4000 = car
+            .engine()
+            .curve()
+            .torque_at(AngularSpeed::from_rpm(4000.0));
+        assert!((at_4000.lb_ft() - 125.0).abs() < 0.1);
+
+        // Built-in presets are there by name too
+        assert!(config.car("gt3rs").is_some());
+        let all = config.all_cars();
+        assert_eq!(all["miata"].name.as_deref(), Some("My Miata"));
+        assert!(all.contains_key("supra"));
+
+        let bad = |extra: &str| {
+            Config::parse(&format!(
+                "[cars.x]\ntorque = 300\nhorsepower = 400\n{}",
+                extra
+            ))
+            .and_then(|config| config.car("x").unwrap().build(config.units).map(|_| ()))
+        };
+        assert!(bad("redline = 500").is_err());
+        assert!(bad("torque_curve = []").is_err());
+        assert!(bad("torque_curve = [[3000, 200], [3000, 250]]").is_err());
+    }
+
+    #[test]
+    fn intensity_and_profile() {
+        let config = Config::parse("intensity = 60\nhaptic_profile = \"gentle\"").unwrap();
+        assert_eq!(config.intensity, Some(60));
+        assert_eq!(config.haptic_profile, HapticProfile::Gentle);
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.intensity, None);
+        assert_eq!(config.haptic_profile, HapticProfile::Standard);
+        assert!(Config::parse("haptic_profile = \"soft\"").is_err());
+    }
+
+    #[test]
+    fn calibration_by_pad_name() {
+        let config = Config::parse(
+            r#"
+            [calibration."Xbox Wireless Controller"]
+            strong = 1.5
+            weak = 0.75
+            "#,
+        )
+        .unwrap();
+        let calibration = config.calibration["Xbox Wireless Controller"];
+        assert_eq!((calibration.strong, calibration.weak), (1.5, 0.75));
+        assert!(Config::parse("[calibration.pad]\nboth = 1.0").is_err());
+    }
+
+    #[test]
+    fn gear_feel_per_gear() {
+        let config = Config::parse(
+            r#"
+            [gear_feel.1]
+            intensity = 1.6
+            duration = 1.3
+
+            [gear_feel.6]
+            intensity = 0.2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.gear_feel["1"].duration, 1.3);
+        assert_eq!(config.gear_feel["6"].intensity, 0.2);
+        assert_eq!(config.gear_feel["6"].duration, 1.0);
+        assert!(Config::parse("[gear_feel.1]\nweight = 2.0").is_err());
+    }
+
+    #[test]
+    fn spread_ratios_run_from_first_to_top() {
+        let ratios = spread_ratios(5);
+        assert_eq!(ratios.len(), 5);
+        assert!((ratios[0] - SPREAD_FIRST_RATIO).abs() < 1e-5);
+        assert!((ratios[4] - SPREAD_TOP_RATIO).abs() < 1e-5);
+        assert!(ratios.windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(spread_ratios(1), vec![1.0]);
+    }
+
+    #[test]
+    fn invalid_presets_are_rejected() {
+        let preset = |extra: &str| {
+            Config::parse(&format!(
+                "[cars.x]\ntorque = 300\nhorsepower = 400\n{}",
+                extra
+            ))
+            .and_then(|config| config.car("x").unwrap().build(config.units).map(|_| ()))
+        };
+        assert!(preset("").is_ok());
+        assert!(preset("gears = 3\ngear_ratios = [2.0, 1.0]").is_err());
+        assert!(preset("gears = 0").is_err());
+        assert!(preset("rumble_scale = -1.0").is_err());
+        assert!(preset("supercharger = true").is_err());
+        assert!(preset("turbo = 1").is_err());
+        assert!(preset("transmission = \"cvt\"").is_err());
+        assert!(Config::parse("[cars.x]\nhorsepower = 400").is_err());
+    }
+
+    #[test]
+    fn json_telemetry_mapping() {
+        assert_eq!(
+            Config::parse(EXAMPLE).unwrap().json_telemetry,
+            JsonMapping::default()
+        );
+        let config = Config::parse("[json_telemetry]\nport = 7000\nrpm = \"engine.rpm\"").unwrap();
+        assert_eq!(config.json_telemetry.port, 7000);
+        assert_eq!(config.json_telemetry.rpm, "engine.rpm");
+        assert_eq!(config.json_telemetry.gear, "gear");
+        assert!(Config::parse("[json_telemetry]\nspeed = \"kmh\"").is_err());
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_config() {
+        let config = Config::load(Path::new("/nonexistent/gear_changer.toml")).unwrap();
+        assert!(config.cars.is_empty());
+    }
+
+    /// `value` as a TOML float, NaN and infinities included.
+    fn toml_float(value: f32) -> String {
+        match value {
+            _ if value.is_nan() => "nan".to_string(),
+            f32::INFINITY => "inf".to_string(),
+            f32::NEG_INFINITY => "-inf".to_string(),
+            _ => format!("{:?}", value),
+        }
+    }
+
+    fn figure() -> impl Strategy<Value = f32> {
+        prop_oneof![
+            proptest::num::f32::ANY,
+            -10.0f32..20_000.0,
+            Just(f32::NAN),
+            Just(f32::INFINITY),
+            Just(0.0f32),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn any_text_is_a_config_or_an_error(text in "\\PC*") {
+            if let Ok(config) = Config::parse(&text) {
+                for preset in config.cars.values() {
+                    let _ = preset.build(config.units);
+                }
+            }
+        }
+
+        #[test]
+        fn any_figures_build_a_car_or_an_error(
+            torque in figure(),
+            horsepower in figure(),
+            redline in figure(),
+            final_drive in figure(),
+            rumble_scale in figure(),
+            gears in any::<u8>(),
+            ratios in prop::collection::vec(figure(), 0..8),
+            curve in prop::collection::vec((figure(), figure()), 0..6),
+        ) {
+            let list = |values: Vec<String>| values.join(", ");
+            let mut text = format!(
+                "[cars.fuzz]\ntorque = {}\nhorsepower = {}\nredline = {}\n\
+                 final_drive = {}\nrumble_scale = {}\n",
+                toml_float(torque),
+                toml_float(horsepower),
+                toml_float(redline),
+                toml_float(final_drive),
+                toml_float(rumble_scale),
+            );
+            if ratios.is_empty() {
+                text += &format!("gears = {}\n", gears);
+            } else {
+                let ratios = ratios.into_iter().map(toml_float).collect();
+                text += &format!("gear_ratios = [{}]\n", list(ratios));
+            }
+            if !curve.is_empty() {
+                let points = curve
+                    .into_iter()
+                    .map(|(rpm, torque)| format!("[{}, {}]", toml_float(rpm), toml_float(torque)))
+                    .collect();
+                text += &format!("torque_curve = [{}]\n", list(points));
+            }
+            let config = Config::parse(&text).unwrap();
+            if let Ok(car) = config.car("fuzz").unwrap().build(config.units) {
+                let intensity = car.rumble_intensity_at(car.torque(), false);
+                prop_assert!((0.0..=1.0).contains(&intensity), "{}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+// `--tui`: a live dashboard in place of the scrolling console output. The
+// main loop redraws it every RENDER_TICK, slower than it polls input, and
+// the session's messages go to its event log instead of the terminal.
+
+use crate::car::GearPosition;
+use crate::haptics::GilrsHaptics;
+use crate::mixer::MixMode;
+use crate::output;
+use crate::playstation::{Rgb, gear_colour};
+use crate::session::Session;
+use crate::trainer::Trainer;
+use crate::units::{Power, Torque, UnitSystem};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::time::Duration;
+
+// The tachometer turns red this close to the redline
+const TACH_WARNING: f32 = 0.9;
+
+/// Everything the dashboard shows, read off the session once per frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    pub position: GearPosition,
+    pub gear_count: u8,
+    pub rpm: f32,
+    pub redline_rpm: f32,
+    pub torque: Torque,
+    pub peak_torque: Torque,
+    pub power: Power,
+    pub peak_power: Power,
+    pub units: UnitSystem,
+    pub speed_mph: f32,
+    pub throttle: Option<f32>,
+    pub brake: f32,
+    pub automatic: bool,
+    pub bouncing: bool,
+    pub muted: bool,
+    pub damage: f32,
+    pub money_shifts: u32,
+    pub controllers: Vec<String>,
+    pub trainer: Option<Trainer>,
+}
+
+impl View {
+    pub fn of(session: &Session<GilrsHaptics>) -> Self {
+        let car = session.car();
+        let engine = car.engine();
+        let haptics = session.haptics();
+
+        let mut controllers: Vec<String> = haptics
+            .gamepads()
+            .iter()
+            .map(|&id| {
+                let gamepad = haptics.gilrs().gamepad(id);
+                let rumble = if gamepad.is_ff_supported() {
+                    "rumble"
+                } else {
+                    "no rumble"
+                };
+                format!("🎮 {} ({})", gamepad.name(), rumble)
+            })
+            .collect();
+        if controllers.is_empty() {
+            controllers.push("⚠️  No gamepad connected".to_string());
+        }
+        controllers.push(match haptics.mix_mode() {
+            Some(MixMode::Sum) => "Mixing: sum".to_string(),
+            Some(MixMode::Max) => "Mixing: max".to_string(),
+            None => "Mixing: off".to_string(),
+        });
+        if let Some(pad) = haptics.playstation_pad() {
+            controllers.push(format!("{} on {}", pad.model(), pad.path().display()));
+        }
+
+        Self {
+            position: session.position(),
+            gear_count: car.gear_count(),
+            rpm: engine.speed().rpm(),
+            redline_rpm: engine.redline().rpm(),
+            torque: engine.torque(),
+            peak_torque: car.torque(),
+            power: Power::from_torque_at(engine.torque(), engine.speed()),
+            peak_power: car.power(),
+            units: car.units(),
+            speed_mph: car.road_speed().mph(),
+            throttle: session.throttle(),
+            brake: session.brake(),
+            automatic: session.automatic(),
+            bouncing: session.is_bouncing(),
+            muted: session.muted(),
+            damage: engine.damage(),
+            money_shifts: car.money_shifts(),
+            controllers,
+            trainer: session.trainer().cloned(),
+        }
+    }
+}
+
+/// Owns the terminal while the dashboard is up: raw mode, the alternate
+/// screen, and the session's messages. All three go back when it drops.
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+}
+
+impl Dashboard {
+    pub fn start() -> io::Result<Self> {
+        let terminal = ratatui::try_init()?;
+        output::capture(true);
+        Ok(Self { terminal })
+    }
+
+    pub fn draw(&mut self, view: &View) -> io::Result<()> {
+        self.terminal.draw(|frame| {
+            // The log only needs as many lines as fit
+            let log = output::last_lines(frame.area().height as usize);
+            render(frame, view, &log);
+        })?;
+        Ok(())
+    }
+
+    /// True once q, Esc or Ctrl+C has been pressed. Raw mode keeps Ctrl+C
+    /// from interrupting, so the dashboard has to look for it itself.
+    pub fn quit_requested(&self) -> io::Result<bool> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        output::capture(false);
+        ratatui::restore();
+    }
+}
+
+/// Lays `view` and the last lines of the event `log` out over the frame.
+pub fn render(frame: &mut Frame, view: &View, log: &[String]) {
+    let [top, middle, bottom] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Length(9),
+        Constraint::Min(3),
+    ])
+    .areas(frame.area());
+    let [gear_area, tach_area] =
+        Layout::horizontal([Constraint::Length(12), Constraint::Min(20)]).areas(top);
+    let [engine_area, controller_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(middle);
+
+    render_gear(frame, view, gear_area);
+    render_tachometer(frame, view, tach_area);
+    render_engine(frame, view, engine_area);
+
+    let mut controllers: Vec<Line> = view
+        .controllers
+        .iter()
+        .map(|c| Line::raw(c.as_str()))
+        .collect();
+    if view.muted {
+        controllers.insert(
+            0,
+            Line::styled("🔇 Rumble muted", Style::default().fg(Color::Yellow)),
+        );
+    }
+    frame.render_widget(
+        Paragraph::new(controllers).block(Block::bordered().title(" Controllers ")),
+        controller_area,
+    );
+
+    // Newest at the bottom, like the console it replaces
+    let shown = log.len().min(bottom.height.saturating_sub(2) as usize);
+    let lines = log[log.len() - shown..].iter().map(String::as_str);
+    frame.render_widget(
+        List::new(lines).block(Block::bordered().title(" Haptic events ")),
+        bottom,
+    );
+}
+
+fn render_gear(frame: &mut Frame, view: &View, area: Rect) {
+    let label = view.position.to_string();
+    // The same colour as the DualSense lightbar
+    let Rgb(r, g, b) = gear_colour(view.position, view.gear_count);
+    let gear = Paragraph::new(vec![Line::raw(""), Line::raw(label)])
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::Rgb(r, g, b))
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::bordered().title(" Gear "));
+    frame.render_widget(gear, area);
+}
+
+fn render_tachometer(frame: &mut Frame, view: &View, area: Rect) {
+    let fraction = if view.redline_rpm > 0.0 {
+        (view.rpm / view.redline_rpm).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let colour = if view.bouncing || fraction >= TACH_WARNING {
+        Color::Red
+    } else {
+        Color::Green
+    };
+    let mut label = format!("{:.0} / {:.0} RPM", view.rpm, view.redline_rpm);
+    if let Some(trainer) = &view.trainer {
+        let window = trainer.window();
+        label.push_str(&format!("  (shift {:.0}–{:.0})", window.low, window.high));
+    }
+    if view.bouncing {
+        label.push_str("  LIMITER");
+    }
+    let tachometer = Gauge::default()
+        .block(Block::bordered().title(" Tachometer "))
+        .gauge_style(Style::default().fg(colour))
+        .ratio(fraction as f64)
+        .label(label);
+    frame.render_widget(tachometer, area);
+}
+
+fn render_engine(frame: &mut Frame, view: &View, area: Rect) {
+    let pedal = |travel: f32| format!("{:.0}%", travel * 100.0);
+    let mut lines = vec![
+        Line::raw(format!(
+            "Torque:   {} (peak {})",
+            view.units.format_torque(view.torque),
+            view.units.format_torque(view.peak_torque)
+        )),
+        Line::raw(format!(
+            "Power:    {} (peak {})",
+            view.units.format_power(view.power),
+            view.units.format_power(view.peak_power)
+        )),
+        Line::raw(format!("Speed:    {:.0} mph", view.speed_mph)),
+        Line::raw(format!(
+            "Throttle: {}   Brake: {}",
+            view.throttle.map_or_else(|| "-".to_string(), pedal),
+            pedal(view.brake)
+        )),
+        Line::raw(format!(
+            "Gearbox:  {}",
+            if view.automatic {
+                "automatic"
+            } else {
+                "manual"
+            }
+        )),
+    ];
+    if let Some(trainer) = &view.trainer {
+        lines.push(Line::raw(format!("🎯 {}", trainer.score())));
+    }
+    if view.money_shifts > 0 {
+        lines.push(Line::raw(format!(
+            "Damage:   -{:.0}% torque ({} money shifts)",
+            view.damage * 100.0,
+            view.money_shifts
+        )));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Engine ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trainer::{Grade, RpmWindow};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn view() -> View {
+        View {
+            position: GearPosition::Gear(3),
+            gear_count: 6,
+            rpm: 6500.0,
+            redline_rpm: 7000.0,
+            torque: Torque::from_lb_ft(280.0),
+            peak_torque: Torque::from_lb_ft(300.0),
+            power: Power::from_hp(346.0),
+            peak_power: Power::from_hp(350.0),
+            units: UnitSystem::Imperial,
+            speed_mph: 74.0,
+            throttle: Some(0.8),
+            brake: 0.0,
+            automatic: false,
+            bouncing: false,
+            muted: false,
+            damage: 0.0,
+            money_shifts: 0,
+            controllers: vec!["🎮 Test Pad (rumble)".to_string()],
+            trainer: None,
+        }
+    }
+
+    fn screen(view: &View, log: &[String]) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| render(frame, view, log)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn shows_the_gear_revs_engine_and_newest_events() {
+        let log: Vec<String> = (1..=40).map(|i| format!("event {}", i)).collect();
+        let screen = screen(&view(), &log);
+        assert!(screen.contains("6500 / 7000 RPM"));
+        assert!(screen.contains("Torque:   280 lb-ft (peak 300 lb-ft)"));
+        assert!(screen.contains("Throttle: 80%   Brake: 0%"));
+        assert!(screen.contains("Test Pad (rumble)"));
+        assert!(screen.contains("event 40"));
+        assert!(!screen.contains("event 1\n"));
+        assert!(!screen.contains("Damage"));
+        assert!(!screen.contains("perfect"));
+        assert!(!screen.contains("muted"));
+
+        let metric = View {
+            units: UnitSystem::Metric,
+            ..view()
+        };
+        let screen = self::screen(&metric, &log);
+        assert!(screen.contains("Torque:   380 Nm (peak 407 Nm)"));
+        assert!(screen.contains("Power:    258 kW (peak 261 kW)"));
+    }
+
+    #[test]
+    fn shows_damage_the_limiter_and_mute() {
+        let view = View {
+            position: GearPosition::Reverse,
+            bouncing: true,
+            muted: true,
+            damage: 0.25,
+            money_shifts: 1,
+            ..view()
+        };
+        let screen = screen(&view, &[]);
+        assert!(screen.contains("LIMITER"));
+        assert!(screen.contains("Damage:   -25% torque (1 money shifts)"));
+        assert!(screen.contains("Rumble muted"));
+    }
+
+    #[test]
+    fn shows_the_target_window_and_score() {
+        let mut trainer = Trainer::new(RpmWindow {
+            low: 5800.0,
+            high: 6400.0,
+        });
+        trainer.record(Grade::Perfect);
+        trainer.record(Grade::Late);
+        let view = View {
+            trainer: Some(trainer),
+            ..view()
+        };
+        let screen = screen(&view, &[]);
+        assert!(screen.contains("6500 / 7000 RPM  (shift 5800–6400)"));
+        assert!(screen.contains("1/2 perfect (0 early, 1 late)"));
+    }
+}
@@ -0,0 +1,543 @@
+// `--drag`: timed runs from a standing start. The car waits staged until
+// the throttle goes down, then pulls on its torque curve through the gear
+// ratios against an assumed weight, air drag and rolling resistance. The
+// driver shifts: too early bogs the engine, too late sits on the limiter
+// with the fuel cut, so the time comes down to the shift points.
+//
+// A 0–60 mph run starts the clock on the throttle. A quarter mile counts
+// down on the staging lights first, three soft pulses then a hard buzz on
+// green, and times the reaction to the green, the elapsed time (ET) from
+// launch to the line and the trap speed over the last 66 ft. Going before
+// the green is a red light. The pad's rumble latency, if it has been
+// measured, comes off the reaction, as the green is felt that much late.
+
+use crate::car::{AIR_DENSITY_KG_M3, Car, DRAG_AREA_M2, ROLLING_RESISTANCE_N, WHEEL_RADIUS_M};
+use crate::haptics::{RumbleCommand, RumblePattern};
+use crate::units::{AngularSpeed, Speed};
+use std::time::Duration;
+
+/// Where a 0–60 run ends.
+pub const FINISH_MPH: f32 = 60.0;
+
+/// A quarter mile, in metres.
+pub const QUARTER_MILE_M: f32 = 402.336;
+
+// Trap speed is averaged over the last 66 ft, like the beams at a strip
+const TRAP_M: f32 = 20.1168;
+
+// A run launches once the throttle is past this, and the car lines up for
+// the next one once it is back below RESTAGE_THROTTLE
+const LAUNCH_THROTTLE: f32 = 0.5;
+const RESTAGE_THROTTLE: f32 = 0.05;
+
+// The clutch slips with the engine here until the wheels catch up
+const LAUNCH_RPM: f32 = 3000.0;
+
+// Staging lights: an amber every interval after staging, then the green
+const AMBER_LIGHTS: u8 = 3;
+const LIGHT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A soft pulse for each amber light.
+pub const AMBER_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 0,
+    weak_magnitude: 14000,
+    duration_ms: 80,
+};
+
+/// A hard buzz on the green.
+pub const GREEN_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: u16::MAX,
+    weak_magnitude: 50000,
+    duration_ms: 300,
+};
+
+// A personal best: three swells, each stronger than the last
+const CELEBRATION: [RumbleCommand; 5] = [
+    RumbleCommand {
+        strong_magnitude: 20000,
+        weak_magnitude: 8000,
+        duration_ms: 120,
+    },
+    RumbleCommand {
+        strong_magnitude: 0,
+        weak_magnitude: 0,
+        duration_ms: 60,
+    },
+    RumbleCommand {
+        strong_magnitude: 38000,
+        weak_magnitude: 16000,
+        duration_ms: 120,
+    },
+    RumbleCommand {
+        strong_magnitude: 0,
+        weak_magnitude: 0,
+        duration_ms: 60,
+    },
+    RumbleCommand {
+        strong_magnitude: u16::MAX,
+        weak_magnitude: 40000,
+        duration_ms: 350,
+    },
+];
+
+/// The rumble for a new personal best.
+pub fn celebration() -> RumblePattern {
+    RumblePattern::new(CELEBRATION.to_vec())
+}
+
+/// What a run is timed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strip {
+    ZeroToSixty,
+    QuarterMile,
+}
+
+impl Strip {
+    pub fn name(self) -> &'static str {
+        match self {
+            Strip::ZeroToSixty => "0–60 mph",
+            Strip::QuarterMile => "quarter mile",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DragState {
+    /// Stopped, waiting for the throttle or counting down the ambers.
+    Staged,
+    /// Quarter mile only: the green is on, the reaction clock running.
+    Green,
+    Running,
+    /// Past the line in this time, waiting for the throttle to lift.
+    Finished(Duration),
+    /// Went before the green, waiting for the throttle to lift.
+    RedLight,
+}
+
+/// How a finished run went.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DragResult {
+    /// To 60 mph, or the quarter mile's ET.
+    pub time: Duration,
+    /// Quarter mile only: from the green to the launch.
+    pub reaction: Option<Duration>,
+    /// Quarter mile only: average over the last 66 ft.
+    pub trap: Option<Speed>,
+}
+
+/// What a step of the run changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DragEvent {
+    /// The 1st to 3rd amber staging light.
+    Amber(u8),
+    Green,
+    RedLight,
+    Launched,
+    Finished {
+        result: DragResult,
+        /// Quicker than `previous_best`, or the first run.
+        personal_best: bool,
+        previous_best: Option<Duration>,
+    },
+    Staged,
+}
+
+/// One drag strip session: the run in progress and the best so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragRun {
+    strip: Strip,
+    mass_kg: f32,
+    state: DragState,
+    speed_m_per_s: f32,
+    distance_m: f32,
+    elapsed: Duration, // Since staging, the green or the launch
+    ambers: u8,
+    reaction: Option<Duration>,
+    trap_entered: Option<Duration>, // Elapsed at the start of the trap
+    best: Option<Duration>,
+    latency: Duration, // Of the green rumble, see `latency`
+}
+
+impl DragRun {
+    /// Runs over `strip` for a car weighing `mass_kg`, to beat `best` if
+    /// there is one.
+    pub fn new(strip: Strip, mass_kg: f32, best: Option<Duration>) -> Self {
+        Self {
+            strip,
+            mass_kg,
+            state: DragState::Staged,
+            speed_m_per_s: 0.0,
+            distance_m: 0.0,
+            elapsed: Duration::ZERO,
+            ambers: 0,
+            reaction: None,
+            trap_entered: None,
+            best,
+            latency: Duration::ZERO,
+        }
+    }
+
+    pub fn strip(&self) -> Strip {
+        self.strip
+    }
+
+    pub fn state(&self) -> DragState {
+        self.state
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn best(&self) -> Option<Duration> {
+        self.best
+    }
+
+    /// Takes `latency` off every reaction from now on.
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    pub fn speed(&self) -> Speed {
+        Speed::from_m_per_s(self.speed_m_per_s)
+    }
+
+    pub fn distance_m(&self) -> f32 {
+        self.distance_m
+    }
+
+    /// Stops the car in first with the engine at idle, ready to launch. A
+    /// quarter mile starts counting down the lights.
+    pub fn stage(&mut self, car: &mut Car) {
+        self.state = DragState::Staged;
+        self.speed_m_per_s = 0.0;
+        self.distance_m = 0.0;
+        self.elapsed = Duration::ZERO;
+        self.ambers = 0;
+        self.reaction = None;
+        self.trap_entered = None;
+        car.set_gear(1);
+        let idle = car.engine().idle();
+        car.engine_mut().set_speed(idle);
+    }
+
+    /// Moves the run on by `dt` with the throttle at 0 to 1. `driving` is
+    /// false when the engine can't push, e.g. in neutral or with the clutch
+    /// down. The car's engine follows the run.
+    pub fn step(
+        &mut self,
+        car: &mut Car,
+        throttle: f32,
+        driving: bool,
+        dt: Duration,
+    ) -> Option<DragEvent> {
+        let launching = driving && throttle >= LAUNCH_THROTTLE;
+        match self.state {
+            DragState::Staged if launching && self.strip == Strip::QuarterMile => {
+                self.state = DragState::RedLight;
+                Some(DragEvent::RedLight)
+            }
+            DragState::Staged if launching => self.launch(car, throttle, dt),
+            DragState::Staged => {
+                // Revving against the brakes
+                let idle = car.engine().idle().rpm();
+                let rpm = idle + (LAUNCH_RPM - idle) * throttle.clamp(0.0, 1.0);
+                car.engine_mut().set_speed(AngularSpeed::from_rpm(rpm));
+                if self.strip == Strip::ZeroToSixty {
+                    return None;
+                }
+                self.elapsed += dt;
+                let lit = (self.elapsed.as_millis() / LIGHT_INTERVAL.as_millis()) as u8;
+                if lit <= self.ambers {
+                    None
+                } else if self.ambers < AMBER_LIGHTS {
+                    self.ambers += 1;
+                    Some(DragEvent::Amber(self.ambers))
+                } else {
+                    self.state = DragState::Green;
+                    self.elapsed = Duration::ZERO;
+                    Some(DragEvent::Green)
+                }
+            }
+            DragState::Green => {
+                self.elapsed += dt;
+                if !launching {
+                    return None;
+                }
+                self.reaction = Some(self.elapsed.saturating_sub(self.latency));
+                self.launch(car, throttle, dt)
+            }
+            DragState::Running => {
+                self.elapsed += dt;
+                self.accelerate(car, throttle, driving, dt);
+                if self.distance_m >= QUARTER_MILE_M - TRAP_M && self.trap_entered.is_none() {
+                    self.trap_entered = Some(self.elapsed);
+                }
+                let finished = match self.strip {
+                    Strip::ZeroToSixty => self.speed().mph() >= FINISH_MPH,
+                    Strip::QuarterMile => self.distance_m >= QUARTER_MILE_M,
+                };
+                finished.then(|| self.finish())
+            }
+            DragState::Finished(_) | DragState::RedLight if throttle <= RESTAGE_THROTTLE => {
+                self.stage(car);
+                Some(DragEvent::Staged)
+            }
+            DragState::Finished(_) | DragState::RedLight => None,
+        }
+    }
+
+    fn launch(&mut self, car: &mut Car, throttle: f32, dt: Duration) -> Option<DragEvent> {
+        self.state = DragState::Running;
+        self.elapsed = Duration::ZERO;
+        self.accelerate(car, throttle, true, dt);
+        Some(DragEvent::Launched)
+    }
+
+    fn finish(&mut self) -> DragEvent {
+        let time = self.elapsed;
+        let trap = match (self.strip, self.trap_entered) {
+            (Strip::QuarterMile, Some(entered)) if time > entered => {
+                Some(Speed::from_m_per_s(TRAP_M / (time - entered).as_secs_f32()))
+            }
+            (Strip::QuarterMile, _) => Some(self.speed()),
+            (Strip::ZeroToSixty, _) => None,
+        };
+        let previous_best = self.best;
+        let personal_best = previous_best.is_none_or(|best| time < best);
+        if personal_best {
+            self.best = Some(time);
+        }
+        self.state = DragState::Finished(time);
+        DragEvent::Finished {
+            result: DragResult {
+                time,
+                reaction: self.reaction,
+                trap,
+            },
+            personal_best,
+            previous_best,
+        }
+    }
+
+    fn accelerate(&mut self, car: &mut Car, throttle: f32, driving: bool, dt: Duration) {
+        let ratio = car.gear_ratios()[car.current_gear() as usize - 1] * car.final_drive();
+        let redline = car.engine().redline().rpm();
+        let wheel_rpm =
+            AngularSpeed::from_rad_per_sec(self.speed_m_per_s / WHEEL_RADIUS_M * ratio).rpm();
+        // Slipping below the launch revs, fuel cut on the limiter
+        let rpm = wheel_rpm.max(LAUNCH_RPM).min(redline);
+        car.engine_mut().set_speed(AngularSpeed::from_rpm(rpm));
+        let torque_nm = if driving && wheel_rpm < redline {
+            car.engine().torque().nm() * throttle.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let speed = self.speed_m_per_s;
+        let drag = 0.5 * AIR_DENSITY_KG_M3 * DRAG_AREA_M2 * speed * speed;
+        let rolling = if speed > 0.0 {
+            ROLLING_RESISTANCE_N
+        } else {
+            0.0
+        };
+        let force = torque_nm * ratio / WHEEL_RADIUS_M - drag - rolling;
+        let new_speed = speed + force / self.mass_kg * dt.as_secs_f32();
+        if new_speed.is_finite() {
+            self.speed_m_per_s = new_speed.max(0.0);
+            self.distance_m += (speed + self.speed_m_per_s) / 2.0 * dt.as_secs_f32();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::{DEFAULT_FINAL_DRIVE, DEFAULT_GEAR_RATIOS, MASS_KG};
+    use crate::units::{Power, Torque};
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    fn car() -> Car {
+        Car::new(
+            Torque::from_lb_ft(300.0),
+            Power::from_hp(400.0),
+            DEFAULT_GEAR_RATIOS.to_vec(),
+            DEFAULT_FINAL_DRIVE,
+        )
+        .unwrap()
+    }
+
+    /// Stays flat out past the launch, upshifting at `shift_rpm`, until
+    /// the run finishes.
+    fn finish(run: &mut DragRun, car: &mut Car, shift_rpm: f32) -> DragEvent {
+        for _ in 0..10_000 {
+            if car.engine().speed().rpm() >= shift_rpm {
+                car.upshift();
+            }
+            if let Some(event) = run.step(car, 1.0, true, STEP) {
+                return event;
+            }
+        }
+        panic!("never finished the {}", run.strip().name());
+    }
+
+    fn result(event: DragEvent) -> DragResult {
+        match event {
+            DragEvent::Finished { result, .. } => result,
+            other => panic!("expected a finish, got {:?}", other),
+        }
+    }
+
+    fn time(mass_kg: f32, shift_rpm: f32) -> Duration {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::ZeroToSixty, mass_kg, None);
+        run.stage(&mut car);
+        run.step(&mut car, 1.0, true, STEP);
+        result(finish(&mut run, &mut car, shift_rpm)).time
+    }
+
+    /// Steps with the throttle at `throttle` until something happens.
+    fn next_event(run: &mut DragRun, car: &mut Car, throttle: f32) -> DragEvent {
+        for _ in 0..1000 {
+            if let Some(event) = run.step(car, throttle, true, STEP) {
+                return event;
+            }
+        }
+        panic!("nothing happened");
+    }
+
+    #[test]
+    fn launches_on_the_throttle_and_times_to_sixty() {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::ZeroToSixty, MASS_KG, None);
+        run.stage(&mut car);
+        assert_eq!(car.current_gear(), 1);
+        assert_eq!(run.step(&mut car, 0.3, true, STEP), None);
+        assert_eq!(run.state(), DragState::Staged);
+        assert_eq!(run.step(&mut car, 1.0, false, STEP), None);
+        assert_eq!(
+            run.step(&mut car, 1.0, true, STEP),
+            Some(DragEvent::Launched)
+        );
+        assert!(run.speed().mph() > 0.0);
+
+        let result = result(finish(&mut run, &mut car, 6500.0));
+        let time = result.time;
+        assert!(time > Duration::from_secs(3) && time < Duration::from_secs(10));
+        assert_eq!((result.reaction, result.trap), (None, None));
+        assert_eq!(run.state(), DragState::Finished(time));
+        assert_eq!(run.best(), Some(time));
+
+        // Still on the throttle past the line, then lifting lines up again
+        assert_eq!(run.step(&mut car, 1.0, true, STEP), None);
+        assert_eq!(run.step(&mut car, 0.0, true, STEP), Some(DragEvent::Staged));
+        assert_eq!(run.speed().mph(), 0.0);
+        assert_eq!(car.current_gear(), 1);
+    }
+
+    #[test]
+    fn shift_points_and_weight_set_the_time() {
+        let good = time(MASS_KG, 6500.0);
+        assert!(time(MASS_KG, 3500.0) > good);
+        assert!(time(MASS_KG * 1.5, 6500.0) > good);
+    }
+
+    #[test]
+    fn only_a_quicker_run_is_a_personal_best() {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::ZeroToSixty, MASS_KG, Some(Duration::from_secs(1)));
+        run.stage(&mut car);
+        run.step(&mut car, 1.0, true, STEP);
+        assert!(matches!(
+            finish(&mut run, &mut car, 6500.0),
+            DragEvent::Finished {
+                personal_best: false,
+                ..
+            }
+        ));
+        assert_eq!(run.best(), Some(Duration::from_secs(1)));
+
+        let mut run = DragRun::new(Strip::ZeroToSixty, MASS_KG, Some(Duration::from_secs(60)));
+        run.stage(&mut car);
+        run.step(&mut car, 1.0, true, STEP);
+        let DragEvent::Finished {
+            result,
+            personal_best,
+            previous_best,
+        } = finish(&mut run, &mut car, 6500.0)
+        else {
+            panic!("expected a finish");
+        };
+        assert!(personal_best);
+        assert_eq!(previous_best, Some(Duration::from_secs(60)));
+        assert_eq!(run.best(), Some(result.time));
+        assert!(celebration().duration_ms() > 0);
+    }
+
+    #[test]
+    fn quarter_mile_counts_down_then_times_reaction_et_and_trap() {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::QuarterMile, MASS_KG, None);
+        run.stage(&mut car);
+        for amber in 1..=AMBER_LIGHTS {
+            assert_eq!(next_event(&mut run, &mut car, 0.0), DragEvent::Amber(amber));
+        }
+        assert_eq!(next_event(&mut run, &mut car, 0.0), DragEvent::Green);
+        assert_eq!(run.state(), DragState::Green);
+
+        // A quarter of a second to react
+        for _ in 0..25 {
+            assert_eq!(run.step(&mut car, 0.0, true, STEP), None);
+        }
+        assert_eq!(next_event(&mut run, &mut car, 1.0), DragEvent::Launched);
+        let result = result(finish(&mut run, &mut car, 6500.0));
+        assert_eq!(result.reaction, Some(Duration::from_millis(260)));
+        assert!(result.time > Duration::from_secs(9) && result.time < Duration::from_secs(20));
+        assert!(run.distance_m() >= QUARTER_MILE_M);
+        // The trap is the average at the end, a shade under the speed at the line
+        let trap = result.trap.unwrap().mph();
+        assert!(trap > 80.0 && trap <= run.speed().mph());
+    }
+
+    #[test]
+    fn the_rumble_latency_comes_off_the_reaction() {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::QuarterMile, MASS_KG, None);
+        run.set_latency(Duration::from_millis(60));
+        run.stage(&mut car);
+        while next_event(&mut run, &mut car, 0.0) != DragEvent::Green {}
+        for _ in 0..25 {
+            run.step(&mut car, 0.0, true, STEP);
+        }
+        assert_eq!(next_event(&mut run, &mut car, 1.0), DragEvent::Launched);
+        let reaction = result(finish(&mut run, &mut car, 6500.0)).reaction;
+        assert_eq!(reaction, Some(Duration::from_millis(200)));
+
+        // Quicker than the pad can be felt is anticipation, not a negative time
+        run.set_latency(Duration::from_millis(500));
+        assert_eq!(next_event(&mut run, &mut car, 0.0), DragEvent::Staged);
+        while next_event(&mut run, &mut car, 0.0) != DragEvent::Green {}
+        assert_eq!(next_event(&mut run, &mut car, 1.0), DragEvent::Launched);
+        let reaction = result(finish(&mut run, &mut car, 6500.0)).reaction;
+        assert_eq!(reaction, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn going_before_the_green_is_a_red_light() {
+        let mut car = car();
+        let mut run = DragRun::new(Strip::QuarterMile, MASS_KG, None);
+        run.stage(&mut car);
+        assert_eq!(next_event(&mut run, &mut car, 0.0), DragEvent::Amber(1));
+        assert_eq!(next_event(&mut run, &mut car, 1.0), DragEvent::RedLight);
+        assert_eq!(run.state(), DragState::RedLight);
+        assert_eq!(run.step(&mut car, 1.0, true, STEP), None);
+        assert_eq!(run.speed().mph(), 0.0);
+        assert_eq!(next_event(&mut run, &mut car, 0.0), DragEvent::Staged);
+        assert_eq!(run.best(), None);
+    }
+}
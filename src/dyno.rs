@@ -0,0 +1,143 @@
+// A dyno chart: torque and power against engine speed from idle to the
+// redline, drawn in plain text for `gear_changer dyno` so an imported curve
+// can be checked before driving on it. Both share one axis, in the driver's
+// units, so in lb-ft and hp they cross at 5252 rpm as they should.
+
+use crate::engine::Engine;
+use crate::units::{AngularSpeed, Power, Torque, UnitSystem};
+
+const TORQUE_MARK: char = '*';
+const POWER_MARK: char = '+';
+const BOTH_MARK: char = '#';
+
+// The peaks are found on a sweep this fine, finer than a column
+const PEAK_STEP_RPM: usize = 10;
+
+// Y axis labels every this many rows, and the axis rounded up to a multiple
+// of AXIS_STEP
+const LABEL_EVERY: usize = 4;
+const AXIS_STEP: f32 = 50.0;
+
+/// The torque and power curves of `engine`, `width` columns of rpm by
+/// `height` rows, with their peaks underneath.
+pub fn chart(engine: &Engine, units: UnitSystem, width: usize, height: usize) -> String {
+    let (idle, redline) = (engine.idle().rpm(), engine.redline().rpm());
+    let sample = |rpm: f32| {
+        let speed = AngularSpeed::from_rpm(rpm);
+        let torque = engine.curve().torque_at(speed);
+        (rpm, torque, Power::from_torque_at(torque, speed))
+    };
+    let samples: Vec<(f32, Torque, Power)> = (0..width)
+        .map(|column| sample(idle + (redline - idle) * column as f32 / (width - 1).max(1) as f32))
+        .collect();
+    let values: Vec<(f32, f32)> = samples
+        .iter()
+        .map(|&(_, torque, power)| (units.torque_in(torque), units.power_in(power)))
+        .collect();
+    let top = values
+        .iter()
+        .flat_map(|&(torque, power)| [torque, power])
+        .fold(AXIS_STEP, f32::max);
+    let top = (top / AXIS_STEP).ceil() * AXIS_STEP;
+
+    let level = |value: f32| ((value / top) * (height - 1) as f32).round() as usize;
+    let mut rows = vec![vec![' '; width]; height];
+    for (column, &(torque, power)) in values.iter().enumerate() {
+        let (torque, power) = (level(torque), level(power));
+        rows[height - 1 - torque][column] = TORQUE_MARK;
+        rows[height - 1 - power][column] = if power == torque {
+            BOTH_MARK
+        } else {
+            POWER_MARK
+        };
+    }
+
+    let mut chart = format!("{}/{}\n", units.torque_unit(), units.power_unit());
+    for (i, row) in rows.iter().enumerate() {
+        let from_bottom = height - 1 - i;
+        let label = if from_bottom.is_multiple_of(LABEL_EVERY) {
+            format!("{:.0}", top * from_bottom as f32 / (height - 1) as f32)
+        } else {
+            String::new()
+        };
+        chart += &format!("{:>6} │{}\n", label, row.iter().collect::<String>());
+    }
+    chart += &format!("{:>6} └{}\n", "", "─".repeat(width));
+    let (low, high) = (format!("{:.0}", idle), format!("{:.0} rpm", redline));
+    chart += &format!(
+        "{:8}{}{}{}\n\n",
+        "",
+        low,
+        " ".repeat(width.saturating_sub(low.len() + high.len())),
+        high
+    );
+
+    let sweep: Vec<(f32, Torque, Power)> = (idle.round() as usize..=redline.round() as usize)
+        .step_by(PEAK_STEP_RPM)
+        .map(|rpm| sample(rpm as f32))
+        .collect();
+    let peak_at = |of: fn(&(f32, Torque, Power)) -> f32| {
+        sweep
+            .iter()
+            .copied()
+            .max_by(|a, b| of(a).total_cmp(&of(b)))
+            .unwrap()
+    };
+    let (torque_rpm, torque, _) = peak_at(|&(_, torque, _)| torque.nm());
+    let (power_rpm, _, power) = peak_at(|&(_, _, power)| power.kw());
+    chart += &format!(
+        "{} torque: peak {} at {:.0} rpm\n",
+        TORQUE_MARK,
+        units.format_torque(torque),
+        torque_rpm
+    );
+    chart += &format!(
+        "{} power:  peak {} at {:.0} rpm\n",
+        POWER_MARK,
+        units.format_power(power),
+        power_rpm
+    );
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TorqueCurve;
+
+    fn engine() -> Engine {
+        let curve = TorqueCurve::from_csv("1000,200\n4000,300\n7000,200", UnitSystem::Imperial);
+        Engine::new(
+            AngularSpeed::from_rpm(1000.0),
+            AngularSpeed::from_rpm(7000.0),
+            curve.unwrap(),
+        )
+    }
+
+    #[test]
+    fn plots_both_curves_and_their_peaks() {
+        let chart = chart(&engine(), UnitSystem::Imperial, 61, 17);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "lb-ft/hp");
+        // Torque peaks mid-chart, in the top row of a 300 axis
+        let top = lines[1];
+        assert!(top.starts_with("   300 │"));
+        assert_eq!(top.chars().nth(8 + 30), Some(TORQUE_MARK));
+        assert!(chart.contains("* torque: peak 300 lb-ft at 4000 rpm"));
+        assert!(chart.contains("+ power:  peak 268 hp at 6500 rpm"));
+        let rpm = lines[19];
+        assert!(rpm.starts_with("        1000 "));
+        assert!(rpm.ends_with("7000 rpm"));
+        assert_eq!(rpm.chars().count(), 8 + 61);
+        // Every column has a mark for each curve
+        let marks = chart
+            .lines()
+            .skip(1)
+            .take(17)
+            .flat_map(str::chars)
+            .filter(|&c| c == TORQUE_MARK || c == POWER_MARK)
+            .count();
+        let both = chart.matches(BOTH_MARK).count();
+        assert_eq!(marks + 2 * both, 2 * 61);
+    }
+}
@@ -0,0 +1,354 @@
+// Engine speed and the torque it makes there.
+
+use crate::units::{AngularSpeed, Torque, UnitSystem};
+
+pub const DEFAULT_IDLE_RPM: f32 = 800.0;
+pub const DEFAULT_REDLINE_RPM: f32 = 7000.0;
+
+// Where a freshly started session is in the rev range
+pub const CRUISE_RPM: f32 = 3500.0;
+
+// Torque lost per unit of over-rev (0.1 = 10% past the redline), and the
+// most an engine can lose in total
+const OVER_REV_DAMAGE: f32 = 0.5;
+const MAX_DAMAGE: f32 = 0.8;
+
+// Shape used when only the peak torque is known: (rpm, fraction of peak)
+const DEFAULT_SHAPE: [(f32, f32); 5] = [
+    (1000.0, 0.55),
+    (2500.0, 0.85),
+    (4000.0, 1.0),
+    (5500.0, 0.95),
+    (7000.0, 0.8),
+];
+
+/// Torque against engine speed. Speeds between two points are linearly
+/// interpolated, speeds outside the curve use the nearest point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorqueCurve {
+    points: Vec<(AngularSpeed, Torque)>, // Sorted by speed, never empty
+}
+
+impl TorqueCurve {
+    pub fn new(mut points: Vec<(AngularSpeed, Torque)>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("torque curve is empty".to_string());
+        }
+        for (speed, torque) in &points {
+            if !speed.rpm().is_finite() || speed.rpm() < 0.0 {
+                return Err(format!("invalid engine speed {} rpm", speed.rpm()));
+            }
+            if !torque.nm().is_finite() {
+                return Err(format!(
+                    "invalid torque {} Nm at {:.0} rpm",
+                    torque.nm(),
+                    speed.rpm()
+                ));
+            }
+        }
+        points.sort_by(|a, b| a.0.rpm().total_cmp(&b.0.rpm()));
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!("{:.0} rpm is listed twice", pair[0].0.rpm()));
+        }
+        Ok(Self { points })
+    }
+
+    pub fn points(&self) -> &[(AngularSpeed, Torque)] {
+        &self.points
+    }
+
+    /// A typical road-car curve scaled so that it peaks at `peak`.
+    pub fn scaled_to(peak: Torque) -> Self {
+        Self {
+            points: DEFAULT_SHAPE
+                .iter()
+                .map(|&(rpm, fraction)| {
+                    (
+                        AngularSpeed::from_rpm(rpm),
+                        Torque::from_nm(peak.nm() * fraction),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Reads a dyno sheet: an `rpm,torque` row per line, separated by commas,
+    /// semicolons or tabs, with any further columns ignored. Torque is in
+    /// `units` unless a header row names lb-ft or Nm. Blank lines and `#`
+    /// comments are skipped.
+    pub fn from_csv(text: &str, units: UnitSystem) -> Result<Self, String> {
+        let mut units = units;
+        let mut points = Vec::new();
+        let rows = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        for (n, line) in rows {
+            let fields: Vec<&str> = line.split([',', ';', '\t']).map(str::trim).collect();
+            let [rpm, torque, ..] = fields[..] else {
+                return Err(format!(
+                    "line {}: expected rpm,torque but found '{}'",
+                    n, line
+                ));
+            };
+            let Ok(rpm) = rpm.parse::<f32>() else {
+                if !points.is_empty() {
+                    return Err(format!("line {}: '{}' is not an rpm", n, rpm));
+                }
+                // A header, which may say what the torque is in
+                let header = torque.to_lowercase();
+                if header.contains("lb") {
+                    units = UnitSystem::Imperial;
+                } else if header.contains("nm") {
+                    units = UnitSystem::Metric;
+                }
+                continue;
+            };
+            let torque = torque
+                .parse::<f32>()
+                .map_err(|_| format!("line {}: '{}' is not a torque", n, torque))?;
+            if torque < 0.0 {
+                return Err(format!("line {}: negative torque {}", n, torque));
+            }
+            points.push((AngularSpeed::from_rpm(rpm), units.torque(torque)));
+        }
+        if points.len() < 2 {
+            return Err("a dyno sheet needs at least two rpm,torque rows".to_string());
+        }
+        Self::new(points)
+    }
+
+    /// The most torque anywhere on the curve.
+    pub fn peak(&self) -> Torque {
+        self.points
+            .iter()
+            .map(|&(_, torque)| torque)
+            .fold(
+                Torque::default(),
+                |peak, torque| {
+                    if torque > peak { torque } else { peak }
+                },
+            )
+    }
+
+    pub fn torque_at(&self, speed: AngularSpeed) -> Torque {
+        let rpm = speed.rpm();
+        let (first_speed, first) = self.points[0];
+        let (last_speed, last) = self.points[self.points.len() - 1];
+        if rpm <= first_speed.rpm() {
+            return first;
+        }
+        if rpm >= last_speed.rpm() {
+            return last;
+        }
+
+        for pair in self.points.windows(2) {
+            let ((low_speed, low), (high_speed, high)) = (pair[0], pair[1]);
+            if rpm < high_speed.rpm() {
+                let t = (rpm - low_speed.rpm()) / (high_speed.rpm() - low_speed.rpm());
+                return Torque::from_nm(low.nm() * (1.0 - t) + high.nm() * t);
+            }
+        }
+        last
+    }
+}
+
+pub struct Engine {
+    speed: AngularSpeed,
+    idle: AngularSpeed,
+    redline: AngularSpeed,
+    curve: TorqueCurve,
+    damage: f32, // Fraction of torque lost to over-revving
+}
+
+impl Engine {
+    pub fn new(idle: AngularSpeed, redline: AngularSpeed, curve: TorqueCurve) -> Self {
+        Self {
+            speed: idle,
+            idle,
+            redline,
+            curve,
+            damage: 0.0,
+        }
+    }
+
+    pub fn speed(&self) -> AngularSpeed {
+        self.speed
+    }
+
+    pub fn idle(&self) -> AngularSpeed {
+        self.idle
+    }
+
+    pub fn redline(&self) -> AngularSpeed {
+        self.redline
+    }
+
+    pub fn curve(&self) -> &TorqueCurve {
+        &self.curve
+    }
+
+    /// Torque at the current engine speed, less any damage.
+    pub fn torque(&self) -> Torque {
+        Torque::from_nm(self.curve.torque_at(self.speed).nm() * (1.0 - self.damage))
+    }
+
+    /// Fraction of torque lost for good, 0 for a healthy engine.
+    pub fn damage(&self) -> f32 {
+        self.damage
+    }
+
+    /// Damages the engine for being forced to `speed`. The further past the
+    /// redline, the more torque it loses. Returns the fraction just lost.
+    pub fn over_rev(&mut self, speed: AngularSpeed) -> f32 {
+        let over = speed.rpm() / self.redline.rpm() - 1.0;
+        if !over.is_finite() || over <= 0.0 {
+            return 0.0;
+        }
+        let before = self.damage;
+        self.damage = (before + over * OVER_REV_DAMAGE).min(MAX_DAMAGE);
+        self.damage - before
+    }
+
+    /// Sets the engine speed, kept between idle and redline.
+    pub fn set_speed(&mut self, speed: AngularSpeed) {
+        let rpm = speed.rpm().clamp(self.idle.rpm(), self.redline.rpm());
+        self.speed = AngularSpeed::from_rpm(rpm);
+    }
+
+    /// Moves the engine speed for a shift at constant road speed: the engine
+    /// turns proportionally to the ratio of the gear it is in.
+    pub fn shift(&mut self, from_ratio: f32, to_ratio: f32) {
+        let rpm = self.speed.rpm() * to_ratio / from_ratio;
+        if rpm.is_finite() {
+            self.set_speed(AngularSpeed::from_rpm(rpm));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpm(rpm: f32) -> AngularSpeed {
+        AngularSpeed::from_rpm(rpm)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-3 * b.abs().max(1.0)
+    }
+
+    fn curve() -> TorqueCurve {
+        TorqueCurve::new(vec![
+            (rpm(6000.0), Torque::from_nm(300.0)),
+            (rpm(1000.0), Torque::from_nm(200.0)),
+            (rpm(4000.0), Torque::from_nm(400.0)),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn curve_interpolates_between_points() {
+        let curve = curve();
+        assert!(close(curve.torque_at(rpm(1000.0)).nm(), 200.0));
+        assert!(close(curve.torque_at(rpm(2500.0)).nm(), 300.0));
+        assert!(close(curve.torque_at(rpm(5000.0)).nm(), 350.0));
+    }
+
+    #[test]
+    fn curve_holds_its_ends() {
+        let curve = curve();
+        assert!(close(curve.torque_at(rpm(0.0)).nm(), 200.0));
+        assert!(close(curve.torque_at(rpm(9000.0)).nm(), 300.0));
+    }
+
+    #[test]
+    fn curve_validation() {
+        assert!(TorqueCurve::new(vec![]).is_err());
+        assert!(TorqueCurve::new(vec![(rpm(f32::NAN), Torque::from_nm(1.0))]).is_err());
+        assert!(TorqueCurve::new(vec![(rpm(1000.0), Torque::from_nm(f32::INFINITY))]).is_err());
+        assert!(
+            TorqueCurve::new(vec![
+                (rpm(1000.0), Torque::from_nm(1.0)),
+                (rpm(1000.0), Torque::from_nm(2.0)),
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn dyno_sheets_from_csv() {
+        let sheet = "# Dyno run 3\nRPM, Torque (lb-ft), HP\n2000, 200, 76\n\n4000, 300, 228\n6000, 250, 286\n";
+        let curve = TorqueCurve::from_csv(sheet, UnitSystem::Metric).unwrap();
+        assert!(close(curve.torque_at(rpm(3000.0)).lb_ft(), 250.0));
+        assert!(close(curve.peak().lb_ft(), 300.0));
+
+        // No header: the torque is in the units asked for
+        let curve = TorqueCurve::from_csv("1000;100\n5000;400", UnitSystem::Metric).unwrap();
+        assert!(close(curve.peak().nm(), 400.0));
+
+        let error = |sheet| TorqueCurve::from_csv(sheet, UnitSystem::Imperial).unwrap_err();
+        assert_eq!(
+            error("rpm,torque\n1000,abc\n"),
+            "line 2: 'abc' is not a torque"
+        );
+        assert_eq!(error("1000,100\nlots,200"), "line 2: 'lots' is not an rpm");
+        assert_eq!(
+            error("1000,100\n2000"),
+            "line 2: expected rpm,torque but found '2000'"
+        );
+        assert_eq!(error("1000,100\n2000,-5"), "line 2: negative torque -5");
+        assert!(error("rpm,torque\n1000,100").contains("at least two"));
+        assert!(error("1000,100\n1000,200").contains("listed twice"));
+    }
+
+    #[test]
+    fn scaled_curve_peaks_at_the_given_torque() {
+        let curve = TorqueCurve::scaled_to(Torque::from_nm(500.0));
+        let peak = (1000..=7000)
+            .step_by(100)
+            .map(|r| curve.torque_at(rpm(r as f32)).nm())
+            .fold(0.0f32, f32::max);
+        assert!(close(peak, 500.0));
+    }
+
+    #[test]
+    fn shifts_follow_the_ratio() {
+        let mut engine = Engine::new(rpm(800.0), rpm(7000.0), curve());
+        engine.set_speed(rpm(6000.0));
+        engine.shift(2.0, 1.5);
+        assert!(close(engine.speed().rpm(), 4500.0));
+        engine.shift(1.5, 2.0);
+        assert!(close(engine.speed().rpm(), 6000.0));
+    }
+
+    #[test]
+    fn over_revving_costs_torque_for_good() {
+        let mut engine = Engine::new(rpm(800.0), rpm(7000.0), curve());
+        engine.set_speed(rpm(4000.0));
+        assert_eq!(engine.over_rev(rpm(6900.0)), 0.0);
+        assert!(close(engine.torque().nm(), 400.0));
+
+        let lost = engine.over_rev(rpm(8400.0));
+        assert!(close(lost, 0.2 * OVER_REV_DAMAGE));
+        assert!(close(engine.torque().nm(), 400.0 * (1.0 - lost)));
+
+        // Never the whole engine
+        engine.over_rev(rpm(70000.0));
+        assert!(close(engine.damage(), MAX_DAMAGE));
+        assert_eq!(engine.over_rev(rpm(f32::NAN)), 0.0);
+    }
+
+    #[test]
+    fn speed_stays_between_idle_and_redline() {
+        let mut engine = Engine::new(rpm(800.0), rpm(7000.0), curve());
+        engine.set_speed(rpm(6000.0));
+        engine.shift(1.0, 3.0);
+        assert!(close(engine.speed().rpm(), 7000.0));
+        engine.shift(3.0, 0.1);
+        assert!(close(engine.speed().rpm(), 800.0));
+        engine.shift(0.0, 1.0);
+        assert!(close(engine.speed().rpm(), 800.0));
+    }
+}
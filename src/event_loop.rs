@@ -0,0 +1,455 @@
+// The gamepad-driven main loop, as tasks on a single-threaded tokio runtime:
+// one reads gamepad (and keyboard) events, one reads telemetry, and one owns
+// the session, turning their inputs into actions as they arrive and ticking
+// the haptics and the driving simulation at a fixed timestep. The readers
+// hand their inputs over a channel.
+
+use crate::bindings::{Bindings, Bound, Press};
+use crate::car::GearPosition;
+use crate::haptics::{GilrsHaptics, HapticController, HapticError};
+use crate::keyboard::Keyboard;
+use crate::pedals::Pedals;
+use crate::reload::{self, Reload};
+use crate::say;
+use crate::session::Session;
+use crate::shifter::HPattern;
+use crate::strict::{SessionErrors, StrictMode};
+use crate::suspend::{SUSPEND_THRESHOLD, SuspendDetector};
+use crate::telemetry::{TelemetryFrame, TelemetrySource};
+use gilrs::{Event, EventType};
+use std::cell::RefCell;
+use std::future;
+use std::io;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::time::{self, Interval, MissedTickBehavior};
+
+// Fixed timestep of the haptics and the simulation, and how often gilrs and
+// the telemetry sources are polled; neither can wake a task by itself
+pub const TICK: Duration = Duration::from_millis(10);
+
+// How often a dashboard is redrawn; nobody reads faster than this
+pub const RENDER_TICK: Duration = Duration::from_millis(50);
+
+// The driving simulation never jumps further than this in one tick
+pub(crate) const MAX_DRIVE_STEP: Duration = Duration::from_millis(100);
+
+/// Hands the pedal positions to the session.
+fn apply_pedals(session: &mut Session<GilrsHaptics>, pedals: &Pedals) {
+    session.set_pedals(
+        pedals.throttle().map(|pedal| pedal.travel()),
+        pedals.brake().map_or(0.0, |pedal| pedal.travel()),
+    );
+    if let Some(down) = pedals.clutch_down() {
+        session.set_clutch_down(down);
+    }
+}
+
+/// Runs `step` on the session and reports a gear change it made. True if a
+/// haptic error has to stop the session.
+pub(crate) fn step<H: HapticController>(
+    session: &mut Session<H>,
+    errors: &mut SessionErrors,
+    on_gear_change: &mut impl FnMut(GearPosition),
+    step: impl FnOnce(&mut Session<H>) -> Result<(), HapticError>,
+) -> bool {
+    let before = session.position();
+    let result = step(session);
+    if session.position() != before {
+        on_gear_change(session.position());
+    }
+    match result {
+        Ok(()) => false,
+        Err(e) => errors.record(format!("rumble failed: {}", e)),
+    }
+}
+
+/// Everything that drives the session. `bindings` turns gamepad input into
+/// actions and `pedals` follow their axes; an `h_pattern` shifter or a
+/// `telemetry` source moves the gearbox too. Without telemetry the pedals
+/// drive the simulated car. The `keyboard` stands in for a missing gamepad.
+pub struct Controls<'a> {
+    pub bindings: &'a mut Bindings,
+    pub pedals: &'a mut Pedals,
+    pub h_pattern: Option<&'a mut HPattern>,
+    pub telemetry: Option<&'a mut dyn TelemetrySource>,
+    pub keyboard: Option<&'a mut Keyboard>,
+}
+
+/// Draws the session somewhere, true to end it.
+pub type Render<'a> = dyn FnMut(&Session<GilrsHaptics>) -> bool + 'a;
+
+/// Runs until the exit input is pressed, `render` asks to quit or a
+/// `--strict=fail-fast` error stops the session. `on_gear_change` is called
+/// with the new gear after every shift. `render` is called every
+/// `RENDER_TICK`, apart from the input polling every `TICK`, and returns
+/// true to end the session. `reload` is checked every
+/// `reload::CHECK_EVERY` for a changed config. Fails only if the runtime
+/// can't start.
+pub fn run(
+    session: &mut Session<GilrsHaptics>,
+    errors: &mut SessionErrors,
+    controls: Controls,
+    on_gear_change: impl FnMut(GearPosition),
+    render: Option<&mut Render<'_>>,
+    reload: Option<&mut Reload<'_>>,
+) -> io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+    let Controls {
+        bindings,
+        pedals,
+        h_pattern,
+        telemetry,
+        keyboard,
+    } = controls;
+    apply_pedals(session, pedals);
+
+    let telemetry_name = telemetry.as_ref().map(|source| source.name().to_string());
+    let session = RefCell::new(session);
+    let (inputs, received) = mpsc::unbounded_channel();
+    let mut driver = Driver {
+        session: &session,
+        errors,
+        bindings,
+        pedals,
+        h_pattern,
+        telemetry_name,
+        telemetry_error: None,
+        suspend_detector: SuspendDetector::new(SUSPEND_THRESHOLD),
+        on_gear_change,
+        last_tick: Instant::now(),
+    };
+    runtime.block_on(async {
+        // The readers never finish; the session ending drops them
+        tokio::select! {
+            () = read_gamepads(&session, keyboard, inputs.clone()) => {}
+            () = read_telemetry(telemetry, inputs) => {}
+            () = driver.drive(received, render, reload) => {}
+        }
+    });
+    Ok(())
+}
+
+/// What the reader tasks hand to the session.
+enum Input {
+    Gamepad(Event),
+    Key(Bound),
+    KeyboardFailed(io::Error),
+    Telemetry(io::Result<TelemetryFrame>),
+}
+
+pub(crate) fn ticking(period: Duration) -> Interval {
+    let mut interval = time::interval(period);
+    // After a stall, carry on from now instead of catching up in a burst
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+/// Sends every gamepad event gilrs has queued, and every key pressed on the
+/// `keyboard`, once a tick.
+async fn read_gamepads(
+    session: &RefCell<&mut Session<GilrsHaptics>>,
+    mut keyboard: Option<&mut Keyboard>,
+    inputs: UnboundedSender<Input>,
+) {
+    let mut ticks = ticking(TICK);
+    loop {
+        ticks.tick().await;
+        let mut events = Vec::new();
+        {
+            let mut session = session.borrow_mut();
+            let gilrs = session.haptics_mut().gilrs_mut();
+            while let Some(event) = gilrs.next_event() {
+                events.push(Input::Gamepad(event));
+            }
+        }
+        if let Some(keyboard) = keyboard.as_deref_mut() {
+            loop {
+                match keyboard.pressed() {
+                    Ok(Some(bound)) => events.push(Input::Key(bound)),
+                    Ok(None) => break,
+                    Err(e) => {
+                        events.push(Input::KeyboardFailed(e));
+                        break;
+                    }
+                }
+            }
+        }
+        for event in events {
+            if inputs.send(event).is_err() {
+                return future::pending().await;
+            }
+        }
+    }
+}
+
+/// Sends every frame, or error, the telemetry `source` has once a tick.
+/// Without a source there is nothing to read.
+async fn read_telemetry(source: Option<&mut dyn TelemetrySource>, inputs: UnboundedSender<Input>) {
+    let Some(source) = source else {
+        return future::pending().await;
+    };
+    let mut ticks = ticking(TICK);
+    loop {
+        ticks.tick().await;
+        let frame = match source.poll() {
+            Ok(Some(frame)) => Ok(frame),
+            Ok(None) => continue,
+            Err(e) => Err(e),
+        };
+        if inputs.send(Input::Telemetry(frame)).is_err() {
+            return future::pending().await;
+        }
+    }
+}
+
+/// Owns the session: acts on inputs as they arrive and ticks it.
+struct Driver<'a, 's, F> {
+    session: &'a RefCell<&'s mut Session<GilrsHaptics>>,
+    errors: &'a mut SessionErrors,
+    bindings: &'a mut Bindings,
+    pedals: &'a mut Pedals,
+    h_pattern: Option<&'a mut HPattern>,
+    telemetry_name: Option<String>, // The game followed drives the car, not the pedals
+    telemetry_error: Option<String>, // Last one shown, to avoid repeats
+    suspend_detector: SuspendDetector,
+    on_gear_change: F,
+    last_tick: Instant,
+}
+
+impl<F: FnMut(GearPosition)> Driver<'_, '_, F> {
+    /// Returns once the session has to stop.
+    async fn drive(
+        &mut self,
+        mut received: UnboundedReceiver<Input>,
+        mut render: Option<&mut Render<'_>>,
+        mut reload: Option<&mut Reload<'_>>,
+    ) {
+        let mut ticks = ticking(TICK);
+        let mut frames = ticking(RENDER_TICK);
+        let mut checks = ticking(reload::CHECK_EVERY);
+        loop {
+            let stop = tokio::select! {
+                Some(input) = received.recv() => self.handle(input),
+                _ = ticks.tick() => self.tick(),
+                _ = frames.tick(), if render.is_some() => {
+                    render.as_deref_mut().is_some_and(|render| render(&self.session.borrow()))
+                }
+                _ = checks.tick(), if reload.is_some() => {
+                    if let Some(reload) = reload.as_deref_mut() {
+                        self.reload(reload);
+                    }
+                    false
+                }
+            };
+            if stop {
+                return;
+            }
+        }
+    }
+
+    /// Acts on one input. True if the session has to stop.
+    fn handle(&mut self, input: Input) -> bool {
+        let mut session = self.session.borrow_mut();
+        let session = &mut **session;
+        match input {
+            Input::Gamepad(Event {
+                id, event, time, ..
+            }) => {
+                if self.suspend_detector.is_stale(time) {
+                    return false;
+                }
+                match event {
+                    EventType::Connected => {
+                        let haptics = session.haptics_mut();
+                        if !haptics.connect(id) {
+                            return false;
+                        }
+                        say!(
+                            "\n🎮 Gamepad connected: {}",
+                            haptics.gilrs().gamepad(id).name()
+                        );
+                        // Its triggers and lightbar are on a new device node
+                        if let Err(e) = haptics.reacquire_playstation_pad() {
+                            say!("⚠️  PlayStation pad: {}", e);
+                        }
+                        step(session, self.errors, &mut self.on_gear_change, |session| {
+                            session.gamepad_connected(Instant::now())
+                        })
+                    }
+                    EventType::Disconnected => {
+                        let haptics = session.haptics_mut();
+                        if haptics.disconnect(id) {
+                            say!(
+                                "\n⚠️  Gamepad disconnected: {} ({} left)",
+                                haptics.gilrs().gamepad(id).name(),
+                                haptics.gamepads().len()
+                            );
+                            if haptics.gamepads().is_empty() {
+                                say!("   Shifting resumes when a gamepad connects");
+                            }
+                        }
+                        false
+                    }
+                    _ => {
+                        // Trigger pedals can be bound to something else as well
+                        if self.pedals.update(&event) {
+                            apply_pedals(session, self.pedals);
+                        }
+
+                        // The shifter is a device of its own, registered or not
+                        let moved = self
+                            .h_pattern
+                            .as_deref_mut()
+                            .and_then(|shifter| shifter.update(&event));
+                        if let Some(position) = moved {
+                            return step(
+                                session,
+                                self.errors,
+                                &mut self.on_gear_change,
+                                |session| session.select(position, Instant::now()),
+                            );
+                        }
+                        match self.bindings.resolve(&event) {
+                            Some(Press::Down(bound))
+                                if bound != Bound::Exit
+                                    && !session.haptics().gamepads().contains(&id) =>
+                            {
+                                false
+                            }
+                            Some(Press::Down(bound)) => self.bound(session, bound),
+                            Some(Press::Up(Bound::LaunchControl)) => {
+                                step(session, self.errors, &mut self.on_gear_change, |session| {
+                                    session.release_launch_control(Instant::now())
+                                })
+                            }
+                            Some(Press::Up(Bound::Starter)) => {
+                                session.release_starter();
+                                false
+                            }
+                            Some(Press::Up(_)) | None => false,
+                        }
+                    }
+                }
+            }
+            Input::Key(bound) => self.bound(session, bound),
+            Input::KeyboardFailed(e) => {
+                say!("\n❌ Keyboard: {}", e);
+                true
+            }
+            Input::Telemetry(Ok(frame)) => {
+                self.telemetry_error = None;
+                step(session, self.errors, &mut self.on_gear_change, |session| {
+                    session.sync(&frame, Instant::now())
+                })
+            }
+            Input::Telemetry(Err(e)) => {
+                let message = e.to_string();
+                if self.telemetry_error.as_ref() != Some(&message) {
+                    let name = self.telemetry_name.as_deref().unwrap_or_default();
+                    say!("\n⚠️  {} telemetry: {}", name, message);
+                    self.telemetry_error = Some(message);
+                }
+                false
+            }
+        }
+    }
+
+    /// Does what an input is bound to. True if the session has to stop.
+    fn bound(&mut self, session: &mut Session<GilrsHaptics>, bound: Bound) -> bool {
+        match bound {
+            Bound::Exit => {
+                say!("\n👋 Exiting...");
+                true
+            }
+            Bound::Action(action) => {
+                step(session, self.errors, &mut self.on_gear_change, |session| {
+                    session.handle(action, Instant::now())
+                })
+            }
+            Bound::LaunchControl => {
+                step(session, self.errors, &mut self.on_gear_change, |session| {
+                    session.engage_launch_control()
+                })
+            }
+            Bound::Starter => step(session, self.errors, &mut self.on_gear_change, |session| {
+                session.crank_starter(Instant::now())
+            }),
+        }
+    }
+
+    /// Takes on whatever changed in the config, if it did.
+    fn reload(&mut self, reload: &mut Reload<'_>) {
+        let mut session = self.session.borrow_mut();
+        let session = &mut **session;
+        let Some(mut tuning) = reload::check(reload, session) else {
+            return;
+        };
+        tuning.apply_to_session(session);
+        if let Some(calibrations) = tuning.calibrations {
+            session.haptics_mut().use_calibrations(calibrations);
+        }
+        if let Some(bindings) = tuning.bindings {
+            *self.bindings = bindings;
+        }
+        if let Some((pedals, refuse_clutchless)) = tuning.pedals {
+            *self.pedals = pedals;
+            if self.pedals.clutch().is_some() {
+                session.use_clutch(refuse_clutchless);
+            } else {
+                session.remove_clutch();
+            }
+            apply_pedals(session, self.pedals);
+        }
+        if let (Some(gates), Some(shifter)) = (tuning.h_pattern, self.h_pattern.as_deref_mut()) {
+            *shifter = gates;
+        }
+    }
+
+    /// One fixed timestep: catches up after a suspend, drives the simulated
+    /// car and ticks the haptics. True if the session has to stop.
+    fn tick(&mut self) -> bool {
+        let tick_start = Instant::now();
+        let elapsed = tick_start - self.last_tick;
+        self.last_tick = tick_start;
+        let mut session = self.session.borrow_mut();
+        let session = &mut **session;
+
+        if let Some(gap) = self.suspend_detector.tick(SystemTime::now()) {
+            say!(
+                "\n💤 System was asleep for {:.0}s, resynchronizing...",
+                gap.as_secs_f32()
+            );
+            session.stop_rumble();
+
+            // Pads may have gone away or come back as new devices while we slept
+            let haptics = session.haptics_mut();
+            haptics.resync();
+            if haptics.gamepads().is_empty() {
+                say!("⚠️  No gamepad connected after resume");
+            }
+            for &id in haptics.gamepads() {
+                say!("🎮 Using gamepad: {}", haptics.gilrs().gamepad(id).name());
+            }
+        }
+
+        if self.telemetry_name.is_none()
+            && step(session, self.errors, &mut self.on_gear_change, |session| {
+                session.drive(elapsed.min(MAX_DRIVE_STEP), Instant::now())
+            })
+        {
+            return true;
+        }
+
+        if !session.haptics().gamepads().is_empty()
+            && let Err(e) = session.poll(Instant::now())
+            && self.errors.record(format!("rumble failed: {}", e))
+        {
+            return true;
+        }
+
+        self.errors.mode() != StrictMode::Off && self.errors.check_tick(tick_start.elapsed())
+    }
+}
@@ -0,0 +1,150 @@
+// Entry points for the cargo-fuzz targets in fuzz/, with the `fuzzing`
+// feature. Each takes whatever bytes the fuzzer comes up with: an input may
+// be refused with an error but must never panic, and whatever is accepted
+// must rumble within range, every gear of every car it builds with its gear
+// feel, envelopes and gearbox.
+//
+//   cargo +nightly fuzz run config
+//   cargo +nightly fuzz run patterns
+
+use crate::bindings::{Binding, Bindings};
+use crate::car::{
+    Car, DEFAULT_FINAL_DRIVE, DEFAULT_GEAR_RATIOS, GearFeelTable, GearMotorMix, parse_gear_ratios,
+};
+use crate::config::Config;
+use crate::engine::TorqueCurve;
+use crate::haptics::{Envelopes, RumblePattern};
+use crate::headless;
+use crate::pedals::Pedals;
+use crate::shifter::HPattern;
+use crate::trainer::RpmWindow;
+use crate::units::{Power, Torque, UnitSystem};
+
+/// gear_changer.toml, see `config`.
+pub fn config(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(config) = Config::parse(text) else {
+        return;
+    };
+    let _ = Bindings::from_config(&config.bindings);
+    let _ = Pedals::from_config(&config.pedals);
+    for calibration in config.calibration.values() {
+        let _ = calibration.validate();
+    }
+    let envelopes = match config.envelopes.validate() {
+        Ok(()) => config.envelopes,
+        Err(_) => Envelopes::default(),
+    };
+    // The gear feel and envelopes are worth a look without any cars too
+    let cars = config
+        .cars
+        .values()
+        .filter_map(|preset| preset.build(config.units).ok())
+        .chain([stock_car()]);
+    for mut car in cars {
+        let _ = HPattern::from_config(&config.h_pattern, car.gear_count());
+        if let Ok(table) = GearFeelTable::from_config(&config.gear_feel, car.gear_count()) {
+            car.set_gear_feel(Some(table));
+        }
+        check_shifts(&mut car, &envelopes);
+    }
+}
+
+/// The shorthand typed on the command line and stdin: `--gears`,
+/// `--gear-mix`, the `--trainer` window, dyno sheets, bindings and headless
+/// commands.
+pub fn patterns(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(ratios) = parse_gear_ratios(text)
+        && let Ok(mut car) = Car::new(
+            Torque::from_lb_ft(300.0),
+            Power::from_hp(400.0),
+            ratios,
+            DEFAULT_FINAL_DRIVE,
+        )
+    {
+        check_shifts(&mut car, &Envelopes::default());
+    }
+    if let Ok(table) = GearMotorMix::parse(text, DEFAULT_GEAR_RATIOS.len() as u8) {
+        let mut car = stock_car();
+        car.set_motor_mix(Some(table));
+        check_shifts(&mut car, &Envelopes::default());
+    }
+    let _ = RpmWindow::parse(text);
+    for units in [UnitSystem::Imperial, UnitSystem::Metric] {
+        let _ = TorqueCurve::from_csv(text, units);
+    }
+    let _ = text.parse::<Binding>();
+    for line in text.lines() {
+        let _ = headless::parse_command(line);
+    }
+}
+
+fn stock_car() -> Car {
+    Car::new(
+        Torque::from_lb_ft(300.0),
+        Power::from_hp(400.0),
+        DEFAULT_GEAR_RATIOS.to_vec(),
+        DEFAULT_FINAL_DRIVE,
+    )
+    .expect("the stock car builds")
+}
+
+/// Shifts into every gear of `car`, up and down, and checks the rumble.
+fn check_shifts(car: &mut Car, envelopes: &Envelopes) {
+    for gear in 1..=car.gear_count() {
+        car.set_gear(gear);
+        for is_downshift in [false, true] {
+            let intensity = car.calculate_rumble_intensity(is_downshift);
+            assert!(
+                (0.0..=1.0).contains(&intensity),
+                "intensity {} in gear {}",
+                intensity,
+                gear
+            );
+            let duration_ms = car.rumble_duration_ms(is_downshift);
+            assert!(duration_ms > 0, "no rumble in gear {}", gear);
+            let command = car.shift_rumble_command(intensity, duration_ms);
+            let pattern = match car.transmission() {
+                Some(transmission) => transmission.shift_pattern(command),
+                None => RumblePattern::single(command),
+            };
+            assert_eq!(pattern.duration_ms(), duration_ms, "gear {}", gear);
+            let envelope = if is_downshift {
+                envelopes.downshift
+            } else {
+                envelopes.upshift
+            };
+            if let Some(envelope) = envelope {
+                let _ = RumblePattern::enveloped(command, &envelope);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn any_bytes_are_refused_or_rumble_in_range(data in any::<Vec<u8>>()) {
+            config(&data);
+            patterns(&data);
+        }
+
+        #[test]
+        fn look_alike_text_is_refused_or_rumbles_in_range(
+            text in "(\\[gear_feel\\.[0-9]\\]\n(intensity|duration) = -?[0-9.e]{1,6}\n){0,4}",
+            spec in "([0-9]{1,2}=[0-9.]{1,4}:[0-9.]{1,4},?){0,4}",
+        ) {
+            config(text.as_bytes());
+            patterns(spec.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,1151 @@
+// What the motors are asked to do, and the device-independent interface
+// that does it.
+
+use crate::calibration::Calibration;
+use crate::car::GearPosition;
+use crate::mixer::{MixMode, Mixer};
+use crate::playstation::{PlayStationPad, Rgb};
+use gilrs::ff::{
+    BaseEffect, BaseEffectType, Effect, EffectBuilder, Envelope, Error as FfError, Repeat, Replay,
+    Ticks,
+};
+use gilrs::{GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+// Gear query pulses: short/long lengths, the gap between them and the strength
+pub const QUERY_SHORT_MS: u32 = 150;
+pub const QUERY_LONG_MS: u32 = 450;
+pub const QUERY_GAP_MS: u32 = 200;
+pub const QUERY_MAGNITUDE: u16 = 40000;
+
+// Gear grind: rapid chatter on the strong motor
+pub const GRIND_MAGNITUDE: u16 = 52000;
+const GRIND_PULSE_MS: u32 = 20;
+const GRIND_GAP_MS: u32 = 15;
+const GRIND_PULSES: u32 = 10;
+
+// Downshift stages as (percent of the rumble's length, strong scale, weak
+// scale): a thunk, its decay, a beat of nothing and an aftershock
+const DOWNSHIFT_STAGES: [(u32, f32, f32); 4] = [
+    (40, 1.0, 1.0),
+    (30, 0.5, 0.6),
+    (10, 0.0, 0.0),
+    (20, 0.35, 0.25),
+];
+
+// Enveloped rumbles are sampled into stages this long
+pub const ENVELOPE_TICK_MS: u32 = 10;
+
+// While mixing, the motors are set this long ahead and refreshed halfway
+// through, so a late tick doesn't leave a gap
+const MIX_HOLD_MS: u32 = 40;
+
+/// Everything that was sent to the motors for one rumble.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RumbleCommand {
+    pub strong_magnitude: u16,
+    pub weak_magnitude: u16,
+    pub duration_ms: u32,
+}
+
+impl RumbleCommand {
+    /// Stretches the command in time by `factor` and scales the magnitudes
+    /// down by the same amount, so the total energy stays about the same.
+    pub fn stretched(&self, factor: f32) -> Self {
+        let factor = factor.max(1.0);
+        Self {
+            strong_magnitude: (self.strong_magnitude as f32 / factor) as u16,
+            weak_magnitude: (self.weak_magnitude as f32 / factor) as u16,
+            duration_ms: (self.duration_ms as f32 * factor) as u32,
+        }
+    }
+}
+
+/// A rumble in stages: keyframes played back to back, each holding its
+/// magnitudes for its duration. A stage with both magnitudes at 0 is a gap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RumblePattern {
+    stages: Vec<RumbleCommand>,
+    sampled_from: Option<(RumbleCommand, Adsr)>, // See `enveloped`
+}
+
+impl RumblePattern {
+    pub fn new(stages: Vec<RumbleCommand>) -> Self {
+        Self {
+            stages,
+            sampled_from: None,
+        }
+    }
+
+    /// A pattern of just `command`.
+    pub fn single(command: RumbleCommand) -> Self {
+        Self::new(vec![command])
+    }
+
+    /// The feel of a shift at `command`'s strength and length: one flat
+    /// thunk going up, thunk → decay → aftershock going down.
+    pub fn shift(command: RumbleCommand, is_downshift: bool) -> Self {
+        if !is_downshift {
+            return Self::single(command);
+        }
+        let mut left_ms = command.duration_ms;
+        let last = DOWNSHIFT_STAGES.len() - 1;
+        let stages = DOWNSHIFT_STAGES
+            .iter()
+            .enumerate()
+            .map(|(i, &(percent, strong, weak))| {
+                // The last stage takes whatever rounding left over
+                let duration_ms = if i == last {
+                    left_ms
+                } else {
+                    command.duration_ms * percent / 100
+                };
+                left_ms -= duration_ms;
+                RumbleCommand {
+                    strong_magnitude: (command.strong_magnitude as f32 * strong) as u16,
+                    weak_magnitude: (command.weak_magnitude as f32 * weak) as u16,
+                    duration_ms,
+                }
+            })
+            .collect();
+        Self::new(stages)
+    }
+
+    /// `command` shaped by `envelope`, sampled every ENVELOPE_TICK_MS so the
+    /// motors follow the ramps.
+    pub fn enveloped(command: RumbleCommand, envelope: &Adsr) -> Self {
+        let ticks = command.duration_ms.div_ceil(ENVELOPE_TICK_MS);
+        let stages = (0..ticks)
+            .map(|i| {
+                let start_ms = i * ENVELOPE_TICK_MS;
+                let duration_ms = ENVELOPE_TICK_MS.min(command.duration_ms - start_ms);
+                let middle_ms = start_ms as f32 + duration_ms as f32 / 2.0;
+                let level = envelope.level_at(middle_ms, command.duration_ms);
+                RumbleCommand {
+                    strong_magnitude: (command.strong_magnitude as f32 * level) as u16,
+                    weak_magnitude: (command.weak_magnitude as f32 * level) as u16,
+                    duration_ms,
+                }
+            })
+            .collect();
+        Self {
+            stages,
+            sampled_from: Some((command, *envelope)),
+        }
+    }
+
+    /// The command and envelope an `enveloped` pattern was sampled from,
+    /// for backends that can play the envelope themselves.
+    pub fn sampled_from(&self) -> Option<(RumbleCommand, Adsr)> {
+        self.sampled_from
+    }
+
+    pub fn stages(&self) -> &[RumbleCommand] {
+        &self.stages
+    }
+
+    pub fn duration_ms(&self) -> u32 {
+        self.stages.iter().map(|stage| stage.duration_ms).sum()
+    }
+
+    /// The strongest each motor gets over the whole length of the pattern.
+    pub fn envelope(&self) -> RumbleCommand {
+        RumbleCommand {
+            strong_magnitude: self
+                .stages
+                .iter()
+                .map(|s| s.strong_magnitude)
+                .max()
+                .unwrap_or(0),
+            weak_magnitude: self
+                .stages
+                .iter()
+                .map(|s| s.weak_magnitude)
+                .max()
+                .unwrap_or(0),
+            duration_ms: self.duration_ms(),
+        }
+    }
+
+    /// `lead` played first, then this pattern. Backends play the result
+    /// stage by stage, even if this pattern was sampled from an envelope.
+    pub fn preceded_by(&self, lead: &[RumbleCommand]) -> Self {
+        Self::new(lead.iter().chain(&self.stages).copied().collect())
+    }
+
+    /// Every stage, and the command an `enveloped` pattern was sampled from,
+    /// put through `f`.
+    pub fn map(&self, f: impl Fn(RumbleCommand) -> RumbleCommand) -> Self {
+        Self {
+            stages: self.stages.iter().map(|&stage| f(stage)).collect(),
+            sampled_from: self
+                .sampled_from
+                .map(|(command, envelope)| (f(command), envelope)),
+        }
+    }
+
+    /// Every stage stretched by `factor`, see [`RumbleCommand::stretched`].
+    pub fn stretched(&self, factor: f32) -> Self {
+        Self::new(self.stages.iter().map(|s| s.stretched(factor)).collect())
+    }
+}
+
+/// How a rumble's intensity rises and falls: up to full over the attack,
+/// down to the sustain level over the decay, held there, and down to
+/// nothing over the release at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Adsr {
+    #[serde(default)]
+    pub attack_ms: u32,
+    #[serde(default)]
+    pub decay_ms: u32,
+    pub sustain_level: f32, // Fraction of full, 0 to 1
+    #[serde(default)]
+    pub release_ms: u32,
+}
+
+impl Adsr {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.sustain_level) {
+            return Err(format!(
+                "sustain_level: {} is outside 0 to 1",
+                self.sustain_level
+            ));
+        }
+        Ok(())
+    }
+
+    /// Intensity `at_ms` into a rumble `duration_ms` long, 0 to 1. A rumble
+    /// too short for the whole envelope releases from wherever it got to.
+    pub fn level_at(&self, at_ms: f32, duration_ms: u32) -> f32 {
+        let (attack, decay) = (self.attack_ms as f32, self.decay_ms as f32);
+        let level = if at_ms < attack {
+            at_ms / attack
+        } else if at_ms < attack + decay {
+            1.0 - (1.0 - self.sustain_level) * (at_ms - attack) / decay
+        } else {
+            self.sustain_level
+        };
+        let left = duration_ms as f32 - at_ms;
+        let release = self.release_ms as f32;
+        if left < release {
+            level * (left / release).max(0.0)
+        } else {
+            level
+        }
+    }
+
+    /// The same shape as a gilrs envelope for a rumble `duration_ms` long,
+    /// if it has one: gilrs only ramps up from 0 and back down, with no
+    /// decay to a sustain level, and wants both ramps to fit.
+    pub fn driver_envelope(&self, duration_ms: u32) -> Option<Envelope> {
+        let (attack, fade) = (
+            Ticks::from_ms(self.attack_ms),
+            Ticks::from_ms(self.release_ms),
+        );
+        if self.sustain_level < 1.0 || attack + fade >= Ticks::from_ms(duration_ms) {
+            return None;
+        }
+        Some(Envelope {
+            attack_length: attack,
+            attack_level: 0.0,
+            fade_length: fade,
+            fade_level: 0.0,
+        })
+    }
+}
+
+/// Envelopes for the rumbles that can have one, from `[envelopes]` in
+/// gear_changer.toml. A rumble without one plays as it always did.
+///
+/// [envelopes.downshift]
+/// attack_ms = 20
+/// decay_ms = 60
+/// sustain_level = 0.5
+/// release_ms = 80
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Envelopes {
+    pub upshift: Option<Adsr>,
+    pub downshift: Option<Adsr>,
+    pub reverse: Option<Adsr>,
+    pub money_shift: Option<Adsr>,
+}
+
+impl Envelopes {
+    pub fn validate(&self) -> Result<(), String> {
+        let named = [
+            ("upshift", self.upshift),
+            ("downshift", self.downshift),
+            ("reverse", self.reverse),
+            ("money_shift", self.money_shift),
+        ];
+        for (name, envelope) in named {
+            if let Some(envelope) = envelope {
+                envelope
+                    .validate()
+                    .map_err(|e| format!("[envelopes.{}] {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One pulse of a multi-pulse rumble, relative to when the rumble starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse {
+    pub after_ms: u32,
+    pub duration_ms: u32,
+}
+
+/// Pulses that tell the driver which gear they are in by feel: N short
+/// pulses for gear N, long-short for neutral, long-long for reverse.
+pub fn gear_query_pulses(position: GearPosition) -> Vec<Pulse> {
+    let lengths = match position {
+        GearPosition::Gear(n) => vec![QUERY_SHORT_MS; n as usize],
+        GearPosition::Neutral => vec![QUERY_LONG_MS, QUERY_SHORT_MS],
+        GearPosition::Reverse => vec![QUERY_LONG_MS, QUERY_LONG_MS],
+    };
+
+    let mut after_ms = 0;
+    lengths
+        .into_iter()
+        .map(|duration_ms| {
+            let pulse = Pulse {
+                after_ms,
+                duration_ms,
+            };
+            after_ms += duration_ms + QUERY_GAP_MS;
+            pulse
+        })
+        .collect()
+}
+
+/// Pulses that feel like gears grinding: a gear that didn't go in.
+pub fn grind_pulses() -> Vec<Pulse> {
+    (0..GRIND_PULSES)
+        .map(|i| Pulse {
+            after_ms: i * (GRIND_PULSE_MS + GRIND_GAP_MS),
+            duration_ms: GRIND_PULSE_MS,
+        })
+        .collect()
+}
+
+/// Total length of a pulse train.
+pub fn pulses_length_ms(pulses: &[Pulse]) -> u32 {
+    pulses.last().map_or(0, |p| p.after_ms + p.duration_ms)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HapticError {
+    /// No device to play on.
+    NoDevice,
+    /// The backend refused or failed to play the effect.
+    Backend(String),
+}
+
+impl fmt::Display for HapticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HapticError::NoDevice => write!(f, "no haptic device"),
+            HapticError::Backend(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for HapticError {}
+
+impl From<FfError> for HapticError {
+    fn from(e: FfError) -> Self {
+        HapticError::Backend(e.to_string())
+    }
+}
+
+impl From<io::Error> for HapticError {
+    fn from(e: io::Error) -> Self {
+        HapticError::Backend(e.to_string())
+    }
+}
+
+/// Something that can rumble. Implementations return as soon as the effect
+/// is scheduled; playback itself runs in the background.
+pub trait HapticController {
+    /// Whether the device can play anything at all.
+    fn is_supported(&self) -> bool;
+
+    /// Plays `command` on both motors, replacing whatever was playing.
+    fn play(&mut self, command: RumbleCommand) -> Result<(), HapticError>;
+
+    /// Plays the stages of `pattern` back to back as one rumble, replacing
+    /// whatever was playing.
+    fn play_pattern(&mut self, pattern: &RumblePattern) -> Result<(), HapticError>;
+
+    /// Plays strong-motor `pulses` as one rumble, replacing whatever was playing.
+    fn play_pulses(&mut self, magnitude: u16, pulses: &[Pulse]) -> Result<(), HapticError>;
+
+    /// Plays `command` every `period_ms` until stopped or replaced.
+    fn play_repeating(&mut self, command: RumbleCommand, period_ms: u32)
+    -> Result<(), HapticError>;
+
+    /// Stops what `play_repeating` started, leaving anything else playing.
+    fn stop_repeating(&mut self) {
+        self.stop();
+    }
+
+    /// Silences the motors.
+    fn stop(&mut self);
+
+    /// Whether new rumbles blend with what is playing instead of replacing it.
+    fn mixes(&self) -> bool {
+        false
+    }
+
+    /// Called once per loop iteration for backends that play over time.
+    fn tick(&mut self, _now: Instant) -> Result<(), HapticError> {
+        Ok(())
+    }
+
+    /// Stiffens the throttle trigger, 0 free to 1 as stiff as it gets, on
+    /// devices with adaptive triggers.
+    fn set_throttle_resistance(&mut self, _stiffness: f32) -> Result<(), HapticError> {
+        Ok(())
+    }
+
+    /// A click in the triggers as a gear goes in, on devices with adaptive
+    /// triggers.
+    fn click_triggers(&mut self) -> Result<(), HapticError> {
+        Ok(())
+    }
+
+    /// Shows `colour`, or flashes a warning instead while `at_redline`, on
+    /// devices with a light.
+    fn set_lightbar(&mut self, _colour: Rgb, _at_redline: bool) -> Result<(), HapticError> {
+        Ok(())
+    }
+}
+
+/// Rumble through gilrs force feedback. Every rumble plays on all registered
+/// gamepads at once, scaled for each by its calibration; pads register as
+/// they connect, optionally only the ones the driver picked.
+pub struct GilrsHaptics {
+    gilrs: Gilrs,
+    gamepads: Vec<GamepadId>,
+    only: Option<Vec<usize>>, // Picked gamepad IDs, None for any pad
+    effects: Vec<Effect>,     // Kept alive until the next rumble replaces them
+    calibrations: BTreeMap<String, Calibration>, // By gamepad name
+    mixer: Option<Mixer>,     // None: every rumble replaces the last
+    mixed: (u16, u16),        // Strong and weak last sent by the mixer
+    refresh_at: Option<Instant>,
+    pad: Option<PlayStationPad>, // Triggers and lightbar, which gilrs can't reach
+    adaptive_triggers: bool,
+    lightbar: bool,
+}
+
+impl GilrsHaptics {
+    pub fn new(gilrs: Gilrs) -> Self {
+        Self {
+            gilrs,
+            gamepads: Vec::new(),
+            only: None,
+            effects: Vec::new(),
+            calibrations: BTreeMap::new(),
+            mixer: None,
+            mixed: (0, 0),
+            refresh_at: None,
+            pad: None,
+            adaptive_triggers: false,
+            lightbar: false,
+        }
+    }
+
+    /// Plays trigger resistance and clicks on a DualSense `pad` if
+    /// `adaptive_triggers`, and shows the gear on its lightbar if `lightbar`,
+    /// alongside the rumble.
+    pub fn use_playstation_pad(
+        &mut self,
+        pad: PlayStationPad,
+        adaptive_triggers: bool,
+        lightbar: bool,
+    ) {
+        self.pad = Some(pad);
+        self.adaptive_triggers = adaptive_triggers;
+        self.lightbar = lightbar;
+    }
+
+    /// Scales the motors of the pads named in `calibrations` from now on.
+    pub fn use_calibrations(&mut self, calibrations: BTreeMap<String, Calibration>) {
+        self.calibrations = calibrations;
+    }
+
+    /// Blends overlapping rumbles by `mode` from now on instead of letting
+    /// each one replace the last. The motors then follow the mix on `tick`.
+    pub fn mix(&mut self, mode: MixMode) {
+        self.stop();
+        self.mixer = Some(Mixer::new(mode));
+    }
+
+    /// How overlapping rumbles blend, `None` if each replaces the last.
+    pub fn mix_mode(&self) -> Option<MixMode> {
+        self.mixer.as_ref().map(Mixer::mode)
+    }
+
+    pub fn playstation_pad(&self) -> Option<&PlayStationPad> {
+        self.pad.as_ref()
+    }
+
+    /// Finds the PlayStation pad in use again after it reconnected, most
+    /// likely as a new hidraw device, keeping the triggers and lightbar
+    /// settings. Does nothing if none was in use.
+    pub fn reacquire_playstation_pad(&mut self) -> io::Result<()> {
+        if self.pad.is_some()
+            && let Some(pad) = PlayStationPad::find()?
+        {
+            self.pad = Some(pad);
+        }
+        Ok(())
+    }
+
+    pub fn gilrs(&self) -> &Gilrs {
+        &self.gilrs
+    }
+
+    pub fn gilrs_mut(&mut self) -> &mut Gilrs {
+        &mut self.gilrs
+    }
+
+    /// The registered gamepads, in the order they were registered.
+    pub fn gamepads(&self) -> &[GamepadId] {
+        &self.gamepads
+    }
+
+    /// Limits the session to the gamepads with these IDs (`None` for any
+    /// pad) and registers every matching one that is connected now.
+    pub fn use_gamepads(&mut self, only: Option<Vec<usize>>) {
+        self.only = only;
+        self.resync();
+    }
+
+    /// Registers a newly connected gamepad. False if it isn't wanted or
+    /// already registered. While no gamepad is registered any pad is taken,
+    /// picked or not, so shifting doesn't stay dead after the picked ones
+    /// went away.
+    pub fn connect(&mut self, id: GamepadId) -> bool {
+        let wanted = self.gamepads.is_empty()
+            || self
+                .only
+                .as_ref()
+                .is_none_or(|only| only.contains(&usize::from(id)));
+        if !wanted || self.gamepads.contains(&id) {
+            return false;
+        }
+        self.gamepads.push(id);
+        true
+    }
+
+    /// Forgets a gamepad that went away. False if it wasn't registered.
+    pub fn disconnect(&mut self, id: GamepadId) -> bool {
+        let before = self.gamepads.len();
+        self.gamepads.retain(|&registered| registered != id);
+        if self.gamepads.is_empty() {
+            self.stop();
+        }
+        self.gamepads.len() != before
+    }
+
+    /// Brings the registered gamepads in line with what is connected, e.g.
+    /// after the system slept.
+    pub fn resync(&mut self) {
+        let connected: Vec<GamepadId> = self.gilrs.gamepads().map(|(id, _)| id).collect();
+        for id in self.gamepads.clone() {
+            if !connected.contains(&id) {
+                self.disconnect(id);
+            }
+        }
+        for id in connected {
+            self.connect(id);
+        }
+    }
+
+    /// Registered gamepads that are connected and can rumble.
+    fn rumble_gamepads(&self) -> Result<Vec<GamepadId>, HapticError> {
+        let gamepads: Vec<GamepadId> = self
+            .gamepads
+            .iter()
+            .copied()
+            .filter(|&id| {
+                self.gilrs
+                    .connected_gamepad(id)
+                    .is_some_and(|gamepad| gamepad.is_ff_supported())
+            })
+            .collect();
+        if gamepads.is_empty() {
+            return Err(HapticError::NoDevice);
+        }
+        Ok(gamepads)
+    }
+
+    /// The gamepads that can rumble, grouped by their calibration so each
+    /// group can share an effect.
+    fn rumble_groups(&self) -> Result<Vec<(Calibration, Vec<GamepadId>)>, HapticError> {
+        let mut groups: Vec<(Calibration, Vec<GamepadId>)> = Vec::new();
+        for id in self.rumble_gamepads()? {
+            let gamepad = self.gilrs.gamepad(id);
+            let calibration = self
+                .calibrations
+                .get(gamepad.name())
+                .copied()
+                .unwrap_or_default();
+            match groups.iter_mut().find(|(c, _)| *c == calibration) {
+                Some((_, ids)) => ids.push(id),
+                None => groups.push((calibration, vec![id])),
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Starts an effect on each group of gamepads, with `start` given the
+    /// group, replacing whatever was playing once all have started.
+    fn play_on_groups(
+        &mut self,
+        mut start: impl FnMut(&mut Gilrs, &[GamepadId], Calibration) -> Result<Effect, HapticError>,
+    ) -> Result<(), HapticError> {
+        let mut effects = Vec::new();
+        for (calibration, gamepads) in self.rumble_groups()? {
+            effects.push(start(&mut self.gilrs, &gamepads, calibration)?);
+        }
+        self.effects = effects;
+        Ok(())
+    }
+}
+
+/// `pattern` on `gamepads`: an envelope gilrs can play goes to it whole,
+/// anything else as stages. A device that takes neither still gets the peak.
+fn start_pattern(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    pattern: &RumblePattern,
+) -> Result<Effect, HapticError> {
+    let driver_envelope = pattern
+        .sampled_from()
+        .and_then(|(command, adsr)| Some((command, adsr.driver_envelope(command.duration_ms)?)));
+    let played = match driver_envelope {
+        Some((command, envelope)) => set_rumble_enveloped(gilrs, gamepads, command, envelope),
+        None => set_rumble_pattern(gilrs, gamepads, pattern),
+    };
+    match played {
+        Ok(effect) => Ok(effect),
+        Err(_) => {
+            let peak = pattern.envelope();
+            Ok(set_rumble(
+                gilrs,
+                gamepads,
+                peak.strong_magnitude,
+                peak.weak_magnitude,
+                peak.duration_ms,
+            )?)
+        }
+    }
+}
+
+impl HapticController for GilrsHaptics {
+    fn is_supported(&self) -> bool {
+        self.rumble_gamepads().is_ok()
+    }
+
+    fn play(&mut self, command: RumbleCommand) -> Result<(), HapticError> {
+        self.play_pattern(&RumblePattern::single(command))
+    }
+
+    fn play_pattern(&mut self, pattern: &RumblePattern) -> Result<(), HapticError> {
+        self.rumble_gamepads()?;
+        if let Some(mixer) = &mut self.mixer {
+            let now = Instant::now();
+            mixer.add(pattern.clone(), now);
+            return self.tick(now);
+        }
+        self.play_on_groups(|gilrs, gamepads, calibration| {
+            start_pattern(gilrs, gamepads, &calibration.pattern(pattern))
+        })
+    }
+
+    fn play_pulses(&mut self, magnitude: u16, pulses: &[Pulse]) -> Result<(), HapticError> {
+        self.rumble_gamepads()?;
+        if let Some(mixer) = &mut self.mixer {
+            let now = Instant::now();
+            mixer.add_pulses(magnitude, pulses, now);
+            return self.tick(now);
+        }
+        self.play_on_groups(|gilrs, gamepads, calibration| {
+            let magnitude = calibration.strong(magnitude);
+            Ok(set_rumble_pulses(gilrs, gamepads, magnitude, pulses)?)
+        })
+    }
+
+    fn play_repeating(
+        &mut self,
+        command: RumbleCommand,
+        period_ms: u32,
+    ) -> Result<(), HapticError> {
+        self.rumble_gamepads()?;
+        if let Some(mixer) = &mut self.mixer {
+            let now = Instant::now();
+            mixer.add_repeating(command, period_ms, now);
+            return self.tick(now);
+        }
+        self.play_on_groups(|gilrs, gamepads, calibration| {
+            let command = calibration.command(command);
+            Ok(set_rumble_repeating(gilrs, gamepads, command, period_ms)?)
+        })
+    }
+
+    fn stop_repeating(&mut self) {
+        match &mut self.mixer {
+            Some(mixer) => mixer.stop_repeating(),
+            None => self.stop(),
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(mixer) = &mut self.mixer {
+            mixer.clear();
+        }
+        self.mixed = (0, 0);
+        self.refresh_at = None;
+        for effect in self.effects.drain(..) {
+            let _ = effect.stop();
+        }
+    }
+
+    fn mixes(&self) -> bool {
+        self.mixer.is_some()
+    }
+
+    fn set_throttle_resistance(&mut self, stiffness: f32) -> Result<(), HapticError> {
+        if let Some(pad) = &mut self.pad
+            && self.adaptive_triggers
+        {
+            pad.set_throttle_resistance(stiffness)?;
+        }
+        Ok(())
+    }
+
+    fn click_triggers(&mut self) -> Result<(), HapticError> {
+        if let Some(pad) = &mut self.pad
+            && self.adaptive_triggers
+        {
+            pad.click(Instant::now())?;
+        }
+        Ok(())
+    }
+
+    fn set_lightbar(&mut self, colour: Rgb, at_redline: bool) -> Result<(), HapticError> {
+        if let Some(pad) = &mut self.pad
+            && self.lightbar
+        {
+            pad.set_lightbar(colour, at_redline, Instant::now())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the motors to the mix when it changed or is about to run out,
+    /// and lets go of a trigger click.
+    fn tick(&mut self, now: Instant) -> Result<(), HapticError> {
+        if let Some(pad) = &mut self.pad {
+            pad.tick(now)?;
+        }
+        let Some(mixer) = &mut self.mixer else {
+            return Ok(());
+        };
+        let mixed = mixer.magnitudes_at(now);
+        let due = self.refresh_at.is_some_and(|at| now >= at);
+        if mixed == self.mixed && !due {
+            return Ok(());
+        }
+        self.mixed = mixed;
+        if mixed == (0, 0) {
+            self.refresh_at = None;
+            for effect in self.effects.drain(..) {
+                let _ = effect.stop();
+            }
+            return Ok(());
+        }
+        self.play_on_groups(|gilrs, gamepads, calibration| {
+            let (strong, weak) = (calibration.strong(mixed.0), calibration.weak(mixed.1));
+            Ok(set_rumble(gilrs, gamepads, strong, weak, MIX_HOLD_MS)?)
+        })?;
+        self.refresh_at = Some(now + Duration::from_millis(MIX_HOLD_MS as u64 / 2));
+        Ok(())
+    }
+}
+
+/// Plays a one-shot rumble on both motors of every gamepad in `gamepads`.
+/// The returned effect stops as soon as it is dropped, so the caller has to
+/// keep it around.
+pub fn set_rumble(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    strong_magnitude: u16,
+    weak_magnitude: u16,
+    duration_ms: u32,
+) -> Result<Effect, FfError> {
+    let command = RumbleCommand {
+        strong_magnitude,
+        weak_magnitude,
+        duration_ms,
+    };
+    set_rumble_enveloped(gilrs, gamepads, command, Envelope::default())
+}
+
+/// Plays `command` on both motors of every gamepad in `gamepads`, ramped in
+/// and out by `envelope` as gilrs plays it.
+pub fn set_rumble_enveloped(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    command: RumbleCommand,
+    envelope: Envelope,
+) -> Result<Effect, FfError> {
+    let play_for = Ticks::from_ms(command.duration_ms);
+    let scheduling = Replay {
+        play_for,
+        ..Default::default()
+    };
+
+    let effect = EffectBuilder::new()
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Strong {
+                magnitude: command.strong_magnitude,
+            },
+            scheduling,
+            envelope,
+        })
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Weak {
+                magnitude: command.weak_magnitude,
+            },
+            scheduling,
+            envelope,
+        })
+        .repeat(Repeat::For(play_for))
+        .gamepads(gamepads)
+        .finish(gilrs)?;
+    effect.play()?;
+
+    Ok(effect)
+}
+
+/// Plays the stages of `pattern` on both motors of every gamepad in
+/// `gamepads` as a single effect, each stage starting when the one before
+/// it ends.
+pub fn set_rumble_pattern(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    pattern: &RumblePattern,
+) -> Result<Effect, FfError> {
+    let total_ms = pattern.duration_ms();
+
+    let mut builder = EffectBuilder::new();
+    let mut after_ms = 0;
+    for stage in pattern.stages() {
+        let scheduling = Replay {
+            after: Ticks::from_ms(after_ms),
+            play_for: Ticks::from_ms(stage.duration_ms),
+            // Long enough that no stage repeats within the effect
+            with_delay: Ticks::from_ms(total_ms),
+        };
+        builder
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: stage.strong_magnitude,
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: stage.weak_magnitude,
+                },
+                scheduling,
+                ..Default::default()
+            });
+        after_ms += stage.duration_ms;
+    }
+
+    let effect = builder
+        .repeat(Repeat::For(Ticks::from_ms(total_ms)))
+        .gamepads(gamepads)
+        .finish(gilrs)?;
+    effect.play()?;
+
+    Ok(effect)
+}
+
+/// Plays a series of strong-motor pulses as a single effect, so the driver
+/// schedules them and the main loop never has to wait.
+pub fn set_rumble_pulses(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    magnitude: u16,
+    pulses: &[Pulse],
+) -> Result<Effect, FfError> {
+    let total_ms = pulses_length_ms(pulses);
+
+    let mut builder = EffectBuilder::new();
+    for pulse in pulses {
+        builder.add_effect(BaseEffect {
+            kind: BaseEffectType::Strong { magnitude },
+            scheduling: Replay {
+                after: Ticks::from_ms(pulse.after_ms),
+                play_for: Ticks::from_ms(pulse.duration_ms),
+                // Long enough that no pulse repeats within the effect
+                with_delay: Ticks::from_ms(total_ms),
+            },
+            ..Default::default()
+        });
+    }
+
+    let effect = builder
+        .repeat(Repeat::For(Ticks::from_ms(total_ms)))
+        .gamepads(gamepads)
+        .finish(gilrs)?;
+    effect.play()?;
+
+    Ok(effect)
+}
+
+/// Plays `command` on every gamepad in `gamepads` once per `period_ms`,
+/// over and over, until the returned effect is stopped or dropped.
+pub fn set_rumble_repeating(
+    gilrs: &mut Gilrs,
+    gamepads: &[GamepadId],
+    command: RumbleCommand,
+    period_ms: u32,
+) -> Result<Effect, FfError> {
+    let scheduling = Replay {
+        play_for: Ticks::from_ms(command.duration_ms),
+        with_delay: Ticks::from_ms(period_ms.saturating_sub(command.duration_ms)),
+        ..Default::default()
+    };
+
+    let effect = EffectBuilder::new()
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Strong {
+                magnitude: command.strong_magnitude,
+            },
+            scheduling,
+            ..Default::default()
+        })
+        .add_effect(BaseEffect {
+            kind: BaseEffectType::Weak {
+                magnitude: command.weak_magnitude,
+            },
+            scheduling,
+            ..Default::default()
+        })
+        .repeat(Repeat::Infinitely)
+        .gamepads(gamepads)
+        .finish(gilrs)?;
+    effect.play()?;
+
+    Ok(effect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn slowmo_stretches_time_and_scales_magnitude() {
+        let original = RumbleCommand {
+            strong_magnitude: 40000,
+            weak_magnitude: 28000,
+            duration_ms: 200,
+        };
+        let replay = original.stretched(4.0);
+        assert_eq!(replay.duration_ms, 800);
+        assert_eq!(replay.strong_magnitude, 10000);
+        assert_eq!(replay.weak_magnitude, 7000);
+    }
+
+    #[test]
+    fn downshift_thunks_decays_and_echoes() {
+        let command = RumbleCommand {
+            strong_magnitude: 40000,
+            weak_magnitude: 28000,
+            duration_ms: 199,
+        };
+        assert_eq!(
+            RumblePattern::shift(command, false),
+            RumblePattern::single(command)
+        );
+
+        let pattern = RumblePattern::shift(command, true);
+        let stages = pattern.stages();
+        assert_eq!(stages.len(), DOWNSHIFT_STAGES.len());
+        assert_eq!(stages[0].strong_magnitude, 40000);
+        assert!(stages[1].strong_magnitude < stages[0].strong_magnitude);
+        assert_eq!(stages[2].strong_magnitude, 0);
+        assert!(stages[3].strong_magnitude > 0);
+        assert_eq!(pattern.duration_ms(), 199);
+        assert_eq!(pattern.envelope(), command);
+    }
+
+    #[test]
+    fn envelope_ramps_in_holds_and_releases() {
+        let envelope = Adsr {
+            attack_ms: 20,
+            decay_ms: 20,
+            sustain_level: 0.5,
+            release_ms: 40,
+        };
+        assert_eq!(envelope.level_at(0.0, 200), 0.0);
+        assert_eq!(envelope.level_at(10.0, 200), 0.5);
+        assert_eq!(envelope.level_at(20.0, 200), 1.0);
+        assert_eq!(envelope.level_at(30.0, 200), 0.75);
+        assert_eq!(envelope.level_at(100.0, 200), 0.5);
+        assert_eq!(envelope.level_at(180.0, 200), 0.25);
+        assert_eq!(envelope.level_at(200.0, 200), 0.0);
+
+        let command = RumbleCommand {
+            strong_magnitude: 40000,
+            weak_magnitude: 20000,
+            duration_ms: 205,
+        };
+        let pattern = RumblePattern::enveloped(command, &envelope);
+        assert_eq!(pattern.stages().len(), 21);
+        assert_eq!(pattern.duration_ms(), 205);
+        assert_eq!(pattern.stages()[20].duration_ms, 5);
+        assert_eq!(pattern.stages()[0].strong_magnitude, 10000);
+        assert_eq!(pattern.stages()[10].strong_magnitude, 20000);
+        assert!(pattern.stages()[20].strong_magnitude < 2000);
+    }
+
+    #[test]
+    fn attack_release_envelopes_go_to_the_driver() {
+        let envelope = Adsr {
+            attack_ms: 50,
+            decay_ms: 0,
+            sustain_level: 1.0,
+            release_ms: 100,
+        };
+        let driver = envelope.driver_envelope(400).unwrap();
+        assert_eq!(driver.attack_length, Ticks::from_ms(50));
+        assert_eq!(driver.fade_length, Ticks::from_ms(100));
+        assert_eq!(driver.attack_level, 0.0);
+        // The ramps don't fit
+        assert_eq!(envelope.driver_envelope(150), None);
+        // No sustain level in gilrs
+        let decaying = Adsr {
+            sustain_level: 0.5,
+            ..envelope
+        };
+        assert_eq!(decaying.driver_envelope(400), None);
+
+        let command = RumbleCommand {
+            strong_magnitude: 40000,
+            weak_magnitude: 20000,
+            duration_ms: 400,
+        };
+        let pattern = RumblePattern::enveloped(command, &envelope);
+        assert_eq!(pattern.sampled_from(), Some((command, envelope)));
+        assert_eq!(pattern.stretched(2.0).sampled_from(), None);
+    }
+
+    #[test]
+    fn envelope_validation() {
+        let config = |text: &str| -> Result<(), String> {
+            let envelopes: Envelopes = toml::from_str(text).map_err(|e| e.to_string())?;
+            envelopes.validate()
+        };
+        assert!(config("[downshift]\nsustain_level = 0.5\nrelease_ms = 80").is_ok());
+        assert!(config("[downshift]\nsustain_level = 1.5").is_err());
+        assert!(config("[downshift]\nattack_ms = 10").is_err());
+        assert!(config("[horn]\nsustain_level = 1").is_err());
+    }
+
+    fn pulse_lengths(position: GearPosition) -> Vec<u32> {
+        gear_query_pulses(position)
+            .iter()
+            .map(|p| p.duration_ms)
+            .collect()
+    }
+
+    #[test]
+    fn gear_query_counts_short_pulses() {
+        assert_eq!(pulse_lengths(GearPosition::Gear(1)), vec![QUERY_SHORT_MS]);
+        assert_eq!(
+            pulse_lengths(GearPosition::Gear(6)),
+            vec![QUERY_SHORT_MS; 6]
+        );
+        assert_eq!(
+            pulse_lengths(GearPosition::Gear(10)),
+            vec![QUERY_SHORT_MS; 10]
+        );
+    }
+
+    #[test]
+    fn gear_query_neutral_and_reverse_signatures() {
+        assert_eq!(
+            pulse_lengths(GearPosition::Neutral),
+            vec![QUERY_LONG_MS, QUERY_SHORT_MS]
+        );
+        assert_eq!(
+            pulse_lengths(GearPosition::Reverse),
+            vec![QUERY_LONG_MS, QUERY_LONG_MS]
+        );
+    }
+
+    #[test]
+    fn gear_query_pulses_are_spaced_apart() {
+        let pulses = gear_query_pulses(GearPosition::Gear(10));
+        assert_eq!(pulses[0].after_ms, 0);
+        for pair in pulses.windows(2) {
+            assert_eq!(
+                pair[1].after_ms,
+                pair[0].after_ms + pair[0].duration_ms + QUERY_GAP_MS
+            );
+        }
+        assert_eq!(
+            pulses_length_ms(&pulses),
+            10 * QUERY_SHORT_MS + 9 * QUERY_GAP_MS
+        );
+    }
+
+    #[test]
+    fn grind_is_rapid_chatter() {
+        let pulses = grind_pulses();
+        assert_eq!(pulses.len(), GRIND_PULSES as usize);
+        for pair in pulses.windows(2) {
+            assert_eq!(
+                pair[1].after_ms - pair[0].after_ms,
+                GRIND_PULSE_MS + GRIND_GAP_MS
+            );
+        }
+        // Over well before a gear query could be mistaken for it
+        assert!(pulses_length_ms(&pulses) < QUERY_LONG_MS);
+    }
+
+    proptest! {
+        #[test]
+        fn slowmo_keeps_energy_similar(
+            strong in 0u16..=u16::MAX,
+            duration_ms in 1u32..1000,
+            factor in 1.0f32..10.0,
+        ) {
+            let original = RumbleCommand { strong_magnitude: strong, weak_magnitude: strong, duration_ms };
+            let replay = original.stretched(factor);
+            let before = strong as f64 * duration_ms as f64;
+            let after = replay.strong_magnitude as f64 * replay.duration_ms as f64;
+            prop_assert!(replay.duration_ms >= duration_ms);
+            prop_assert!(replay.strong_magnitude <= strong);
+            // Only integer truncation of either side may lose energy
+            let rounding = strong as f64 + duration_ms as f64 * factor as f64;
+            prop_assert!((before - after).abs() <= rounding);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+// `--headless`: no controller at all. The driver's actions come from stdin,
+// one per line, or the gearbox follows a game's telemetry, and every rumble
+// is logged with its magnitudes and durations instead of played. For CI, and
+// for debugging telemetry parsers on a machine without a gamepad:
+//
+//   printf 'throttle 0.8\nupshift\nquery_gear\n' | gear_changer run --headless
+//
+// A held input is let go of with `off`, e.g. `starter off`. `profile track`
+// switches to a saved profile and `save_profile track` saves one by name.
+
+use crate::bindings::{BINDABLE, Bound};
+use crate::event_loop::{MAX_DRIVE_STEP, TICK, step, ticking};
+use crate::haptics::{HapticController, HapticError, Pulse, RumbleCommand, RumblePattern};
+use crate::reload::{self, Reload};
+use crate::say;
+use crate::session::{Action, Session};
+use crate::strict::{SessionErrors, StrictMode};
+use crate::telemetry::TelemetrySource;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Logs every rumble it is asked for instead of playing it.
+#[derive(Debug, Default)]
+pub struct LogHaptics;
+
+impl HapticController for LogHaptics {
+    fn is_supported(&self) -> bool {
+        true
+    }
+
+    fn play(&mut self, command: RumbleCommand) -> Result<(), HapticError> {
+        say!("   📳 would rumble {}", describe(command));
+        Ok(())
+    }
+
+    fn play_pattern(&mut self, pattern: &RumblePattern) -> Result<(), HapticError> {
+        say!(
+            "   📳 would rumble {} stage(s), {} ms:",
+            pattern.stages().len(),
+            pattern.duration_ms()
+        );
+        for &stage in pattern.stages() {
+            say!("      {}", describe(stage));
+        }
+        Ok(())
+    }
+
+    fn play_pulses(&mut self, magnitude: u16, pulses: &[Pulse]) -> Result<(), HapticError> {
+        say!(
+            "   📳 would pulse strong {}: {}",
+            magnitude,
+            describe_pulses(pulses)
+        );
+        Ok(())
+    }
+
+    fn play_repeating(
+        &mut self,
+        command: RumbleCommand,
+        period_ms: u32,
+    ) -> Result<(), HapticError> {
+        say!(
+            "   📳 would repeat every {} ms: {}",
+            period_ms,
+            describe(command)
+        );
+        Ok(())
+    }
+
+    fn stop_repeating(&mut self) {
+        say!("   📳 would stop repeating");
+    }
+
+    fn stop(&mut self) {
+        say!("   📳 would stop");
+    }
+}
+
+fn describe(command: RumbleCommand) -> String {
+    format!(
+        "strong {} / weak {} for {} ms",
+        command.strong_magnitude, command.weak_magnitude, command.duration_ms
+    )
+}
+
+fn describe_pulses(pulses: &[Pulse]) -> String {
+    pulses
+        .iter()
+        .map(|pulse| format!("{} ms at +{} ms", pulse.duration_ms, pulse.after_ms))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One line of stdin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Anything a gamepad input can be bound to, by its `[bindings]` key.
+    Bound(Bound),
+    /// Letting go of a held input, e.g. `launch_control off`.
+    Release(Bound),
+    Throttle(f32),
+    Brake(f32),
+    /// `gear 3`: straight into a gear, see `ShiftMode`.
+    Gear(u8),
+    /// `profile track`: switch to a saved profile, see `profiles`.
+    Profile(String),
+    /// `save_profile track`: save the car and rumble settings by name.
+    SaveProfile(String),
+}
+
+/// Reads a line of stdin. Blank lines and `#` comments are `None`.
+pub fn parse_command(line: &str) -> Result<Option<Command>, String> {
+    let line = line.split('#').next().unwrap_or_default().trim();
+    let mut words = line.split_whitespace();
+    let Some(word) = words.next() else {
+        return Ok(None);
+    };
+    let command = match word {
+        "throttle" | "brake" => {
+            let travel: f32 = words
+                .next()
+                .and_then(|travel| travel.parse().ok())
+                .filter(|travel| (0.0..=1.0).contains(travel))
+                .ok_or_else(|| format!("{} needs a travel from 0 to 1", word))?;
+            if word == "throttle" {
+                Command::Throttle(travel)
+            } else {
+                Command::Brake(travel)
+            }
+        }
+        "gear" => words
+            .next()
+            .and_then(|gear| gear.parse().ok())
+            .map(Command::Gear)
+            .ok_or_else(|| "gear needs a gear number".to_string())?,
+        "profile" => words
+            .next()
+            .map(|name| Command::Profile(name.to_string()))
+            .ok_or_else(|| "profile needs a profile name".to_string())?,
+        // Without a name it saves to the profile in use, like the binding
+        "save_profile" if words.clone().next().is_some() => {
+            Command::SaveProfile(words.next().unwrap_or_default().to_string())
+        }
+        _ => {
+            let (bound, _) = BINDABLE
+                .iter()
+                .find(|(_, name)| *name == word)
+                .ok_or_else(|| format!("unknown command '{}'", word))?;
+            if bound.is_held() && words.clone().next() == Some("off") {
+                words.next();
+                Command::Release(*bound)
+            } else {
+                Command::Bound(*bound)
+            }
+        }
+    };
+    match words.next() {
+        Some(extra) => Err(format!("unexpected '{}' after {}", extra, word)),
+        None => Ok(Some(command)),
+    }
+}
+
+/// Runs until `exit`, until stdin runs out and the last rumble has played
+/// (unless following a game's telemetry), or until a `--strict=fail-fast`
+/// error stops the session. A changed config is taken on through `reload`.
+/// Fails only if the runtime can't start.
+pub fn run(
+    session: &mut Session<LogHaptics>,
+    errors: &mut SessionErrors,
+    mut telemetry: Option<&mut dyn TelemetrySource>,
+    mut reload: Option<&mut Reload<'_>>,
+) -> io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()?;
+
+    // Stdin only blocks, so it gets a thread of its own
+    let (inputs, mut received) = mpsc::unbounded_channel();
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if inputs.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    runtime.block_on(async {
+        let mut ticks = ticking(TICK);
+        let mut checks = ticking(reload::CHECK_EVERY);
+        let mut last_tick = Instant::now();
+        let mut reading = true;
+        let following_telemetry = telemetry.is_some();
+        let mut telemetry_error = None; // Last one shown, to avoid repeats
+        loop {
+            let stop = tokio::select! {
+                line = received.recv(), if reading => match line {
+                    Some(line) => handle_line(session, errors, &line),
+                    None => {
+                        reading = false;
+                        false
+                    }
+                },
+                _ = ticks.tick() => {
+                    let tick_start = Instant::now();
+                    let elapsed = tick_start - last_tick;
+                    last_tick = tick_start;
+                    tick(session, errors, telemetry.as_deref_mut(), &mut telemetry_error, elapsed)
+                        || (errors.mode() != StrictMode::Off
+                            && errors.check_tick(tick_start.elapsed()))
+                        // A script that ran out ends once its last rumble
+                        // has played; a game can keep going
+                        || (!reading && !following_telemetry && !session.is_rumbling(Instant::now()))
+                }
+                _ = checks.tick(), if reload.is_some() => {
+                    let tuning = reload
+                        .as_deref_mut()
+                        .and_then(|reload| reload::check(reload, session));
+                    if let Some(mut tuning) = tuning {
+                        tuning.apply_to_session(session);
+                    }
+                    false
+                }
+            };
+            if stop {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Acts on a line of stdin. True if the session has to stop.
+fn handle_line(session: &mut Session<LogHaptics>, errors: &mut SessionErrors, line: &str) -> bool {
+    let command = match parse_command(line) {
+        Ok(Some(command)) => command,
+        Ok(None) => return false,
+        Err(e) => {
+            say!("⚠️  stdin: {}", e);
+            return false;
+        }
+    };
+    match command {
+        Command::Bound(Bound::Exit) => {
+            say!("\n👋 Exiting...");
+            true
+        }
+        Command::Bound(Bound::Action(action)) => step(session, errors, &mut |_| {}, |session| {
+            session.handle(action, Instant::now())
+        }),
+        Command::Bound(Bound::LaunchControl) => step(session, errors, &mut |_| {}, |session| {
+            session.engage_launch_control()
+        }),
+        Command::Bound(Bound::Starter) => step(session, errors, &mut |_| {}, |session| {
+            session.crank_starter(Instant::now())
+        }),
+        Command::Release(Bound::LaunchControl) => step(session, errors, &mut |_| {}, |session| {
+            session.release_launch_control(Instant::now())
+        }),
+        Command::Release(Bound::Starter) => {
+            session.release_starter();
+            false
+        }
+        Command::Release(_) => false,
+        Command::Gear(gear) => step(session, errors, &mut |_| {}, |session| {
+            session.handle(Action::SelectGear(gear), Instant::now())
+        }),
+        Command::Profile(name) => step(session, errors, &mut |_| {}, |session| {
+            session.switch_profile(&name)
+        }),
+        Command::SaveProfile(name) => {
+            session.save_profile(Some(&name));
+            false
+        }
+        Command::Throttle(travel) => {
+            let brake = session.brake();
+            session.set_pedals(Some(travel), brake);
+            false
+        }
+        Command::Brake(travel) => {
+            let throttle = session.throttle();
+            session.set_pedals(throttle, travel);
+            false
+        }
+    }
+}
+
+/// Follows the game or drives the simulated car for one tick. True if the
+/// session has to stop.
+fn tick(
+    session: &mut Session<LogHaptics>,
+    errors: &mut SessionErrors,
+    telemetry: Option<&mut (dyn TelemetrySource + '_)>,
+    telemetry_error: &mut Option<String>,
+    elapsed: Duration,
+) -> bool {
+    let stepped = match telemetry {
+        Some(source) => match source.poll() {
+            Ok(Some(frame)) => {
+                *telemetry_error = None;
+                step(session, errors, &mut |_| {}, |session| {
+                    session.sync(&frame, Instant::now())
+                })
+            }
+            Ok(None) => false,
+            Err(e) => {
+                let message = e.to_string();
+                if telemetry_error.as_ref() != Some(&message) {
+                    say!("⚠️  {} telemetry: {}", source.name(), message);
+                    *telemetry_error = Some(message);
+                }
+                false
+            }
+        },
+        None => step(session, errors, &mut |_| {}, |session| {
+            session.drive(elapsed.min(MAX_DRIVE_STEP), Instant::now())
+        }),
+    };
+    stepped
+        || session
+            .poll(Instant::now())
+            .is_err_and(|e| errors.record(format!("rumble failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Action;
+
+    #[test]
+    fn stdin_commands() {
+        assert_eq!(
+            parse_command("upshift"),
+            Ok(Some(Command::Bound(Bound::Action(Action::Upshift))))
+        );
+        assert_eq!(
+            parse_command("  query_gear  # by feel"),
+            Ok(Some(Command::Bound(Bound::Action(Action::QueryGear))))
+        );
+        assert_eq!(parse_command("exit"), Ok(Some(Command::Bound(Bound::Exit))));
+        assert_eq!(
+            parse_command("throttle 0.75"),
+            Ok(Some(Command::Throttle(0.75)))
+        );
+        assert_eq!(parse_command("brake 1"), Ok(Some(Command::Brake(1.0))));
+        assert_eq!(parse_command("gear 5"), Ok(Some(Command::Gear(5))));
+        assert!(parse_command("gear R").is_err());
+        assert_eq!(
+            parse_command("launch_control off"),
+            Ok(Some(Command::Release(Bound::LaunchControl)))
+        );
+        assert_eq!(
+            parse_command("profile wet"),
+            Ok(Some(Command::Profile("wet".to_string())))
+        );
+        assert_eq!(
+            parse_command("save_profile wet"),
+            Ok(Some(Command::SaveProfile("wet".to_string())))
+        );
+        assert_eq!(
+            parse_command("save_profile"),
+            Ok(Some(Command::Bound(Bound::Action(Action::SaveProfile))))
+        );
+        assert!(parse_command("profile").is_err());
+        assert_eq!(parse_command(""), Ok(None));
+        assert_eq!(parse_command("# a comment"), Ok(None));
+
+        assert!(parse_command("upshfit").is_err());
+        assert!(parse_command("throttle").is_err());
+        assert!(parse_command("throttle 1.5").is_err());
+        assert!(parse_command("upshift now").is_err());
+        assert!(parse_command("upshift off").is_err());
+    }
+
+    #[test]
+    fn rumbles_are_described_not_played() {
+        let command = RumbleCommand {
+            strong_magnitude: 40000,
+            weak_magnitude: 12000,
+            duration_ms: 150,
+        };
+        assert_eq!(describe(command), "strong 40000 / weak 12000 for 150 ms");
+        let pulses = [
+            Pulse {
+                after_ms: 0,
+                duration_ms: 60,
+            },
+            Pulse {
+                after_ms: 120,
+                duration_ms: 60,
+            },
+        ];
+        assert_eq!(describe_pulses(&pulses), "60 ms at +0 ms, 60 ms at +120 ms");
+        assert!(LogHaptics.is_supported());
+        assert_eq!(LogHaptics.play(command), Ok(()));
+    }
+}
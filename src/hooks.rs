@@ -0,0 +1,32 @@
+// Custom haptic logic plugged into a session: something that can stand in
+// for the built-in shift rumble and add a rumble of its own as the car is
+// driven. A Rhai script is one, see `script` with the `scripting` feature.
+
+use crate::car::GearPosition;
+use crate::haptics::{RumbleCommand, RumblePattern};
+
+/// The car as the tick hook sees it, once per drive step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TickState {
+    pub gear: GearPosition,
+    pub rpm: f32,
+    pub redline: f32,
+    pub throttle: f32, // 0 to 1, 0 without a throttle pedal
+    pub brake: f32,
+    pub speed_kmh: f32,
+    pub limiter: bool, // The limiter bounce is playing
+}
+
+pub trait HapticHooks {
+    /// The rumble for a shift `from` one gear `to` another with the engine
+    /// at `rpm`, after the shift. `None` keeps the built-in one.
+    fn on_shift(&mut self, from: GearPosition, to: GearPosition, rpm: f32)
+    -> Option<RumblePattern>;
+
+    /// A rumble to repeat from now on, until a later tick returns something
+    /// else. The limiter, free revs, turbo spool, launch stutter and crank
+    /// come first, and anything else that plays replaces it for a while;
+    /// it is played again on the first tick after. `None` for nothing of
+    /// its own.
+    fn on_tick(&mut self, state: &TickState) -> Option<RumbleCommand>;
+}
@@ -0,0 +1,169 @@
+// The driver's own say in how hard everything rumbles: a master intensity
+// from 0 to 200% and a profile for drivers who find rumble uncomfortable.
+// It applies to every rumble the session plays, after the shift feel, the
+// envelopes and the rest have shaped it, so one setting covers them all.
+
+use crate::haptics::{Pulse, RumbleCommand, RumblePattern};
+use serde::{Deserialize, Serialize};
+
+pub const MAX_PERCENT: u16 = 200;
+
+// The gentle profile's strongest rumble, about 40% of full, and how much
+// longer it draws every rumble out so nothing lands as a jolt
+const GENTLE_CAP: u16 = 26000;
+const GENTLE_STRETCH: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HapticProfile {
+    /// Rumbles as they are, scaled by the master intensity.
+    #[default]
+    Standard,
+    /// For sensory sensitivities: no rumble stronger than GENTLE_CAP, and
+    /// every one drawn out longer and softer.
+    Gentle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intensity {
+    master: f32, // 1 as shaped, up to 2
+    profile: HapticProfile,
+}
+
+impl Default for Intensity {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            profile: HapticProfile::Standard,
+        }
+    }
+}
+
+impl Intensity {
+    pub fn new(percent: u16, profile: HapticProfile) -> Result<Self, String> {
+        if percent > MAX_PERCENT {
+            return Err(format!("intensity: {}% is over {}%", percent, MAX_PERCENT));
+        }
+        Ok(Self {
+            master: percent as f32 / 100.0,
+            profile,
+        })
+    }
+
+    pub fn percent(&self) -> u16 {
+        (self.master * 100.0).round() as u16
+    }
+
+    pub fn profile(&self) -> HapticProfile {
+        self.profile
+    }
+
+    fn magnitude(&self, magnitude: u16) -> u16 {
+        let scaled = (magnitude as f32 * self.master).min(u16::MAX as f32) as u16;
+        match self.profile {
+            HapticProfile::Standard => scaled,
+            HapticProfile::Gentle => scaled.min(GENTLE_CAP),
+        }
+    }
+
+    fn duration_ms(&self, duration_ms: u32) -> u32 {
+        match self.profile {
+            HapticProfile::Standard => duration_ms,
+            HapticProfile::Gentle => (duration_ms as f32 * GENTLE_STRETCH).round() as u32,
+        }
+    }
+
+    pub fn command(&self, command: RumbleCommand) -> RumbleCommand {
+        RumbleCommand {
+            strong_magnitude: self.magnitude(command.strong_magnitude),
+            weak_magnitude: self.magnitude(command.weak_magnitude),
+            duration_ms: self.duration_ms(command.duration_ms),
+        }
+    }
+
+    pub fn pattern(&self, pattern: &RumblePattern) -> RumblePattern {
+        pattern.map(|stage| self.command(stage))
+    }
+
+    /// Strong-motor `pulses` at `magnitude`, the gaps drawn out with them.
+    pub fn pulses(&self, magnitude: u16, pulses: &[Pulse]) -> (u16, Vec<Pulse>) {
+        let pulses = pulses
+            .iter()
+            .map(|pulse| Pulse {
+                after_ms: self.duration_ms(pulse.after_ms),
+                duration_ms: self.duration_ms(pulse.duration_ms),
+            })
+            .collect();
+        (self.magnitude(magnitude), pulses)
+    }
+
+    /// `command` repeating every `period_ms`, the period drawn out with it.
+    pub fn repeating(&self, command: RumbleCommand, period_ms: u32) -> (RumbleCommand, u32) {
+        (self.command(command), self.duration_ms(period_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THUNK: RumbleCommand = RumbleCommand {
+        strong_magnitude: 40000,
+        weak_magnitude: 10000,
+        duration_ms: 200,
+    };
+
+    #[test]
+    fn master_scales_and_the_gentle_profile_caps_and_stretches() {
+        assert_eq!(Intensity::default().command(THUNK), THUNK);
+
+        let half = Intensity::new(50, HapticProfile::Standard).unwrap();
+        assert_eq!(
+            half.command(THUNK),
+            RumbleCommand {
+                strong_magnitude: 20000,
+                weak_magnitude: 5000,
+                duration_ms: 200,
+            }
+        );
+        let double = Intensity::new(200, HapticProfile::Standard).unwrap();
+        assert_eq!(double.command(THUNK).strong_magnitude, u16::MAX);
+        assert_eq!(double.command(THUNK).weak_magnitude, 20000);
+
+        let gentle = Intensity::new(200, HapticProfile::Gentle).unwrap();
+        assert_eq!(
+            gentle.command(THUNK),
+            RumbleCommand {
+                strong_magnitude: GENTLE_CAP,
+                weak_magnitude: 20000,
+                duration_ms: 300,
+            }
+        );
+        let pulses = [
+            Pulse {
+                after_ms: 0,
+                duration_ms: 20,
+            },
+            Pulse {
+                after_ms: 40,
+                duration_ms: 20,
+            },
+        ];
+        let (magnitude, stretched) = gentle.pulses(52000, &pulses);
+        assert_eq!(magnitude, GENTLE_CAP);
+        assert_eq!(
+            stretched[1],
+            Pulse {
+                after_ms: 60,
+                duration_ms: 30
+            }
+        );
+        assert_eq!(gentle.repeating(THUNK, 250).1, 375);
+
+        assert_eq!(half.percent(), 50);
+        assert_eq!(
+            Intensity::new(250, HapticProfile::Standard),
+            Err("intensity: 250% is over 200%".to_string())
+        );
+    }
+}
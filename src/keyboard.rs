@@ -0,0 +1,133 @@
+// Keyboard controls for when no gamepad is connected, so the simulator still
+// shifts without one. Keys are read from the terminal in raw mode; there is
+// nothing to rumble, so the session only reports what it would have played.
+
+use crate::bindings::Bound;
+use crate::output;
+use crate::session::Action;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use std::io;
+use std::time::Duration;
+
+/// Every key with what it does, for the controls table.
+pub const KEYS: [(&str, Bound); 11] = [
+    ("↑ / →", Bound::Action(Action::Upshift)),
+    ("↓ / ←", Bound::Action(Action::Downshift)),
+    ("S", Bound::Action(Action::ReplaySlowmo)),
+    ("G", Bound::Action(Action::QueryGear)),
+    ("A", Bound::Action(Action::ToggleAutomatic)),
+    ("M", Bound::Action(Action::ToggleMute)),
+    ("P", Bound::Action(Action::NextProfile)),
+    ("W", Bound::Action(Action::SaveProfile)),
+    ("N", Bound::Action(Action::Neutral)),
+    ("R", Bound::Action(Action::Reverse)),
+    ("Esc / Q", Bound::Exit),
+];
+
+/// What `key` is bound to, if anything. Ctrl+C exits too, since raw mode
+/// keeps it from interrupting.
+pub fn bound_for(key: KeyEvent) -> Option<Bound> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return (key.code == KeyCode::Char('c')).then_some(Bound::Exit);
+    }
+    let action = match key.code {
+        KeyCode::Up | KeyCode::Right => Action::Upshift,
+        KeyCode::Down | KeyCode::Left => Action::Downshift,
+        KeyCode::Char('s' | 'S') => Action::ReplaySlowmo,
+        KeyCode::Char('g' | 'G') => Action::QueryGear,
+        KeyCode::Char('a' | 'A') => Action::ToggleAutomatic,
+        KeyCode::Char('m' | 'M') => Action::ToggleMute,
+        KeyCode::Char('p' | 'P') => Action::NextProfile,
+        KeyCode::Char('w' | 'W') => Action::SaveProfile,
+        KeyCode::Char('n' | 'N') => Action::Neutral,
+        KeyCode::Char('r' | 'R') => Action::Reverse,
+        KeyCode::Char(digit @ '1'..='9') => Action::SelectGear(digit as u8 - b'0'),
+        KeyCode::Esc | KeyCode::Char('q' | 'Q') => return Some(Bound::Exit),
+        _ => return None,
+    };
+    Some(Bound::Action(action))
+}
+
+/// Holds the terminal in raw mode while keys are being read, and lets it go
+/// when dropped.
+pub struct Keyboard(());
+
+impl Keyboard {
+    pub fn start() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        output::raw(true);
+        Ok(Self(()))
+    }
+
+    /// The next bound key pressed, without waiting for one.
+    pub fn pressed(&mut self) -> io::Result<Option<Bound>> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && let Some(bound) = bound_for(key)
+            {
+                return Ok(Some(bound));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        output::raw(false);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn keys_shift_and_exit() {
+        let none = KeyModifiers::NONE;
+        for (code, action) in [
+            (KeyCode::Up, Action::Upshift),
+            (KeyCode::Right, Action::Upshift),
+            (KeyCode::Down, Action::Downshift),
+            (KeyCode::Left, Action::Downshift),
+            (KeyCode::Char('S'), Action::ReplaySlowmo),
+            (KeyCode::Char('g'), Action::QueryGear),
+            (KeyCode::Char('a'), Action::ToggleAutomatic),
+            (KeyCode::Char('M'), Action::ToggleMute),
+            (KeyCode::Char('p'), Action::NextProfile),
+            (KeyCode::Char('W'), Action::SaveProfile),
+            (KeyCode::Char('4'), Action::SelectGear(4)),
+            (KeyCode::Char('n'), Action::Neutral),
+            (KeyCode::Char('R'), Action::Reverse),
+        ] {
+            assert_eq!(bound_for(press(code, none)), Some(Bound::Action(action)));
+        }
+        assert_eq!(bound_for(press(KeyCode::Esc, none)), Some(Bound::Exit));
+        assert_eq!(
+            bound_for(press(KeyCode::Char('q'), none)),
+            Some(Bound::Exit)
+        );
+        assert_eq!(
+            bound_for(press(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Bound::Exit)
+        );
+        assert_eq!(bound_for(press(KeyCode::Char('c'), none)), None);
+        assert_eq!(
+            bound_for(press(KeyCode::Char('a'), KeyModifiers::CONTROL)),
+            None
+        );
+
+        let mut release = press(KeyCode::Up, none);
+        release.kind = KeyEventKind::Release;
+        assert_eq!(bound_for(release), None);
+    }
+}
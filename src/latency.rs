@@ -0,0 +1,325 @@
+// End-to-end rumble latency estimate without external gear.
+//
+// With the pad lying on a desk, the sticks sit still apart from a little
+// sensor noise. Rumble shakes them just enough to move the reported axis
+// values, so the delay between firing a pulse and the first stick sample that
+// leaves its resting noise band is a proxy for the rumble latency. Kept with
+// a profile, it comes off the reaction times of `--drag` runs driven with it.
+
+use crate::haptics::set_rumble;
+use gilrs::{Axis, Event, EventType, GamepadId, Gilrs};
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TRIALS: usize = 20;
+// Fewer detections than this and the pad is treated as not measurable
+const MIN_DETECTIONS: usize = 5;
+
+const SETTLE: Duration = Duration::from_millis(400);
+const PULSE_MS: u32 = 300;
+const LISTEN: Duration = Duration::from_millis(500);
+const REST: Duration = Duration::from_millis(500);
+
+// Smallest stick movement that counts as a disturbance, whatever the noise
+const MIN_DISTURBANCE: f32 = 0.02;
+// A disturbance must also clear the resting noise by this factor
+const NOISE_FACTOR: f32 = 3.0;
+// Detections further than this many (scaled) MADs from the median are dropped
+const OUTLIER_MADS: f32 = 3.0;
+
+const STICK_AXES: [Axis; 4] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::RightStickX,
+    Axis::RightStickY,
+];
+
+/// One stick axis reading, timestamped relative to the start of a trial phase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StickSample {
+    pub at_ms: f32,
+    pub axis: usize, // Index into STICK_AXES
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    pub mean_ms: f32,
+    pub std_dev_ms: f32,
+    pub used: usize,
+    pub rejected: usize,
+}
+
+/// Finds the first sample after the pulse that leaves its axis' resting band.
+///
+/// `resting` are samples taken while the pad was still, `after_pulse` are
+/// timestamped from the moment the pulse was fired. Axes without resting
+/// samples can't be judged and are ignored.
+pub fn detect_disturbance(resting: &[StickSample], after_pulse: &[StickSample]) -> Option<f32> {
+    let mut bands: [Option<(f32, f32)>; STICK_AXES.len()] = [None; STICK_AXES.len()];
+    for (axis, band) in bands.iter_mut().enumerate() {
+        let values: Vec<f32> = resting
+            .iter()
+            .filter(|s| s.axis == axis)
+            .map(|s| s.value)
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let noise = values
+            .iter()
+            .map(|v| (v - mean).abs())
+            .fold(0.0f32, f32::max);
+        *band = Some((mean, (noise * NOISE_FACTOR).max(MIN_DISTURBANCE)));
+    }
+
+    after_pulse
+        .iter()
+        .find(|s| {
+            bands
+                .get(s.axis)
+                .copied()
+                .flatten()
+                .is_some_and(|(mean, threshold)| (s.value - mean).abs() > threshold)
+        })
+        .map(|s| s.at_ms)
+}
+
+/// Mean and standard deviation of the detected latencies after dropping
+/// outliers, or `None` if too few trials detected anything.
+pub fn summarize(latencies_ms: &[f32]) -> Option<LatencyStats> {
+    if latencies_ms.len() < MIN_DETECTIONS {
+        return None;
+    }
+
+    let median = median(latencies_ms);
+    let deviations: Vec<f32> = latencies_ms.iter().map(|l| (l - median).abs()).collect();
+    // 1.4826 makes the MAD comparable to a standard deviation; 1 ms keeps a
+    // perfectly consistent run from rejecting everything but the median
+    let mad = (median_of(deviations) * 1.4826).max(1.0);
+
+    let kept: Vec<f32> = latencies_ms
+        .iter()
+        .copied()
+        .filter(|l| (l - median).abs() <= OUTLIER_MADS * mad)
+        .collect();
+
+    let mean = kept.iter().sum::<f32>() / kept.len() as f32;
+    let variance = kept.iter().map(|l| (l - mean).powi(2)).sum::<f32>() / kept.len() as f32;
+    Some(LatencyStats {
+        mean_ms: mean,
+        std_dev_ms: variance.sqrt(),
+        used: kept.len(),
+        rejected: latencies_ms.len() - kept.len(),
+    })
+}
+
+fn median(values: &[f32]) -> f32 {
+    median_of(values.to_vec())
+}
+
+fn median_of(mut values: Vec<f32>) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+/// Collects stick samples from `gamepad_id` for `length`.
+fn record(gilrs: &mut Gilrs, gamepad_id: GamepadId, length: Duration) -> Vec<StickSample> {
+    let start = Instant::now();
+    let mut samples = Vec::new();
+    while start.elapsed() < length {
+        while let Some(Event { id, event, .. }) = gilrs.next_event() {
+            if id != gamepad_id {
+                continue;
+            }
+            if let EventType::AxisChanged(axis, value, _) = event
+                && let Some(index) = STICK_AXES.iter().position(|a| *a == axis)
+            {
+                samples.push(StickSample {
+                    at_ms: start.elapsed().as_secs_f32() * 1000.0,
+                    axis: index,
+                    value,
+                });
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    samples
+}
+
+/// Runs the interactive measurement. Expects a `Gilrs` built without the
+/// default filters so that stick jitter isn't swallowed by the deadzone.
+/// The latency, if the pad's sticks let it be measured.
+pub fn run(gilrs: &mut Gilrs, gamepad_id: GamepadId) -> Option<LatencyStats> {
+    println!("\n⏱️  RUMBLE LATENCY MEASUREMENT");
+    println!("   Lay the controller flat on the desk, hands off the sticks.");
+    println!(
+        "   {} pulses will follow, about 30 seconds in total.",
+        TRIALS
+    );
+    print!("   Press Enter when ready...");
+    io::stdout().flush().unwrap();
+    io::stdin().read_line(&mut String::new()).unwrap();
+
+    if !gilrs.gamepad(gamepad_id).is_ff_supported() {
+        println!("   ⚠️  Rumble not supported on this gamepad");
+        return None;
+    }
+
+    let mut latencies = Vec::new();
+    for trial in 1..=TRIALS {
+        // The current stick positions seed the resting band
+        let gamepad = gilrs.gamepad(gamepad_id);
+        let mut resting: Vec<StickSample> = STICK_AXES
+            .iter()
+            .enumerate()
+            .map(|(axis, a)| StickSample {
+                at_ms: 0.0,
+                axis,
+                value: gamepad.value(*a),
+            })
+            .collect();
+        resting.extend(record(gilrs, gamepad_id, SETTLE));
+
+        let effect = match set_rumble(gilrs, &[gamepad_id], u16::MAX, u16::MAX, PULSE_MS) {
+            Ok(effect) => effect,
+            Err(e) => {
+                println!("   ❌ Rumble failed: {}", e);
+                return None;
+            }
+        };
+        let after_pulse = record(gilrs, gamepad_id, LISTEN);
+        drop(effect);
+
+        match detect_disturbance(&resting, &after_pulse) {
+            Some(latency) => {
+                println!("   Trial {:>2}: {:.1} ms", trial, latency);
+                latencies.push(latency);
+            }
+            None => println!("   Trial {:>2}: no disturbance", trial),
+        }
+        record(gilrs, gamepad_id, REST);
+    }
+
+    let stats = summarize(&latencies);
+    match &stats {
+        Some(stats) => {
+            println!(
+                "\n✅ Latency: {:.1} ms (σ {:.1} ms) from {} trials, {} outlier(s) rejected",
+                stats.mean_ms, stats.std_dev_ms, stats.used, stats.rejected
+            );
+        }
+        None => {
+            println!(
+                "\n⚠️  Only {} of {} pulses moved the sticks measurably.",
+                latencies.len(),
+                TRIALS
+            );
+            println!("   This pad doesn't leak rumble into its sticks enough to measure latency.");
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(axis: usize, points: &[(f32, f32)]) -> Vec<StickSample> {
+        points
+            .iter()
+            .map(|&(at_ms, value)| StickSample { at_ms, axis, value })
+            .collect()
+    }
+
+    // Left stick X of a pad at rest, drifting a little around 0.0039
+    fn resting_fixture() -> Vec<StickSample> {
+        trace(
+            0,
+            &[
+                (0.0, 0.0039),
+                (52.0, 0.0078),
+                (131.0, 0.0039),
+                (207.0, 0.0000),
+                (288.0, 0.0039),
+                (371.0, 0.0078),
+            ],
+        )
+    }
+
+    #[test]
+    fn detects_first_sample_outside_resting_band() {
+        let after = trace(
+            0,
+            &[
+                (8.0, 0.0078),
+                (19.0, 0.0039),
+                (31.0, 0.0430), // Rumble kicks in
+                (36.0, -0.0352),
+                (44.0, 0.0508),
+            ],
+        );
+        assert_eq!(detect_disturbance(&resting_fixture(), &after), Some(31.0));
+    }
+
+    #[test]
+    fn resting_noise_is_not_a_disturbance() {
+        let after = trace(0, &[(10.0, 0.0000), (60.0, 0.0078), (120.0, 0.0039)]);
+        assert_eq!(detect_disturbance(&resting_fixture(), &after), None);
+    }
+
+    #[test]
+    fn noisy_axis_needs_a_bigger_jump() {
+        // Resting band of ±0.02 around 0.5 → threshold 0.06
+        let resting = trace(2, &[(0.0, 0.48), (100.0, 0.52), (200.0, 0.50)]);
+        let after = trace(2, &[(12.0, 0.55), (25.0, 0.57)]);
+        assert_eq!(detect_disturbance(&resting, &after), Some(25.0));
+    }
+
+    #[test]
+    fn axes_without_resting_samples_are_ignored() {
+        let after = trace(3, &[(5.0, 0.9)]);
+        assert_eq!(detect_disturbance(&resting_fixture(), &after), None);
+    }
+
+    #[test]
+    fn each_axis_uses_its_own_band() {
+        let mut resting = resting_fixture();
+        resting.extend(trace(1, &[(0.0, -0.2), (100.0, -0.2)]));
+        // -0.2 is far from axis 0's band but right on axis 1's
+        let after = [trace(1, &[(10.0, -0.2)]), trace(0, &[(22.0, 0.05)])].concat();
+        assert_eq!(detect_disturbance(&resting, &after), Some(22.0));
+    }
+
+    #[test]
+    fn summary_rejects_outliers() {
+        let mut latencies = vec![31.0, 29.0, 33.0, 30.0, 32.0, 31.0, 30.0, 32.0];
+        latencies.push(240.0); // Someone bumped the desk
+        let stats = summarize(&latencies).unwrap();
+        assert_eq!(stats.used, 8);
+        assert_eq!(stats.rejected, 1);
+        assert!((stats.mean_ms - 31.0).abs() < 1e-4);
+        assert!(stats.std_dev_ms > 1.0 && stats.std_dev_ms < 1.5);
+    }
+
+    #[test]
+    fn summary_of_identical_trials() {
+        let stats = summarize(&[40.0; 6]).unwrap();
+        assert_eq!(stats.used, 6);
+        assert_eq!(stats.mean_ms, 40.0);
+        assert_eq!(stats.std_dev_ms, 0.0);
+    }
+
+    #[test]
+    fn too_few_detections_is_unmeasurable() {
+        assert_eq!(summarize(&[30.0, 31.0, 29.0]), None);
+        assert_eq!(summarize(&[]), None);
+    }
+}
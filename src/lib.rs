@@ -0,0 +1,51 @@
+//! Gear-shift haptic feedback for gamepads.
+//!
+//! [`Car`] models the car, its [`engine::Engine`] and how hard each shift
+//! should rumble,
+//! [`HapticController`] is anything that can play a rumble, and [`Session`]
+//! ties the two together for a stream of driver [`Action`]s. The gilrs-driven
+//! main loop used by the binary lives in [`event_loop`], and
+//! [`dashboard`] can draw it live in the terminal.
+
+pub mod bindings;
+pub mod calibration;
+pub mod car;
+pub mod config;
+pub mod dashboard;
+pub mod drag;
+pub mod dyno;
+pub mod engine;
+pub mod event_loop;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+pub mod haptics;
+pub mod headless;
+pub mod hooks;
+pub mod intensity;
+pub mod keyboard;
+pub mod latency;
+pub mod mixer;
+pub mod output;
+pub mod pedals;
+pub mod playstation;
+pub mod presets;
+pub mod profiles;
+pub mod reload;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "serial-display")]
+pub mod serial_display;
+pub mod session;
+pub mod shift_log;
+pub mod shifter;
+pub mod strict;
+pub mod suspend;
+pub mod telemetry;
+pub mod trainer;
+pub mod transmission;
+pub mod turbo;
+pub mod units;
+
+pub use car::{Car, GearPosition};
+pub use haptics::{GilrsHaptics, HapticController, HapticError, RumbleCommand};
+pub use session::{Action, Session};
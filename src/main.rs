@@ -2,112 +2,395 @@
 // [dependencies]
 // gilrs = "0.10"
 
-use gilrs::{Button, Event, EventType, Gilrs};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use gear_changer::GearPosition;
+use gear_changer::bindings::{self, BINDABLE, Bindings, Bound};
+use gear_changer::calibration::{self, Calibration, Motors};
+use gear_changer::car::{
+    Car, DEFAULT_FINAL_DRIVE, DEFAULT_GEAR_RATIOS, GearFeelTable, GearMotorMix, MASS_KG,
+};
+use gear_changer::config::{CONFIG_PATH, CarPreset, Config};
+use gear_changer::dashboard::{Dashboard, View};
+use gear_changer::drag::{DragRun, Strip};
+use gear_changer::dyno;
+use gear_changer::engine::TorqueCurve;
+use gear_changer::event_loop::{self, Controls, Render};
+use gear_changer::haptics::{GilrsHaptics, HapticController, RumbleCommand};
+use gear_changer::headless::{self, LogHaptics};
+use gear_changer::intensity::{self, HapticProfile, Intensity};
+use gear_changer::keyboard::{self, Keyboard};
+use gear_changer::latency;
+use gear_changer::mixer::MixMode;
+use gear_changer::pedals::Pedals;
+use gear_changer::playstation::PlayStationPad;
+use gear_changer::profiles::{Profile, Profiles};
+use gear_changer::reload::{ConfigWatcher, Tuning};
+#[cfg(feature = "scripting")]
+use gear_changer::script::Script;
+#[cfg(feature = "serial-display")]
+use gear_changer::serial_display;
+use gear_changer::session::{
+    self, Action, DEFAULT_LAUNCH_RPM, DEFAULT_SLOWMO_FACTOR, Session, ShiftMode,
+};
+use gear_changer::shift_log::{self, ShiftLog};
+use gear_changer::shifter::HPattern;
+use gear_changer::strict::{SessionErrors, StrictMode};
+use gear_changer::telemetry::TelemetrySource;
+use gear_changer::telemetry::assetto_corsa::{self, AssettoCorsa};
+use gear_changer::telemetry::dirt::{self, DirtRally};
+use gear_changer::telemetry::f1::{self, F1};
+use gear_changer::telemetry::forza::{self, Forza};
+use gear_changer::telemetry::iracing::IRacing;
+use gear_changer::telemetry::json::JsonTelemetry;
+use gear_changer::telemetry::outgauge::{self, OutGauge};
+use gear_changer::trainer::{RpmWindow, Trainer};
+use gear_changer::transmission::Transmission;
+use gear_changer::units::{Power, Torque, UnitSystem};
+use gilrs::{GamepadId, Gilrs, GilrsBuilder};
+use std::collections::BTreeMap;
+use std::fs;
 use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use toml_edit::DocumentMut;
 
-struct Car {
-    torque: f32,     // lb-ft
-    horsepower: f32, // HP
-    current_gear: u8,
-    max_torque: f32, // Maximum possible torque for calculations
+/// Gear-shift haptic feedback for gamepads. Without a subcommand, asks for
+/// the car's specs and starts a session.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-impl Car {
-    fn new(torque: f32, horsepower: f32) -> Self {
-        Self {
-            torque,
-            horsepower,
-            current_gear: 3,
-            max_torque: 1000.0, // Assuming max 1000 lb-ft for scaling
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Drive a car and feel the shifts
+    Run(Box<RunArgs>),
+    /// List connected gamepads and whether they can rumble
+    ListGamepads,
+    /// List the car presets, built-in and from gear_changer.toml
+    Cars,
+    /// Chart a car's torque and power curves, e.g. to check a --dyno sheet
+    Dyno(CarArgs),
+    /// Play one rumble on the first gamepad
+    TestRumble(TestRumbleArgs),
+    /// Estimate rumble latency from stick jitter
+    MeasureLatency,
+    /// Play the rumbles of a --shift-log back on the first gamepad, with
+    /// their original timing
+    Replay(ReplayArgs),
+    /// Press the input for each action and save them to gear_changer.toml
+    Bind,
+    /// Feel for the faintest rumble on each motor of the first gamepad, and
+    /// save scaling for it to gear_changer.toml so every pad feels alike
+    Calibrate,
+    /// List serial ports for --serial-display
+    #[cfg(feature = "serial-display")]
+    ListSerial,
+}
+
+/// Which car to drive, or chart with `dyno`.
+#[derive(Debug, Default, Args)]
+struct CarArgs {
+    /// Peak torque in lb-ft, or Nm with metric --units (asked for if missing)
+    #[arg(long)]
+    torque: Option<f32>,
+    /// Peak horsepower, or kW / PS with metric --units (asked for if missing)
+    #[arg(long, visible_alias = "power")]
+    hp: Option<f32>,
+    /// Units to type in and show torque and power in
+    /// [default: the config's, or imperial]
+    #[arg(long, value_enum)]
+    units: Option<UnitsArg>,
+    /// Torque curve from a dyno sheet: a CSV of rpm,torque rows, torque in
+    /// --units unless the header names lb-ft or Nm
+    #[arg(long, value_name = "CSV")]
+    dyno: Option<PathBuf>,
+    /// Car preset by name instead of --torque/--hp, built-in or from
+    /// gear_changer.toml (see `cars`)
+    #[arg(long, conflicts_with_all = ["torque", "hp", "gears", "final_drive"])]
+    car: Option<String>,
+    /// Gear ratios, first gear first
+    #[arg(long, value_delimiter = ',', value_name = "RATIOS")]
+    gears: Option<Vec<f32>>,
+    /// Final drive ratio
+    #[arg(long)]
+    final_drive: Option<f32>,
+}
+
+#[derive(Debug, Default, Args)]
+struct RunArgs {
+    #[command(flatten)]
+    spec: CarArgs,
+    /// Start with a saved profile's car and rumble settings (switch and save
+    /// them with the bound inputs)
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["car", "torque", "hp", "gears", "final_drive", "dyno"])]
+    profile: Option<String>,
+    /// Gearbox type, which sets how long shifts take and how they feel
+    /// [default: the preset's, or plain shift rumbles]
+    #[arg(long, value_enum)]
+    transmission: Option<TransmissionArg>,
+    /// Fit a turbocharger: a rumble that builds with boost, and a blow-off
+    /// flutter on a lift or upshift
+    #[arg(long)]
+    turbo: bool,
+    /// Per-gear motor balance, e.g. 1=1.2:0.4,6=0.3:1.0
+    #[arg(long, value_name = "gear=strong:weak,...")]
+    gear_mix: Option<String>,
+    /// Follow a game's gearbox instead of the shift buttons
+    #[arg(long, value_enum)]
+    telemetry: Option<TelemetryArg>,
+    /// Shift with an H-pattern shifter, gates from [h_pattern] in gear_changer.toml
+    #[arg(long, conflicts_with = "telemetry")]
+    h_pattern: bool,
+    /// Which gear changes go in: one at a time, or straight into any gear
+    /// (number keys, the H-pattern shifter) [default: direct with
+    /// --h-pattern, sequential otherwise]
+    #[arg(long, value_enum, value_name = "MODE", conflicts_with = "telemetry")]
+    shift_mode: Option<ShiftModeArg>,
+    /// Start with the automatic shifting (toggle it with the bound input)
+    #[arg(long, conflicts_with_all = ["telemetry", "h_pattern"])]
+    automatic: bool,
+    /// Where the game serves telemetry, or where to listen for games that send
+    /// it [default: this machine, the game's usual port]
+    #[arg(long, value_name = "HOST:PORT", requires = "telemetry")]
+    telemetry_address: Option<SocketAddr>,
+    /// Treat haptic errors as failures
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "report")]
+    strict: Option<StrictArg>,
+    /// How strong every rumble is, in percent [default: the config's, or 100]
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u16).range(0..=intensity::MAX_PERCENT as i64))]
+    intensity: Option<u16>,
+    /// Soften every rumble for sensory sensitivities [default: the config's,
+    /// or standard]
+    #[arg(long, value_enum, value_name = "PROFILE")]
+    haptic_profile: Option<HapticProfileArg>,
+    /// How many times slower slow-mo replays play the last shift [default:
+    /// the config's, or 4]
+    #[arg(long, value_name = "FACTOR", value_parser = parse_slowmo_factor)]
+    slowmo: Option<f32>,
+    /// Blend overlapping rumbles instead of letting each one cut off the last
+    #[arg(long, value_enum, value_name = "MODE")]
+    mix: Option<MixArg>,
+    /// Stiffen a DualSense's throttle trigger near the redline and click both
+    /// triggers on shifts (Linux, needs write access to its hidraw device)
+    #[arg(long)]
+    adaptive_triggers: bool,
+    /// Show the gear on a DualSense or DualShock 4 lightbar, flashing red at
+    /// the redline (Linux, needs write access to its hidraw device)
+    #[arg(long)]
+    lightbar: bool,
+    /// Show a live dashboard instead of scrolling messages (q or Esc quits)
+    #[arg(long)]
+    tui: bool,
+    /// Run without a controller: actions from stdin, one per line, and every
+    /// rumble logged instead of played
+    #[arg(long, conflicts_with_all = ["h_pattern", "mix", "adaptive_triggers", "lightbar", "tui", "gamepads"])]
+    headless: bool,
+    /// Record every shift and its rumble to this file: CSV if it ends in
+    /// .csv, JSON Lines otherwise
+    #[arg(long, value_name = "PATH")]
+    shift_log: Option<PathBuf>,
+    /// Practice shift timing: every shift button press is graded against a
+    /// target RPM window, with its own rumble and a running score
+    #[arg(long, conflicts_with_all = ["telemetry", "h_pattern", "automatic"])]
+    trainer: bool,
+    /// The trainer's target window, upshifts by the revs at the press and
+    /// downshifts by where they land [default: just short of the redline]
+    #[arg(long, value_name = "LOW-HIGH", requires = "trainer", value_parser = RpmWindow::parse)]
+    target_rpm: Option<RpmWindow>,
+    /// Timed runs from a standing start with the throttle pedal; personal
+    /// bests are kept in gear_changer.toml
+    #[arg(long, value_enum, value_name = "STRIP", num_args = 0..=1, require_equals = true, default_missing_value = "0-60", conflicts_with_all = ["telemetry", "h_pattern", "automatic"])]
+    drag: Option<DragArg>,
+    /// The car's weight for --drag, in kg [default: 1500]
+    #[arg(long, value_name = "KG", requires = "drag")]
+    weight: Option<f32>,
+    /// Where holding the launch control input holds the revs [default: 4000]
+    #[arg(long, value_name = "RPM", conflicts_with = "telemetry")]
+    launch_rpm: Option<f32>,
+    /// Only use these gamepads, by ID from list-gamepads, or any pad while none of them is connected [default: all, including ones plugged in later]
+    #[arg(long, value_delimiter = ',', value_name = "IDS")]
+    gamepads: Option<Vec<usize>>,
+    /// Mirror the gear on a serial display
+    #[cfg(feature = "serial-display")]
+    #[arg(long, value_name = "PORT:BAUD", value_parser = serial_display::parse_target)]
+    serial_display: Option<(String, u32)>,
+    /// Custom shift and tick rumbles from a Rhai script with on_shift and
+    /// on_tick hooks
+    #[cfg(feature = "scripting")]
+    #[arg(long, value_name = "PATH")]
+    script: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TelemetryArg {
+    /// Assetto Corsa UDP remote telemetry
+    AssettoCorsa,
+    /// DiRT Rally 2.0 / EA WRC extradata UDP telemetry
+    DirtRally,
+    /// F1 23 / F1 24 UDP telemetry
+    F1,
+    /// Forza Motorsport / Horizon "Dash" Data Out
+    Forza,
+    /// iRacing shared memory (Windows, same machine)
+    #[value(name = "iracing")]
+    IRacing,
+    /// Newline-delimited JSON over UDP, mapped by [json_telemetry] in gear_changer.toml
+    Json,
+    /// OutGauge UDP, e.g. from BeamNG.drive
+    #[value(name = "outgauge")]
+    OutGauge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ShiftModeArg {
+    /// One gear up or down at a time; skipping gears grinds
+    Sequential,
+    /// Straight into any gear
+    Direct,
+}
+
+impl From<ShiftModeArg> for ShiftMode {
+    fn from(arg: ShiftModeArg) -> Self {
+        match arg {
+            ShiftModeArg::Sequential => ShiftMode::Sequential,
+            ShiftModeArg::Direct => ShiftMode::Direct,
         }
     }
+}
 
-    fn calculate_rumble_intensity(&self, is_downshift: bool) -> f32 {
-        // Base intensity from torque (0.0 to 1.0)
-        let mut intensity = self.torque / self.max_torque;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TransmissionArg {
+    /// Dual-clutch: crisp 80 ms shifts
+    Dct,
+    /// Clutch and lever: 300–500 ms with a gap while the torque is cut
+    Manual,
+    /// Old torque-converter automatic: slow, slurred shifts
+    Auto,
+}
 
-        // Downshifts are 30% stronger, upshifts are 20% lighter
-        if is_downshift {
-            intensity *= 1.3;
-        } else {
-            intensity *= 0.8;
+impl From<TransmissionArg> for Transmission {
+    fn from(arg: TransmissionArg) -> Self {
+        match arg {
+            TransmissionArg::Dct => Transmission::Dct,
+            TransmissionArg::Manual => Transmission::Manual,
+            TransmissionArg::Auto => Transmission::Automatic,
         }
-
-        // Clamp between 0.0 and 1.0
-        intensity.min(1.0).max(0.0)
     }
+}
 
-    fn upshift(&mut self, gamepad_id: gilrs::GamepadId, gilrs: &mut Gilrs) {
-        if self.current_gear < 6 {
-            self.current_gear += 1;
-            let intensity = self.calculate_rumble_intensity(false);
-
-            println!("\n🔼 UPSHIFT → Gear {}", self.current_gear);
-            println!("   Rumble Intensity: {:.1}%", intensity * 100.0);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum UnitsArg {
+    /// lb-ft and hp
+    Imperial,
+    /// Nm and kW
+    Metric,
+    /// Nm and metric horsepower
+    MetricPs,
+}
 
-            // Trigger rumble
-            self.trigger_rumble(gamepad_id, gilrs, intensity, false);
-        } else {
-            println!("\n⚠️  Already in highest gear!");
+impl From<UnitsArg> for UnitSystem {
+    fn from(arg: UnitsArg) -> Self {
+        match arg {
+            UnitsArg::Imperial => UnitSystem::Imperial,
+            UnitsArg::Metric => UnitSystem::Metric,
+            UnitsArg::MetricPs => UnitSystem::MetricPs,
         }
     }
+}
 
-    fn downshift(&mut self, gamepad_id: gilrs::GamepadId, gilrs: &mut Gilrs) {
-        if self.current_gear > 1 {
-            self.current_gear -= 1;
-            let intensity = self.calculate_rumble_intensity(true);
-
-            println!("\n🔽 DOWNSHIFT → Gear {}", self.current_gear);
-            println!("   Rumble Intensity: {:.1}%", intensity * 100.0);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HapticProfileArg {
+    /// Rumbles as they are, at the --intensity
+    Standard,
+    /// Capped well short of full strength, and longer and softer
+    Gentle,
+}
 
-            // Trigger rumble
-            self.trigger_rumble(gamepad_id, gilrs, intensity, true);
-        } else {
-            println!("\n⚠️  Already in first gear!");
+impl From<HapticProfileArg> for HapticProfile {
+    fn from(arg: HapticProfileArg) -> Self {
+        match arg {
+            HapticProfileArg::Standard => HapticProfile::Standard,
+            HapticProfileArg::Gentle => HapticProfile::Gentle,
         }
     }
+}
 
-    fn trigger_rumble(
-        &self,
-        gamepad_id: gilrs::GamepadId,
-        gilrs: &mut Gilrs,
-        intensity: f32,
-        is_downshift: bool,
-    ) {
-        let gamepad = gilrs.gamepad(gamepad_id);
-
-        // Duration in milliseconds
-        let duration = if is_downshift { 200 } else { 150 };
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MixArg {
+    /// Overlapping rumbles add up
+    Sum,
+    /// The strongest overlapping rumble wins
+    Max,
+}
 
-        // Try to trigger rumble
-        if gamepad.is_ff_supported() {
-            let strong_magnitude = (intensity * 65535.0) as u16;
-            let weak_magnitude = (intensity * 0.7 * 65535.0) as u16;
+impl From<MixArg> for MixMode {
+    fn from(arg: MixArg) -> Self {
+        match arg {
+            MixArg::Sum => MixMode::Sum,
+            MixArg::Max => MixMode::Max,
+        }
+    }
+}
 
-            // Note: gilrs rumble support varies by platform
-            // This creates a simple rumble effect
-            let _ =
-                gilrs
-                    .gamepad(gamepad_id)
-                    .set_rumble(strong_magnitude, weak_magnitude, duration);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DragArg {
+    /// 0–60 mph, launched on the throttle
+    #[value(name = "0-60")]
+    ZeroToSixty,
+    /// Off the staging lights, with reaction time, ET and trap speed
+    QuarterMile,
+}
 
-            println!("   💥 Rumble triggered!");
-        } else {
-            println!("   ⚠️  Rumble not supported on this gamepad");
+impl From<DragArg> for Strip {
+    fn from(arg: DragArg) -> Self {
+        match arg {
+            DragArg::ZeroToSixty => Strip::ZeroToSixty,
+            DragArg::QuarterMile => Strip::QuarterMile,
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum StrictArg {
+    /// Count every error and exit nonzero when the session ends
+    Report,
+    /// Exit nonzero on the first error
+    FailFast,
+}
 
-    fn display_status(&self) {
-        println!("\n┌─────────────────────────────────┐");
-        println!("│      CURRENT STATUS             │");
-        println!("├─────────────────────────────────┤");
-        println!("│ Gear:       {}                   │", self.current_gear);
-        println!("│ Torque:     {:.0} lb-ft          │", self.torque);
-        println!("│ Horsepower: {:.0} HP             │", self.horsepower);
-        println!("└─────────────────────────────────┘");
+impl From<StrictArg> for StrictMode {
+    fn from(arg: StrictArg) -> Self {
+        match arg {
+            StrictArg::Report => StrictMode::Report,
+            StrictArg::FailFast => StrictMode::FailFast,
+        }
     }
 }
 
+#[derive(Debug, Args)]
+struct TestRumbleArgs {
+    /// Strong motor, 0.0 to 1.0
+    #[arg(long, default_value_t = 1.0)]
+    strong: f32,
+    /// Weak motor, 0.0 to 1.0
+    #[arg(long, default_value_t = 1.0)]
+    weak: f32,
+    #[arg(long, default_value_t = 500)]
+    duration_ms: u32,
+}
+
+#[derive(Debug, Args)]
+struct ReplayArgs {
+    /// A JSON Lines log written by run --shift-log
+    path: PathBuf,
+}
+
 fn get_input(prompt: &str) -> String {
     print!("{}", prompt);
     io::stdout().flush().unwrap();
@@ -116,95 +399,1509 @@ fn get_input(prompt: &str) -> String {
     input.trim().to_string()
 }
 
-fn main() {
+fn open_gilrs(default_filters: bool) -> Result<Gilrs, String> {
+    GilrsBuilder::new()
+        .with_default_filters(default_filters)
+        .build()
+        .map_err(|e| format!("Failed to initialize gamepad support: {}", e))
+}
+
+fn first_gamepad(gilrs: &Gilrs) -> Option<GamepadId> {
+    let (id, gamepad) = gilrs.gamepads().next()?;
+    println!("\n🎮 Gamepad found: {}", gamepad.name());
+    Some(id)
+}
+
+/// Reads a gamepad menu answer: blank or "all" for every pad, otherwise
+/// comma-separated menu numbers. Returns indices into the menu.
+fn parse_gamepad_choice(input: &str, count: usize) -> Result<Option<Vec<usize>>, String> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("all") {
+        return Ok(None);
+    }
+    input
+        .split(',')
+        .map(|number| match number.trim().parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Ok(n - 1),
+            _ => Err(format!("pick 1 to {}", count)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Lists the connected gamepads and asks which to use. `None` for all.
+fn pick_gamepads(gilrs: &Gilrs) -> Option<Vec<usize>> {
+    let gamepads: Vec<_> = gilrs.gamepads().collect();
+    println!("\n🎮 Several gamepads connected:");
+    for (number, (id, gamepad)) in gamepads.iter().enumerate() {
+        println!(
+            "  {}) {} [ID {}] — rumble: {}",
+            number + 1,
+            gamepad.name(),
+            id,
+            if gamepad.is_ff_supported() {
+                "yes"
+            } else {
+                "no"
+            }
+        );
+    }
+    loop {
+        let input = get_input("Use which? (e.g. 1 or 1,3; Enter for all): ");
+        match parse_gamepad_choice(&input, gamepads.len()) {
+            Ok(choice) => {
+                return choice.map(|picked| {
+                    picked
+                        .into_iter()
+                        .map(|index| usize::from(gamepads[index].0))
+                        .collect()
+                });
+            }
+            Err(e) => println!("⚠️  {}", e),
+        }
+    }
+}
+
+/// The car `args` describe. Only `interactive` does it ask for what is
+/// missing; otherwise stdin may be for something else.
+fn build_car(args: &CarArgs, config: &Config, interactive: bool) -> Result<Car, String> {
+    let units = args.units.map_or(config.units, UnitSystem::from);
+    let dyno = match &args.dyno {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("--dyno {}: {}", path.display(), e))?;
+            let curve = TorqueCurve::from_csv(&text, units)
+                .map_err(|e| format!("--dyno {}: {}", path.display(), e))?;
+            println!("📈 Torque curve from {}", path.display());
+            Some(curve)
+        }
+        None => None,
+    };
+    let mut car = pick_car(args, config, units, dyno.as_ref(), interactive)?;
+    if let Some(curve) = dyno {
+        car.set_torque_curve(curve);
+    }
+    Ok(car)
+}
+
+/// A preset, or the car typed in, which takes its peak torque from `dyno`
+/// if none was given.
+fn pick_car(
+    args: &CarArgs,
+    config: &Config,
+    units: UnitSystem,
+    dyno: Option<&TorqueCurve>,
+    interactive: bool,
+) -> Result<Car, String> {
+    if let Some(name) = &args.car {
+        let preset = config
+            .car(name)
+            .ok_or_else(|| format!("--car: no preset '{}' (see `cars`)", name))?;
+        return use_preset(name, preset, units);
+    }
+
+    // Nothing typed in: offer the presets first
+    if args.torque.is_none() && args.hp.is_none() && interactive {
+        let cars = config.all_cars();
+        println!("🏎️  Car presets:");
+        for (i, (name, preset)) in cars.iter().enumerate() {
+            println!("   {:>2}. {}", i + 1, preset_summary(name, preset, units));
+        }
+        let picked = get_input("Pick a car by number or name, or press Enter to type in specs: ");
+        if !picked.is_empty() {
+            let by_number = picked
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| cars.iter().nth(n.checked_sub(1)?));
+            match by_number.or_else(|| cars.get_key_value(picked.as_str())) {
+                Some((name, preset)) => return use_preset(name, preset, units),
+                None => println!("⚠️  No preset '{}'\n", picked),
+            }
+        }
+    }
+
+    // Get car specs from user; headless, stdin is for the actions
+    let ask = |prompt: &str| {
+        if interactive {
+            get_input(prompt)
+        } else {
+            String::new()
+        }
+    };
+    // The example is the same car whichever units it is in
+    let (example_torque, example_power) = (Torque::from_lb_ft(300.0), Power::from_hp(400.0));
+    let torque = args
+        .torque
+        .map(|value| units.torque(value))
+        .or(dyno.map(TorqueCurve::peak))
+        .unwrap_or_else(|| {
+            ask(&format!(
+                "Enter car torque [e.g., {}]: ",
+                units.format_torque(example_torque)
+            ))
+            .parse::<f32>()
+            .map_or(example_torque, |value| units.torque(value))
+        });
+    let power = args.hp.map(|value| units.power(value)).unwrap_or_else(|| {
+        ask(&format!(
+            "Enter car power [e.g., {}]: ",
+            units.format_power(example_power)
+        ))
+        .parse::<f32>()
+        .map_or(example_power, |value| units.power(value))
+    });
+
+    let mut car = Car::new(
+        torque,
+        power,
+        args.gears
+            .clone()
+            .unwrap_or_else(|| DEFAULT_GEAR_RATIOS.to_vec()),
+        args.final_drive.unwrap_or(DEFAULT_FINAL_DRIVE),
+    )?;
+    car.set_units(units);
+    Ok(car)
+}
+
+fn use_preset(name: &str, preset: &CarPreset, units: UnitSystem) -> Result<Car, String> {
+    println!("🏎️  Using preset '{}'", name);
+    preset
+        .build(units)
+        .map_err(|e| format!("preset '{}': {}", name, e))
+}
+
+/// One line about a preset for the menu and `cars`: its name, figures in
+/// `units`, redline and gearbox.
+fn preset_summary(name: &str, preset: &CarPreset, units: UnitSystem) -> String {
+    let car = match preset.build(units) {
+        Ok(car) => car,
+        Err(e) => return format!("{:<14} ⚠️  {}", name, e),
+    };
+    let mut summary = format!(
+        "{:<14} {} — {}, {}, {:.0} rpm, {} gears",
+        name,
+        preset.name.as_deref().unwrap_or(name),
+        units.format_torque(car.torque()),
+        units.format_power(car.power()),
+        car.engine().redline().rpm(),
+        car.gear_count()
+    );
+    if let Some(transmission) = car.transmission() {
+        summary += &format!(", {}", transmission.name());
+    }
+    if car.turbo().is_some() {
+        summary += ", turbo";
+    }
+    summary
+}
+
+fn list_cars() -> Result<i32, String> {
+    let config = Config::load(Path::new(CONFIG_PATH))?;
+    for (name, preset) in config.all_cars() {
+        println!("{}", preset_summary(name, preset, config.units));
+    }
+    println!("\nAdd your own under [cars.<name>] in {}", CONFIG_PATH);
+    Ok(0)
+}
+
+// Columns of rpm and rows of torque and power in the `dyno` chart
+const DYNO_WIDTH: usize = 60;
+const DYNO_HEIGHT: usize = 17;
+
+fn dyno_chart(args: CarArgs) -> Result<i32, String> {
+    let config = Config::load(Path::new(CONFIG_PATH))?;
+    let car = build_car(&args, &config, true)?;
+    println!();
+    print!(
+        "{}",
+        dyno::chart(car.engine(), car.units(), DYNO_WIDTH, DYNO_HEIGHT)
+    );
+    Ok(0)
+}
+
+fn open_telemetry(
+    args: &RunArgs,
+    config: &Config,
+) -> Result<Option<Box<dyn TelemetrySource>>, String> {
+    let Some(game) = args.telemetry else {
+        return Ok(None);
+    };
+    let source: Box<dyn TelemetrySource> = match game {
+        TelemetryArg::AssettoCorsa => {
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([127, 0, 0, 1], assetto_corsa::DEFAULT_PORT).into());
+            println!("\n📡 Connecting to Assetto Corsa telemetry at {}", address);
+            Box::new(
+                AssettoCorsa::connect(address)
+                    .map_err(|e| format!("Assetto Corsa telemetry: {}", e))?,
+            )
+        }
+        TelemetryArg::DirtRally => {
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([0, 0, 0, 0], dirt::DEFAULT_PORT).into());
+            println!("\n📡 Listening for DiRT Rally telemetry on {}", address);
+            Box::new(
+                DirtRally::listen(address).map_err(|e| format!("DiRT Rally telemetry: {}", e))?,
+            )
+        }
+        TelemetryArg::F1 => {
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([0, 0, 0, 0], f1::DEFAULT_PORT).into());
+            println!("\n📡 Listening for F1 telemetry on {}", address);
+            Box::new(F1::listen(address).map_err(|e| format!("F1 telemetry: {}", e))?)
+        }
+        TelemetryArg::Forza => {
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([0, 0, 0, 0], forza::DEFAULT_PORT).into());
+            println!("\n📡 Listening for Forza Data Out on {}", address);
+            Box::new(Forza::listen(address).map_err(|e| format!("Forza telemetry: {}", e))?)
+        }
+        TelemetryArg::IRacing => {
+            if args.telemetry_address.is_some() {
+                return Err("--telemetry-address: iRacing is read from shared memory".to_string());
+            }
+            println!("\n📡 Reading iRacing telemetry");
+            Box::new(IRacing::open().map_err(|e| format!("iRacing telemetry: {}", e))?)
+        }
+        TelemetryArg::Json => {
+            let mapping = config.json_telemetry.clone();
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([0, 0, 0, 0], mapping.port).into());
+            println!("\n📡 Listening for JSON telemetry on {}", address);
+            Box::new(
+                JsonTelemetry::listen(address, mapping)
+                    .map_err(|e| format!("JSON telemetry: {}", e))?,
+            )
+        }
+        TelemetryArg::OutGauge => {
+            let address = args
+                .telemetry_address
+                .unwrap_or_else(|| ([0, 0, 0, 0], outgauge::DEFAULT_PORT).into());
+            println!("\n📡 Listening for OutGauge on {}", address);
+            Box::new(OutGauge::listen(address).map_err(|e| format!("OutGauge: {}", e))?)
+        }
+    };
+    Ok(Some(source))
+}
+
+const CONTROLS: [(Bound, &str); 13] = [
+    (Bound::Action(Action::Downshift), "Downshift (stronger)"),
+    (Bound::Action(Action::Upshift), "Upshift (lighter)"),
+    (Bound::Action(Action::ReplaySlowmo), "Slow-mo last shift"),
+    (Bound::Action(Action::QueryGear), "Query gear by feel"),
+    (Bound::Action(Action::ToggleAutomatic), "Manual / automatic"),
+    (Bound::Action(Action::ToggleMute), "Mute / unmute rumble"),
+    (Bound::Action(Action::NextProfile), "Next profile"),
+    (Bound::Action(Action::SaveProfile), "Save profile"),
+    (Bound::Action(Action::Neutral), "Neutral"),
+    (Bound::Action(Action::Reverse), "Reverse"),
+    (Bound::LaunchControl, "Launch control"),
+    (Bound::Starter, "Starter (hold)"),
+    (Bound::Exit, "Exit"),
+];
+
+fn print_keyboard_controls() {
+    println!("\n┌──────────────────────────────────────┐");
+    println!("│          KEYBOARD CONTROLS           │");
+    println!("├──────────────────────────────────────┤");
+    for (bound, description) in CONTROLS {
+        if let Some((key, _)) = keyboard::KEYS.iter().find(|(_, b)| *b == bound) {
+            println!("│ {:<13} → {:<20} │", key, description);
+        }
+    }
+    println!("│ {:<13} → {:<20} │", "1–9", "Into a gear");
+    println!("└──────────────────────────────────────┘");
+}
+
+fn print_controls(bindings: &Bindings, pedals: &Pedals, h_pattern: Option<&HPattern>) {
+    println!("\n┌──────────────────────────────────────┐");
+    println!("│               CONTROLS               │");
+    println!("├──────────────────────────────────────┤");
+    for (bound, description) in CONTROLS {
+        let input = bindings
+            .input_for(bound)
+            .map_or("(unbound)".to_string(), |input| input.to_string());
+        println!("│ {:<13} → {:<20} │", input, description);
+    }
+    let named_pedals = [
+        (pedals.throttle(), "Throttle"),
+        (pedals.brake(), "Brake"),
+        (pedals.clutch(), "Clutch"),
+    ];
+    for (pedal, name) in named_pedals {
+        if let Some(pedal) = pedal {
+            println!("│ {:<13} → {:<20} │", pedal.input().to_string(), name);
+        }
+    }
+    for (input, position) in h_pattern.map_or(&[][..], HPattern::gates) {
+        let gate = match position {
+            GearPosition::Gear(gear) => format!("Gate {}", gear),
+            GearPosition::Neutral => "Neutral".to_string(),
+            GearPosition::Reverse => "Gate R".to_string(),
+        };
+        println!("│ {:<13} → {:<20} │", input.to_string(), gate);
+    }
+    println!("└──────────────────────────────────────┘");
+}
+
+fn run(args: RunArgs) -> Result<i32, String> {
+    let mut session_errors = SessionErrors::new(args.strict.map_or(StrictMode::Off, Into::into));
+
+    #[cfg(feature = "serial-display")]
+    let serial_display = args.serial_display.clone().map(|(port, baud)| {
+        println!("📟 Gear display on {} @ {} baud", port, baud);
+        serial_display::SerialDisplay::open(port, baud)
+    });
+
     println!("╔═══════════════════════════════════════╗");
     println!("║  GEAR SHIFT HAPTIC FEEDBACK SIMULATOR ║");
     println!("╚═══════════════════════════════════════╝\n");
 
-    // Get car specs from user
-    let torque_input = get_input("Enter car torque (lb-ft) [e.g., 300]: ");
-    let torque = torque_input.parse::<f32>().unwrap_or(300.0);
-
-    let hp_input = get_input("Enter car horsepower [e.g., 400]: ");
-    let horsepower = hp_input.parse::<f32>().unwrap_or(400.0);
+    let config = Config::load(Path::new(CONFIG_PATH))?;
+    let mut bindings =
+        Bindings::from_config(&config.bindings).map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    let mut profiles = match Profiles::open() {
+        Ok(profiles) => Some(profiles),
+        Err(e) => {
+            println!("⚠️  Profiles: {}", e);
+            None
+        }
+    };
+    let profile = match (&args.profile, &mut profiles) {
+        (Some(name), Some(profiles)) => {
+            let profile = profiles
+                .load(name)
+                .map_err(|e| format!("--profile {}: {}", name, e))?;
+            match profile.latency_ms {
+                Some(ms) => println!("👤 Using profile '{}', rumble latency {:.1} ms", name, ms),
+                None => println!("👤 Using profile '{}'", name),
+            }
+            Some(profile)
+        }
+        (Some(name), None) => return Err(format!("--profile {}: no profiles here", name)),
+        (None, _) => None,
+    };
+    let mut car = match &profile {
+        Some(profile) => profile
+            .build_car()
+            .map_err(|e| format!("--profile: {}", e))?,
+        None => build_car(&args.spec, &config, !args.headless)?,
+    };
+    fit_out(&args, &mut car)?;
+    car.set_gear_feel(gear_feel(&config, car.gear_count())?);
 
-    let mut car = Car::new(torque, horsepower);
+    let mut pedals =
+        Pedals::from_config(&config.pedals).map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    config
+        .envelopes
+        .validate()
+        .map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    validate_calibrations(&config)?;
+    let intensity = Intensity::new(
+        args.intensity
+            .or(profile.as_ref().map(|profile| profile.intensity))
+            .or(config.intensity)
+            .unwrap_or(100),
+        args.haptic_profile.map_or(
+            profile
+                .as_ref()
+                .map_or(config.haptic_profile, |profile| profile.haptic_profile),
+            HapticProfile::from,
+        ),
+    )
+    .map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    let slowmo_factor = slowmo_factor(&args, &config)?;
+    let mut h_pattern = if args.h_pattern {
+        Some(h_pattern_gates(&config, car.gear_count())?)
+    } else {
+        None
+    };
 
     println!("\n✅ Car configured!");
-    car.display_status();
+    car.display_status(GearPosition::Gear(car.current_gear()));
+    if intensity != Intensity::default() {
+        let gentle = match intensity.profile() {
+            HapticProfile::Standard => "",
+            HapticProfile::Gentle => ", gentle profile",
+        };
+        println!("🎚️  Rumble intensity {}%{}", intensity.percent(), gentle);
+    }
 
-    // Initialize gilrs
-    let mut gilrs = match Gilrs::new() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("❌ Failed to initialize gamepad support: {}", e);
-            return;
+    #[cfg(feature = "serial-display")]
+    if let Some(display) = &serial_display {
+        display.show_gear(GearPosition::Gear(car.current_gear()));
+    }
+
+    let mut telemetry = open_telemetry(&args, &config)?;
+    let shift_log = match &args.shift_log {
+        Some(path) => {
+            let log = ShiftLog::create(path)
+                .map_err(|e| format!("--shift-log {}: {}", path.display(), e))?;
+            println!("📝 Logging shifts to {}", path.display());
+            Some(log)
+        }
+        None => None,
+    };
+    #[cfg(feature = "scripting")]
+    let script = match &args.script {
+        Some(path) => {
+            let script = Script::load(path).map_err(|e| format!("--script {}", e))?;
+            println!("📜 Rumbles scripted by {}", path.display());
+            Some(script)
+        }
+        None => None,
+    };
+    let trainer = args.trainer.then(|| {
+        let window = args
+            .target_rpm
+            .unwrap_or_else(|| RpmWindow::default_for(car.engine()));
+        println!(
+            "🎯 Trainer: shift between {:.0} and {:.0} rpm",
+            window.low, window.high
+        );
+        Trainer::new(window)
+    });
+    // Personal bests are kept by preset, or by the specs typed in
+    let drag_car = args.spec.car.clone().unwrap_or_else(|| {
+        format!(
+            "{:.0} lb-ft, {:.0} hp",
+            car.torque().lb_ft(),
+            car.power().hp()
+        )
+    });
+    let strip = args.drag.map(Strip::from);
+    let drag_best = strip
+        .and_then(|strip| match strip {
+            Strip::ZeroToSixty => config.drag_best.get(&drag_car),
+            Strip::QuarterMile => config.quarter_mile_best.get(&drag_car),
+        })
+        .map(|&seconds| Duration::from_secs_f32(seconds));
+    let drag = if let Some(strip) = strip {
+        let weight = args.weight.unwrap_or(MASS_KG);
+        if !weight.is_finite() || weight <= 0.0 {
+            return Err(format!("--weight: invalid weight {}", weight));
+        }
+        // Headless runs take the throttle from stdin
+        if !args.headless && pedals.throttle().is_none() {
+            return Err(format!(
+                "--drag needs a throttle pedal, see [pedals] in {}",
+                CONFIG_PATH
+            ));
+        }
+        print!("🏁 Drag: {} at {:.0} kg", strip.name(), weight);
+        match drag_best {
+            Some(best) => println!(", best {:.3} s", best.as_secs_f32()),
+            None => println!(),
+        }
+        let mut run = DragRun::new(strip, weight, drag_best);
+        run.set_latency(profile.as_ref().map(Profile::latency).unwrap_or_default());
+        Some(run)
+    } else {
+        None
+    };
+
+    let launch_rpm = args.launch_rpm.unwrap_or(DEFAULT_LAUNCH_RPM);
+    let (idle, redline) = (car.engine().idle().rpm(), car.engine().redline().rpm());
+    if !(idle..redline).contains(&launch_rpm) {
+        return Err(format!(
+            "--launch-rpm: {} rpm isn't between idle ({:.0}) and the redline ({:.0})",
+            launch_rpm, idle, redline
+        ));
+    }
+
+    let shift_mode = args.shift_mode.map_or(
+        if args.h_pattern {
+            ShiftMode::Direct
+        } else {
+            ShiftMode::Sequential
+        },
+        ShiftMode::from,
+    );
+
+    // Tuning goes on while the session runs
+    let mut watcher = ConfigWatcher::new(PathBuf::from(CONFIG_PATH));
+    let mut loaded = config.clone();
+    let mut reload = |car: &Car, profile: Option<&str>| {
+        if !watcher.changed() {
+            return None;
         }
+        Some(Config::load(Path::new(CONFIG_PATH)).and_then(|new| {
+            let tuning = retune(&args, &loaded, &new, car, profile)?;
+            loaded = new;
+            Ok(tuning)
+        }))
     };
 
-    // Check for connected gamepads
-    let mut active_gamepad = None;
-    for (_id, gamepad) in gilrs.gamepads() {
-        println!("\n🎮 Gamepad found: {}", gamepad.name());
-        active_gamepad = Some(gamepad.id());
-        break;
+    if args.headless {
+        println!(
+            "\n🖥️  Headless: actions from stdin (upshift, downshift, gear 3, throttle 0.8, ...),"
+        );
+        println!("   rumbles logged instead of played");
+        let mut session = Session::new(car, LogHaptics);
+        session.set_envelopes(config.envelopes);
+        session.set_intensity(intensity);
+        session.set_profiles(profiles);
+        #[cfg(feature = "scripting")]
+        session.set_hooks(script.map(|script| Box::new(script) as _));
+        session.set_shift_log(shift_log);
+        session.set_trainer(trainer);
+        session.set_drag(drag);
+        session.set_slowmo_factor(slowmo_factor);
+        session.set_launch_rpm(launch_rpm);
+        session.set_shift_mode(shift_mode);
+        if args.automatic {
+            session.set_automatic(true);
+        }
+        headless::run(
+            &mut session,
+            &mut session_errors,
+            telemetry
+                .as_mut()
+                .map(|source| &mut **source as &mut dyn TelemetrySource),
+            Some(&mut reload),
+        )
+        .map_err(|e| format!("main loop: {}", e))?;
+        save_drag_best(&drag_car, drag_best, session.drag())?;
+        return Ok(session_errors.finish());
     }
 
-    if active_gamepad.is_none() {
-        println!("\n⚠️  No gamepad detected! Please connect a gamepad and restart.");
-        println!("Press Enter to exit...");
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        return;
+    let gilrs = open_gilrs(true)?;
+    let only = match &args.gamepads {
+        Some(ids) => Some(ids.clone()),
+        None if gilrs.gamepads().count() > 1 => pick_gamepads(&gilrs),
+        None => None,
+    };
+    let mut haptics = GilrsHaptics::new(gilrs);
+    haptics.use_gamepads(only);
+    haptics.use_calibrations(config.calibration.clone());
+    if let Some(mode) = args.mix {
+        haptics.mix(mode.into());
+    }
+    if args.adaptive_triggers || args.lightbar {
+        match PlayStationPad::find() {
+            Ok(Some(pad)) => {
+                println!("🎮 {} on {}", pad.model(), pad.path().display());
+                if args.adaptive_triggers && !pad.has_adaptive_triggers() {
+                    println!("⚠️  --adaptive-triggers: a {} has none", pad.model());
+                }
+                haptics.use_playstation_pad(pad, args.adaptive_triggers, args.lightbar);
+            }
+            Ok(None) => println!("⚠️  No DualSense or DualShock 4 found"),
+            Err(e) => println!("⚠️  PlayStation pad: {}", e),
+        }
+    }
+    // Without a gamepad the keyboard shifts and nothing rumbles
+    let use_keyboard = haptics.gamepads().is_empty();
+    if use_keyboard {
+        if args.gamepads.is_some() {
+            println!("\n⚠️  None of the --gamepads are connected (see list-gamepads).");
+        } else {
+            println!("\n⚠️  No gamepad detected!");
+        }
+        println!("⌨️  Shifting with the keyboard instead, without rumble.");
+    }
+    for &id in haptics.gamepads() {
+        println!("\n🎮 Gamepad found: {}", haptics.gilrs().gamepad(id).name());
     }
 
-    println!("\n┌─────────────────────────────────┐");
-    println!("│         CONTROLS                │");
-    println!("├─────────────────────────────────┤");
-    println!("│ X Button → Downshift (stronger)│");
-    println!("│ B Button → Upshift (lighter)   │");
-    println!("│ Start    → Exit                 │");
-    println!("└─────────────────────────────────┘");
+    print_controls(&bindings, &pedals, h_pattern.as_ref());
+    if use_keyboard {
+        print_keyboard_controls();
+    }
     println!("\n🏁 Ready! Start shifting...\n");
 
-    // Main event loop
-    loop {
-        while let Some(Event { id, event, .. }) = gilrs.next_event() {
-            match event {
-                EventType::ButtonPressed(button, _) => {
-                    match button {
-                        Button::West => {
-                            // X button = Downshift
-                            if let Some(gamepad_id) = active_gamepad {
-                                car.downshift(gamepad_id, &mut gilrs);
-                            }
-                        }
-                        Button::East => {
-                            // B button = Upshift
-                            if let Some(gamepad_id) = active_gamepad {
-                                car.upshift(gamepad_id, &mut gilrs);
-                            }
-                        }
-                        Button::Start => {
-                            println!("\n👋 Exiting...");
-                            return;
-                        }
-                        _ => {}
-                    }
-                }
-                EventType::Connected => {
-                    println!("\n🎮 Gamepad connected!");
-                    active_gamepad = Some(id);
-                }
-                EventType::Disconnected => {
-                    println!("\n⚠️  Gamepad disconnected!");
-                    active_gamepad = None;
-                }
-                _ => {}
+    let mut session = Session::new(car, haptics);
+    session.set_envelopes(config.envelopes);
+    session.set_intensity(intensity);
+    session.set_profiles(profiles);
+    #[cfg(feature = "scripting")]
+    session.set_hooks(script.map(|script| Box::new(script) as _));
+    session.set_shift_log(shift_log);
+    session.set_trainer(trainer);
+    session.set_drag(drag);
+    session.set_slowmo_factor(slowmo_factor);
+    session.set_launch_rpm(launch_rpm);
+    session.set_shift_mode(shift_mode);
+    if pedals.clutch().is_some() {
+        session.use_clutch(config.pedals.refuse_clutchless);
+    }
+    if args.automatic {
+        session.set_automatic(true);
+    }
+    if h_pattern.is_some() {
+        // Whatever gate the lever is in gets reported when it moves
+        session
+            .select(GearPosition::Neutral, Instant::now())
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut keyboard = if use_keyboard {
+        Some(Keyboard::start().map_err(|e| format!("keyboard: {}", e))?)
+    } else {
+        None
+    };
+    let mut dashboard = if args.tui {
+        Some(Dashboard::start().map_err(|e| format!("--tui: {}", e))?)
+    } else {
+        None
+    };
+    // A terminal that can't be drawn on any more ends the session too
+    let mut render = dashboard.as_mut().map(|dashboard| {
+        // The keyboard reads the keys itself when it is in use
+        move |session: &Session<GilrsHaptics>| {
+            dashboard.draw(&View::of(session)).is_err()
+                || (!use_keyboard && dashboard.quit_requested().unwrap_or(true))
+        }
+    });
+
+    event_loop::run(
+        &mut session,
+        &mut session_errors,
+        Controls {
+            bindings: &mut bindings,
+            pedals: &mut pedals,
+            h_pattern: h_pattern.as_mut(),
+            telemetry: telemetry
+                .as_mut()
+                .map(|source| &mut **source as &mut dyn TelemetrySource),
+            keyboard: keyboard.as_mut(),
+        },
+        |_position| {
+            #[cfg(feature = "serial-display")]
+            if let Some(display) = &serial_display {
+                display.show_gear(_position);
+            }
+        },
+        render.as_mut().map(|render| render as &mut Render<'_>),
+        Some(&mut reload),
+    )
+    .map_err(|e| format!("main loop: {}", e))?;
+    // The terminal goes back before the strict report is printed
+    drop(dashboard);
+    drop(keyboard);
+
+    save_drag_best(&drag_car, drag_best, session.drag())?;
+    Ok(session_errors.finish())
+}
+
+/// `car` fitted out as the options ask: motor mix, gearbox and turbo.
+fn fit_out(args: &RunArgs, car: &mut Car) -> Result<(), String> {
+    if let Some(spec) = &args.gear_mix {
+        let table = GearMotorMix::parse(spec, car.gear_count())
+            .map_err(|e| format!("--gear-mix: {}", e))?;
+        car.set_motor_mix(Some(table));
+    }
+    if let Some(transmission) = args.transmission {
+        car.set_transmission(Some(transmission.into()));
+    }
+    if args.turbo {
+        car.set_turbo(true);
+    }
+    Ok(())
+}
+
+/// The `[gear_feel]` table for a car with `gear_count` gears, if there is one.
+fn gear_feel(config: &Config, gear_count: u8) -> Result<Option<GearFeelTable>, String> {
+    if config.gear_feel.is_empty() {
+        return Ok(None);
+    }
+    GearFeelTable::from_config(&config.gear_feel, gear_count)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", CONFIG_PATH, e))
+}
+
+fn validate_calibrations(config: &Config) -> Result<(), String> {
+    for (pad, calibration) in &config.calibration {
+        calibration
+            .validate()
+            .map_err(|e| format!("{}: [calibration.\"{}\"] {}", CONFIG_PATH, pad, e))?;
+    }
+    Ok(())
+}
+
+fn parse_slowmo_factor(factor: &str) -> Result<f32, String> {
+    let factor = factor
+        .parse()
+        .map_err(|_| format!("invalid factor '{}'", factor))?;
+    session::check_slowmo_factor(factor)
+}
+
+/// --slowmo, or the config's.
+fn slowmo_factor(args: &RunArgs, config: &Config) -> Result<f32, String> {
+    match (args.slowmo, config.slowmo_factor) {
+        (Some(factor), _) => Ok(factor),
+        (None, Some(factor)) => session::check_slowmo_factor(factor)
+            .map_err(|e| format!("{}: slowmo_factor: {}", CONFIG_PATH, e)),
+        (None, None) => Ok(DEFAULT_SLOWMO_FACTOR),
+    }
+}
+
+fn h_pattern_gates(config: &Config, gear_count: u8) -> Result<HPattern, String> {
+    HPattern::from_config(&config.h_pattern, gear_count)
+        .map_err(|e| format!("--h-pattern: {}: {}", CONFIG_PATH, e))
+}
+
+/// What changed from `old` to `new` that a session driving `car` can take
+/// on. All of `new` is checked, changed or not, so a mistake anywhere in
+/// it keeps the session as it was. Options given on the command line win
+/// over the config here as they do at the start, and so does the rumble of
+/// the `profile` in use.
+fn retune(
+    args: &RunArgs,
+    old: &Config,
+    new: &Config,
+    car: &Car,
+    profile: Option<&str>,
+) -> Result<Tuning, String> {
+    let prefix = |e: String| format!("{}: {}", CONFIG_PATH, e);
+    let bindings = Bindings::from_config(&new.bindings).map_err(prefix)?;
+    let pedals = Pedals::from_config(&new.pedals).map_err(prefix)?;
+    new.envelopes.validate().map_err(prefix)?;
+    validate_calibrations(new)?;
+
+    // Only a preset can change under the car; a profile or specs typed in can't
+    let rebuilt = match args.spec.car.as_deref() {
+        Some(name) if new.car(name) != old.car(name) => {
+            let preset = new
+                .car(name)
+                .ok_or_else(|| prefix(format!("the preset '{}' is gone", name)))?;
+            let units = args.spec.units.map_or(new.units, UnitSystem::from);
+            let mut rebuilt = preset
+                .build(units)
+                .map_err(|e| prefix(format!("preset '{}': {}", name, e)))?;
+            if args.spec.dyno.is_some() {
+                rebuilt.set_torque_curve(car.engine().curve().clone());
+            }
+            fit_out(args, &mut rebuilt)?;
+            Some(rebuilt)
+        }
+        _ => None,
+    };
+    let gear_count = rebuilt.as_ref().map_or(car.gear_count(), Car::gear_count);
+    let gear_feel = gear_feel(new, gear_count)?;
+    let h_pattern = if args.h_pattern {
+        Some(h_pattern_gates(new, gear_count)?)
+    } else {
+        None
+    };
+    let intensity = Intensity::new(
+        args.intensity.or(new.intensity).unwrap_or(100),
+        args.haptic_profile
+            .map_or(new.haptic_profile, HapticProfile::from),
+    )
+    .map_err(prefix)?;
+    let intensity_changed = profile.is_none()
+        && ((args.intensity.is_none() && new.intensity != old.intensity)
+            || (args.haptic_profile.is_none() && new.haptic_profile != old.haptic_profile));
+    let slowmo_factor = slowmo_factor(args, new)?;
+
+    Ok(Tuning {
+        gear_feel: (new.gear_feel != old.gear_feel || (rebuilt.is_some() && gear_feel.is_some()))
+            .then_some(gear_feel),
+        car: rebuilt,
+        envelopes: (new.envelopes != old.envelopes).then_some(new.envelopes),
+        intensity: intensity_changed.then_some(intensity),
+        slowmo_factor: (args.slowmo.is_none() && new.slowmo_factor != old.slowmo_factor)
+            .then_some(slowmo_factor),
+        calibrations: (new.calibration != old.calibration).then(|| new.calibration.clone()),
+        bindings: (new.bindings != old.bindings).then_some(bindings),
+        pedals: (new.pedals != old.pedals).then_some((pedals, new.pedals.refuse_clutchless)),
+        h_pattern: h_pattern.filter(|_| new.h_pattern != old.h_pattern),
+    })
+}
+
+/// Writes a personal best `run` set for `car` back to the config, if it
+/// beat `before`.
+fn save_drag_best(
+    car: &str,
+    before: Option<Duration>,
+    run: Option<&DragRun>,
+) -> Result<(), String> {
+    let Some((strip, best)) = run.and_then(|run| Some((run.strip(), run.best()?))) else {
+        return Ok(());
+    };
+    if before.is_some_and(|before| before <= best) {
+        return Ok(());
+    }
+    let key = match strip {
+        Strip::ZeroToSixty => "drag_best",
+        Strip::QuarterMile => "quarter_mile_best",
+    };
+    edit_config(|document| {
+        let table = document
+            .entry(key)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()));
+        table[car] = toml_edit::value(best.as_millis() as f64 / 1000.0);
+    })?;
+    println!(
+        "💾 {} best of {:.3} s saved to {}",
+        strip.name(),
+        best.as_secs_f32(),
+        CONFIG_PATH
+    );
+    Ok(())
+}
+
+/// Rewrites gear_changer.toml with `edit`, keeping its comments and layout.
+fn edit_config(edit: impl FnOnce(&mut DocumentMut)) -> Result<(), String> {
+    let path = Path::new(CONFIG_PATH);
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", CONFIG_PATH, e)),
+    };
+    let mut document: DocumentMut = text
+        .parse()
+        .map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    edit(&mut document);
+    fs::write(path, document.to_string()).map_err(|e| format!("{}: {}", CONFIG_PATH, e))
+}
+
+fn list_gamepads() -> Result<i32, String> {
+    let gilrs = open_gilrs(true)?;
+    let mut found = false;
+    for (id, gamepad) in gilrs.gamepads() {
+        found = true;
+        println!(
+            "[{}] {} — rumble: {}",
+            id,
+            gamepad.name(),
+            if gamepad.is_ff_supported() {
+                "yes"
+            } else {
+                "no"
             }
+        );
+    }
+    if !found {
+        println!("No gamepads connected");
+    }
+    Ok(0)
+}
+
+fn test_rumble(args: TestRumbleArgs) -> Result<i32, String> {
+    let gilrs = open_gilrs(true)?;
+    let gamepad_id = first_gamepad(&gilrs).ok_or("No gamepad detected")?;
+
+    let mut haptics = GilrsHaptics::new(gilrs);
+    haptics.use_gamepads(Some(vec![gamepad_id.into()]));
+    if !haptics.is_supported() {
+        return Err("Rumble not supported on this gamepad".to_string());
+    }
+
+    let command = RumbleCommand {
+        strong_magnitude: (args.strong.clamp(0.0, 1.0) * 65535.0) as u16,
+        weak_magnitude: (args.weak.clamp(0.0, 1.0) * 65535.0) as u16,
+        duration_ms: args.duration_ms,
+    };
+    println!(
+        "💥 Rumble: strong {} / weak {} for {} ms",
+        command.strong_magnitude, command.weak_magnitude, command.duration_ms
+    );
+    haptics
+        .play(command)
+        .map_err(|e| format!("Rumble failed: {}", e))?;
+    // The effect stops when `haptics` is dropped
+    thread::sleep(Duration::from_millis(command.duration_ms as u64));
+    Ok(0)
+}
+
+fn replay(args: ReplayArgs) -> Result<i32, String> {
+    let path = args.path.display();
+    let text = fs::read_to_string(&args.path).map_err(|e| format!("{}: {}", path, e))?;
+    let records = shift_log::read_json_lines(&text).map_err(|e| format!("{}: {}", path, e))?;
+    let schedule = shift_log::replay_schedule(&records);
+    let Some((_, last)) = schedule.last() else {
+        return Err(format!("{}: no rumbles to replay", path));
+    };
+    let last_ms = last.duration_ms;
+    let config = Config::load(Path::new(CONFIG_PATH))?;
+
+    let gilrs = open_gilrs(true)?;
+    let gamepad_id = first_gamepad(&gilrs).ok_or("No gamepad detected")?;
+    let mut haptics = GilrsHaptics::new(gilrs);
+    haptics.use_gamepads(Some(vec![gamepad_id.into()]));
+    haptics.use_calibrations(config.calibration);
+    if !haptics.is_supported() {
+        return Err("Rumble not supported on this gamepad".to_string());
+    }
+
+    println!(
+        "⏯️  Replaying {} shift rumble(s) from {}",
+        schedule.len(),
+        path
+    );
+    let start = Instant::now();
+    for (at, record) in schedule {
+        thread::sleep((start + at).saturating_duration_since(Instant::now()));
+        println!(
+            "   {:>7.2}s  {} {} → {}: strong {} / weak {} for {} ms",
+            at.as_secs_f32(),
+            record.kind.name(),
+            record.from,
+            record.to,
+            record.strong,
+            record.weak,
+            record.duration_ms
+        );
+        if let Some(pattern) = record.pattern() {
+            haptics
+                .play_pattern(&pattern)
+                .map_err(|e| format!("Rumble failed: {}", e))?;
         }
+    }
+    // The effect stops when `haptics` is dropped
+    thread::sleep(Duration::from_millis(last_ms as u64));
+    Ok(0)
+}
+
+fn measure_latency() -> Result<i32, String> {
+    // Stick jitter is what the measurement listens for, so don't filter it out
+    let mut gilrs = open_gilrs(false)?;
+    let gamepad_id = first_gamepad(&gilrs).ok_or("No gamepad detected")?;
+    if let Some(stats) = latency::run(&mut gilrs, gamepad_id) {
+        save_latency(stats.mean_ms)?;
+    }
+    Ok(0)
+}
+
+/// Offers to keep `ms` with one of the saved profiles.
+fn save_latency(ms: f32) -> Result<(), String> {
+    let mut profiles = Profiles::open()?;
+    let names = profiles.names()?;
+    if names.is_empty() {
+        println!("   Save a profile in a session to keep the latency with it");
+        return Ok(());
+    }
+    println!("\n👤 Profiles: {}", names.join(", "));
+    let name = get_input("Keep the latency with which profile? (Enter to skip): ");
+    if name.is_empty() {
+        return Ok(());
+    }
+    let mut profile = profiles.read(&name)?;
+    profile.latency_ms = Some((ms * 10.0).round() / 10.0);
+    let path = profiles.save(&name, &profile)?;
+    println!("💾 {:.1} ms saved to {}", ms, path.display());
+    Ok(())
+}
+
+fn bind() -> Result<i32, String> {
+    const WAIT: Duration = Duration::from_secs(5);
+
+    let path = Path::new(CONFIG_PATH);
+    let config = Config::load(path)?;
+    let mut bindings =
+        Bindings::from_config(&config.bindings).map_err(|e| format!("{}: {}", CONFIG_PATH, e))?;
+    let mut gilrs = open_gilrs(true)?;
+    first_gamepad(&gilrs).ok_or("No gamepad detected")?;
+
+    println!(
+        "\nPress the input for each action, or wait {} s to keep it.",
+        WAIT.as_secs()
+    );
+    for (bound, key) in BINDABLE {
+        let current = bindings
+            .input_for(bound)
+            .map_or("unbound".to_string(), |input| input.to_string());
+        println!("\n➡️  {} (now {})", key, current);
+
+        // Whatever was pressed before the prompt doesn't count
+        while gilrs.next_event().is_some() {}
+        let deadline = Instant::now() + WAIT;
+        let mut captured = None;
+        while captured.is_none() {
+            let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            if let Some(event) = gilrs.next_event_blocking(Some(left)) {
+                captured = bindings::captured(&event.event);
+            }
+        }
+        match captured {
+            Some(input) => {
+                println!("   ✅ {}", input);
+                bindings.set(bound, input);
+                // Let go of it before the next prompt
+                thread::sleep(Duration::from_millis(500));
+            }
+            None => println!("   kept"),
+        }
+    }
+
+    edit_config(|document| {
+        let mut table = toml_edit::Table::new();
+        for (key, input) in bindings.entries() {
+            table.insert(&key, toml_edit::value(input));
+        }
+        document.insert("bindings", toml_edit::Item::Table(table));
+    })?;
+    println!("\n💾 Saved to {}", CONFIG_PATH);
+    Ok(0)
+}
+
+// How long each `calibrate` step rumbles
+const CALIBRATION_STEP_MS: u32 = 400;
+
+fn calibrate() -> Result<i32, String> {
+    let gilrs = open_gilrs(true)?;
+    let gamepad_id = first_gamepad(&gilrs).ok_or("No gamepad detected")?;
+    let name = gilrs.gamepad(gamepad_id).name().to_string();
+    let mut haptics = GilrsHaptics::new(gilrs);
+    haptics.use_gamepads(Some(vec![gamepad_id.into()]));
+    if !haptics.is_supported() {
+        return Err("Rumble not supported on this gamepad".to_string());
+    }
+
+    println!("\nEach step is a short rumble, a little stronger than the last.");
+    println!("Answer y as soon as you feel it, r to feel it again, or Enter if you don't.");
+    let strong = felt_at(&mut haptics, Motors::Strong)?;
+    let weak = felt_at(&mut haptics, Motors::Weak)?;
+    let calibration = Calibration::from_thresholds(strong, weak);
+    // The pad as it will feel from now on
+    haptics.use_calibrations(BTreeMap::from([(name.clone(), calibration)]));
+    let both = felt_at(&mut haptics, Motors::Both)?;
+
+    let level =
+        |felt: Option<f32>| felt.map_or("never".to_string(), |l| format!("{:.0}%", l * 100.0));
+    println!("\n🎚️  {}", name);
+    println!(
+        "   Strong motor felt at {}: ×{:.2}",
+        level(strong),
+        calibration.strong
+    );
+    println!(
+        "   Weak motor felt at {}: ×{:.2}",
+        level(weak),
+        calibration.weak
+    );
+    println!("   Both, calibrated, felt at {}", level(both));
+    if get_input("\nSave for this pad? [Y/n] ").eq_ignore_ascii_case("n") {
+        println!("Not saved");
+        return Ok(0);
+    }
+    let rounded = |scale: f32| (scale as f64 * 100.0).round() / 100.0;
+    edit_config(|document| {
+        let calibrations = document
+            .entry("calibration")
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()));
+        let mut table = toml_edit::Table::new();
+        table.insert("strong", toml_edit::value(rounded(calibration.strong)));
+        table.insert("weak", toml_edit::value(rounded(calibration.weak)));
+        calibrations[name.as_str()] = toml_edit::Item::Table(table);
+    })?;
+    println!("\n💾 Saved to {}", CONFIG_PATH);
+    Ok(0)
+}
+
+/// Plays `motors` at each calibration step until the driver feels one,
+/// and returns its level, `None` if they felt none.
+fn felt_at(haptics: &mut GilrsHaptics, motors: Motors) -> Result<Option<f32>, String> {
+    println!("\n➡️  {}", motors.name());
+    for level in calibration::STEPS {
+        loop {
+            haptics
+                .play(motors.command(level, CALIBRATION_STEP_MS))
+                .map_err(|e| format!("Rumble failed: {}", e))?;
+            let answer = get_input(&format!("   {:>3.0}%: feel it? [y/r/N] ", level * 100.0));
+            match answer.to_ascii_lowercase().as_str() {
+                "y" | "yes" => return Ok(Some(level)),
+                "r" => continue,
+                _ => break,
+            }
+        }
+    }
+    println!("   Not felt at all");
+    Ok(None)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Run(args)) => run(*args),
+        None => run(RunArgs::default()),
+        Some(Command::ListGamepads) => list_gamepads(),
+        Some(Command::Cars) => list_cars(),
+        Some(Command::Dyno(args)) => dyno_chart(args),
+        Some(Command::TestRumble(args)) => test_rumble(args),
+        Some(Command::MeasureLatency) => measure_latency(),
+        Some(Command::Replay(args)) => replay(args),
+        Some(Command::Bind) => bind(),
+        Some(Command::Calibrate) => calibrate(),
+        #[cfg(feature = "serial-display")]
+        Some(Command::ListSerial) => {
+            serial_display::list_ports();
+            Ok(0)
+        }
+    };
+
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("gear_changer").chain(args.iter().copied()))
+    }
+
+    fn run_args(args: &[&str]) -> RunArgs {
+        match parse(args).unwrap().command {
+            Some(Command::Run(args)) => *args,
+            other => panic!("expected run, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_arguments_is_interactive() {
+        assert!(parse(&[]).unwrap().command.is_none());
+    }
+
+    #[test]
+    fn run_takes_specs() {
+        let args = run_args(&["run", "--torque", "300", "--hp", "400"]);
+        assert_eq!(args.spec.torque, Some(300.0));
+        assert_eq!(args.spec.hp, Some(400.0));
+        assert_eq!(args.strict, None);
+
+        let args = run_args(&["run", "--gears", "3.2,1.9,1.3,1.0", "--final-drive", "3.9"]);
+        assert_eq!(args.spec.gears, Some(vec![3.2, 1.9, 1.3, 1.0]));
+        assert_eq!(args.spec.final_drive, Some(3.9));
+    }
+
+    #[test]
+    fn a_profile_instead_of_specs() {
+        assert_eq!(
+            run_args(&["run", "--profile", "track"]).profile.as_deref(),
+            Some("track")
+        );
+        assert!(parse(&["run", "--profile", "track", "--car", "miata"]).is_err());
+        assert!(parse(&["run", "--profile", "track", "--torque", "300"]).is_err());
+    }
+
+    #[test]
+    fn retune_takes_on_only_what_changed() {
+        let args = run_args(&["run", "--car", "mine", "--haptic-profile", "gentle"]);
+        let old = Config::parse("[cars.mine]\ntorque = 300\nhorsepower = 400").unwrap();
+        let car = old.car("mine").unwrap().build(old.units).unwrap();
+
+        let tuning = retune(&args, &old, &old, &car, None).unwrap();
+        assert!(tuning.changes().is_empty());
+
+        let new = Config::parse(
+            "intensity = 60\nhaptic_profile = \"standard\"\n\
+             [cars.mine]\ntorque = 300\nhorsepower = 400\ngears = 4\n\
+             [gear_feel.1]\nintensity = 1.5\n\
+             [envelopes.upshift]\nattack_ms = 30\nsustain_level = 0.5",
+        )
+        .unwrap();
+        let tuning = retune(&args, &old, &new, &car, None).unwrap();
+        assert_eq!(
+            tuning.changes(),
+            ["car", "gear feel", "envelopes", "intensity"]
+        );
+        assert_eq!(tuning.car.as_ref().unwrap().gear_count(), 4);
+        // --haptic-profile wins over the file
+        let intensity = tuning.intensity.unwrap();
+        assert_eq!(intensity.percent(), 60);
+        assert_eq!(intensity.profile(), HapticProfile::Gentle);
+        // A profile in use keeps its own rumble, as it does at the start
+        let tuning = retune(&args, &old, &new, &car, Some("track")).unwrap();
+        assert_eq!(tuning.changes(), ["car", "gear feel", "envelopes"]);
+        assert!(tuning.intensity.is_none());
+
+        // A mistake anywhere keeps everything as it was
+        let bad = Config::parse("intensity = 60\n[bindings]\nupshift = \"Turbo\"").unwrap();
+        assert!(retune(&args, &old, &bad, &car, None).is_err());
+        let gone = Config::parse("intensity = 60").unwrap();
+        assert!(retune(&args, &old, &gone, &car, None).is_err());
+    }
+
+    #[test]
+    fn slowmo_factor_from_the_flag_or_the_config() {
+        let config = Config::parse("slowmo_factor = 3").unwrap();
+        assert_eq!(slowmo_factor(&run_args(&["run"]), &config), Ok(3.0));
+        assert_eq!(
+            slowmo_factor(&run_args(&["run"]), &Config::default()),
+            Ok(DEFAULT_SLOWMO_FACTOR)
+        );
+        let args = run_args(&["run", "--slowmo", "2.5"]);
+        assert_eq!(slowmo_factor(&args, &config), Ok(2.5));
+        assert!(parse(&["run", "--slowmo", "1"]).is_err());
+        assert!(parse(&["run", "--slowmo", "fast"]).is_err());
+
+        let bad = Config::parse("slowmo_factor = 0.5").unwrap();
+        assert!(slowmo_factor(&run_args(&["run"]), &bad).is_err());
+        // Reloaded unless --slowmo is given
+        let old = Config::default();
+        let car = old.car("miata").unwrap().build(old.units).unwrap();
+        let tuning = retune(&run_args(&["run"]), &old, &config, &car, None).unwrap();
+        assert_eq!(tuning.slowmo_factor, Some(3.0));
+        let tuning = retune(&args, &old, &config, &car, None).unwrap();
+        assert!(tuning.changes().is_empty());
+        assert!(retune(&run_args(&["run"]), &old, &bad, &car, None).is_err());
+    }
+
+    #[test]
+    fn telemetry_source_and_address() {
+        let args = run_args(&["run", "--telemetry", "assetto-corsa"]);
+        assert_eq!(args.telemetry, Some(TelemetryArg::AssettoCorsa));
+        assert_eq!(args.telemetry_address, None);
+
+        let args = run_args(&[
+            "run",
+            "--telemetry",
+            "assetto-corsa",
+            "--telemetry-address",
+            "192.168.1.20:9996",
+        ]);
+        assert_eq!(
+            args.telemetry_address,
+            Some("192.168.1.20:9996".parse().unwrap())
+        );
+        assert!(parse(&["run", "--telemetry-address", "127.0.0.1:9996"]).is_err());
+
+        assert_eq!(
+            run_args(&["run", "--telemetry", "iracing"]).telemetry,
+            Some(TelemetryArg::IRacing)
+        );
+        assert_eq!(
+            run_args(&["run", "--telemetry", "f1"]).telemetry,
+            Some(TelemetryArg::F1)
+        );
+        assert_eq!(
+            run_args(&["run", "--telemetry", "dirt-rally"]).telemetry,
+            Some(TelemetryArg::DirtRally)
+        );
+        assert_eq!(
+            run_args(&["run", "--telemetry", "outgauge"]).telemetry,
+            Some(TelemetryArg::OutGauge)
+        );
+        assert_eq!(
+            run_args(&["run", "--telemetry", "json"]).telemetry,
+            Some(TelemetryArg::Json)
+        );
+        assert_eq!(
+            run_args(&["run", "--telemetry", "forza"]).telemetry,
+            Some(TelemetryArg::Forza)
+        );
+    }
+
+    #[test]
+    fn gamepad_subset() {
+        assert_eq!(run_args(&["run"]).gamepads, None);
+        assert_eq!(
+            run_args(&["run", "--gamepads", "0,2"]).gamepads,
+            Some(vec![0, 2])
+        );
+        assert!(parse(&["run", "--gamepads", "pad"]).is_err());
+    }
+
+    #[test]
+    fn h_pattern_replaces_telemetry() {
+        assert!(!run_args(&["run"]).h_pattern);
+        assert!(run_args(&["run", "--h-pattern"]).h_pattern);
+        assert!(parse(&["run", "--h-pattern", "--telemetry", "f1"]).is_err());
+    }
+
+    #[test]
+    fn automatic_is_for_the_simulated_box() {
+        assert!(run_args(&["run", "--automatic"]).automatic);
+        assert!(parse(&["run", "--automatic", "--h-pattern"]).is_err());
+        assert!(parse(&["run", "--automatic", "--telemetry", "forza"]).is_err());
+    }
+
+    #[test]
+    fn trainer_window() {
+        let args = run_args(&["run", "--trainer", "--target-rpm", "5800-6400"]);
+        assert!(args.trainer);
+        assert_eq!(
+            args.target_rpm,
+            Some(RpmWindow {
+                low: 5800.0,
+                high: 6400.0
+            })
+        );
+        assert_eq!(run_args(&["run", "--trainer"]).target_rpm, None);
+        assert!(parse(&["run", "--target-rpm", "5800-6400"]).is_err());
+        assert!(parse(&["run", "--trainer", "--target-rpm", "6400"]).is_err());
+        assert!(parse(&["run", "--trainer", "--telemetry", "f1"]).is_err());
+    }
+
+    #[test]
+    fn drag_flags() {
+        let args = run_args(&["run", "--drag", "--weight", "1200"]);
+        assert_eq!(args.drag, Some(DragArg::ZeroToSixty));
+        assert_eq!(args.weight, Some(1200.0));
+        assert_eq!(
+            run_args(&["run", "--drag=quarter-mile"]).drag,
+            Some(DragArg::QuarterMile)
+        );
+        assert!(parse(&["run", "--drag=half-mile"]).is_err());
+        assert!(parse(&["run", "--weight", "1200"]).is_err());
+        assert!(parse(&["run", "--drag", "--automatic"]).is_err());
+    }
+
+    #[test]
+    fn shift_modes() {
+        assert_eq!(run_args(&["run"]).shift_mode, None);
+        assert_eq!(
+            run_args(&["run", "--h-pattern", "--shift-mode", "sequential"]).shift_mode,
+            Some(ShiftModeArg::Sequential)
+        );
+        assert!(parse(&["run", "--shift-mode", "direct", "--telemetry", "f1"]).is_err());
+    }
+
+    #[test]
+    fn transmission_types() {
+        let args = run_args(&["run", "--transmission", "dct"]);
+        assert_eq!(args.transmission, Some(TransmissionArg::Dct));
+        assert_eq!(
+            Transmission::from(
+                run_args(&["run", "--transmission", "auto"])
+                    .transmission
+                    .unwrap()
+            ),
+            Transmission::Automatic
+        );
+        assert!(parse(&["run", "--transmission", "cvt"]).is_err());
+    }
+
+    #[test]
+    fn built_in_presets_by_name() {
+        let car = build_car(
+            &run_args(&["run", "--car", "gt3rs"]).spec,
+            &Config::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(car.gear_count(), 7);
+        assert!(
+            build_car(
+                &run_args(&["run", "--car", "trabant"]).spec,
+                &Config::default(),
+                false
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn metric_units_read_torque_and_power() {
+        let args = run_args(&[
+            "run", "--units", "metric", "--torque", "469", "--power", "386",
+        ]);
+        assert_eq!(args.spec.units, Some(UnitsArg::Metric));
+        let car = build_car(&args.spec, &Config::default(), false).unwrap();
+        assert!((car.torque().lb_ft() - 346.0).abs() < 0.5);
+        assert!((car.power().hp() - 518.0).abs() < 0.5);
+        assert_eq!(car.units(), UnitSystem::Metric);
+        assert_eq!(
+            UnitSystem::from(
+                run_args(&["run", "--units", "metric-ps"])
+                    .spec
+                    .units
+                    .unwrap()
+            ),
+            UnitSystem::MetricPs
+        );
+    }
+
+    #[test]
+    fn launch_rpm() {
+        assert_eq!(
+            run_args(&["run", "--launch-rpm", "4500"]).launch_rpm,
+            Some(4500.0)
+        );
+        assert_eq!(run_args(&["run"]).launch_rpm, None);
+        assert!(parse(&["run", "--launch-rpm", "4500", "--telemetry", "f1"]).is_err());
+    }
+
+    #[test]
+    fn gamepad_menu_choice() {
+        assert_eq!(parse_gamepad_choice("", 3), Ok(None));
+        assert_eq!(parse_gamepad_choice(" All ", 3), Ok(None));
+        assert_eq!(parse_gamepad_choice("2", 3), Ok(Some(vec![1])));
+        assert_eq!(parse_gamepad_choice("1, 3", 3), Ok(Some(vec![0, 2])));
+        assert!(parse_gamepad_choice("0", 3).is_err());
+        assert!(parse_gamepad_choice("4", 3).is_err());
+        assert!(parse_gamepad_choice("wheel", 3).is_err());
+    }
+
+    #[test]
+    fn strict_flag_parsing() {
+        assert_eq!(run_args(&["run"]).strict, None);
+        assert_eq!(
+            run_args(&["run", "--strict"]).strict,
+            Some(StrictArg::Report)
+        );
+        assert_eq!(
+            run_args(&["run", "--strict=fail-fast"]).strict,
+            Some(StrictArg::FailFast)
+        );
+        assert!(parse(&["run", "--strict=sometimes"]).is_err());
+    }
+
+    #[test]
+    fn preset_excludes_manual_specs() {
+        assert_eq!(
+            run_args(&["run", "--car", "gt3rs"]).spec.car.as_deref(),
+            Some("gt3rs")
+        );
+        assert!(parse(&["run", "--car", "gt3rs", "--torque", "300"]).is_err());
+    }
+
+    #[test]
+    fn other_subcommands() {
+        assert!(matches!(
+            parse(&["list-gamepads"]).unwrap().command,
+            Some(Command::ListGamepads)
+        ));
+        assert!(matches!(
+            parse(&["bind"]).unwrap().command,
+            Some(Command::Bind)
+        ));
+        assert!(matches!(
+            parse(&["calibrate"]).unwrap().command,
+            Some(Command::Calibrate)
+        ));
+        assert!(matches!(
+            parse(&["cars"]).unwrap().command,
+            Some(Command::Cars)
+        ));
+        match parse(&["dyno", "--car", "supra"]).unwrap().command {
+            Some(Command::Dyno(args)) => assert_eq!(args.car.as_deref(), Some("supra")),
+            other => panic!("expected dyno, got {:?}", other),
+        }
+        match parse(&["test-rumble", "--strong", "0.5"]).unwrap().command {
+            Some(Command::TestRumble(args)) => {
+                assert_eq!(args.strong, 0.5);
+                assert_eq!(args.weak, 1.0);
+                assert_eq!(args.duration_ms, 500);
+            }
+            other => panic!("expected test-rumble, got {:?}", other),
+        }
+        match parse(&["replay", "session.jsonl"]).unwrap().command {
+            Some(Command::Replay(args)) => assert_eq!(args.path, PathBuf::from("session.jsonl")),
+            other => panic!("expected replay, got {:?}", other),
+        }
+        assert!(parse(&["replay"]).is_err());
+    }
 
-        // Small delay to prevent CPU spinning
-        std::thread::sleep(std::time::Duration::from_millis(10));
+    #[test]
+    fn cli_definition_is_consistent() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
     }
 }
@@ -0,0 +1,216 @@
+// Blends rumbles that overlap in time. Without it every rumble replaces the
+// one before, so a second shift cuts the first off mid-thunk; with it every
+// rumble keeps playing its own pattern as a layer and the motors get the sum
+// or the strongest of the layers at each moment.
+
+use crate::haptics::{Pulse, RumbleCommand, RumblePattern};
+use std::time::Instant;
+
+/// How overlapping layers combine on each motor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixMode {
+    /// Layers add up, clamped to full power.
+    Sum,
+    /// The strongest layer wins.
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Layer {
+    started: Instant,
+    pattern: RumblePattern,
+    repeats: bool, // Loops until stopped instead of ending
+}
+
+impl Layer {
+    /// This layer's magnitudes at `now`, `None` once it has ended.
+    fn magnitudes_at(&self, now: Instant) -> Option<(u16, u16)> {
+        if now < self.started {
+            return Some((0, 0));
+        }
+        let length_ms = self.pattern.duration_ms();
+        let mut at_ms = (now - self.started).as_millis() as u64;
+        if self.repeats && length_ms > 0 {
+            at_ms %= length_ms as u64;
+        }
+        let mut start_ms = 0;
+        for stage in self.pattern.stages() {
+            start_ms += stage.duration_ms as u64;
+            if at_ms < start_ms {
+                return Some((stage.strong_magnitude, stage.weak_magnitude));
+            }
+        }
+        None
+    }
+}
+
+/// Every rumble that is playing, and how they combine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mixer {
+    mode: MixMode,
+    layers: Vec<Layer>,
+}
+
+impl Mixer {
+    pub fn new(mode: MixMode) -> Self {
+        Self {
+            mode,
+            layers: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> MixMode {
+        self.mode
+    }
+
+    /// Starts `pattern` at `now` on top of whatever is playing.
+    pub fn add(&mut self, pattern: RumblePattern, now: Instant) {
+        self.layers.push(Layer {
+            started: now,
+            pattern,
+            repeats: false,
+        });
+    }
+
+    /// Starts strong-motor `pulses` at `now` on top of whatever is playing.
+    pub fn add_pulses(&mut self, magnitude: u16, pulses: &[Pulse], now: Instant) {
+        let mut stages = Vec::new();
+        let mut end_ms = 0;
+        for pulse in pulses {
+            let gap_ms = pulse.after_ms.saturating_sub(end_ms);
+            if gap_ms > 0 {
+                stages.push(silence(gap_ms));
+            }
+            stages.push(RumbleCommand {
+                strong_magnitude: magnitude,
+                weak_magnitude: 0,
+                duration_ms: pulse.duration_ms,
+            });
+            end_ms = pulse.after_ms + pulse.duration_ms;
+        }
+        self.add(RumblePattern::new(stages), now);
+    }
+
+    /// Plays `command` every `period_ms` from `now` until stopped. There is
+    /// only ever one repeating layer; a new one replaces the old.
+    pub fn add_repeating(&mut self, command: RumbleCommand, period_ms: u32, now: Instant) {
+        self.stop_repeating();
+        let mut stages = vec![command];
+        let gap_ms = period_ms.saturating_sub(command.duration_ms);
+        if gap_ms > 0 {
+            stages.push(silence(gap_ms));
+        }
+        self.layers.push(Layer {
+            started: now,
+            pattern: RumblePattern::new(stages),
+            repeats: true,
+        });
+    }
+
+    pub fn stop_repeating(&mut self) {
+        self.layers.retain(|layer| !layer.repeats);
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// What the strong and weak motors should be doing at `now`. Layers that
+    /// have ended are dropped.
+    pub fn magnitudes_at(&mut self, now: Instant) -> (u16, u16) {
+        let mut playing = Vec::new();
+        self.layers.retain(|layer| match layer.magnitudes_at(now) {
+            Some(magnitudes) => {
+                playing.push(magnitudes);
+                true
+            }
+            None => false,
+        });
+        playing
+            .into_iter()
+            .fold((0, 0), |(strong, weak), (s, w)| match self.mode {
+                MixMode::Sum => (strong.saturating_add(s), weak.saturating_add(w)),
+                MixMode::Max => (strong.max(s), weak.max(w)),
+            })
+    }
+}
+
+fn silence(duration_ms: u32) -> RumbleCommand {
+    RumbleCommand {
+        strong_magnitude: 0,
+        weak_magnitude: 0,
+        duration_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn command(strong: u16, weak: u16, duration_ms: u32) -> RumbleCommand {
+        RumbleCommand {
+            strong_magnitude: strong,
+            weak_magnitude: weak,
+            duration_ms,
+        }
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn overlapping_layers_combine() {
+        let now = Instant::now();
+        for (mode, expected) in [
+            (MixMode::Sum, (u16::MAX, 30000)),
+            (MixMode::Max, (40000, 20000)),
+        ] {
+            let mut mixer = Mixer::new(mode);
+            mixer.add(RumblePattern::single(command(40000, 10000, 200)), now);
+            mixer.add(
+                RumblePattern::single(command(30000, 20000, 100)),
+                now + ms(50),
+            );
+            assert_eq!(mixer.magnitudes_at(now + ms(10)), (40000, 10000));
+            assert_eq!(mixer.magnitudes_at(now + ms(60)), expected);
+            // The second shift ended, the first is still going
+            assert_eq!(mixer.magnitudes_at(now + ms(160)), (40000, 10000));
+            assert_eq!(mixer.magnitudes_at(now + ms(200)), (0, 0));
+            assert!(mixer.is_idle());
+        }
+    }
+
+    #[test]
+    fn repeating_layer_loops_under_the_rest() {
+        let now = Instant::now();
+        let mut mixer = Mixer::new(MixMode::Max);
+        mixer.add_repeating(command(45000, 30000, 25), 70, now);
+        assert_eq!(mixer.magnitudes_at(now + ms(10)), (45000, 30000));
+        assert_eq!(mixer.magnitudes_at(now + ms(40)), (0, 0));
+        assert_eq!(mixer.magnitudes_at(now + ms(7010)), (45000, 30000));
+
+        mixer.add_pulses(
+            52000,
+            &[Pulse {
+                after_ms: 20,
+                duration_ms: 20,
+            }],
+            now + ms(7000),
+        );
+        assert_eq!(mixer.magnitudes_at(now + ms(7010)), (45000, 30000));
+        assert_eq!(mixer.magnitudes_at(now + ms(7030)), (52000, 0));
+
+        // Only one limiter at a time, and it can go on its own
+        mixer.add_repeating(command(1000, 1000, 25), 70, now);
+        mixer.stop_repeating();
+        assert_eq!(mixer.magnitudes_at(now + ms(7030)), (52000, 0));
+        assert_eq!(mixer.magnitudes_at(now + ms(7040)), (0, 0));
+        assert!(mixer.is_idle());
+    }
+}
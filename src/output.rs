@@ -0,0 +1,95 @@
+// Where the session's messages go: printed as they happen, or kept for the
+// dashboard to show while it owns the terminal. Everything that can print
+// during a session goes through `say!` instead of `println!`.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// The dashboard only ever shows the last few
+const KEPT_LINES: usize = 200;
+
+static CAPTURED: Mutex<Option<VecDeque<String>>> = Mutex::new(None);
+
+// A terminal in raw mode doesn't return the carriage on a newline
+static RAW: AtomicBool = AtomicBool::new(false);
+
+/// `println!` unless the dashboard is capturing the output.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::say(format!($($arg)*))
+    };
+}
+
+pub fn say(text: String) {
+    let mut captured = CAPTURED.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(lines) = captured.as_mut() else {
+        if RAW.load(Ordering::Relaxed) {
+            print!("{}\r\n", text.replace('\n', "\r\n"));
+        } else {
+            println!("{}", text);
+        }
+        return;
+    };
+    // The blank lines that space messages out on a console only waste rows
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        if lines.len() == KEPT_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+}
+
+/// Starts keeping messages instead of printing them, or goes back to
+/// printing and forgets whatever was kept.
+pub fn capture(on: bool) {
+    let mut captured = CAPTURED.lock().unwrap_or_else(|e| e.into_inner());
+    *captured = on.then(VecDeque::new);
+}
+
+/// Tells `say` the terminal is in raw mode, or back out of it.
+pub fn raw(on: bool) {
+    RAW.store(on, Ordering::Relaxed);
+}
+
+pub fn is_captured() -> bool {
+    CAPTURED.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// The last `count` kept messages, oldest first.
+pub fn last_lines(count: usize) -> Vec<String> {
+    let captured = CAPTURED.lock().unwrap_or_else(|e| e.into_inner());
+    captured.as_ref().map_or_else(Vec::new, |lines| {
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captured_lines_skip_blanks_and_keep_the_last() {
+        // The only test touching the global capture, so nothing races it
+        capture(true);
+        say!("\n🔼 UPSHIFT → Gear {}", 4);
+        say("   Engine: 3000 rpm\n".to_string());
+        assert_eq!(
+            last_lines(5),
+            ["🔼 UPSHIFT → Gear 4", "   Engine: 3000 rpm"]
+        );
+        for i in 0..KEPT_LINES {
+            say(i.to_string());
+        }
+        assert_eq!(last_lines(1), [(KEPT_LINES - 1).to_string()]);
+        assert_eq!(last_lines(KEPT_LINES * 2).len(), KEPT_LINES);
+        capture(false);
+        assert!(!is_captured());
+        assert!(last_lines(5).is_empty());
+    }
+}
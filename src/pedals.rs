@@ -0,0 +1,355 @@
+// Analog pedals on gamepad triggers or wheel axes. `[pedals]` in
+// gear_changer.toml names them the same way `[bindings]` does. Triggers
+// report 0 to 1 on their own; axes are signed towards the end of travel
+// that means "pressed":
+//
+// [pedals]
+// throttle = "RightTrigger2"  # the default, "" for no throttle pedal
+// brake = "LeftTrigger2"      # the default, "" for no brake pedal
+// clutch = "+axis:2"          # -1 released, +1 on the floor
+// clutch_threshold = 0.8      # how far down counts as a clean shift
+// refuse_clutchless = false   # true: a shift without the clutch doesn't go in
+//
+// [pedals.response.throttle]  # optional, per pedal, for worn or noisy pots
+// deadzone = 0.05             # travel ignored off the top
+// saturation = 0.95           # travel that already counts as the floor
+// curve = "expo"              # finer at the top of travel, "linear" by default
+
+use crate::bindings::Input;
+use gilrs::{Axis, Button, EventType};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+const PEDAL_NAMES: [&str; 3] = ["throttle", "brake", "clutch"];
+
+// What an expo curve raises travel to the power of
+const EXPO_POWER: f32 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PedalConfig {
+    pub throttle: String,
+    pub brake: String,
+    pub clutch: Option<String>,
+    pub clutch_threshold: f32,
+    pub refuse_clutchless: bool,
+    pub response: BTreeMap<String, Response>, // Pedal → response
+}
+
+impl Default for PedalConfig {
+    fn default() -> Self {
+        Self {
+            throttle: "RightTrigger2".to_string(),
+            brake: "LeftTrigger2".to_string(),
+            clutch: None,
+            clutch_threshold: 0.8,
+            refuse_clutchless: false,
+            response: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Travel squared: gentle at the top, where a pedal is feathered.
+    Expo,
+}
+
+/// How a pedal's raw travel becomes what the engine sees.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Response {
+    pub deadzone: f32,
+    pub saturation: f32,
+    pub curve: Curve,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.0,
+            saturation: 1.0,
+            curve: Curve::Linear,
+        }
+    }
+}
+
+impl Response {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.deadzone) {
+            return Err(format!("deadzone: {} is outside 0 to 1", self.deadzone));
+        }
+        if !(self.deadzone < self.saturation && self.saturation <= 1.0) {
+            return Err(format!(
+                "saturation: {} is not between the deadzone, {}, and 1",
+                self.saturation, self.deadzone
+            ));
+        }
+        Ok(())
+    }
+
+    /// `raw` travel, 0 to 1, past the deadzone and up to saturation, spread
+    /// over 0 to 1 and through the curve.
+    pub fn apply(&self, raw: f32) -> f32 {
+        let travel = ((raw - self.deadzone) / (self.saturation - self.deadzone)).clamp(0.0, 1.0);
+        match self.curve {
+            Curve::Linear => travel,
+            Curve::Expo => travel.powf(EXPO_POWER),
+        }
+    }
+}
+
+/// One pedal: an analog button or axis direction, and how far along it the
+/// pedal is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pedal {
+    input: Input,
+    response: Response,
+    travel: f32, // 0 released, 1 on the floor, after the response
+}
+
+impl Pedal {
+    pub fn new(input: Input) -> Self {
+        Self::with_response(input, Response::default())
+    }
+
+    pub fn with_response(input: Input, response: Response) -> Self {
+        Self {
+            input,
+            response,
+            travel: 0.0,
+        }
+    }
+
+    pub fn input(&self) -> Input {
+        self.input
+    }
+
+    pub fn response(&self) -> Response {
+        self.response
+    }
+
+    pub fn travel(&self) -> f32 {
+        self.travel
+    }
+
+    /// Follows an analog button. Returns false if it's some other input.
+    fn button_moved(&mut self, button: Button, value: f32, code: u32) -> bool {
+        if !self.input.is_button(button, code) {
+            return false;
+        }
+        self.travel = self.response.apply(value);
+        true
+    }
+
+    /// Follows an axis. Returns false if it's some other input.
+    fn axis_moved(&mut self, axis: Axis, value: f32, code: u32) -> bool {
+        let (Input::Axis(_, positive) | Input::AxisCode(_, positive)) = self.input else {
+            return false;
+        };
+        if !self.input.is_axis(axis, code) {
+            return false;
+        }
+        let towards_pressed = if positive { value } else { -value };
+        self.travel = self.response.apply((towards_pressed + 1.0) / 2.0);
+        true
+    }
+}
+
+/// Every configured pedal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pedals {
+    throttle: Option<Pedal>,
+    brake: Option<Pedal>,
+    clutch: Option<Pedal>,
+    clutch_threshold: f32,
+}
+
+impl Pedals {
+    pub fn from_config(config: &PedalConfig) -> Result<Self, String> {
+        for (name, response) in &config.response {
+            if !PEDAL_NAMES.contains(&name.as_str()) {
+                return Err(format!("[pedals.response] unknown pedal '{}'", name));
+            }
+            response
+                .validate()
+                .map_err(|e| format!("[pedals.response.{}] {}", name, e))?;
+        }
+        let pedal = |name: &str, input: Option<&str>| {
+            let response = config.response.get(name).copied().unwrap_or_default();
+            input
+                .filter(|input| !input.trim().is_empty())
+                .map(|input| {
+                    input
+                        .parse()
+                        .map(|input| Pedal::with_response(input, response))
+                })
+                .transpose()
+                .map_err(|e| format!("[pedals] {}: {}", name, e))
+        };
+        if !(0.0..=1.0).contains(&config.clutch_threshold) {
+            return Err(format!(
+                "[pedals] clutch_threshold: {} is outside 0 to 1",
+                config.clutch_threshold
+            ));
+        }
+        Ok(Self {
+            throttle: pedal("throttle", Some(&config.throttle))?,
+            brake: pedal("brake", Some(&config.brake))?,
+            clutch: pedal("clutch", config.clutch.as_deref())?,
+            clutch_threshold: config.clutch_threshold,
+        })
+    }
+
+    pub fn throttle(&self) -> Option<&Pedal> {
+        self.throttle.as_ref()
+    }
+
+    pub fn brake(&self) -> Option<&Pedal> {
+        self.brake.as_ref()
+    }
+
+    pub fn clutch(&self) -> Option<&Pedal> {
+        self.clutch.as_ref()
+    }
+
+    /// Whether the clutch is down far enough to shift, `None` without a
+    /// clutch pedal.
+    pub fn clutch_down(&self) -> Option<bool> {
+        let clutch = self.clutch.as_ref()?;
+        Some(clutch.travel() >= self.clutch_threshold)
+    }
+
+    /// Follows `event`. Returns true if it moved a pedal.
+    pub fn update(&mut self, event: &EventType) -> bool {
+        match *event {
+            EventType::ButtonChanged(button, value, code) => {
+                self.button_changed(button, value, code.into_u32())
+            }
+            EventType::AxisChanged(axis, value, code) => {
+                self.axis_changed(axis, value, code.into_u32())
+            }
+            _ => false,
+        }
+    }
+
+    fn pedals_mut(&mut self) -> impl Iterator<Item = &mut Pedal> {
+        [&mut self.throttle, &mut self.brake, &mut self.clutch]
+            .into_iter()
+            .flatten()
+    }
+
+    fn button_changed(&mut self, button: Button, value: f32, code: u32) -> bool {
+        // Every pedal on the input follows it, not just the first
+        let mut moved = false;
+        for pedal in self.pedals_mut() {
+            moved |= pedal.button_moved(button, value, code);
+        }
+        moved
+    }
+
+    fn axis_changed(&mut self, axis: Axis, value: f32, code: u32) -> bool {
+        let mut moved = false;
+        for pedal in self.pedals_mut() {
+            moved |= pedal.axis_moved(axis, value, code);
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pedals(config: &str) -> Result<Pedals, String> {
+        let config: PedalConfig = toml::from_str(config).map_err(|e| e.to_string())?;
+        Pedals::from_config(&config)
+    }
+
+    #[test]
+    fn triggers_by_default() {
+        let mut pedals = pedals("").unwrap();
+        assert_eq!(pedals.clutch_down(), None);
+        assert!(pedals.button_changed(Button::RightTrigger2, 0.25, 0));
+        assert_eq!(pedals.throttle().unwrap().travel(), 0.25);
+        assert!(pedals.button_changed(Button::LeftTrigger2, 1.0, 0));
+        assert_eq!(pedals.brake().unwrap().travel(), 1.0);
+        assert!(!pedals.button_changed(Button::South, 1.0, 0));
+
+        let pedals = self::pedals("throttle = \"\"\nbrake = \"-axis:1\"").unwrap();
+        assert_eq!(pedals.throttle(), None);
+        assert_eq!(pedals.brake().unwrap().input(), Input::AxisCode(1, false));
+    }
+
+    #[test]
+    fn clutch_travel_and_threshold() {
+        let mut pedals = pedals("clutch = \"+axis:2\"").unwrap();
+        assert_eq!(pedals.clutch_down(), Some(false));
+        assert!(pedals.axis_changed(Axis::Unknown, 0.0, 2));
+        assert_eq!(pedals.clutch().unwrap().travel(), 0.5);
+        assert_eq!(pedals.clutch_down(), Some(false));
+        assert!(pedals.axis_changed(Axis::Unknown, 0.7, 2));
+        assert_eq!(pedals.clutch_down(), Some(true));
+
+        // Some other axis
+        assert!(!pedals.axis_changed(Axis::Unknown, -1.0, 3));
+        assert_eq!(pedals.clutch_down(), Some(true));
+    }
+
+    #[test]
+    fn reversed_pedal() {
+        let mut pedals = pedals("clutch = \"-LeftZ\"\nclutch_threshold = 0.5").unwrap();
+        pedals.axis_changed(Axis::LeftZ, 1.0, 0);
+        assert_eq!(pedals.clutch().unwrap().travel(), 0.0);
+        pedals.axis_changed(Axis::LeftZ, -1.0, 0);
+        assert_eq!(pedals.clutch().unwrap().travel(), 1.0);
+    }
+
+    #[test]
+    fn deadzone_saturation_and_curve() {
+        let mut pedals = pedals(
+            "[response.throttle]\ndeadzone = 0.1\nsaturation = 0.9\n\
+             [response.clutch]\ncurve = \"expo\"",
+        )
+        .unwrap();
+        // A noisy pot resting just off zero gives no throttle
+        pedals.button_changed(Button::RightTrigger2, 0.08, 0);
+        assert_eq!(pedals.throttle().unwrap().travel(), 0.0);
+        pedals.button_changed(Button::RightTrigger2, 0.5, 0);
+        assert!((pedals.throttle().unwrap().travel() - 0.5).abs() < 1e-6);
+        pedals.button_changed(Button::RightTrigger2, 0.95, 0);
+        assert_eq!(pedals.throttle().unwrap().travel(), 1.0);
+        // Untouched
+        pedals.button_changed(Button::LeftTrigger2, 0.08, 0);
+        assert_eq!(pedals.brake().unwrap().travel(), 0.08);
+
+        let expo = Response {
+            curve: Curve::Expo,
+            ..Response::default()
+        };
+        assert_eq!(expo.apply(0.5), 0.25);
+        assert_eq!(expo.apply(1.0), 1.0);
+
+        assert_eq!(
+            self::pedals("[response.clutch]\nsaturation = 0.1\ndeadzone = 0.2").err(),
+            Some(
+                "[pedals.response.clutch] saturation: 0.1 is not between the deadzone, 0.2, and 1"
+                    .to_string()
+            )
+        );
+        assert!(self::pedals("[response.horn]\ndeadzone = 0.1").is_err());
+        assert!(self::pedals("[response.brake]\ndeadzone = 1.0").is_err());
+        assert!(self::pedals("[response.brake]\ncurve = \"log\"").is_err());
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(pedals("throttle = \"Turbo\"").is_err());
+        assert!(pedals("clutch = \"+Throttle\"").is_err());
+        assert!(pedals("clutch = \"+axis:2\"\nclutch_threshold = 1.5").is_err());
+        assert!(pedals("horn = \"+axis:2\"").is_err());
+    }
+}
@@ -0,0 +1,485 @@
+// What Sony pads can do beyond rumble, which gilrs can't reach: the
+// DualSense's adaptive triggers and the lightbar on the DualSense and the
+// DualShock 4. Both are set with raw HID output reports written straight to
+// the pad's hidraw device (Linux only; elsewhere no pad is found). Only the
+// fields in use are marked valid in a report, so rumble and the rest keep
+// whatever gilrs and the system set.
+//
+// The throttle is taken to be on R2, as in the default `[pedals]`.
+
+use crate::car::GearPosition;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const SONY_VENDOR: u16 = 0x054c;
+const DUALSENSE_PRODUCTS: [u16; 2] = [0x0ce6, 0x0df2]; // DualSense, DualSense Edge
+const DUALSHOCK4_PRODUCTS: [u16; 3] = [0x05c4, 0x09cc, 0x0ba0]; // v1, v2, wireless adapter
+const BUS_BLUETOOTH: u16 = 0x0005;
+const BT_CRC_SEED: u8 = 0xa2;
+
+// DualSense output report layout, see the Linux hid-playstation driver
+const DS_USB_REPORT_ID: u8 = 0x02;
+const DS_BT_REPORT_ID: u8 = 0x31;
+const DS_BT_TAG: u8 = 0x10;
+const DS_COMMON_LEN: usize = 47;
+const DS_BT_PADDING: usize = 24;
+const DS_FLAG0_RIGHT_TRIGGER: u8 = 0x04;
+const DS_FLAG0_LEFT_TRIGGER: u8 = 0x08;
+const DS_FLAG1_LIGHTBAR: u8 = 0x04;
+const DS_FLAG2_LIGHTBAR_SETUP: u8 = 0x02;
+const DS_LIGHTBAR_SETUP_LIGHT_OUT: u8 = 0x02; // Ends the blue start-up glow
+const DS_RIGHT_TRIGGER_AT: usize = 10;
+const DS_LEFT_TRIGGER_AT: usize = 21;
+const DS_FLAG2_AT: usize = 38;
+const DS_LIGHTBAR_SETUP_AT: usize = 41;
+const DS_LIGHTBAR_AT: usize = 44;
+
+// DualShock 4 output report layout
+const DS4_USB_REPORT_ID: u8 = 0x05;
+const DS4_USB_LEN: usize = 32;
+const DS4_BT_REPORT_ID: u8 = 0x11;
+const DS4_BT_HW_CONTROL: u8 = 0xc0; // HID report with a CRC
+const DS4_BT_LEN: usize = 78;
+const DS4_FLAG0_LIGHTBAR: u8 = 0x02;
+const DS4_LIGHTBAR_AT: usize = 5; // After the flags, a reserved byte and the motors
+
+// A gear going in: both triggers go rigid this long
+const CLICK: TriggerEffect = TriggerEffect::Resistance {
+    start: 0,
+    force: u8::MAX,
+};
+const CLICK_MS: u64 = 80;
+
+// Lightbar colours: neutral, reverse, and the red that flashes at the
+// redline, on and off every FLASH_MS
+const NEUTRAL_COLOUR: Rgb = Rgb(255, 255, 255);
+const REVERSE_COLOUR: Rgb = Rgb(255, 120, 0);
+const REDLINE_COLOUR: Rgb = Rgb(255, 0, 0);
+const DARK: Rgb = Rgb(0, 0, 0);
+const FLASH_MS: u64 = 100;
+
+// Gears run through the hues from green in first to violet in top
+const FIRST_GEAR_HUE: f32 = 120.0;
+const TOP_GEAR_HUE: f32 = 290.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    DualSense,
+    DualShock4,
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Model::DualSense => write!(f, "DualSense"),
+            Model::DualShock4 => write!(f, "DualShock 4"),
+        }
+    }
+}
+
+/// A lightbar colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+/// The lightbar colour for `position` in a box with `gear_count` gears.
+pub fn gear_colour(position: GearPosition, gear_count: u8) -> Rgb {
+    match position {
+        GearPosition::Neutral => NEUTRAL_COLOUR,
+        GearPosition::Reverse => REVERSE_COLOUR,
+        GearPosition::Gear(gear) => {
+            let along = if gear_count > 1 {
+                (gear.clamp(1, gear_count) - 1) as f32 / (gear_count - 1) as f32
+            } else {
+                0.0
+            };
+            hue(FIRST_GEAR_HUE + (TOP_GEAR_HUE - FIRST_GEAR_HUE) * along)
+        }
+    }
+}
+
+/// The fully saturated, full brightness colour at `degrees` round the
+/// colour wheel.
+fn hue(degrees: f32) -> Rgb {
+    let sector = (degrees.rem_euclid(360.0) / 60.0) as u8;
+    let rising = (degrees.rem_euclid(60.0) / 60.0 * 255.0) as u8;
+    let falling = 255 - rising;
+    match sector {
+        0 => Rgb(255, rising, 0),
+        1 => Rgb(falling, 255, 0),
+        2 => Rgb(0, 255, rising),
+        3 => Rgb(0, falling, 255),
+        4 => Rgb(rising, 0, 255),
+        _ => Rgb(255, 0, falling),
+    }
+}
+
+/// What one trigger does against the finger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEffect {
+    Off,
+    /// Pushes back with `force` from `start` (0 released, 255 pulled) on.
+    Resistance {
+        start: u8,
+        force: u8,
+    },
+}
+
+impl TriggerEffect {
+    /// The 11 bytes the pad takes for the effect.
+    fn bytes(self) -> [u8; 11] {
+        let mut bytes = [0; 11];
+        match self {
+            TriggerEffect::Off => bytes[0] = 0x05,
+            TriggerEffect::Resistance { start, force } => {
+                bytes[..3].copy_from_slice(&[0x01, start, force]);
+            }
+        }
+        bytes
+    }
+}
+
+/// The parts of a pad one output report sets; `None` leaves them be.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Output {
+    triggers: Option<(TriggerEffect, TriggerEffect)>, // Right, left
+    lightbar: Option<Rgb>,
+    release_lightbar: bool, // Take the lightbar over from the system
+}
+
+/// A Sony pad's triggers and lightbar, and what they were last told to do.
+pub struct PlayStationPad {
+    path: PathBuf,
+    file: File,
+    model: Model,
+    bluetooth: bool,
+    sequence: u8, // DualSense Bluetooth reports count from 0 to 15 and wrap
+    throttle: TriggerEffect,
+    click_until: Option<Instant>,
+    colour: Option<Rgb>,            // Wanted, None until the first one
+    shown: Option<Rgb>,             // On the lightbar right now
+    flash: Option<(bool, Instant)>, // Redline flash: lit, and when that flips
+}
+
+impl PlayStationPad {
+    /// The first DualSense or DualShock 4 among the hidraw devices, if any
+    /// is connected.
+    pub fn find() -> io::Result<Option<Self>> {
+        let entries = match fs::read_dir("/sys/class/hidraw") {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let Ok(uevent) = fs::read_to_string(entry.path().join("device/uevent")) else {
+                continue;
+            };
+            let Some((bus, vendor, product)) = parse_hid_id(&uevent) else {
+                continue;
+            };
+            let model = match product {
+                _ if vendor != SONY_VENDOR => continue,
+                p if DUALSENSE_PRODUCTS.contains(&p) => Model::DualSense,
+                p if DUALSHOCK4_PRODUCTS.contains(&p) => Model::DualShock4,
+                _ => continue,
+            };
+            let path = Path::new("/dev").join(entry.file_name());
+            return Self::open(path, model, bus == BUS_BLUETOOTH).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn open(path: PathBuf, model: Model, bluetooth: bool) -> io::Result<Self> {
+        let file = OpenOptions::new().write(true).open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            model,
+            bluetooth,
+            sequence: 0,
+            throttle: TriggerEffect::Off,
+            click_until: None,
+            colour: None,
+            shown: None,
+            flash: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    pub fn has_adaptive_triggers(&self) -> bool {
+        self.model == Model::DualSense
+    }
+
+    /// Stiffens R2, 0 free to 1 as stiff as it gets. Only writes to the pad
+    /// when the force changes.
+    pub fn set_throttle_resistance(&mut self, stiffness: f32) -> io::Result<()> {
+        let force = (stiffness.clamp(0.0, 1.0) * u8::MAX as f32) as u8;
+        let throttle = if force == 0 {
+            TriggerEffect::Off
+        } else {
+            TriggerEffect::Resistance { start: 0, force }
+        };
+        if throttle == self.throttle || !self.has_adaptive_triggers() {
+            return Ok(());
+        }
+        self.throttle = throttle;
+        if self.click_until.is_some() {
+            return Ok(()); // Goes out when the click ends
+        }
+        self.set_triggers(throttle, TriggerEffect::Off)
+    }
+
+    /// Both triggers go rigid for a moment.
+    pub fn click(&mut self, now: Instant) -> io::Result<()> {
+        if !self.has_adaptive_triggers() {
+            return Ok(());
+        }
+        self.click_until = Some(now + Duration::from_millis(CLICK_MS));
+        self.set_triggers(CLICK, CLICK)
+    }
+
+    /// Shows `colour` on the lightbar, or flashes red instead while
+    /// `at_redline`. Only writes to the pad when what it shows changes.
+    pub fn set_lightbar(&mut self, colour: Rgb, at_redline: bool, now: Instant) -> io::Result<()> {
+        self.colour = Some(colour);
+        match (at_redline, self.flash) {
+            (true, None) => self.flash = Some((true, now + Duration::from_millis(FLASH_MS))),
+            (false, Some(_)) => self.flash = None,
+            _ => {}
+        }
+        self.show_lightbar()
+    }
+
+    /// Lets go of a click that has run its time and flips the redline flash.
+    pub fn tick(&mut self, now: Instant) -> io::Result<()> {
+        if let Some((lit, flips_at)) = self.flash
+            && now >= flips_at
+        {
+            self.flash = Some((!lit, now + Duration::from_millis(FLASH_MS)));
+            self.show_lightbar()?;
+        }
+        if self.click_until.is_none_or(|until| now < until) {
+            return Ok(());
+        }
+        self.click_until = None;
+        self.set_triggers(self.throttle, TriggerEffect::Off)
+    }
+
+    fn set_triggers(&mut self, right: TriggerEffect, left: TriggerEffect) -> io::Result<()> {
+        self.send(Output {
+            triggers: Some((right, left)),
+            ..Output::default()
+        })
+    }
+
+    fn show_lightbar(&mut self) -> io::Result<()> {
+        let wanted = match self.flash {
+            Some((true, _)) => REDLINE_COLOUR,
+            Some((false, _)) => DARK,
+            None => match self.colour {
+                Some(colour) => colour,
+                None => return Ok(()),
+            },
+        };
+        if self.shown == Some(wanted) {
+            return Ok(());
+        }
+        let release_lightbar = self.shown.is_none();
+        self.shown = Some(wanted);
+        self.send(Output {
+            lightbar: Some(wanted),
+            release_lightbar,
+            ..Output::default()
+        })
+    }
+
+    fn send(&mut self, output: Output) -> io::Result<()> {
+        let report = match self.model {
+            Model::DualSense => dualsense_report(self.bluetooth, self.sequence, &output),
+            Model::DualShock4 => dualshock4_report(self.bluetooth, &output),
+        };
+        self.sequence = (self.sequence + 1) % 16;
+        self.file.write_all(&report)
+    }
+}
+
+/// Bus, vendor and product from the HID_ID line of a hidraw uevent, e.g.
+/// `HID_ID=0005:0000054C:00000CE6`.
+fn parse_hid_id(uevent: &str) -> Option<(u16, u16, u16)> {
+    let id = uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("HID_ID="))?;
+    let mut parts = id.split(':').map(|part| u32::from_str_radix(part, 16));
+    let (bus, vendor, product) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    Some((bus as u16, vendor as u16, product as u16))
+}
+
+/// A DualSense output report that sets what `output` asks for.
+fn dualsense_report(bluetooth: bool, sequence: u8, output: &Output) -> Vec<u8> {
+    let mut common = [0; DS_COMMON_LEN];
+    if let Some((right, left)) = output.triggers {
+        common[0] |= DS_FLAG0_RIGHT_TRIGGER | DS_FLAG0_LEFT_TRIGGER;
+        common[DS_RIGHT_TRIGGER_AT..DS_RIGHT_TRIGGER_AT + 11].copy_from_slice(&right.bytes());
+        common[DS_LEFT_TRIGGER_AT..DS_LEFT_TRIGGER_AT + 11].copy_from_slice(&left.bytes());
+    }
+    if let Some(Rgb(r, g, b)) = output.lightbar {
+        common[1] |= DS_FLAG1_LIGHTBAR;
+        common[DS_LIGHTBAR_AT..DS_LIGHTBAR_AT + 3].copy_from_slice(&[r, g, b]);
+    }
+    if output.release_lightbar {
+        common[DS_FLAG2_AT] |= DS_FLAG2_LIGHTBAR_SETUP;
+        common[DS_LIGHTBAR_SETUP_AT] = DS_LIGHTBAR_SETUP_LIGHT_OUT;
+    }
+
+    if !bluetooth {
+        let mut report = vec![DS_USB_REPORT_ID];
+        report.extend_from_slice(&common);
+        return report;
+    }
+    let mut report = vec![DS_BT_REPORT_ID, sequence << 4, DS_BT_TAG];
+    report.extend_from_slice(&common);
+    report.extend_from_slice(&[0; DS_BT_PADDING]);
+    with_crc(report)
+}
+
+/// A DualShock 4 output report that sets what `output` asks for. It has
+/// no adaptive triggers, so only the lightbar.
+fn dualshock4_report(bluetooth: bool, output: &Output) -> Vec<u8> {
+    let mut common = [0; 10];
+    if let Some(Rgb(r, g, b)) = output.lightbar {
+        common[0] |= DS4_FLAG0_LIGHTBAR;
+        common[DS4_LIGHTBAR_AT..DS4_LIGHTBAR_AT + 3].copy_from_slice(&[r, g, b]);
+    }
+
+    let (mut report, len) = if bluetooth {
+        (vec![DS4_BT_REPORT_ID, DS4_BT_HW_CONTROL, 0], DS4_BT_LEN - 4)
+    } else {
+        (vec![DS4_USB_REPORT_ID], DS4_USB_LEN)
+    };
+    report.extend_from_slice(&common);
+    report.resize(len, 0);
+    if bluetooth { with_crc(report) } else { report }
+}
+
+/// `report` with the CRC Bluetooth output reports end in: over the report
+/// with the HID output header in front.
+fn with_crc(mut report: Vec<u8>) -> Vec<u8> {
+    let crc = crc32(&[&[BT_CRC_SEED][..], &report].concat());
+    report.extend_from_slice(&crc.to_le_bytes());
+    report
+}
+
+/// CRC-32 as used by zip and Ethernet.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(u32::MAX, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hid_id_from_uevent() {
+        let uevent =
+            "DRIVER=playstation\nHID_ID=0005:0000054C:00000CE6\nHID_NAME=Wireless Controller\n";
+        assert_eq!(parse_hid_id(uevent), Some((0x0005, 0x054c, 0x0ce6)));
+        assert_eq!(parse_hid_id("DRIVER=hid-generic\n"), None);
+        assert_eq!(parse_hid_id("HID_ID=0003:zz:0CE6\n"), None);
+    }
+
+    #[test]
+    fn crc_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn dualsense_reports_set_only_what_is_asked() {
+        let right = TriggerEffect::Resistance {
+            start: 0,
+            force: 200,
+        };
+        let triggers = Output {
+            triggers: Some((right, TriggerEffect::Off)),
+            ..Output::default()
+        };
+        let usb = dualsense_report(false, 0, &triggers);
+        assert_eq!(usb.len(), 48);
+        assert_eq!(usb[0], DS_USB_REPORT_ID);
+        assert_eq!(usb[1], DS_FLAG0_RIGHT_TRIGGER | DS_FLAG0_LEFT_TRIGGER);
+        assert_eq!(usb[2], 0); // Lights untouched
+        assert_eq!(usb[3], 0); // Rumble too
+        assert_eq!(&usb[11..14], &[0x01, 0, 200]);
+        assert_eq!(usb[22], 0x05);
+
+        let bt = dualsense_report(true, 3, &triggers);
+        assert_eq!(bt.len(), 78);
+        assert_eq!(&bt[..3], &[DS_BT_REPORT_ID, 0x30, DS_BT_TAG]);
+        assert_eq!(&bt[3..50], &usb[1..]);
+        let crc = crc32(&[&[BT_CRC_SEED][..], &bt[..74]].concat());
+        assert_eq!(&bt[74..], &crc.to_le_bytes());
+
+        let lightbar = Output {
+            lightbar: Some(Rgb(1, 2, 3)),
+            release_lightbar: true,
+            ..Output::default()
+        };
+        let usb = dualsense_report(false, 0, &lightbar);
+        assert_eq!(&usb[1..3], &[0, DS_FLAG1_LIGHTBAR]);
+        assert_eq!(usb[1 + DS_FLAG2_AT], DS_FLAG2_LIGHTBAR_SETUP);
+        assert_eq!(&usb[45..48], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn dualshock4_reports_set_the_lightbar() {
+        let output = Output {
+            lightbar: Some(Rgb(10, 20, 30)),
+            ..Output::default()
+        };
+        let usb = dualshock4_report(false, &output);
+        assert_eq!(usb.len(), DS4_USB_LEN);
+        assert_eq!(&usb[..2], &[DS4_USB_REPORT_ID, DS4_FLAG0_LIGHTBAR]);
+        assert_eq!(&usb[6..9], &[10, 20, 30]);
+        assert_eq!(usb[4..6], [0, 0]); // Rumble untouched
+
+        let bt = dualshock4_report(true, &output);
+        assert_eq!(bt.len(), DS4_BT_LEN);
+        assert_eq!(&bt[3..13], &usb[1..11]);
+        let crc = crc32(&[&[BT_CRC_SEED][..], &bt[..74]].concat());
+        assert_eq!(&bt[74..], &crc.to_le_bytes());
+    }
+
+    #[test]
+    fn gears_run_from_green_to_violet() {
+        assert_eq!(gear_colour(GearPosition::Gear(1), 6), Rgb(0, 255, 0));
+        assert_eq!(gear_colour(GearPosition::Neutral, 6), NEUTRAL_COLOUR);
+        assert_eq!(gear_colour(GearPosition::Reverse, 6), REVERSE_COLOUR);
+        let top = gear_colour(GearPosition::Gear(6), 6);
+        assert!(top.2 == 255 && top.0 > 0 && top.1 == 0);
+        // None of them is the redline red
+        for gear in 1..=6 {
+            assert_ne!(gear_colour(GearPosition::Gear(gear), 6), REDLINE_COLOUR);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// The car presets that ship with the binary, from `presets.toml`: real cars
+// with their own torque curves, gearboxes and redlines. `--car <name>` and
+// the menu at start-up pick from these and the driver's own [cars] in
+// gear_changer.toml, which win on a name clash.
+
+use crate::config::{CarPreset, Config};
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+static BUILTIN: LazyLock<BTreeMap<String, CarPreset>> = LazyLock::new(|| {
+    let config = Config::parse(include_str!("presets.toml")).expect("presets.toml is valid");
+    // Their figures stay in the file's units whatever the driver reads in
+    config
+        .cars
+        .into_iter()
+        .map(|(name, preset)| {
+            let units = preset.units.or(Some(config.units));
+            (name, CarPreset { units, ..preset })
+        })
+        .collect()
+});
+
+/// Every built-in preset by name.
+pub fn builtin() -> &'static BTreeMap<String, CarPreset> {
+    &BUILTIN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::{AngularSpeed, Power, Torque, UnitSystem};
+
+    #[test]
+    fn every_builtin_preset_builds() {
+        assert!(builtin().len() >= 4);
+        for (name, preset) in builtin() {
+            let car = preset
+                .build(UnitSystem::Imperial)
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert!(preset.name.is_some(), "{} has no name", name);
+            // The peak torque is where the curve peaks
+            let redline = car.engine().redline();
+            let peak = (800..redline.rpm() as u32)
+                .step_by(50)
+                .map(|rpm| {
+                    car.engine()
+                        .curve()
+                        .torque_at(AngularSpeed::from_rpm(rpm as f32))
+                })
+                .fold(Torque::default(), |a, b| if b > a { b } else { a });
+            assert!((peak.nm() - car.torque().nm()).abs() < 1.0, "{}", name);
+            // And the power it makes on the way peaks near the figure given
+            let power = (800..redline.rpm() as u32)
+                .step_by(50)
+                .map(|rpm| {
+                    let speed = AngularSpeed::from_rpm(rpm as f32);
+                    Power::from_torque_at(car.engine().curve().torque_at(speed), speed).kw()
+                })
+                .fold(0.0, f32::max);
+            assert!((power / car.power().kw() - 1.0).abs() < 0.03, "{}", name);
+        }
+        let miata = builtin()["miata"].build(UnitSystem::Imperial).unwrap();
+        assert!((miata.torque().lb_ft() - 151.2).abs() < 0.5);
+        assert!((miata.engine().redline().rpm() - 7500.0).abs() < 0.5);
+        assert_eq!(miata.gear_count(), 6);
+    }
+}
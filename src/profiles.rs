@@ -0,0 +1,271 @@
+// Named snapshots of the car and the rumble settings, saved and switched
+// between during a session. One file per profile in the config directory:
+// ~/.config/gear_changer/profiles on Linux, %APPDATA%\gear_changer\profiles
+// on Windows, ~/Library/Application Support/gear_changer/profiles on macOS.
+//
+// # track.toml
+// intensity = 80
+// haptic_profile = "gentle"
+//
+// latency_ms = 23.5     # written by measure-latency, off drag reactions
+//
+// [car]                 # the same as a [cars.*] preset in gear_changer.toml
+// torque = 346
+// horsepower = 518
+// gear_ratios = [3.75, 2.38, 1.72, 1.34, 1.11, 0.96, 0.84]
+
+use crate::car::Car;
+use crate::config::CarPreset;
+use crate::intensity::{HapticProfile, Intensity};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What `save_profile` saves to when no profile is in use yet.
+pub const DEFAULT_NAME: &str = "default";
+
+const EXTENSION: &str = "toml";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub intensity: u16, // Percent
+    #[serde(default)]
+    pub haptic_profile: HapticProfile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f32>, // Rumble latency measured on the pad
+    pub car: CarPreset,
+}
+
+impl Profile {
+    pub fn snapshot(car: &Car, intensity: Intensity) -> Self {
+        Self {
+            intensity: intensity.percent(),
+            haptic_profile: intensity.profile(),
+            latency_ms: None,
+            car: CarPreset::from_car(car),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        let mut value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        tidy(&mut value);
+        toml::to_string(&value).map_err(|e| e.to_string())
+    }
+
+    /// The car, shown in the units it was saved in.
+    pub fn build_car(&self) -> Result<Car, String> {
+        self.car.build(self.car.units.unwrap_or_default())
+    }
+
+    pub fn intensity(&self) -> Result<Intensity, String> {
+        Intensity::new(self.intensity, self.haptic_profile)
+    }
+
+    /// The rumble latency to take off drag reaction times, none if it
+    /// hasn't been measured.
+    pub fn latency(&self) -> Duration {
+        self.latency_ms
+            .and_then(|ms| Duration::try_from_secs_f32(ms / 1000.0).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// f32 figures come out widened to f64, 3.2 as 3.200000047683716; back to
+/// the shortest number that reads as the same f32.
+fn tidy(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => {
+            *float = (*float as f32).to_string().parse().unwrap_or(*float);
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(tidy),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, value)| tidy(value)),
+        _ => {}
+    }
+}
+
+/// The saved profiles, and which one is in use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profiles {
+    dir: PathBuf,
+    active: Option<String>,
+}
+
+impl Profiles {
+    /// The profiles in the config directory.
+    pub fn open() -> Result<Self, String> {
+        let config = dirs::config_dir().ok_or("no config directory for this user")?;
+        Ok(Self::in_dir(config.join("gear_changer").join("profiles")))
+    }
+
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Self { dir, active: None }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The profile last loaded or saved.
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Every saved profile, by name in order. None before the first save.
+    pub fn names(&self) -> Result<Vec<String>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("{}: {}", self.dir.display(), e)),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("{}: {}", self.dir.display(), e))?
+                .path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+                && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// The profile after the active one, round to the first. None if there
+    /// are no others.
+    pub fn next(&self) -> Result<Option<String>, String> {
+        let names = self.names()?;
+        let after = self
+            .active()
+            .and_then(|active| names.iter().position(|name| name == active))
+            .map_or(0, |index| index + 1);
+        let next = names.get(after).or(names.first());
+        Ok(next
+            .filter(|&next| Some(next.as_str()) != self.active())
+            .cloned())
+    }
+
+    /// Loads `name` and makes it the active profile.
+    pub fn load(&mut self, name: &str) -> Result<Profile, String> {
+        let profile = self.read(name)?;
+        self.active = Some(name.to_string());
+        Ok(profile)
+    }
+
+    /// Reads `name` without making it the active profile.
+    pub fn read(&self, name: &str) -> Result<Profile, String> {
+        let path = self.path(name)?;
+        let text = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => format!("no profile '{}'", name),
+            _ => format!("{}: {}", path.display(), e),
+        })?;
+        Profile::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Saves `profile` as `name`, replacing any profile already called
+    /// that, and makes it the active one. Returns where it went.
+    pub fn save(&mut self, name: &str, profile: &Profile) -> Result<PathBuf, String> {
+        let path = self.path(name)?;
+        let text = profile.to_toml()?;
+        fs::create_dir_all(&self.dir).map_err(|e| format!("{}: {}", self.dir.display(), e))?;
+        fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.active = Some(name.to_string());
+        Ok(path)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let allowed = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(allowed) {
+            return Err(format!(
+                "'{}' can't be a profile name: letters, digits, - and _ only",
+                name
+            ));
+        }
+        Ok(self.dir.join(name).with_extension(EXTENSION))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::transmission::Transmission;
+
+    #[test]
+    fn a_snapshot_reads_back_as_the_same_car_and_settings() {
+        let config = Config::parse(
+            "[cars.classic]\ntorque = 180\nhorsepower = 150\n\
+             gear_ratios = [3.2, 1.9, 1.3, 1.0]\ntransmission = \"manual\"",
+        )
+        .unwrap();
+        let car = config.car("classic").unwrap().build(config.units).unwrap();
+        let intensity = Intensity::new(80, HapticProfile::Gentle).unwrap();
+        let profile = Profile::snapshot(&car, intensity);
+
+        let text = profile.to_toml().unwrap();
+        assert!(
+            text.contains("gear_ratios = [3.2, 1.9, 1.3, 1.0]"),
+            "{}",
+            text
+        );
+        let read = Profile::parse(&text).unwrap();
+        assert_eq!(read, profile);
+        assert_eq!(read.intensity(), Ok(intensity));
+        let again = read.build_car().unwrap();
+        assert_eq!(again.gear_ratios(), car.gear_ratios());
+        assert_eq!(again.transmission(), Some(Transmission::Manual));
+        assert!((again.torque().lb_ft() - 180.0).abs() < 0.01);
+
+        assert!(Profile::parse("intensity = 80").is_err());
+    }
+
+    #[test]
+    fn profiles_are_saved_by_name_and_cycled_in_order() {
+        let dir =
+            std::env::temp_dir().join(format!("gear_changer_profiles_{}", std::process::id()));
+        let mut profiles = Profiles::in_dir(dir.clone());
+        assert_eq!(profiles.names(), Ok(Vec::new()));
+        assert_eq!(profiles.next(), Ok(None));
+
+        let config = Config::default();
+        let miata = config.car("miata").unwrap().build(config.units).unwrap();
+        let profile = Profile::snapshot(&miata, Intensity::default());
+        for name in ["track", "road", "wet"] {
+            profiles.save(name, &profile).unwrap();
+        }
+        assert_eq!(profiles.active(), Some("wet"));
+        assert_eq!(profiles.names().unwrap(), ["road", "track", "wet"]);
+        assert_eq!(profiles.next(), Ok(Some("road".to_string())));
+        assert_eq!(profiles.load("road").as_ref(), Ok(&profile));
+        assert_eq!(profiles.next(), Ok(Some("track".to_string())));
+
+        // A measured latency is written only once there is one
+        assert!(!profile.to_toml().unwrap().contains("latency_ms"));
+        let measured = Profile {
+            latency_ms: Some(23.5),
+            ..profile.clone()
+        };
+        profiles.save("wet", &measured).unwrap();
+        assert_eq!(profiles.read("wet"), Ok(measured));
+        assert_eq!(profiles.active(), Some("wet"));
+        profiles.load("road").unwrap();
+
+        assert_eq!(
+            profiles.load("dry").err(),
+            Some("no profile 'dry'".to_string())
+        );
+        assert_eq!(profiles.active(), Some("road"));
+        assert!(profiles.save("../escape", &profile).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
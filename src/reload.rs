@@ -0,0 +1,181 @@
+// Hot reloading of gear_changer.toml. A running session checks the file
+// every CHECK_EVERY, and when it has been written to takes on whatever
+// changed in it: envelopes, intensity, the slow-mo factor, gear feel,
+// calibrations, bindings, pedals, the H-pattern gates and the preset being
+// driven. Command-line flags still win over the file, and so does the
+// intensity of a profile in use, as they do at the start. A file that
+// doesn't parse or validate is reported and the session carries on as it
+// was, so rumble feel can be tuned with the pad in hand.
+
+use crate::bindings::Bindings;
+use crate::calibration::Calibration;
+use crate::car::{Car, GearFeelTable};
+use crate::config::CONFIG_PATH;
+use crate::haptics::{Envelopes, HapticController};
+use crate::intensity::Intensity;
+use crate::pedals::Pedals;
+use crate::profiles::Profiles;
+use crate::say;
+use crate::session::Session;
+use crate::shifter::HPattern;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const CHECK_EVERY: Duration = Duration::from_millis(500);
+
+/// Notices a file being written to, by its modification time.
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>, // None while there is no file
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        Self { path, modified }
+    }
+
+    /// Whether the file was written, created or deleted since the last
+    /// check.
+    pub fn changed(&mut self) -> bool {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// What changed in the config, ready to use. `None` is left as it is.
+#[derive(Default)]
+pub struct Tuning {
+    pub car: Option<Car>, // The preset driven, built again
+    pub gear_feel: Option<Option<GearFeelTable>>,
+    pub envelopes: Option<Envelopes>,
+    pub intensity: Option<Intensity>,
+    pub slowmo_factor: Option<f32>,
+    pub calibrations: Option<BTreeMap<String, Calibration>>,
+    pub bindings: Option<Bindings>,
+    pub pedals: Option<(Pedals, bool)>, // And whether clutchless shifts are refused
+    pub h_pattern: Option<HPattern>,
+}
+
+impl Tuning {
+    /// What is in it, for the reload message.
+    pub fn changes(&self) -> Vec<&'static str> {
+        [
+            (self.car.is_some(), "car"),
+            (self.gear_feel.is_some(), "gear feel"),
+            (self.envelopes.is_some(), "envelopes"),
+            (self.intensity.is_some(), "intensity"),
+            (self.slowmo_factor.is_some(), "slow-mo"),
+            (self.calibrations.is_some(), "calibration"),
+            (self.bindings.is_some(), "bindings"),
+            (self.pedals.is_some(), "pedals"),
+            (self.h_pattern.is_some(), "H-pattern gates"),
+        ]
+        .into_iter()
+        .filter_map(|(changed, name)| changed.then_some(name))
+        .collect()
+    }
+
+    /// Hands the car and the rumble settings to `session`; the rest is for
+    /// whoever reads the inputs.
+    pub fn apply_to_session<H: HapticController>(&mut self, session: &mut Session<H>) {
+        if let Some(car) = self.car.take() {
+            session.set_car(car);
+        }
+        if let Some(gear_feel) = self.gear_feel.take() {
+            session.car_mut().set_gear_feel(gear_feel);
+        }
+        if let Some(envelopes) = self.envelopes.take() {
+            session.set_envelopes(envelopes);
+        }
+        if let Some(intensity) = self.intensity.take() {
+            session.set_intensity(intensity);
+        }
+        if let Some(factor) = self.slowmo_factor.take() {
+            session.set_slowmo_factor(factor);
+        }
+    }
+}
+
+/// Reads the config again if it changed, given the car being driven and
+/// the profile in use. `None` if it hasn't.
+pub type Reload<'a> = dyn FnMut(&Car, Option<&str>) -> Option<Result<Tuning, String>> + 'a;
+
+/// Runs `reload` for `session` and says what came of it. The tuning to
+/// apply, if any.
+pub fn check<H: HapticController>(reload: &mut Reload<'_>, session: &Session<H>) -> Option<Tuning> {
+    let profile = session.profiles().and_then(Profiles::active);
+    match reload(session.car(), profile)? {
+        Ok(tuning) => {
+            let changes = tuning.changes();
+            if changes.is_empty() {
+                say!("\n🔄 {} reloaded, nothing in use changed", CONFIG_PATH);
+            } else {
+                say!("\n🔄 {} reloaded: {}", CONFIG_PATH, changes.join(", "));
+            }
+            Some(tuning)
+        }
+        Err(e) => {
+            say!("\n⚠️  Config not reloaded: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::tests::MockHaptics;
+    use crate::units::{Power, Torque};
+
+    #[test]
+    fn a_written_file_is_noticed_once() {
+        let path = std::env::temp_dir().join(format!("gear_changer_reload_{}", std::process::id()));
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(!watcher.changed());
+        fs::write(&path, "units = \"metric\"").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+    }
+
+    #[test]
+    fn only_what_changed_reaches_the_session() {
+        let car = |gears: usize| {
+            Car::new(
+                Torque::from_lb_ft(300.0),
+                Power::from_hp(400.0),
+                vec![1.0; gears],
+                3.5,
+            )
+            .unwrap()
+        };
+        let mut session = Session::new(car(6), MockHaptics::new());
+        let half = Intensity::new(50, Default::default()).unwrap();
+        let mut tuning = Tuning {
+            car: Some(car(4)),
+            intensity: Some(half),
+            ..Tuning::default()
+        };
+        assert_eq!(tuning.changes(), ["car", "intensity"]);
+        tuning.apply_to_session(&mut session);
+        assert_eq!(session.car().gear_count(), 4);
+        assert_eq!(session.intensity(), half);
+        assert!(tuning.changes().is_empty());
+        assert!(Tuning::default().changes().is_empty());
+    }
+}
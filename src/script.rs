@@ -0,0 +1,270 @@
+// `run --script`, with the `scripting` feature: custom haptic logic written
+// in Rhai. A script defines either hook or both; gears are numbers, with 0
+// for neutral and -1 for reverse, and magnitudes go from 0 to 65535.
+//
+// fn on_shift(from, to, rpm) {      // after the shift
+//     if to == 1 {
+//         return [#{ strong: 60000, weak: 0, ms: 120 },
+//                 #{ strong: 0, weak: 30000, ms: 80 }];
+//     }
+//     ()                            // the built-in rumble
+// }
+//
+// fn on_tick(state) {               // gear, rpm, redline, throttle, brake,
+//     if state.rpm > 0.9 * state.redline {      // speed_kmh and limiter
+//         return #{ strong: 0, weak: 20000, ms: 40 };
+//     }
+//     ()                            // nothing of its own
+// }
+//
+// on_shift returns the stages to play one after another. What on_tick
+// returns repeats until a later tick returns something else, giving way to
+// the limiter and the other built-in rumbles while they play. A hook that
+// fails is reported and the built-in rumble plays instead; on_tick isn't
+// called again after it fails.
+
+use crate::car::GearPosition;
+use crate::haptics::{RumbleCommand, RumblePattern};
+use crate::hooks::{HapticHooks, TickState};
+use crate::say;
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use std::path::Path;
+
+/// A hook that runs longer than this is stopped, so a loop in a script
+/// can't hang the session.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// The longest stage or repeat a script can ask for.
+const MAX_MS: i64 = 10_000;
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    on_shift: bool, // Whether the script defines the hook
+    on_tick: bool,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let engine = new_engine();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::with(engine, ast).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let engine = new_engine();
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Self::with(engine, ast)
+    }
+
+    fn with(engine: Engine, ast: AST) -> Result<Self, String> {
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let (on_shift, on_tick) = (defines("on_shift", 3), defines("on_tick", 1));
+        if !on_shift && !on_tick {
+            return Err("defines neither on_shift(from, to, rpm) nor on_tick(state)".to_string());
+        }
+        Ok(Self {
+            engine,
+            ast,
+            on_shift,
+            on_tick,
+        })
+    }
+
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, String> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+}
+
+impl HapticHooks for Script {
+    fn on_shift(
+        &mut self,
+        from: GearPosition,
+        to: GearPosition,
+        rpm: f32,
+    ) -> Option<RumblePattern> {
+        if !self.on_shift {
+            return None;
+        }
+        let args = (gear_number(from), gear_number(to), rpm as rhai::FLOAT);
+        match self.call("on_shift", args).and_then(stages) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                say!("   ⚠️  Script on_shift: {}", e);
+                None
+            }
+        }
+    }
+
+    fn on_tick(&mut self, state: &TickState) -> Option<RumbleCommand> {
+        if !self.on_tick {
+            return None;
+        }
+        let mut map = Map::new();
+        let float = |value: f32| Dynamic::from_float(value as rhai::FLOAT);
+        map.insert("gear".into(), Dynamic::from_int(gear_number(state.gear)));
+        map.insert("rpm".into(), float(state.rpm));
+        map.insert("redline".into(), float(state.redline));
+        map.insert("throttle".into(), float(state.throttle));
+        map.insert("brake".into(), float(state.brake));
+        map.insert("speed_kmh".into(), float(state.speed_kmh));
+        map.insert("limiter".into(), Dynamic::from_bool(state.limiter));
+        let result = self.call("on_tick", (map,)).and_then(|value| {
+            if value.is_unit() {
+                return Ok(None);
+            }
+            command(value).map(Some)
+        });
+        match result {
+            Ok(command) => command,
+            Err(e) => {
+                say!("\n⚠️  Script on_tick: {}; it won't be called again", e);
+                self.on_tick = false;
+                None
+            }
+        }
+    }
+}
+
+/// 0 for neutral, -1 for reverse.
+fn gear_number(position: GearPosition) -> rhai::INT {
+    match position {
+        GearPosition::Gear(gear) => gear as rhai::INT,
+        GearPosition::Neutral => 0,
+        GearPosition::Reverse => -1,
+    }
+}
+
+/// `()` for none, or an array of stages.
+fn stages(value: Dynamic) -> Result<Option<RumblePattern>, String> {
+    if value.is_unit() {
+        return Ok(None);
+    }
+    let type_name = value.type_name();
+    let stages = value
+        .into_array()
+        .map_err(|_| format!("returned {}, not an array of stages or ()", type_name))?;
+    if stages.is_empty() {
+        return Err("returned no stages".to_string());
+    }
+    let stages = stages.into_iter().map(command).collect::<Result<_, _>>()?;
+    Ok(Some(RumblePattern::new(stages)))
+}
+
+/// A `#{ strong, weak, ms }` map.
+fn command(value: Dynamic) -> Result<RumbleCommand, String> {
+    let type_name = value.type_name();
+    let map = value
+        .try_cast::<Map>()
+        .ok_or_else(|| format!("returned {}, not #{{ strong, weak, ms }}", type_name))?;
+    let field = |name: &str, max: i64| {
+        let value = map
+            .get(name)
+            .ok_or_else(|| format!("{} is missing", name))?;
+        let number = value
+            .as_int()
+            .or_else(|_| value.as_float().map(|float| float.round() as rhai::INT))
+            .map_err(|_| format!("{}: {} is not a number", name, value))?;
+        if !(0..=max).contains(&number) {
+            return Err(format!("{}: {} is outside 0 to {}", name, number, max));
+        }
+        Ok(number)
+    };
+    let command = RumbleCommand {
+        strong_magnitude: field("strong", u16::MAX as i64)? as u16,
+        weak_magnitude: field("weak", u16::MAX as i64)? as u16,
+        duration_ms: field("ms", MAX_MS)? as u32,
+    };
+    if let Some(name) = map
+        .keys()
+        .find(|key| !["strong", "weak", "ms"].contains(&key.as_str()))
+    {
+        return Err(format!("unknown field '{}'", name));
+    }
+    if command.duration_ms == 0 {
+        return Err("ms: 0 is too short".to_string());
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(rpm: f32) -> TickState {
+        TickState {
+            gear: GearPosition::Gear(3),
+            rpm,
+            redline: 7000.0,
+            throttle: 1.0,
+            brake: 0.0,
+            speed_kmh: 90.0,
+            limiter: false,
+        }
+    }
+
+    #[test]
+    fn hooks_return_rumbles_or_leave_the_built_in_ones() {
+        let mut script = Script::compile(
+            "fn on_shift(from, to, rpm) {
+                 if to == -1 { return [#{ strong: 60000, weak: 0, ms: 100 },
+                                       #{ strong: 0, weak: 30000.4, ms: 50 }]; }
+                 ()
+             }
+             fn on_tick(state) {
+                 if state.rpm > 0.9 * state.redline { #{ strong: 0, weak: 20000, ms: 40 } }
+             }",
+        )
+        .unwrap();
+        let pattern = script
+            .on_shift(GearPosition::Neutral, GearPosition::Reverse, 900.0)
+            .unwrap();
+        assert_eq!(pattern.duration_ms(), 150);
+        assert_eq!(pattern.stages()[1].weak_magnitude, 30000);
+        assert_eq!(
+            script.on_shift(GearPosition::Gear(1), GearPosition::Gear(2), 4000.0),
+            None
+        );
+        assert_eq!(script.on_tick(&state(3000.0)), None);
+        assert_eq!(
+            script.on_tick(&state(6800.0)),
+            Some(RumbleCommand {
+                strong_magnitude: 0,
+                weak_magnitude: 20000,
+                duration_ms: 40,
+            })
+        );
+    }
+
+    #[test]
+    fn a_failing_tick_hook_is_not_called_again() {
+        let mut script = Script::compile(
+            "fn on_tick(state) {
+                 if state.rpm > 5000.0 { return #{ strong: 70000, weak: 0, ms: 40 }; }
+                 #{ strong: 100, weak: 0, ms: 40 }
+             }",
+        )
+        .unwrap();
+        assert!(script.on_tick(&state(3000.0)).is_some());
+        assert_eq!(script.on_tick(&state(6000.0)), None);
+        assert_eq!(script.on_tick(&state(3000.0)), None);
+
+        assert!(Script::compile("fn on_tick() { () }").is_err());
+        assert!(Script::compile("fn on_tick(state) {").is_err());
+        let mut endless = Script::compile("fn on_tick(state) { loop {} }").unwrap();
+        assert_eq!(endless.on_tick(&state(3000.0)), None);
+    }
+}
@@ -0,0 +1,284 @@
+// External gear display (7-segment / LED) driven over a serial port.
+//
+// Line protocol, one message per line:
+//   G1..G9, G10.. → numbered gear
+//   GN            → neutral
+//   GR            → reverse
+//   H             → heartbeat, sent when nothing else has been written for a while
+
+use crate::say;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::car::GearPosition;
+
+// How often the display hears from us when the gear doesn't change
+const HEARTBEAT: Duration = Duration::from_secs(1);
+
+// Lines waiting for the writer thread; anything beyond this is dropped
+const QUEUE_LEN: usize = 16;
+
+// Serial writes give up after this long instead of hanging the writer
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+type Opener = Box<dyn FnMut() -> io::Result<Box<dyn Write + Send>> + Send>;
+
+pub fn gear_line(position: GearPosition) -> String {
+    match position {
+        GearPosition::Gear(n) => format!("G{}\n", n),
+        GearPosition::Neutral => "GN\n".to_string(),
+        GearPosition::Reverse => "GR\n".to_string(),
+    }
+}
+
+/// Parses the `<port>:<baud>` argument of `--serial-display`.
+pub fn parse_target(target: &str) -> Result<(String, u32), String> {
+    let (port, baud) = target
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected <port>:<baud>, got '{}'", target))?;
+    let baud = baud
+        .parse::<u32>()
+        .map_err(|_| format!("invalid baud rate '{}'", baud))?;
+    if port.is_empty() {
+        return Err("missing serial port name".to_string());
+    }
+    Ok((port.to_string(), baud))
+}
+
+pub fn list_ports() {
+    match serialport::available_ports() {
+        Ok(ports) if ports.is_empty() => println!("No serial ports found."),
+        Ok(ports) => {
+            println!("Available serial ports:");
+            for port in ports {
+                match port.port_type {
+                    serialport::SerialPortType::UsbPort(usb) => println!(
+                        "  {}  USB {:04x}:{:04x} {}",
+                        port.port_name,
+                        usb.vid,
+                        usb.pid,
+                        usb.product.unwrap_or_default()
+                    ),
+                    _ => println!("  {}", port.port_name),
+                }
+            }
+        }
+        Err(e) => eprintln!("❌ Failed to list serial ports: {}", e),
+    }
+}
+
+/// Sends gear changes to the display without ever blocking the caller.
+///
+/// Writing happens on a background thread behind a bounded queue, so a
+/// wedged or unplugged device only costs dropped lines, never a stalled
+/// shift. The port is reopened automatically after a write error, which
+/// covers USB re-enumeration.
+pub struct SerialDisplay {
+    tx: Option<SyncSender<String>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl SerialDisplay {
+    pub fn open(port: String, baud: u32) -> Self {
+        Self::spawn(
+            Box::new(move || {
+                let port = serialport::new(&port, baud).timeout(WRITE_TIMEOUT).open()?;
+                Ok(Box::new(port) as Box<dyn Write + Send>)
+            }),
+            HEARTBEAT,
+        )
+    }
+
+    fn spawn(open: Opener, heartbeat: Duration) -> Self {
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+        let writer = thread::spawn(move || write_loop(rx, open, heartbeat));
+        Self {
+            tx: Some(tx),
+            writer: Some(writer),
+        }
+    }
+
+    pub fn show_gear(&self, position: GearPosition) {
+        if let Some(tx) = &self.tx
+            && let Err(TrySendError::Full(_)) = tx.try_send(gear_line(position))
+        {
+            say!("   ⚠️  Serial display not keeping up, dropped an update");
+        }
+    }
+}
+
+impl Drop for SerialDisplay {
+    fn drop(&mut self) {
+        // Closing the queue lets the writer drain what's left and exit
+        self.tx.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_loop(rx: Receiver<String>, mut open: Opener, heartbeat: Duration) {
+    let mut port: Option<Box<dyn Write + Send>> = None;
+    let mut last_gear: Option<String> = None;
+
+    loop {
+        let line = match rx.recv_timeout(heartbeat) {
+            Ok(line) => {
+                last_gear = Some(line.clone());
+                line
+            }
+            Err(RecvTimeoutError::Timeout) => "H\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+
+        if port.is_none() {
+            // At most one reopen attempt per line or heartbeat
+            port = open().ok();
+            // A freshly opened display doesn't know the gear yet
+            if let (Some(p), Some(gear)) = (port.as_mut(), &last_gear)
+                && *gear != line
+                && p.write_all(gear.as_bytes()).is_err()
+            {
+                port = None;
+            }
+        }
+
+        if let Some(p) = port.as_mut()
+            && p.write_all(line.as_bytes())
+                .and_then(|_| p.flush())
+                .is_err()
+        {
+            port = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn opener(buf: SharedBuf) -> Opener {
+        Box::new(move || Ok(Box::new(buf.clone()) as Box<dyn Write + Send>))
+    }
+
+    #[test]
+    fn parses_port_and_baud() {
+        assert_eq!(
+            parse_target("/dev/ttyUSB0:115200"),
+            Ok(("/dev/ttyUSB0".to_string(), 115200))
+        );
+        assert_eq!(parse_target("COM3:9600"), Ok(("COM3".to_string(), 9600)));
+        assert!(parse_target("/dev/ttyUSB0").is_err());
+        assert!(parse_target("/dev/ttyUSB0:fast").is_err());
+        assert!(parse_target(":9600").is_err());
+    }
+
+    #[test]
+    fn scripted_session_bytes() {
+        let buf = SharedBuf::default();
+        let display = SerialDisplay::spawn(opener(buf.clone()), Duration::from_secs(60));
+        for position in [
+            GearPosition::Gear(1),
+            GearPosition::Gear(2),
+            GearPosition::Neutral,
+            GearPosition::Reverse,
+            GearPosition::Neutral,
+            GearPosition::Gear(10),
+        ] {
+            display.show_gear(position);
+        }
+        drop(display);
+
+        assert_eq!(buf.contents(), "G1\nG2\nGN\nGR\nGN\nG10\n");
+    }
+
+    #[test]
+    fn heartbeat_when_idle() {
+        let buf = SharedBuf::default();
+        let display = SerialDisplay::spawn(opener(buf.clone()), Duration::from_millis(20));
+        display.show_gear(GearPosition::Gear(3));
+        thread::sleep(Duration::from_millis(100));
+        drop(display);
+
+        let contents = buf.contents();
+        assert!(contents.starts_with("G3\nH\n"), "got {:?}", contents);
+    }
+
+    #[test]
+    fn reopens_and_resends_gear_after_failure() {
+        let buf = SharedBuf::default();
+        let inner = buf.clone();
+        let mut attempts = 0;
+        let open: Opener = Box::new(move || {
+            attempts += 1;
+            if attempts == 1 {
+                Err(io::Error::new(io::ErrorKind::NotFound, "unplugged"))
+            } else {
+                Ok(Box::new(inner.clone()) as Box<dyn Write + Send>)
+            }
+        });
+
+        let display = SerialDisplay::spawn(open, Duration::from_millis(20));
+        display.show_gear(GearPosition::Gear(2));
+        thread::sleep(Duration::from_millis(60));
+        drop(display);
+
+        // G2 was lost while unplugged, so the next heartbeat reopens and resends it
+        let contents = buf.contents();
+        assert!(contents.starts_with("G2\nH\n"), "got {:?}", contents);
+    }
+
+    #[test]
+    fn wedged_device_never_blocks_shifting() {
+        // Stuck in its first write until the test lets go of it
+        struct Wedged(Receiver<()>);
+        impl Write for Wedged {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                let _ = self.0.recv();
+                Err(io::Error::new(io::ErrorKind::TimedOut, "wedged"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (release, wedge) = mpsc::channel();
+        let mut wedge = Some(wedge);
+        let display = SerialDisplay::spawn(
+            Box::new(move || match wedge.take() {
+                Some(wedge) => Ok(Box::new(Wedged(wedge)) as Box<dyn Write + Send>),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "unplugged")),
+            }),
+            Duration::from_secs(60),
+        );
+        // Far more than the queue holds, all while the writer is stuck
+        for gear in 0..(QUEUE_LEN as u8 * 4) {
+            display.show_gear(GearPosition::Gear(gear));
+        }
+        drop(release);
+        drop(display);
+    }
+}
@@ -0,0 +1,3261 @@
+// One driving session: turns driver actions into gear changes and rumbles,
+// independent of where the actions come from or which device plays them.
+
+use crate::car::{Car, GearPosition};
+use crate::drag::{AMBER_RUMBLE, DragEvent, DragRun, GREEN_RUMBLE, Strip, celebration};
+use crate::haptics::{
+    Adsr, Envelopes, GRIND_MAGNITUDE, HapticController, HapticError, Pulse, QUERY_MAGNITUDE,
+    RumbleCommand, RumblePattern, gear_query_pulses, grind_pulses, pulses_length_ms,
+};
+use crate::hooks::{HapticHooks, TickState};
+use crate::intensity::Intensity;
+use crate::playstation::gear_colour;
+use crate::profiles::{self, Profile, Profiles};
+use crate::say;
+use crate::shift_log::{ShiftKind, ShiftLog, ShiftRecord, gear_label};
+use crate::telemetry::TelemetryFrame;
+use crate::trainer::{Grade, Trainer};
+use crate::units::{AngularSpeed, Torque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Slow-motion replays stretch the last shift this many times, unless the
+// config or --slowmo says otherwise
+pub const DEFAULT_SLOWMO_FACTOR: f32 = 4.0;
+
+// A light buzz when the game's traction control starts cutting in
+const TRACTION_CONTROL_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 0,
+    weak_magnitude: 20000,
+    duration_ms: 80,
+};
+
+// A dull clunk when reverse goes in
+const REVERSE_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 30000,
+    weak_magnitude: 0,
+    duration_ms: 120,
+};
+
+// Reverse keeps warbling on the strong motor after its clunk, slow and low
+const REVERSE_WARBLE: [(u16, u32); 6] = [
+    (26000, 70),
+    (9000, 70),
+    (22000, 70),
+    (7000, 70),
+    (16000, 70),
+    (4000, 70),
+];
+
+// Free revs in neutral: a weak-motor pulse that quickens and firms up as
+// the throttle opens, in REV_STEPS steps from REV_SLOWEST_MS to REV_FASTEST_MS
+const REV_STEPS: f32 = 4.0;
+const REV_PULSE_MS: u32 = 20;
+const REV_MAGNITUDE: u16 = 36000;
+const REV_SLOWEST_MS: u32 = 120;
+const REV_FASTEST_MS: u32 = 40;
+
+// Turbo spool: a low, steady weak-motor rumble that rises with the boost in
+// SPOOL_STEPS steps. The blow-off is a flutter of FLUTTER_STAGES stages
+// alternating between the motors, dying away, as strong as the boost vented
+const SPOOL_STEPS: f32 = 4.0;
+const SPOOL_MS: u32 = 50;
+const SPOOL_MAGNITUDE: u16 = 24000;
+const FLUTTER_STAGES: u16 = 8;
+const FLUTTER_MS: u32 = 25;
+const FLUTTER_MAGNITUDE: u16 = 45000;
+
+// Letting the clutch up in gear below STALL_MARGIN_RPM over idle, with the
+// throttle under STALL_THROTTLE, stalls the engine: it shudders down through
+// STALL_JUDDERS judders, each weaker than the last, into silence
+const STALL_MARGIN_RPM: f32 = 300.0;
+const STALL_THROTTLE: f32 = 0.15;
+const STALL_JUDDERS: u16 = 6;
+const STALL_JUDDER_MS: u32 = 70;
+const STALL_MAGNITUDE: u16 = 55000;
+
+// The starter motor whirring while held; the engine catches after CRANK_MS
+const CRANK_PULSE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 0,
+    weak_magnitude: 28000,
+    duration_ms: 35,
+};
+const CRANK_PERIOD_MS: u32 = 60;
+const CRANK_MS: u64 = 800;
+const CAUGHT_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 40000,
+    weak_magnitude: 20000,
+    duration_ms: 250,
+};
+
+// A downshift that throws the engine past the redline: both motors flat out
+// for most of a second
+const MONEY_SHIFT_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: u16::MAX,
+    weak_magnitude: u16::MAX,
+    duration_ms: 800,
+};
+
+// Two quick taps when a gamepad (re)connects mid-session
+const CONNECTED_PULSES: [Pulse; 2] = [
+    Pulse {
+        after_ms: 0,
+        duration_ms: 60,
+    },
+    Pulse {
+        after_ms: 120,
+        duration_ms: 60,
+    },
+];
+
+// Reverse only goes in below this road speed
+const REVERSE_MAX_KMH: f32 = 10.0;
+
+// A shift with the throttle closed still rumbles this much of a full one
+const THROTTLE_RUMBLE_FLOOR: f32 = 0.4;
+
+// Rev limiter bounce: a sharp tap every period while the engine sits on the
+// limiter with the throttle open past LIMITER_THROTTLE
+const LIMITER_PULSE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 45000,
+    weak_magnitude: 30000,
+    duration_ms: 25,
+};
+const LIMITER_PERIOD_MS: u32 = 70;
+const LIMITER_THROTTLE: f32 = 0.1;
+
+// Launch control: the engine stutters against the rev cut at the launch rpm,
+// then the clutch drops in one long shove
+pub const DEFAULT_LAUNCH_RPM: f32 = 4000.0;
+const LAUNCH_STUTTER: RumbleCommand = RumbleCommand {
+    strong_magnitude: 20000,
+    weak_magnitude: 45000,
+    duration_ms: 30,
+};
+const LAUNCH_STUTTER_PERIOD_MS: u32 = 90;
+const LAUNCH_RUMBLE: RumbleCommand = RumbleCommand {
+    strong_magnitude: 60000,
+    weak_magnitude: 40000,
+    duration_ms: 700,
+};
+
+// Rev-match blip ahead of a downshift rumble: the weak motor, stronger the
+// further the revs have to climb, never weaker than BLIP_FLOOR of full
+const BLIP_MAGNITUDE: u16 = 50000;
+const BLIP_FLOOR: f32 = 0.2;
+const BLIP_MS: u32 = 60;
+const BLIP_GAP_MS: u32 = 30;
+
+// Adaptive throttle triggers start stiffening past this fraction of the rev
+// range above idle, and are as stiff as they get at the redline
+const TRIGGER_STIFFEN_FROM: f32 = 0.7;
+
+// The lightbar flashes past this fraction of the rev range above idle
+const LIGHTBAR_FLASH_FROM: f32 = 0.95;
+
+// Automatic mode shifts up past this fraction of the rev range above idle,
+// and down below this one
+const AUTO_UPSHIFT: f32 = 0.9;
+const AUTO_DOWNSHIFT: f32 = 0.2;
+
+/// Something the driver asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Upshift,
+    Downshift,
+    /// Replay the last shift rumble stretched out in time.
+    ReplaySlowmo,
+    /// Tell the current gear by feel.
+    QueryGear,
+    /// Switch between shifting by hand and the automatic.
+    ToggleAutomatic,
+    /// Silence every rumble, or bring them back.
+    ToggleMute,
+    /// Switch to the next saved profile, see [`Session::switch_profile`].
+    NextProfile,
+    /// Save the car and rumble settings to the profile in use.
+    SaveProfile,
+    /// Go straight to a gear, e.g. from a number key. See [`ShiftMode`].
+    SelectGear(u8),
+    /// Out of gear; the car rolls and the engine revs freely.
+    Neutral,
+    /// Into reverse, only while (nearly) stopped.
+    Reverse,
+}
+
+/// Which gear changes the box takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShiftMode {
+    /// One gear up or down at a time; a jump past the next gear grinds and
+    /// stays out.
+    #[default]
+    Sequential,
+    /// Straight into any gear, as through an H-pattern gate.
+    Direct,
+}
+
+pub struct Session<H: HapticController> {
+    car: Car,
+    haptics: H,
+    last_shift_rumble: Option<RumblePattern>, // For slow-mo replays
+    rumble_until: Option<Instant>,            // When the current rumble finishes playing
+    gear_query_pending: bool,                 // Waiting for a shift rumble to finish
+    telemetry_gear: Option<GearPosition>,     // Gear in the last telemetry frame
+    traction_control: bool,                   // TC active in the last telemetry frame
+    selected: Option<GearPosition>,           // Where an H-pattern shifter has the box
+    clutch_down: Option<bool>,                // None without a clutch pedal
+    refuse_clutchless: bool,                  // Shifts without the clutch don't go in
+    throttle: Option<f32>,                    // None without a throttle pedal
+    brake: f32,
+    automatic: bool, // The box shifts by itself as the pedals drive
+    bouncing: bool,  // The rev limiter bounce is playing
+    envelopes: Envelopes,
+    intensity: Intensity, // Applied to every rumble on its way out
+    muted: bool,          // Nothing reaches the motors
+    profiles: Option<Profiles>,
+    hooks: Option<Box<dyn HapticHooks>>,
+    hum: Option<RumbleCommand>, // The tick hook's rumble, while it repeats
+    shift_log: Option<ShiftLog>,
+    trainer: Option<Trainer>,
+    grade_pending: Option<Grade>, // Waiting for a shift rumble to finish
+    drag: Option<DragRun>,
+    slowmo_factor: f32,
+    launch_rpm: f32,
+    launch_control: bool,           // Held at launch_rpm until let go
+    shift_done_at: Option<Instant>, // When the transmission takes another shift
+    shift_mode: ShiftMode,
+    out_of_gear: Option<GearPosition>, // Neutral or reverse from the buttons
+    revving: u8,                       // Free-rev step playing in neutral, 0 for none
+    spooling: u8,                      // Turbo spool step playing, 0 for none
+    stalled: bool,
+    clutch_was_down: bool,           // At the last drive step
+    cranking_since: Option<Instant>, // The starter is held
+}
+
+impl<H: HapticController> Session<H> {
+    pub fn new(car: Car, haptics: H) -> Self {
+        Self {
+            car,
+            haptics,
+            last_shift_rumble: None,
+            rumble_until: None,
+            gear_query_pending: false,
+            telemetry_gear: None,
+            traction_control: false,
+            selected: None,
+            clutch_down: None,
+            refuse_clutchless: false,
+            throttle: None,
+            brake: 0.0,
+            automatic: false,
+            bouncing: false,
+            envelopes: Envelopes::default(),
+            intensity: Intensity::default(),
+            muted: false,
+            profiles: None,
+            hooks: None,
+            hum: None,
+            shift_log: None,
+            trainer: None,
+            grade_pending: None,
+            drag: None,
+            slowmo_factor: DEFAULT_SLOWMO_FACTOR,
+            launch_rpm: DEFAULT_LAUNCH_RPM,
+            launch_control: false,
+            shift_done_at: None,
+            shift_mode: ShiftMode::default(),
+            out_of_gear: None,
+            revving: 0,
+            spooling: 0,
+            stalled: false,
+            clutch_was_down: false,
+            cranking_since: None,
+        }
+    }
+
+    pub fn car(&self) -> &Car {
+        &self.car
+    }
+
+    pub fn car_mut(&mut self) -> &mut Car {
+        &mut self.car
+    }
+
+    pub fn haptics(&self) -> &H {
+        &self.haptics
+    }
+
+    pub fn haptics_mut(&mut self) -> &mut H {
+        &mut self.haptics
+    }
+
+    pub fn handle(&mut self, action: Action, now: Instant) -> Result<(), HapticError> {
+        match action {
+            Action::Upshift => self.shift(false, now),
+            Action::Downshift => self.shift(true, now),
+            Action::ReplaySlowmo => self.replay_slowmo(now),
+            Action::QueryGear => self.query_gear(now),
+            Action::ToggleAutomatic => {
+                self.set_automatic(!self.automatic);
+                Ok(())
+            }
+            Action::ToggleMute => self.toggle_mute(),
+            Action::NextProfile => self.next_profile(),
+            Action::SaveProfile => {
+                self.save_profile(None);
+                Ok(())
+            }
+            Action::SelectGear(gear) => self.select_gear(gear, now),
+            Action::Neutral => {
+                self.neutral();
+                Ok(())
+            }
+            Action::Reverse => self.reverse(now),
+        }
+    }
+
+    /// Called once per loop iteration to start anything that was waiting.
+    pub fn poll(&mut self, now: Instant) -> Result<(), HapticError> {
+        self.haptics.tick(now)?;
+        let revs = self.rev_fraction(self.car.engine().speed().rpm());
+        let stiffness = if self.muted {
+            0.0
+        } else {
+            (revs - TRIGGER_STIFFEN_FROM) / (1.0 - TRIGGER_STIFFEN_FROM)
+        };
+        self.haptics
+            .set_throttle_resistance(stiffness.clamp(0.0, 1.0))?;
+        let colour = gear_colour(self.position(), self.car.gear_count());
+        self.haptics
+            .set_lightbar(colour, revs >= LIGHTBAR_FLASH_FROM)?;
+        self.poll_starter(now)?;
+        self.poll_grade(now)?;
+        self.poll_gear_query(now)
+    }
+
+    /// Follows a game's gearbox: a gear change in `frame` plays the same
+    /// rumble as a shift button would. Neutral and reverse only pass through.
+    /// Traction control cutting in buzzes lightly unless a shift is playing.
+    pub fn sync(&mut self, frame: &TelemetryFrame, now: Instant) -> Result<(), HapticError> {
+        self.car.engine_mut().set_speed(frame.engine_speed);
+        self.sync_gear(frame, now)?;
+
+        let traction_control = frame.traction_control == Some(true);
+        let cut_in = traction_control && !self.traction_control;
+        self.traction_control = traction_control;
+        if cut_in && !self.is_rumbling(now) && self.haptics.is_supported() {
+            self.play(TRACTION_CONTROL_RUMBLE, now)?;
+        }
+        self.tick_hook(now)
+    }
+
+    fn sync_gear(&mut self, frame: &TelemetryFrame, now: Instant) -> Result<(), HapticError> {
+        let previous = self.telemetry_gear.replace(frame.gear);
+        if previous == Some(frame.gear) {
+            return Ok(());
+        }
+        let GearPosition::Gear(gear) = frame.gear else {
+            return Ok(());
+        };
+        let from = self.car.current_gear();
+        if !self.car.set_gear(gear) {
+            say!(
+                "\n⚠️  Game is in gear {} but this car only has {}",
+                gear,
+                self.car.gear_count()
+            );
+            return Ok(());
+        }
+        // The first frame only tells us where the game already is
+        if previous.is_none() || gear == from {
+            return Ok(());
+        }
+
+        let is_downshift = gear < from;
+        if is_downshift {
+            say!("\n🔽 DOWNSHIFT → Gear {} (game)", gear);
+        } else {
+            say!("\n🔼 UPSHIFT → Gear {} (game)", gear);
+        }
+        if let Some(power) = frame.power {
+            say!("   Power:      {}", self.car.units().format_power(power));
+        }
+        let torque = frame.torque.unwrap_or_else(|| self.car.engine().torque());
+        // The game blips for itself
+        self.shift_rumble(is_downshift, GearPosition::Gear(from), torque, 0.0, now)
+    }
+
+    /// Shapes the shift, reverse and money shift rumbles from now on.
+    pub fn set_envelopes(&mut self, envelopes: Envelopes) {
+        self.envelopes = envelopes;
+    }
+
+    /// Scales, caps and draws out every rumble from now on.
+    pub fn set_intensity(&mut self, intensity: Intensity) {
+        self.intensity = intensity;
+    }
+
+    pub fn intensity(&self) -> Intensity {
+        self.intensity
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Silences the motors and triggers until toggled back, without
+    /// changing anything else: shifts are timed and logged as before, only
+    /// nothing is felt. The repeating rumbles pick up again once unmuted.
+    pub fn toggle_mute(&mut self) -> Result<(), HapticError> {
+        self.muted = !self.muted;
+        self.hum = None;
+        self.bouncing = false;
+        self.revving = 0;
+        self.spooling = 0;
+        if self.muted {
+            say!("\n🔇 Rumble muted");
+            self.haptics.stop();
+            return self.haptics.set_throttle_resistance(0.0);
+        }
+        say!("\n🔊 Rumble unmuted");
+        self.resume_held()
+    }
+
+    /// Starts the launch stutter or the crank again if its input is still
+    /// held, after something stopped it.
+    fn resume_held(&mut self) -> Result<(), HapticError> {
+        if !self.haptics.is_supported() {
+            return Ok(());
+        }
+        if self.launch_control {
+            self.play_repeating(LAUNCH_STUTTER, LAUNCH_STUTTER_PERIOD_MS)?;
+        } else if self.cranking_since.is_some() {
+            self.play_repeating(CRANK_PULSE, CRANK_PERIOD_MS)?;
+        }
+        Ok(())
+    }
+
+    /// Drives `car` from now on, in the same gear if it has one and its
+    /// top gear if not. The per-gear motor mix and feel carry over to a
+    /// car with as many gears.
+    pub fn set_car(&mut self, mut car: Car) {
+        if car.gear_count() == self.car.gear_count() {
+            car.set_motor_mix(self.car.motor_mix().cloned());
+            car.set_gear_feel(self.car.gear_feel().cloned());
+        }
+        car.set_gear(self.car.current_gear().min(car.gear_count()));
+        car.engine_mut().set_speed(self.car.engine().speed());
+        self.car = car;
+        self.hum = None;
+        self.bouncing = false;
+        self.revving = 0;
+        self.spooling = 0;
+        self.stop_repeating();
+    }
+
+    /// Lets `hooks` shape the shift rumbles and add a rumble of their own
+    /// from now on.
+    pub fn set_hooks(&mut self, hooks: Option<Box<dyn HapticHooks>>) {
+        self.hooks = hooks;
+        self.hum = None;
+    }
+
+    /// Saves and switches between `profiles` from now on.
+    pub fn set_profiles(&mut self, profiles: Option<Profiles>) {
+        self.profiles = profiles;
+    }
+
+    pub fn profiles(&self) -> Option<&Profiles> {
+        self.profiles.as_ref()
+    }
+
+    /// Drives the car saved as profile `name`, with its rumble settings.
+    /// A profile that can't be loaded is reported and nothing changes.
+    pub fn switch_profile(&mut self, name: &str) -> Result<(), HapticError> {
+        let Some(profiles) = &mut self.profiles else {
+            say!("\n⚠️  Profiles aren't available");
+            return Ok(());
+        };
+        let loaded = profiles.load(name).and_then(|profile| {
+            Ok((
+                profile.build_car()?,
+                profile.intensity()?,
+                profile.latency(),
+            ))
+        });
+        let (car, intensity, latency) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                say!("\n⚠️  Profile '{}': {}", name, e);
+                return Ok(());
+            }
+        };
+        self.set_car(car);
+        self.intensity = intensity;
+        if let Some(run) = &mut self.drag {
+            run.set_latency(latency);
+        }
+        let units = self.car.units();
+        say!(
+            "\n👤 Profile '{}': {}, {}, {} gears, rumble {}%",
+            name,
+            units.format_torque(self.car.torque()),
+            units.format_power(self.car.power()),
+            self.car.gear_count(),
+            intensity.percent()
+        );
+        if self.launch_control {
+            self.hold_launch_rpm();
+        }
+        self.resume_held()
+    }
+
+    fn next_profile(&mut self) -> Result<(), HapticError> {
+        let Some(profiles) = &self.profiles else {
+            say!("\n⚠️  Profiles aren't available");
+            return Ok(());
+        };
+        match profiles.next() {
+            Ok(Some(name)) => self.switch_profile(&name),
+            Ok(None) => {
+                say!("\n👤 No other profile saved yet");
+                Ok(())
+            }
+            Err(e) => {
+                say!("\n⚠️  Profiles: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Saves the car and rumble settings as profile `name`, or as the
+    /// profile in use.
+    pub fn save_profile(&mut self, name: Option<&str>) {
+        let Some(profiles) = &mut self.profiles else {
+            say!("\n⚠️  Profiles aren't available");
+            return;
+        };
+        let name = name
+            .or(profiles.active())
+            .unwrap_or(profiles::DEFAULT_NAME)
+            .to_string();
+        let mut profile = Profile::snapshot(&self.car, self.intensity);
+        // The latency is measured outside a session, see `latency`
+        profile.latency_ms = profiles.read(&name).ok().and_then(|saved| saved.latency_ms);
+        match profiles.save(&name, &profile) {
+            Ok(path) => say!("\n💾 Profile '{}' saved to {}", name, path.display()),
+            Err(e) => say!("\n⚠️  Profile '{}': {}", name, e),
+        }
+    }
+
+    /// Records every shift from now on to `log`, or stops recording.
+    pub fn set_shift_log(&mut self, log: Option<ShiftLog>) {
+        self.shift_log = log;
+    }
+
+    /// Grades every shift button press from now on, or stops grading.
+    pub fn set_trainer(&mut self, trainer: Option<Trainer>) {
+        self.trainer = trainer;
+        self.grade_pending = None;
+    }
+
+    pub fn trainer(&self) -> Option<&Trainer> {
+        self.trainer.as_ref()
+    }
+
+    /// Lines the car up for timed 0–60 runs from now on, or goes back to
+    /// driving freely.
+    pub fn set_drag(&mut self, drag: Option<DragRun>) {
+        self.drag = drag;
+        if let Some(run) = &mut self.drag {
+            run.stage(&mut self.car);
+            self.say_staged();
+        }
+    }
+
+    pub fn drag(&self) -> Option<&DragRun> {
+        self.drag.as_ref()
+    }
+
+    /// How many times slower slow-mo replays play the last shift, see
+    /// [`check_slowmo_factor`].
+    pub fn set_slowmo_factor(&mut self, factor: f32) {
+        self.slowmo_factor = factor;
+    }
+
+    /// Where launch control holds the revs.
+    pub fn set_launch_rpm(&mut self, rpm: f32) {
+        self.launch_rpm = rpm;
+    }
+
+    pub fn launch_control(&self) -> bool {
+        self.launch_control
+    }
+
+    /// Holds the engine at the launch rpm with the clutch in, stuttering
+    /// against the rev cut, until `release_launch_control`. Only from first
+    /// gear, and not while following a game.
+    pub fn engage_launch_control(&mut self) -> Result<(), HapticError> {
+        if self.launch_control {
+            return Ok(());
+        }
+        if self.telemetry_gear.is_some() {
+            say!("\n⚠️  No launch control while following the game");
+            return Ok(());
+        }
+        if self.stalled {
+            say!("\n⚠️  The engine has stalled: start it first");
+            return Ok(());
+        }
+        if self.position() != GearPosition::Gear(1) {
+            say!("\n⚠️  Launch control only works in first gear");
+            return Ok(());
+        }
+        self.launch_control = true;
+        self.bouncing = false;
+        self.hold_launch_rpm();
+        say!("\n🚀 LAUNCH CONTROL — holding {:.0} rpm", self.launch_rpm);
+        if self.haptics.is_supported() {
+            self.play_repeating(LAUNCH_STUTTER, LAUNCH_STUTTER_PERIOD_MS)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the clutch with one long shove.
+    pub fn release_launch_control(&mut self, now: Instant) -> Result<(), HapticError> {
+        if !self.launch_control {
+            return Ok(());
+        }
+        self.launch_control = false;
+        self.stop_repeating();
+        say!("\n🚀 LAUNCH! Clutch out at {:.0} rpm", self.launch_rpm);
+        if self.haptics.is_supported() {
+            self.play(LAUNCH_RUMBLE, now)?;
+        }
+        Ok(())
+    }
+
+    /// Lets go of launch control without launching, once the box has left
+    /// first gear under it.
+    fn cancel_launch_control(&mut self) {
+        self.launch_control = false;
+        self.stop_repeating();
+    }
+
+    fn hold_launch_rpm(&mut self) {
+        let rpm = AngularSpeed::from_rpm(self.launch_rpm);
+        self.car.engine_mut().set_speed(rpm);
+    }
+
+    pub fn shift_mode(&self) -> ShiftMode {
+        self.shift_mode
+    }
+
+    /// Sets which gear changes go in from now on, from the shifter as well
+    /// as from `Action::SelectGear`.
+    pub fn set_shift_mode(&mut self, mode: ShiftMode) {
+        self.shift_mode = mode;
+    }
+
+    pub fn stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Shudders to a stop; nothing drives until the starter brings the
+    /// engine back.
+    fn stall(&mut self, now: Instant) -> Result<(), HapticError> {
+        self.stalled = true;
+        self.launch_control = false;
+        self.bouncing = false;
+        self.revving = 0;
+        self.spooling = 0;
+        self.stop_repeating();
+        say!("\n💀 STALLED — clutch down or neutral, then hold the starter");
+        if !self.haptics.is_supported() {
+            return Ok(());
+        }
+        let judders = (0..STALL_JUDDERS).flat_map(|i| {
+            let left = (STALL_JUDDERS - i) as f32 / STALL_JUDDERS as f32;
+            let magnitude = (STALL_MAGNITUDE as f32 * left) as u16;
+            [
+                RumbleCommand {
+                    strong_magnitude: magnitude,
+                    weak_magnitude: magnitude / 3,
+                    duration_ms: STALL_JUDDER_MS,
+                },
+                RumbleCommand {
+                    strong_magnitude: magnitude / 4,
+                    weak_magnitude: 0,
+                    duration_ms: STALL_JUDDER_MS,
+                },
+            ]
+        });
+        self.play_pattern(&RumblePattern::new(judders.collect()), now)
+    }
+
+    /// Starts cranking a stalled engine, which catches once the starter
+    /// has been held for long enough. Only with the clutch down or out of
+    /// gear.
+    pub fn crank_starter(&mut self, now: Instant) -> Result<(), HapticError> {
+        if !self.stalled {
+            say!("\n⚠️  The engine is already running");
+            return Ok(());
+        }
+        if self.cranking_since.is_some() {
+            return Ok(());
+        }
+        let in_gear = matches!(self.position(), GearPosition::Gear(_));
+        if in_gear && self.clutch_down != Some(true) {
+            say!("\n⚠️  Clutch down or neutral to start the engine");
+            return Ok(());
+        }
+        say!("\n🔑 Cranking...");
+        self.cranking_since = Some(now);
+        if self.haptics.is_supported() {
+            self.play_repeating(CRANK_PULSE, CRANK_PERIOD_MS)?;
+        }
+        Ok(())
+    }
+
+    /// Lets go of the starter, before the engine caught or after.
+    pub fn release_starter(&mut self) {
+        if self.cranking_since.take().is_some() {
+            self.stop_repeating();
+            say!("   Starter released before the engine caught");
+        }
+    }
+
+    /// Brings the engine back once the starter has cranked it long enough.
+    fn poll_starter(&mut self, now: Instant) -> Result<(), HapticError> {
+        let Some(since) = self.cranking_since else {
+            return Ok(());
+        };
+        if now - since < Duration::from_millis(CRANK_MS) {
+            return Ok(());
+        }
+        self.cranking_since = None;
+        self.stalled = false;
+        self.stop_repeating();
+        let idle = self.car.engine().idle();
+        self.car.engine_mut().set_speed(idle);
+        say!("\n🔥 Engine running");
+        if self.haptics.is_supported() {
+            self.play(CAUGHT_RUMBLE, now)?;
+        }
+        Ok(())
+    }
+
+    /// Where the H-pattern shifter has the box, `None` when shifting with
+    /// the sequential actions.
+    pub fn selected(&self) -> Option<GearPosition> {
+        self.selected
+    }
+
+    /// Where the gearbox is: the shifter's position if there is one,
+    /// neutral or reverse from the buttons, the car's gear otherwise.
+    pub fn position(&self) -> GearPosition {
+        self.selected
+            .or(self.out_of_gear)
+            .unwrap_or(GearPosition::Gear(self.car.current_gear()))
+    }
+
+    /// `Action::Neutral`: out of gear until a shift puts it back in.
+    fn neutral(&mut self) {
+        if self.selected.is_some() {
+            say!("\n⚠️  Use the H-pattern shifter to change gear");
+            return;
+        }
+        if self.out_of_gear != Some(GearPosition::Neutral) {
+            self.out_of_gear = Some(GearPosition::Neutral);
+            say!("\n⚪ NEUTRAL");
+        }
+    }
+
+    /// `Action::Reverse`: into reverse if the car has (nearly) stopped and
+    /// the clutch rules allow; otherwise it grinds and the box stays put.
+    fn reverse(&mut self, now: Instant) -> Result<(), HapticError> {
+        if self.selected.is_some() {
+            say!("\n⚠️  Use the H-pattern shifter to change gear");
+            return Ok(());
+        }
+        if self.out_of_gear == Some(GearPosition::Reverse) {
+            return Ok(());
+        }
+        if let Some(why) = self.reverse_refused() {
+            return self.grind(&format!("{} won't go in", why), now);
+        }
+        let from = self.position();
+        self.out_of_gear = Some(GearPosition::Reverse);
+        self.engage_reverse(from, now)
+    }
+
+    /// Why reverse can't go in right now, if it can't.
+    fn reverse_refused(&self) -> Option<String> {
+        let speed = self.car.road_speed();
+        if speed.kmh() > REVERSE_MAX_KMH {
+            Some(format!("reverse at {:.0} mph", speed.mph()))
+        } else if self.refuses_clutchless() {
+            Some("reverse without the clutch".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// The clunk and warble of reverse going in `from` somewhere else, or a
+    /// grind without the clutch.
+    fn engage_reverse(&mut self, from: GearPosition, now: Instant) -> Result<(), HapticError> {
+        say!("\n◀️  REVERSE");
+        if self.is_clutchless() {
+            return self.grind("in without the clutch", now);
+        }
+        let torque = self.car.engine().torque();
+        if !self.haptics.is_supported() {
+            self.log_shift(ShiftKind::Reverse, from, torque, None, None);
+            return Ok(());
+        }
+        let warble: Vec<_> = REVERSE_WARBLE
+            .iter()
+            .map(|&(strong_magnitude, duration_ms)| RumbleCommand {
+                strong_magnitude,
+                weak_magnitude: 0,
+                duration_ms,
+            })
+            .collect();
+        let clunk = shaped(REVERSE_RUMBLE, self.envelopes.reverse);
+        let pattern = RumblePattern::new(warble).preceded_by(clunk.stages());
+        self.play_pattern(&pattern, now)?;
+        self.log_shift(ShiftKind::Reverse, from, torque, None, Some(&pattern));
+        Ok(())
+    }
+
+    /// Follows an H-pattern shifter into `position`. Reverse while still
+    /// rolling, or anything the clutch rules refuse, grinds and stays out:
+    /// the box is in neutral until the lever goes somewhere else. A gear
+    /// that over-revs the engine goes in as a money shift. Sequential shifts
+    /// are off from the first call on.
+    pub fn select(&mut self, position: GearPosition, now: Instant) -> Result<(), HapticError> {
+        let previous = self.selected.replace(position);
+        match position {
+            GearPosition::Neutral => {
+                if previous != Some(GearPosition::Neutral) {
+                    say!("\n⚪ NEUTRAL");
+                }
+                Ok(())
+            }
+            GearPosition::Reverse => {
+                if let Some(why) = self.reverse_refused() {
+                    return self.refuse(&why, now);
+                }
+                let from = previous.unwrap_or(GearPosition::Gear(self.car.current_gear()));
+                self.engage_reverse(from, now)
+            }
+            GearPosition::Gear(gear) => {
+                if gear < 1 || gear > self.car.gear_count() {
+                    say!("\n⚠️  This car has no gear {}", gear);
+                    self.selected = Some(GearPosition::Neutral);
+                    return Ok(());
+                }
+                if self.refuses_clutchless() {
+                    return self.refuse(&format!("gear {} without the clutch", gear), now);
+                }
+                let from = self.car.current_gear();
+                if self.shift_mode == ShiftMode::Sequential && gear.abs_diff(from) > 1 {
+                    return self.refuse(&format!("gear {} straight from {}", gear, from), now);
+                }
+                if self.car.is_money_shift(gear) {
+                    return self.money_shift(gear, now);
+                }
+                let climb = self.rev_climb(gear);
+                self.car.shift_into(gear);
+                let is_downshift = gear < from;
+                if is_downshift {
+                    say!("\n🔽 DOWNSHIFT → Gear {}", gear);
+                } else if gear > from {
+                    say!("\n🔼 UPSHIFT → Gear {}", gear);
+                } else {
+                    say!("\n⚙️  Back into gear {}", gear);
+                }
+                self.engaged(
+                    is_downshift,
+                    previous.unwrap_or(GearPosition::Gear(from)),
+                    climb,
+                    now,
+                )
+            }
+        }
+    }
+
+    /// Where the throttle (`None` without a pedal) and brake are, 0 to 1.
+    pub fn set_pedals(&mut self, throttle: Option<f32>, brake: f32) {
+        self.throttle = throttle;
+        self.brake = brake;
+    }
+
+    pub fn throttle(&self) -> Option<f32> {
+        self.throttle
+    }
+
+    pub fn brake(&self) -> f32 {
+        self.brake
+    }
+
+    /// Moves the simulated car on by `dt` with the pedals where they are,
+    /// shifting if the automatic is on. Without a throttle pedal the engine
+    /// speed only changes with shifts. In neutral, in reverse or with the
+    /// clutch down the car coasts. Launch control holds the clutch in too.
+    /// The tick hook, if any, sees the car after each step.
+    pub fn drive(&mut self, dt: Duration, now: Instant) -> Result<(), HapticError> {
+        self.drive_car(dt, now)?;
+        self.tick_hook(now)
+    }
+
+    fn drive_car(&mut self, dt: Duration, now: Instant) -> Result<(), HapticError> {
+        if self.launch_control && self.position() != GearPosition::Gear(1) {
+            self.cancel_launch_control();
+        }
+        let Some(throttle) = self.throttle else {
+            if self.launch_control {
+                self.hold_launch_rpm();
+            }
+            return Ok(());
+        };
+        let in_gear = matches!(self.position(), GearPosition::Gear(_));
+        let released = self.clutch_was_down && self.clutch_down == Some(false);
+        self.clutch_was_down = self.clutch_down == Some(true);
+        let idle = self.car.engine().idle().rpm();
+        let lugging = self.car.engine().speed().rpm() < idle + STALL_MARGIN_RPM;
+        if released && in_gear && !self.stalled && lugging && throttle < STALL_THROTTLE {
+            self.stall(now)?;
+        }
+        let clutch_out = self.clutch_down != Some(true) && !self.launch_control && !self.stalled;
+        if let Some(run) = &mut self.drag {
+            let driving = in_gear && clutch_out;
+            if let Some(event) = run.step(&mut self.car, throttle, driving, dt) {
+                self.drag_event(event, now)?;
+            }
+            if self.launch_control {
+                self.hold_launch_rpm();
+                return Ok(());
+            }
+            let throttle = if driving { throttle } else { 0.0 };
+            self.spool_turbo(throttle, dt, now)?;
+            return self.bounce_off_the_limiter(throttle, now);
+        }
+        if self.launch_control {
+            self.car.coast(self.brake, dt);
+            self.hold_launch_rpm();
+            return Ok(());
+        }
+        let free_revs = if self.position() == GearPosition::Neutral && !self.stalled {
+            throttle
+        } else {
+            0.0
+        };
+        let throttle = if in_gear && clutch_out {
+            self.car.drive(throttle, self.brake, dt);
+            throttle
+        } else {
+            self.car.coast(self.brake, dt);
+            0.0
+        };
+        if self.automatic && in_gear && !self.stalled {
+            self.auto_shift(now)?;
+        }
+        self.spool_turbo(throttle, dt, now)?;
+        // Out of neutral the free revs stop before the limiter can start
+        self.rev_freely(free_revs, now)?;
+        self.bounce_off_the_limiter(throttle, now)
+    }
+
+    /// Repeats whatever the tick hook returns, from when it returns
+    /// something new. The built-in repeating rumbles come first and
+    /// anything else that plays replaces it; the hook's rumble is back on
+    /// the first step after they're done.
+    fn tick_hook(&mut self, now: Instant) -> Result<(), HapticError> {
+        if self.hooks.is_none() {
+            return Ok(());
+        }
+        let engine = self.car.engine();
+        let state = TickState {
+            gear: self.position(),
+            rpm: engine.speed().rpm(),
+            redline: engine.redline().rpm(),
+            throttle: self.throttle.unwrap_or(0.0),
+            brake: self.brake,
+            speed_kmh: self.car.road_speed().kmh(),
+            limiter: self.bouncing,
+        };
+        let Some(hooks) = &mut self.hooks else {
+            return Ok(());
+        };
+        let hum = hooks.on_tick(&state);
+        let built_in = self.bouncing
+            || self.launch_control
+            || self.revving > 0
+            || self.spooling > 0
+            || self.cranking_since.is_some();
+        if built_in || hum == self.hum {
+            return Ok(());
+        }
+        let Some(command) = hum else {
+            self.stop_repeating();
+            return Ok(());
+        };
+        let waiting = self.is_rumbling(now) && !self.haptics.mixes();
+        if waiting || !self.haptics.is_supported() {
+            return Ok(());
+        }
+        self.play_repeating(command, command.duration_ms)?;
+        self.hum = hum;
+        Ok(())
+    }
+
+    /// Pulses with the throttle while the engine is out of gear, quicker
+    /// the further it's open; stops once it's closed or back in gear. Like
+    /// the limiter bounce, anything else that plays comes first.
+    fn rev_freely(&mut self, throttle: f32, now: Instant) -> Result<(), HapticError> {
+        let step = (throttle.clamp(0.0, 1.0) * REV_STEPS).ceil() as u8;
+        if step == self.revving {
+            return Ok(());
+        }
+        if step == 0 {
+            self.stop_repeating();
+            self.revving = 0;
+            return Ok(());
+        }
+        let waiting = self.is_rumbling(now) && !self.haptics.mixes();
+        if waiting || !self.haptics.is_supported() {
+            return Ok(());
+        }
+        let open = step as f32 / REV_STEPS;
+        let period_ms = REV_SLOWEST_MS - ((REV_SLOWEST_MS - REV_FASTEST_MS) as f32 * open) as u32;
+        let pulse = RumbleCommand {
+            strong_magnitude: 0,
+            weak_magnitude: (REV_MAGNITUDE as f32 * open) as u16,
+            duration_ms: REV_PULSE_MS,
+        };
+        self.play_repeating(pulse, period_ms)?;
+        self.revving = step;
+        Ok(())
+    }
+
+    /// Builds boost with the throttle the engine is driven at and rumbles
+    /// with it, blowing off when the driver lifts. The other repeating
+    /// rumbles take over from the spool while they play.
+    fn spool_turbo(
+        &mut self,
+        throttle: f32,
+        dt: Duration,
+        now: Instant,
+    ) -> Result<(), HapticError> {
+        let revs = self.rev_fraction(self.car.engine().speed().rpm());
+        let Some(turbo) = self.car.turbo_mut() else {
+            return Ok(());
+        };
+        let vented = turbo.spool(throttle, revs, dt);
+        let step = (turbo.boost() * SPOOL_STEPS).round() as u8;
+        if let Some(boost) = vented {
+            self.blow_off(boost, now)?;
+        }
+        if self.bouncing || self.launch_control || self.revving > 0 || self.cranking_since.is_some()
+        {
+            self.spooling = 0;
+            return Ok(());
+        }
+        if step == self.spooling {
+            return Ok(());
+        }
+        if step == 0 {
+            self.stop_repeating();
+            self.spooling = 0;
+            return Ok(());
+        }
+        let waiting = self.is_rumbling(now) && !self.haptics.mixes();
+        if waiting || !self.haptics.is_supported() {
+            return Ok(());
+        }
+        let spool = RumbleCommand {
+            strong_magnitude: 0,
+            weak_magnitude: (SPOOL_MAGNITUDE as f32 * step as f32 / SPOOL_STEPS) as u16,
+            duration_ms: SPOOL_MS,
+        };
+        self.play_repeating(spool, SPOOL_MS)?;
+        self.spooling = step;
+        Ok(())
+    }
+
+    /// The blow-off valve fluttering as `boost` vents. A mixing backend plays
+    /// it over the shift rumble; otherwise it only plays if nothing else is.
+    fn blow_off(&mut self, boost: f32, now: Instant) -> Result<(), HapticError> {
+        say!("   💨 Blow-off ({:.0}% boost)", boost * 100.0);
+        let waiting = self.is_rumbling(now) && !self.haptics.mixes();
+        if waiting || !self.haptics.is_supported() {
+            return Ok(());
+        }
+        let flutter = (0..FLUTTER_STAGES).map(|i| {
+            let left = (FLUTTER_STAGES - i) as f32 / FLUTTER_STAGES as f32;
+            let magnitude = (FLUTTER_MAGNITUDE as f32 * boost.min(1.0) * left) as u16;
+            let (strong, weak) = if i % 2 == 0 {
+                (magnitude / 3, magnitude)
+            } else {
+                (magnitude, magnitude / 3)
+            };
+            RumbleCommand {
+                strong_magnitude: strong,
+                weak_magnitude: weak,
+                duration_ms: FLUTTER_MS,
+            }
+        });
+        let pattern = self
+            .intensity
+            .pattern(&RumblePattern::new(flutter.collect()));
+        self.replace_repeating();
+        if !self.muted {
+            self.haptics.play_pattern(&pattern)?;
+        }
+        // Layered, so the shift rumble may well outlast it
+        let until = now + Duration::from_millis(pattern.duration_ms() as u64);
+        self.rumble_until = self.rumble_until.max(Some(until));
+        Ok(())
+    }
+
+    fn drag_event(&mut self, event: DragEvent, now: Instant) -> Result<(), HapticError> {
+        let supported = self.haptics.is_supported();
+        match event {
+            DragEvent::Amber(light) => {
+                say!("🟡 {}", light);
+                if supported {
+                    self.play(AMBER_RUMBLE, now)?;
+                }
+            }
+            DragEvent::Green => {
+                say!("🟢 GO!");
+                if supported {
+                    self.play(GREEN_RUMBLE, now)?;
+                }
+            }
+            DragEvent::RedLight => {
+                say!("\n🔴 RED LIGHT — went before the green");
+                say!("   Lift off to line up again");
+            }
+            DragEvent::Launched => {
+                if self.drag.as_ref().map(DragRun::strip) == Some(Strip::ZeroToSixty) {
+                    say!("\n🟢 GO!");
+                }
+            }
+            DragEvent::Staged => self.say_staged(),
+            DragEvent::Finished {
+                result,
+                personal_best,
+                previous_best,
+            } => {
+                match (result.reaction, result.trap) {
+                    (Some(reaction), Some(trap)) => {
+                        say!(
+                            "\n🏁 Quarter mile: {:.3} s @ {:.1} mph",
+                            result.time.as_secs_f32(),
+                            trap.mph()
+                        );
+                        match self.drag.as_ref().map(DragRun::latency) {
+                            Some(latency) if !latency.is_zero() => say!(
+                                "   Reaction: {:.3} s, less {} ms rumble latency",
+                                reaction.as_secs_f32(),
+                                latency.as_millis()
+                            ),
+                            _ => say!("   Reaction: {:.3} s", reaction.as_secs_f32()),
+                        }
+                    }
+                    _ => say!("\n🏁 0–60 mph in {:.2} s", result.time.as_secs_f32()),
+                }
+                match (personal_best, previous_best) {
+                    (true, Some(best)) => say!(
+                        "   🏆 PERSONAL BEST! {:.2} s quicker",
+                        (best - result.time).as_secs_f32()
+                    ),
+                    (true, None) => say!("   🏆 PERSONAL BEST!"),
+                    (false, Some(best)) => say!("   Best: {:.2} s", best.as_secs_f32()),
+                    (false, None) => {}
+                }
+                say!("   Lift off to line up again");
+                if personal_best && supported {
+                    self.play_pattern(&celebration(), now)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn say_staged(&self) {
+        match self.drag.as_ref().map(DragRun::strip) {
+            Some(Strip::ZeroToSixty) => say!("\n🚦 Staged in first: floor it to launch"),
+            Some(Strip::QuarterMile) => {
+                say!("\n🚦 Staged in first: off the throttle, go on the green")
+            }
+            None => {}
+        }
+    }
+
+    /// Taps away while the engine sits at the redline with the throttle
+    /// open, until the driver shifts up or lifts. Anything else that plays
+    /// replaces the bounce; it comes back once that's done.
+    fn bounce_off_the_limiter(&mut self, throttle: f32, now: Instant) -> Result<(), HapticError> {
+        let engine = self.car.engine();
+        let on_limiter =
+            throttle > LIMITER_THROTTLE && engine.speed().rpm() >= engine.redline().rpm() - 1.0;
+        if !on_limiter {
+            if self.bouncing {
+                self.stop_repeating();
+                self.bouncing = false;
+            }
+            return Ok(());
+        }
+        // A mixing backend plays the limiter under whatever else is going on
+        let waiting = self.is_rumbling(now) && !self.haptics.mixes();
+        if self.bouncing || waiting || !self.haptics.is_supported() {
+            return Ok(());
+        }
+        say!("\n🔴 REV LIMITER");
+        self.play_repeating(LIMITER_PULSE, LIMITER_PERIOD_MS)?;
+        self.bouncing = true;
+        Ok(())
+    }
+
+    pub fn is_bouncing(&self) -> bool {
+        self.bouncing
+    }
+
+    pub fn automatic(&self) -> bool {
+        self.automatic
+    }
+
+    /// Turns the automatic on or off. It can't take over from an H-pattern
+    /// shifter.
+    pub fn set_automatic(&mut self, on: bool) {
+        if on && self.selected.is_some() {
+            say!("\n⚠️  No automatic with the H-pattern shifter");
+            return;
+        }
+        self.automatic = on;
+        if on {
+            say!("\n🅰️  AUTOMATIC");
+        } else {
+            say!("\nⓂ️  MANUAL");
+        }
+    }
+
+    /// Where `rpm` is in the rev range: 0 at idle, 1 at the redline.
+    fn rev_fraction(&self, rpm: f32) -> f32 {
+        let engine = self.car.engine();
+        (rpm - engine.idle().rpm()) / (engine.redline().rpm() - engine.idle().rpm())
+    }
+
+    /// Shifts up near the redline and down when the revs drop, as long as
+    /// the lower gear doesn't land straight back at the upshift point.
+    fn auto_shift(&mut self, now: Instant) -> Result<(), HapticError> {
+        if self.is_still_shifting(now) {
+            return Ok(());
+        }
+        let gear = self.car.current_gear();
+        let revs = self.rev_fraction(self.car.engine().speed().rpm());
+        let target = if revs >= AUTO_UPSHIFT && gear < self.car.gear_count() {
+            gear + 1
+        } else if revs < AUTO_DOWNSHIFT
+            && gear > 1
+            && self.rev_fraction(self.car.engine_speed_in(gear - 1).rpm()) < AUTO_UPSHIFT
+        {
+            gear - 1
+        } else {
+            return Ok(());
+        };
+
+        let climb = self.rev_climb(target);
+        self.car.shift_into(target);
+        let is_downshift = target < gear;
+        if is_downshift {
+            say!("\n🔽 DOWNSHIFT → Gear {} (auto)", target);
+        } else {
+            say!("\n🔼 UPSHIFT → Gear {} (auto)", target);
+        }
+        self.shifting(is_downshift, now);
+        let torque = self.car.engine().torque();
+        self.shift_rumble(is_downshift, GearPosition::Gear(gear), torque, climb, now)
+    }
+
+    /// Whether the transmission is still busy with the last shift.
+    fn is_still_shifting(&self, now: Instant) -> bool {
+        self.shift_done_at.is_some_and(|done| now < done)
+    }
+
+    /// Keeps the transmission busy for as long as a shift takes it.
+    fn shifting(&mut self, is_downshift: bool, now: Instant) {
+        self.shift_done_at = self.car.transmission().map(|transmission| {
+            now + Duration::from_millis(transmission.shift_ms(is_downshift).into())
+        });
+    }
+
+    /// Makes shifts depend on a clutch pedal, released for now. With
+    /// `refuse_clutchless` a shift without it doesn't go in, otherwise it
+    /// goes in with a grind instead of the usual rumble.
+    pub fn use_clutch(&mut self, refuse_clutchless: bool) {
+        self.clutch_down = Some(false);
+        self.refuse_clutchless = refuse_clutchless;
+    }
+
+    /// Shifts without a clutch pedal again.
+    pub fn remove_clutch(&mut self) {
+        self.clutch_down = None;
+        self.refuse_clutchless = false;
+    }
+
+    /// Whether the clutch pedal is down far enough to shift, `None` without
+    /// a clutch pedal.
+    pub fn clutch_down(&self) -> Option<bool> {
+        self.clutch_down
+    }
+
+    pub fn set_clutch_down(&mut self, down: bool) {
+        if self.clutch_down.is_some() {
+            self.clutch_down = Some(down);
+        }
+    }
+
+    fn is_clutchless(&self) -> bool {
+        self.clutch_down == Some(false)
+    }
+
+    fn refuses_clutchless(&self) -> bool {
+        self.is_clutchless() && self.refuse_clutchless
+    }
+
+    /// The rumble for a gear that just went in `from` another, with the
+    /// revs `climb`ing that many rpm: a grind without the clutch, the usual
+    /// shift rumble otherwise.
+    fn engaged(
+        &mut self,
+        is_downshift: bool,
+        from: GearPosition,
+        climb: f32,
+        now: Instant,
+    ) -> Result<(), HapticError> {
+        if self.is_clutchless() {
+            return self.grind("in without the clutch", now);
+        }
+        let torque = self.car.engine().torque();
+        self.shift_rumble(is_downshift, from, torque, climb, now)?;
+        match self.car.turbo_mut() {
+            Some(turbo) if !is_downshift => match turbo.vent() {
+                Some(boost) => self.blow_off(boost, now),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// How many rpm the revs climb going into `gear` at this road speed,
+    /// negative going up.
+    fn rev_climb(&self, gear: u8) -> f32 {
+        self.car.engine_speed_in(gear).rpm() - self.car.engine().speed().rpm()
+    }
+
+    /// A bad engagement: `what` stays out and an H-pattern box is in neutral.
+    fn refuse(&mut self, what: &str, now: Instant) -> Result<(), HapticError> {
+        if self.selected.is_some() {
+            self.selected = Some(GearPosition::Neutral);
+        }
+        self.grind(&format!("{} won't go in", what), now)
+    }
+
+    /// Forces `gear` in past the redline. The engine loses torque for the
+    /// rest of the session, which every later shift rumble feels.
+    fn money_shift(&mut self, gear: u8, now: Instant) -> Result<(), HapticError> {
+        let from = self.position();
+        let rpm = self.car.engine_speed_in(gear).rpm();
+        let lost = self.car.money_shift(gear).unwrap_or(0.0);
+        say!("\n💸 MONEY SHIFT → Gear {} at {:.0} rpm", gear, rpm);
+        say!(
+            "   Engine damage: -{:.0}% torque ({:.0}% in total)",
+            lost * 100.0,
+            self.car.engine().damage() * 100.0
+        );
+        self.car.display_status(self.position());
+
+        let torque = self.car.engine().torque();
+        if !self.haptics.is_supported() {
+            say!("   ⚠️  Rumble not supported on this gamepad");
+            self.log_shift(ShiftKind::MoneyShift, from, torque, None, None);
+            return Ok(());
+        }
+        let pattern = shaped(MONEY_SHIFT_RUMBLE, self.envelopes.money_shift);
+        self.play_pattern(&pattern, now)?;
+        self.log_shift(ShiftKind::MoneyShift, from, torque, None, Some(&pattern));
+        self.last_shift_rumble = Some(pattern);
+        Ok(())
+    }
+
+    fn grind(&mut self, message: &str, now: Instant) -> Result<(), HapticError> {
+        say!("\n💢 GRIND — {}", message);
+        if !self.haptics.is_supported() {
+            say!("   ⚠️  Rumble not supported on this gamepad");
+            return Ok(());
+        }
+        self.play_pulses(GRIND_MAGNITUDE, &grind_pulses(), now)
+    }
+
+    pub fn is_rumbling(&self, now: Instant) -> bool {
+        self.rumble_until.is_some_and(|until| now < until)
+    }
+
+    /// Tells the driver a gamepad is (back) in use with two quick taps.
+    pub fn gamepad_connected(&mut self, now: Instant) -> Result<(), HapticError> {
+        if !self.haptics.is_supported() {
+            return Ok(());
+        }
+        self.play_pulses(QUERY_MAGNITUDE, &CONNECTED_PULSES, now)
+    }
+
+    /// Silences the motors and forgets anything waiting to play.
+    pub fn stop_rumble(&mut self) {
+        self.haptics.stop();
+        self.hum = None;
+        self.rumble_until = None;
+        self.gear_query_pending = false;
+        self.grade_pending = None;
+        self.bouncing = false;
+        self.launch_control = false;
+        self.revving = 0;
+        self.spooling = 0;
+        self.cranking_since = None;
+    }
+
+    fn shift(&mut self, is_downshift: bool, now: Instant) -> Result<(), HapticError> {
+        if self.selected.is_some() {
+            say!("\n⚠️  Use the H-pattern shifter to change gear");
+            return Ok(());
+        }
+        if self.out_of_gear.is_some() {
+            if is_downshift {
+                say!("\n⚠️  Out of gear: shift up into first");
+                return Ok(());
+            }
+            return self.jump_to(1, now);
+        }
+        let gear = self.car.current_gear();
+        if is_downshift && gear <= 1 {
+            say!("\n⚠️  Already in first gear!");
+            return Ok(());
+        }
+        if !is_downshift && gear >= self.car.gear_count() {
+            say!("\n⚠️  Already in highest gear!");
+            return Ok(());
+        }
+        let target = if is_downshift { gear - 1 } else { gear + 1 };
+        self.jump_to(target, now)
+    }
+
+    /// `Action::SelectGear`: straight into `target` in direct mode, only
+    /// into a neighbouring gear in sequential mode.
+    fn select_gear(&mut self, target: u8, now: Instant) -> Result<(), HapticError> {
+        if self.selected.is_some() {
+            say!("\n⚠️  Use the H-pattern shifter to change gear");
+            return Ok(());
+        }
+        if target < 1 || target > self.car.gear_count() {
+            say!("\n⚠️  This car has no gear {}", target);
+            return Ok(());
+        }
+        let from = self.position();
+        if from == GearPosition::Gear(target) {
+            say!("\n⚠️  Already in gear {}!", target);
+            return Ok(());
+        }
+        // Neutral and reverse sit just below first
+        let gear = match from {
+            GearPosition::Gear(gear) => gear,
+            _ => 0,
+        };
+        if self.shift_mode == ShiftMode::Sequential && target.abs_diff(gear) > 1 {
+            return self.grind(
+                &format!("no jumping from {} to {} on a sequential box", from, target),
+                now,
+            );
+        }
+        self.jump_to(target, now)
+    }
+
+    /// Shifts from the current gear into `target`, graded and rumbled.
+    fn jump_to(&mut self, target: u8, now: Instant) -> Result<(), HapticError> {
+        let from = self.position();
+        // Neutral and reverse sit just below first
+        let gear = match from {
+            GearPosition::Gear(gear) => gear,
+            _ => 0,
+        };
+        let is_downshift = target < gear;
+        if self.is_still_shifting(now) {
+            let transmission = self.car.transmission().map_or("", |t| t.name());
+            say!("\n⏳ The {} box is still shifting", transmission);
+            return Ok(());
+        }
+        if self.refuses_clutchless() {
+            return self.refuse("the shift without the clutch", now);
+        }
+        self.shifting(is_downshift, now);
+        self.out_of_gear = None;
+        // Graded on the revs as the button went down
+        let graded_rpm = if is_downshift {
+            self.car.engine_speed_in(target).rpm()
+        } else {
+            self.car.engine().speed().rpm()
+        };
+        if is_downshift && self.car.is_money_shift(target) {
+            self.money_shift(target, now)?;
+        } else {
+            let climb = if is_downshift {
+                self.rev_climb(target)
+            } else {
+                0.0
+            };
+            self.car.shift_into(target);
+            if is_downshift {
+                say!("\n🔽 DOWNSHIFT → Gear {}", target);
+            } else {
+                say!("\n🔼 UPSHIFT → Gear {}", target);
+            }
+            self.engaged(is_downshift, from, climb, now)?;
+        }
+        self.grade(is_downshift, graded_rpm, now)
+    }
+
+    /// Scores a shift made at `rpm` (where a downshift lands) when
+    /// training, and feels the grade once the shift rumble has played.
+    fn grade(&mut self, is_downshift: bool, rpm: f32, now: Instant) -> Result<(), HapticError> {
+        let Some(trainer) = &mut self.trainer else {
+            return Ok(());
+        };
+        let window = trainer.window();
+        let grade = if is_downshift {
+            window.grade_downshift(rpm)
+        } else {
+            window.grade_upshift(rpm)
+        };
+        trainer.record(grade);
+        say!(
+            "   🎯 {} at {:.0} rpm (target {:.0}–{:.0}) — {}",
+            grade.name(),
+            rpm,
+            window.low,
+            window.high,
+            trainer.score()
+        );
+        if !self.haptics.is_supported() {
+            return Ok(());
+        }
+        self.grade_pending = Some(grade);
+        self.poll_grade(now)
+    }
+
+    fn poll_grade(&mut self, now: Instant) -> Result<(), HapticError> {
+        if self.is_rumbling(now) {
+            return Ok(());
+        }
+        let Some(grade) = self.grade_pending.take() else {
+            return Ok(());
+        };
+        self.play_pulses(QUERY_MAGNITUDE, grade.pulses(), now)
+    }
+
+    /// Plays the rumble for a shift `from` a gear into the current one made
+    /// at `torque`. A downshift that had the revs `climb` is blipped first
+    /// if the car blips.
+    fn shift_rumble(
+        &mut self,
+        is_downshift: bool,
+        from: GearPosition,
+        torque: Torque,
+        climb: f32,
+        now: Instant,
+    ) -> Result<(), HapticError> {
+        let kind = if is_downshift {
+            ShiftKind::Downshift
+        } else {
+            ShiftKind::Upshift
+        };
+        if !self.muted {
+            self.haptics.click_triggers()?;
+        }
+        let gear = self.car.current_gear();
+        let mut intensity = self.car.rumble_intensity_at(torque, is_downshift);
+        say!(
+            "   Engine:     {:.0} rpm, {}",
+            self.car.engine().speed().rpm(),
+            self.car.units().format_torque(torque)
+        );
+        if let Some(throttle) = self.throttle {
+            say!("   Throttle:   {:.0}%", throttle * 100.0);
+            intensity *= THROTTLE_RUMBLE_FLOOR + (1.0 - THROTTLE_RUMBLE_FLOOR) * throttle;
+        }
+        say!("   Rumble Intensity: {:.1}%", intensity * 100.0);
+
+        if !self.haptics.is_supported() {
+            say!("   ⚠️  Rumble not supported on this gamepad");
+            self.log_shift(kind, from, torque, Some(intensity), None);
+            return Ok(());
+        }
+
+        if let Some(table) = self.car.motor_mix() {
+            let (mix, interpolated) = table.mix_for(gear);
+            say!(
+                "   Motor Mix:  gear {}{} → strong ×{:.2} / weak ×{:.2}",
+                gear,
+                if interpolated { " (interpolated)" } else { "" },
+                mix.strong,
+                mix.weak
+            );
+        }
+        if let Some(feel) = self.car.gear_feel().and_then(|table| table.feel_for(gear)) {
+            say!(
+                "   Gear Feel:  gear {} → intensity ×{:.2} / length ×{:.2}",
+                gear,
+                feel.intensity,
+                feel.duration
+            );
+        }
+
+        let duration = self.car.rumble_duration_ms(is_downshift);
+        let command = self.car.shift_rumble_command(intensity, duration);
+        let envelope = if is_downshift {
+            self.envelopes.downshift
+        } else {
+            self.envelopes.upshift
+        };
+        let mut pattern = match (envelope, self.car.transmission()) {
+            (Some(envelope), _) => RumblePattern::enveloped(command, &envelope),
+            (None, Some(transmission)) => transmission.shift_pattern(command),
+            (None, None) => RumblePattern::shift(command, is_downshift),
+        };
+        if is_downshift && climb > 0.0 && self.car.auto_blip() {
+            let blip = self.blip(climb);
+            say!(
+                "   Blip:       +{:.0} rpm ({:.0}%)",
+                climb,
+                blip.weak_magnitude as f32 / u16::MAX as f32 * 100.0
+            );
+            let gap = RumbleCommand {
+                strong_magnitude: 0,
+                weak_magnitude: 0,
+                duration_ms: BLIP_GAP_MS,
+            };
+            pattern = pattern.preceded_by(&[blip, gap]);
+        }
+        let (to, rpm) = (self.position(), self.car.engine().speed().rpm());
+        if let Some(hooks) = &mut self.hooks
+            && let Some(custom) = hooks.on_shift(from, to, rpm)
+        {
+            say!("   📜 Script rumble: {} ms", custom.duration_ms());
+            pattern = custom;
+        }
+        self.play_pattern(&pattern, now)?;
+        self.log_shift(kind, from, torque, Some(intensity), Some(&pattern));
+        self.last_shift_rumble = Some(pattern);
+        if self.muted {
+            say!("   🔇 Rumble muted");
+        } else {
+            say!("   💥 Rumble triggered!");
+        }
+        Ok(())
+    }
+
+    /// A throttle blip to bring the revs up `climb` rpm, stronger the more
+    /// of the rev range that is.
+    fn blip(&self, climb: f32) -> RumbleCommand {
+        let engine = self.car.engine();
+        let range = engine.redline().rpm() - engine.idle().rpm();
+        let level = BLIP_FLOOR + (1.0 - BLIP_FLOOR) * (climb / range).clamp(0.0, 1.0);
+        RumbleCommand {
+            strong_magnitude: 0,
+            weak_magnitude: (BLIP_MAGNITUDE as f32 * level) as u16,
+            duration_ms: BLIP_MS,
+        }
+    }
+
+    /// Writes a shift `from` a gear into the current one to the shift log,
+    /// with the `pattern` it played. A log that can't be written to is
+    /// dropped rather than failing the session.
+    fn log_shift(
+        &mut self,
+        kind: ShiftKind,
+        from: GearPosition,
+        torque: Torque,
+        intensity: Option<f32>,
+        pattern: Option<&RumblePattern>,
+    ) {
+        if self.shift_log.is_none() {
+            return;
+        }
+        let peak = pattern.map_or(
+            RumbleCommand {
+                strong_magnitude: 0,
+                weak_magnitude: 0,
+                duration_ms: 0,
+            },
+            RumblePattern::envelope,
+        );
+        let record = ShiftRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_millis() as u64),
+            kind,
+            from: gear_label(from),
+            to: gear_label(self.position()),
+            rpm: self.car.engine().speed().rpm(),
+            torque_lb_ft: torque.lb_ft(),
+            intensity,
+            strong: peak.strong_magnitude,
+            weak: peak.weak_magnitude,
+            duration_ms: peak.duration_ms,
+            stages: pattern.map_or_else(Vec::new, |pattern| pattern.stages().to_vec()),
+        };
+        if let Some(log) = &mut self.shift_log
+            && let Err(e) = log.record(&record)
+        {
+            say!("   ⚠️  Shift log: {}; no more shifts will be logged", e);
+            self.shift_log = None;
+        }
+    }
+
+    /// Anything that plays replaces the limiter bounce, the turbo spool and
+    /// the tick hook's rumble unless the backend mixes them.
+    fn replace_repeating(&mut self) {
+        if !self.haptics.mixes() {
+            self.hum = None;
+            self.bouncing = false;
+            self.spooling = 0;
+        }
+    }
+
+    fn play(&mut self, command: RumbleCommand, now: Instant) -> Result<(), HapticError> {
+        self.replace_repeating();
+        let command = self.intensity.command(command);
+        if !self.muted {
+            self.haptics.play(command)?;
+        }
+        self.rumble_until = Some(now + Duration::from_millis(command.duration_ms as u64));
+        Ok(())
+    }
+
+    fn play_pattern(&mut self, pattern: &RumblePattern, now: Instant) -> Result<(), HapticError> {
+        self.replace_repeating();
+        let pattern = self.intensity.pattern(pattern);
+        if !self.muted {
+            self.haptics.play_pattern(&pattern)?;
+        }
+        self.rumble_until = Some(now + Duration::from_millis(pattern.duration_ms() as u64));
+        Ok(())
+    }
+
+    fn play_pulses(
+        &mut self,
+        magnitude: u16,
+        pulses: &[Pulse],
+        now: Instant,
+    ) -> Result<(), HapticError> {
+        self.replace_repeating();
+        let (magnitude, pulses) = self.intensity.pulses(magnitude, pulses);
+        if !self.muted {
+            self.haptics.play_pulses(magnitude, &pulses)?;
+        }
+        let total_ms = pulses_length_ms(&pulses);
+        self.rumble_until = Some(now + Duration::from_millis(total_ms as u64));
+        Ok(())
+    }
+
+    /// Stops whichever repeating rumble is playing, the hook's included.
+    fn stop_repeating(&mut self) {
+        self.hum = None;
+        self.haptics.stop_repeating();
+    }
+
+    /// Unlike the others, a repeating rumble doesn't count as rumbling:
+    /// anything else may play over or instead of it.
+    fn play_repeating(
+        &mut self,
+        command: RumbleCommand,
+        period_ms: u32,
+    ) -> Result<(), HapticError> {
+        self.hum = None;
+        let (command, period_ms) = self.intensity.repeating(command, period_ms);
+        if self.muted {
+            return Ok(());
+        }
+        self.haptics.play_repeating(command, period_ms)
+    }
+
+    /// Asks for the gear query pulses. They never overlap a shift rumble:
+    /// if one is still playing the query waits for `poll`.
+    fn query_gear(&mut self, now: Instant) -> Result<(), HapticError> {
+        self.gear_query_pending = true;
+        if self.is_rumbling(now) {
+            say!("\n🔎 Gear query waiting for the shift rumble to finish...");
+            return Ok(());
+        }
+        self.poll_gear_query(now)
+    }
+
+    fn poll_gear_query(&mut self, now: Instant) -> Result<(), HapticError> {
+        if !self.gear_query_pending || self.is_rumbling(now) {
+            return Ok(());
+        }
+        self.gear_query_pending = false;
+
+        let position = self.position();
+        let pulses = gear_query_pulses(position);
+        let name = match position {
+            GearPosition::Gear(gear) => format!("gear {}", gear),
+            GearPosition::Neutral => "neutral".to_string(),
+            GearPosition::Reverse => "reverse".to_string(),
+        };
+        say!("\n🔎 GEAR QUERY → {} pulse(s) for {}", pulses.len(), name);
+
+        if !self.haptics.is_supported() {
+            say!("   ⚠️  Rumble not supported on this gamepad");
+            return Ok(());
+        }
+
+        self.play_pulses(QUERY_MAGNITUDE, &pulses, now)
+    }
+
+    fn replay_slowmo(&mut self, now: Instant) -> Result<(), HapticError> {
+        let Some(pattern) = self.last_shift_rumble.clone() else {
+            say!("\n⚠️  No shift to replay yet!");
+            return Ok(());
+        };
+        let replay = pattern.stretched(self.slowmo_factor);
+        let (original, stretched) = (pattern.envelope(), replay.envelope());
+
+        say!(
+            "\n🐢 SLOW-MO REPLAY ({}×) — not a shift",
+            self.slowmo_factor
+        );
+        say!(
+            "   Original: strong {} / weak {} for {} ms",
+            original.strong_magnitude,
+            original.weak_magnitude,
+            original.duration_ms
+        );
+        say!(
+            "   Replay:   strong {} / weak {} for {} ms",
+            stretched.strong_magnitude,
+            stretched.weak_magnitude,
+            stretched.duration_ms
+        );
+
+        if self.haptics.is_supported() {
+            self.play_pattern(&replay, now)?;
+        } else {
+            say!("   ⚠️  Rumble not supported on this gamepad");
+        }
+        Ok(())
+    }
+}
+
+/// `factor` if a replay stretched by it is slower than the shift was.
+pub fn check_slowmo_factor(factor: f32) -> Result<f32, String> {
+    if factor.is_finite() && factor > 1.0 {
+        Ok(factor)
+    } else {
+        Err(format!("slow-mo factor {} isn't above 1", factor))
+    }
+}
+
+/// `command` shaped by `envelope` if it has one.
+fn shaped(command: RumbleCommand, envelope: Option<Adsr>) -> RumblePattern {
+    match envelope {
+        Some(envelope) => RumblePattern::enveloped(command, &envelope),
+        None => RumblePattern::single(command),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::car::{DEFAULT_FINAL_DRIVE, DEFAULT_GEAR_RATIOS, MASS_KG};
+    use crate::drag::DragState;
+    use crate::haptics::Pulse;
+    use crate::intensity::HapticProfile;
+    use crate::playstation::Rgb;
+    use crate::shift_log::LogFormat;
+    use crate::shift_log::tests::Shared;
+    use crate::trainer::RpmWindow;
+    use crate::transmission::Transmission;
+    use crate::units::{AngularSpeed, Power, Torque};
+
+    /// Records what would have been sent to the motors.
+    pub(crate) struct MockHaptics {
+        pub supported: bool,
+        pub fail: bool,
+        pub played: Vec<RumbleCommand>, // Patterns by their envelope
+        pub patterns: Vec<RumblePattern>,
+        pub pulses: Vec<Vec<Pulse>>,
+        pub repeating: Vec<(RumbleCommand, u32)>,
+        pub stops: usize,
+        pub mixes: bool,
+        pub resistance: f32,
+        pub clicks: usize,
+        pub lightbar: Option<(Rgb, bool)>,
+    }
+
+    impl MockHaptics {
+        pub fn new() -> Self {
+            Self {
+                supported: true,
+                fail: false,
+                played: Vec::new(),
+                patterns: Vec::new(),
+                pulses: Vec::new(),
+                repeating: Vec::new(),
+                stops: 0,
+                mixes: false,
+                resistance: 0.0,
+                clicks: 0,
+                lightbar: None,
+            }
+        }
+    }
+
+    impl HapticController for MockHaptics {
+        fn is_supported(&self) -> bool {
+            self.supported
+        }
+
+        fn play(&mut self, command: RumbleCommand) -> Result<(), HapticError> {
+            if self.fail {
+                return Err(HapticError::Backend("mock failure".to_string()));
+            }
+            self.played.push(command);
+            Ok(())
+        }
+
+        fn play_pattern(&mut self, pattern: &RumblePattern) -> Result<(), HapticError> {
+            self.play(pattern.envelope())?;
+            self.patterns.push(pattern.clone());
+            Ok(())
+        }
+
+        fn play_pulses(&mut self, _magnitude: u16, pulses: &[Pulse]) -> Result<(), HapticError> {
+            if self.fail {
+                return Err(HapticError::Backend("mock failure".to_string()));
+            }
+            self.pulses.push(pulses.to_vec());
+            Ok(())
+        }
+
+        fn play_repeating(
+            &mut self,
+            command: RumbleCommand,
+            period_ms: u32,
+        ) -> Result<(), HapticError> {
+            if self.fail {
+                return Err(HapticError::Backend("mock failure".to_string()));
+            }
+            self.repeating.push((command, period_ms));
+            Ok(())
+        }
+
+        fn stop(&mut self) {
+            self.stops += 1;
+        }
+
+        fn mixes(&self) -> bool {
+            self.mixes
+        }
+
+        fn set_throttle_resistance(&mut self, stiffness: f32) -> Result<(), HapticError> {
+            self.resistance = stiffness;
+            Ok(())
+        }
+
+        fn click_triggers(&mut self) -> Result<(), HapticError> {
+            self.clicks += 1;
+            Ok(())
+        }
+
+        fn set_lightbar(&mut self, colour: Rgb, at_redline: bool) -> Result<(), HapticError> {
+            self.lightbar = Some((colour, at_redline));
+            Ok(())
+        }
+    }
+
+    fn session() -> Session<MockHaptics> {
+        let car = Car::new(
+            Torque::from_lb_ft(300.0),
+            Power::from_hp(400.0),
+            DEFAULT_GEAR_RATIOS.to_vec(),
+            DEFAULT_FINAL_DRIVE,
+        )
+        .unwrap();
+        Session::new(car, MockHaptics::new())
+    }
+
+    #[test]
+    fn shifts_change_gear_and_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        session.handle(Action::Downshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 3);
+
+        let played = &session.haptics().played;
+        assert_eq!(played.len(), 2);
+        assert_eq!(played[0].duration_ms, 150);
+        assert_eq!(played[1].duration_ms, 200 + BLIP_MS + BLIP_GAP_MS);
+        assert!(played[1].strong_magnitude > played[0].strong_magnitude);
+
+        // Up is one thunk, down has stages after the blip
+        let patterns = &session.haptics().patterns;
+        assert_eq!(patterns[0], RumblePattern::single(played[0]));
+        assert!(patterns[1].stages().len() > 3);
+    }
+
+    #[test]
+    fn downshifts_blip_harder_the_further_the_revs_climb() {
+        let mut session = session();
+        let now = Instant::now();
+        let blip_at = |session: &mut Session<MockHaptics>, rpm: f32| {
+            session.car_mut().set_gear(3);
+            let rpm = AngularSpeed::from_rpm(rpm);
+            session.car_mut().engine_mut().set_speed(rpm);
+            session.handle(Action::Downshift, now).unwrap();
+            let stages = session.haptics().patterns.last().unwrap().stages().to_vec();
+            (stages[0], stages[1])
+        };
+        let (gentle, gap) = blip_at(&mut session, 2000.0);
+        let (hard, _) = blip_at(&mut session, 4500.0);
+        assert_eq!(gentle.strong_magnitude, 0);
+        assert_eq!(gentle.duration_ms, BLIP_MS);
+        assert_eq!((gap.strong_magnitude, gap.weak_magnitude), (0, 0));
+        assert!(hard.weak_magnitude > gentle.weak_magnitude);
+        assert!(hard.weak_magnitude <= BLIP_MAGNITUDE);
+
+        // Cars without auto-blip go straight to the shift rumble
+        session.car_mut().set_auto_blip(false);
+        session.handle(Action::Upshift, now).unwrap();
+        session.handle(Action::Downshift, now).unwrap();
+        let played = session.haptics().played.last().unwrap();
+        assert_eq!(played.duration_ms, 200);
+    }
+
+    #[test]
+    fn transmissions_set_the_feel_and_pace_of_shifts() {
+        let mut session = session();
+        let now = Instant::now();
+        session
+            .car_mut()
+            .set_transmission(Some(Transmission::Manual));
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(
+            session.haptics().patterns[0],
+            Transmission::Manual.shift_pattern(session.haptics().played[0])
+        );
+
+        // The lever is still moving
+        session
+            .handle(Action::Upshift, now + Duration::from_millis(200))
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        session
+            .handle(Action::Upshift, now + Duration::from_millis(300))
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 5);
+
+        // A dual-clutch box is ready again almost at once
+        session.car_mut().set_transmission(Some(Transmission::Dct));
+        let later = now + Duration::from_secs(1);
+        session.handle(Action::Upshift, later).unwrap();
+        session
+            .handle(Action::Upshift, later + Duration::from_millis(80))
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 6);
+        assert_eq!(session.haptics().played.last().unwrap().duration_ms, 80);
+    }
+
+    fn session_with_intensity(intensity: Intensity) -> Session<MockHaptics> {
+        let mut session = session();
+        session.set_intensity(intensity);
+        session
+    }
+
+    #[test]
+    fn the_intensity_reaches_every_rumble() {
+        let gentle = Intensity::new(150, HapticProfile::Gentle).unwrap();
+        let mut session = session_with_intensity(gentle);
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        let played = session.haptics().played[0];
+        assert_eq!(played.duration_ms, 225);
+        assert!(played.strong_magnitude <= 26000);
+        // Drawn out, so a query waits for the longer rumble
+        session
+            .handle(Action::QueryGear, now + Duration::from_millis(200))
+            .unwrap();
+        assert!(session.haptics().pulses.is_empty());
+        session.poll(now + Duration::from_millis(230)).unwrap();
+        assert_eq!(session.haptics().pulses.len(), 1);
+
+        session
+            .handle(Action::SelectGear(6), now + Duration::from_secs(5))
+            .unwrap();
+        let grind = gentle.pulses(GRIND_MAGNITUDE, &grind_pulses()).1;
+        assert_eq!(session.haptics().pulses[1], grind);
+
+        let mut limiter = session_with_intensity(gentle);
+        limiter.set_pedals(Some(1.0), 0.0);
+        for _ in 0..200 {
+            limiter.drive(Duration::from_millis(100), now).unwrap();
+        }
+        assert_eq!(
+            limiter.haptics().repeating,
+            vec![gentle.repeating(LIMITER_PULSE, LIMITER_PERIOD_MS)]
+        );
+    }
+
+    #[test]
+    fn sequential_mode_grinds_on_a_jump_direct_mode_takes_it() {
+        let mut sequential = session();
+        let now = Instant::now();
+        sequential.handle(Action::SelectGear(5), now).unwrap();
+        assert_eq!(sequential.car().current_gear(), 3);
+        assert_eq!(sequential.haptics().pulses, [grind_pulses()]);
+        sequential.handle(Action::SelectGear(4), now).unwrap();
+        assert_eq!(sequential.car().current_gear(), 4);
+
+        // Through the gate as well
+        sequential.select(GearPosition::Neutral, now).unwrap();
+        sequential.select(GearPosition::Gear(2), now).unwrap();
+        assert_eq!(sequential.car().current_gear(), 4);
+        assert_eq!(sequential.selected(), Some(GearPosition::Neutral));
+        assert_eq!(sequential.haptics().pulses.len(), 2);
+
+        let mut direct = session();
+        direct.set_shift_mode(ShiftMode::Direct);
+        direct.handle(Action::SelectGear(6), now).unwrap();
+        assert_eq!(direct.car().current_gear(), 6);
+        direct.handle(Action::SelectGear(9), now).unwrap();
+        assert_eq!(direct.car().current_gear(), 6);
+        assert_eq!(direct.haptics().played.len(), 1);
+        assert!(direct.haptics().pulses.is_empty());
+    }
+
+    #[test]
+    fn envelopes_shape_the_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+        session.set_envelopes(Envelopes {
+            upshift: Some(Adsr {
+                attack_ms: 50,
+                decay_ms: 0,
+                sustain_level: 1.0,
+                release_ms: 50,
+            }),
+            ..Envelopes::default()
+        });
+        session.handle(Action::Upshift, now).unwrap();
+        let pattern = &session.haptics().patterns[0];
+        assert_eq!(pattern.stages().len(), 15);
+        assert_eq!(pattern.duration_ms(), 150);
+        let peak = pattern.envelope().strong_magnitude;
+        assert!(pattern.stages()[0].strong_magnitude < peak / 5);
+        assert_eq!(pattern.stages()[7].strong_magnitude, peak);
+        assert!(pattern.stages()[14].strong_magnitude < peak / 5);
+
+        // Downshifts still have their stages, after the blip
+        session.handle(Action::Downshift, now).unwrap();
+        assert_eq!(session.haptics().patterns[1].stages().len(), 2 + 4);
+    }
+
+    #[test]
+    fn reconnected_gamepad_gets_a_confirmation() {
+        let mut session = session();
+        let now = Instant::now();
+        session.gamepad_connected(now).unwrap();
+        assert_eq!(session.haptics().pulses, [CONNECTED_PULSES.to_vec()]);
+        assert!(session.is_rumbling(now + Duration::from_millis(150)));
+
+        session.haptics_mut().supported = false;
+        session.gamepad_connected(now).unwrap();
+        assert_eq!(session.haptics().pulses.len(), 1);
+    }
+
+    #[test]
+    fn unsupported_device_still_shifts() {
+        let mut session = session();
+        session.haptics_mut().supported = false;
+        session.handle(Action::Upshift, Instant::now()).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        assert!(session.haptics().played.is_empty());
+    }
+
+    #[test]
+    fn backend_errors_are_returned() {
+        let mut session = session();
+        session.haptics_mut().fail = true;
+        assert!(session.handle(Action::Upshift, Instant::now()).is_err());
+    }
+
+    #[test]
+    fn slowmo_replays_the_last_shift() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::ReplaySlowmo, now).unwrap();
+        assert!(session.haptics().played.is_empty());
+
+        session.handle(Action::Downshift, now).unwrap();
+        session.handle(Action::ReplaySlowmo, now).unwrap();
+        let patterns = &session.haptics().patterns;
+        assert_eq!(patterns[1], patterns[0].stretched(DEFAULT_SLOWMO_FACTOR));
+        // A replay is not a shift
+        assert_eq!(session.car().current_gear(), 2);
+
+        session.set_slowmo_factor(2.5);
+        session.handle(Action::ReplaySlowmo, now).unwrap();
+        let patterns = &session.haptics().patterns;
+        assert_eq!(patterns[2], patterns[0].stretched(2.5));
+        assert_eq!(check_slowmo_factor(2.5), Ok(2.5));
+        assert!(check_slowmo_factor(1.0).is_err());
+        assert!(check_slowmo_factor(f32::NAN).is_err());
+    }
+
+    #[test]
+    fn gear_query_waits_for_shift_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        assert!(session.is_rumbling(now));
+        session.handle(Action::QueryGear, now).unwrap();
+        assert!(session.haptics().pulses.is_empty());
+
+        session.poll(now + Duration::from_millis(100)).unwrap();
+        assert!(session.haptics().pulses.is_empty());
+        session.poll(now + Duration::from_millis(150)).unwrap();
+        assert_eq!(session.haptics().pulses.len(), 1);
+        assert_eq!(session.haptics().pulses[0].len(), 4);
+
+        // Only once
+        session.poll(now + Duration::from_secs(10)).unwrap();
+        assert_eq!(session.haptics().pulses.len(), 1);
+    }
+
+    #[test]
+    fn trainer_grades_shifts_after_their_rumble() {
+        let mut session = session();
+        session.set_trainer(Some(Trainer::new(RpmWindow {
+            low: 5000.0,
+            high: 6000.0,
+        })));
+        let now = Instant::now();
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(5500.0));
+        session.handle(Action::Upshift, now).unwrap();
+        assert!(session.haptics().pulses.is_empty());
+        session.poll(now + Duration::from_secs(1)).unwrap();
+        assert_eq!(session.haptics().pulses, [Grade::Perfect.pulses()]);
+
+        // Back down from the same revs lands far above the window
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(5500.0));
+        let later = now + Duration::from_secs(2);
+        session.handle(Action::Downshift, later).unwrap();
+        session.poll(later + Duration::from_secs(1)).unwrap();
+        assert_eq!(session.haptics().pulses[1], Grade::Early.pulses());
+        assert_eq!(
+            session.trainer().unwrap().score(),
+            "1/2 perfect (1 early, 0 late)"
+        );
+
+        // A shift that doesn't go in isn't graded
+        session.use_clutch(true);
+        session.handle(Action::Downshift, later).unwrap();
+        assert_eq!(session.trainer().unwrap().shifts(), 2);
+    }
+
+    fn frame(gear: GearPosition, rpm: f32) -> TelemetryFrame {
+        TelemetryFrame {
+            gear,
+            engine_speed: crate::units::AngularSpeed::from_rpm(rpm),
+            torque: None,
+            power: None,
+            shift_light: None,
+            traction_control: None,
+        }
+    }
+
+    #[test]
+    fn telemetry_gear_changes_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+
+        // Joining mid-race: the first frame only syncs the gear
+        session
+            .sync(&frame(GearPosition::Gear(1), 5000.0), now)
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 1);
+        assert!(session.haptics().played.is_empty());
+
+        session
+            .sync(&frame(GearPosition::Gear(1), 6500.0), now)
+            .unwrap();
+        assert!(session.haptics().played.is_empty());
+        assert!((session.car().engine().speed().rpm() - 6500.0).abs() < 0.1);
+
+        session
+            .sync(&frame(GearPosition::Gear(3), 4500.0), now)
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 3);
+        assert_eq!(session.haptics().played.len(), 1);
+        assert_eq!(session.haptics().played[0].duration_ms, 150);
+
+        session
+            .sync(&frame(GearPosition::Gear(2), 6000.0), now)
+            .unwrap();
+        assert_eq!(session.haptics().played.len(), 2);
+        assert_eq!(session.haptics().played[1].duration_ms, 200);
+    }
+
+    #[test]
+    fn telemetry_through_neutral() {
+        let mut session = session();
+        let now = Instant::now();
+        session
+            .sync(&frame(GearPosition::Gear(2), 3000.0), now)
+            .unwrap();
+        session
+            .sync(&frame(GearPosition::Neutral, 900.0), now)
+            .unwrap();
+        session
+            .sync(&frame(GearPosition::Gear(2), 3000.0), now)
+            .unwrap();
+        assert!(session.haptics().played.is_empty());
+
+        session
+            .sync(&frame(GearPosition::Reverse, 900.0), now)
+            .unwrap();
+        session
+            .sync(&frame(GearPosition::Gear(1), 3000.0), now)
+            .unwrap();
+        assert_eq!(session.haptics().played.len(), 1);
+    }
+
+    #[test]
+    fn telemetry_uses_reported_torque() {
+        let mut session = session();
+        let now = Instant::now();
+        session
+            .sync(&frame(GearPosition::Gear(3), 3000.0), now)
+            .unwrap();
+        let mut upshift = frame(GearPosition::Gear(4), 3000.0);
+        upshift.torque = Some(Torque::from_lb_ft(1000.0));
+        session.sync(&upshift, now).unwrap();
+        assert_eq!(
+            session.haptics().played[0].strong_magnitude,
+            (0.8f32 * 65535.0) as u16
+        );
+    }
+
+    #[test]
+    fn traction_control_buzzes_when_it_cuts_in() {
+        let mut session = session();
+        let now = Instant::now();
+        let mut tc = frame(GearPosition::Gear(3), 5000.0);
+        tc.traction_control = Some(true);
+        session.sync(&tc, now).unwrap();
+        session.sync(&tc, now + Duration::from_secs(1)).unwrap();
+        assert_eq!(session.haptics().played, vec![TRACTION_CONTROL_RUMBLE]);
+
+        // Not over a shift
+        let mut upshift = frame(GearPosition::Gear(4), 4000.0);
+        session
+            .sync(&upshift, now + Duration::from_secs(2))
+            .unwrap();
+        upshift.traction_control = Some(true);
+        session
+            .sync(&upshift, now + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(session.haptics().played.len(), 2);
+        assert_eq!(session.haptics().played[1].duration_ms, 150);
+    }
+
+    #[test]
+    fn telemetry_gear_outside_the_box_is_ignored() {
+        let mut session = session();
+        let now = Instant::now();
+        session
+            .sync(&frame(GearPosition::Gear(5), 3000.0), now)
+            .unwrap();
+        session
+            .sync(&frame(GearPosition::Gear(8), 3000.0), now)
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 5);
+        assert!(session.haptics().played.is_empty());
+    }
+
+    #[test]
+    fn h_pattern_engages_gears_through_neutral() {
+        let mut session = session();
+        let now = Instant::now();
+        session.select(GearPosition::Neutral, now).unwrap();
+        session.select(GearPosition::Gear(4), now).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        assert_eq!(session.selected(), Some(GearPosition::Gear(4)));
+        assert_eq!(session.haptics().played[0].duration_ms, 150);
+
+        session.select(GearPosition::Neutral, now).unwrap();
+        session.select(GearPosition::Gear(3), now).unwrap();
+        assert_eq!(session.car().current_gear(), 3);
+        assert_eq!(
+            session.haptics().played[1].duration_ms,
+            200 + BLIP_MS + BLIP_GAP_MS
+        );
+
+        // The sequential buttons don't fight the lever
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 3);
+        assert_eq!(session.haptics().played.len(), 2);
+    }
+
+    #[test]
+    fn shifts_are_logged_with_their_rumble() {
+        let written = Shared::default();
+        let mut session = session();
+        session.set_shift_log(Some(ShiftLog::new(
+            Box::new(written.clone()),
+            LogFormat::JsonLines,
+        )));
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        session.select(GearPosition::Neutral, now).unwrap();
+        session.select(GearPosition::Gear(3), now).unwrap();
+
+        let text = String::from_utf8(written.0.borrow().clone()).unwrap();
+        let records: Vec<ShiftRecord> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, ShiftKind::Upshift);
+        assert_eq!(
+            (records[0].from.as_str(), records[0].to.as_str()),
+            ("3", "4")
+        );
+        assert_eq!(
+            records[0].strong,
+            session.haptics().played[0].strong_magnitude
+        );
+        assert_eq!(records[0].duration_ms, 150);
+        assert!(records[0].intensity.is_some());
+        assert_eq!(records[1].kind, ShiftKind::Downshift);
+        assert_eq!(
+            (records[1].from.as_str(), records[1].to.as_str()),
+            ("N", "3")
+        );
+        assert_eq!(records[1].stages, session.haptics().patterns[1].stages());
+    }
+
+    #[test]
+    fn bad_engagements_grind_and_stay_out() {
+        let mut session = session();
+        let now = Instant::now();
+        // Still rolling at cruise speed
+        session
+            .select(GearPosition::Reverse, now + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(session.selected(), Some(GearPosition::Neutral));
+        assert_eq!(session.haptics().pulses, vec![grind_pulses()]);
+        assert!(session.haptics().played.is_empty());
+
+        // Nearly stopped in first, reverse goes in
+        session.car_mut().set_gear(1);
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(crate::units::AngularSpeed::from_rpm(800.0));
+        session
+            .select(GearPosition::Reverse, now + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(session.selected(), Some(GearPosition::Reverse));
+        let stages = session.haptics().patterns[0].stages();
+        assert_eq!(stages[0], REVERSE_RUMBLE);
+        assert_eq!(stages.len(), 1 + REVERSE_WARBLE.len());
+    }
+
+    #[test]
+    fn neutral_and_reverse_from_the_buttons() {
+        let mut session = session();
+        let now = Instant::now();
+        session.set_pedals(Some(0.0), 0.0);
+        session.handle(Action::Neutral, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Neutral);
+
+        // The engine revs freely, quicker the further the throttle opens
+        session.set_pedals(Some(0.3), 0.0);
+        session.drive(Duration::from_millis(10), now).unwrap();
+        session.set_pedals(Some(1.0), 0.0);
+        session.drive(Duration::from_millis(10), now).unwrap();
+        let repeating = &session.haptics().repeating;
+        assert_eq!(repeating.len(), 2);
+        assert!(repeating[1].1 < repeating[0].1);
+        assert!(repeating[1].0.weak_magnitude > repeating[0].0.weak_magnitude);
+        assert_eq!(session.car().current_gear(), 3);
+
+        // Still rolling: reverse grinds and the box stays in neutral
+        session.handle(Action::Reverse, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Neutral);
+        assert_eq!(session.haptics().pulses, [grind_pulses()]);
+
+        session.car_mut().set_gear(1);
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(800.0));
+        session.handle(Action::Reverse, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Reverse);
+        assert_eq!(session.haptics().patterns[0].stages()[0], REVERSE_RUMBLE);
+
+        // Back into first with an upshift
+        session.handle(Action::Downshift, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Reverse);
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Gear(1));
+    }
+
+    #[test]
+    fn first_from_neutral_or_reverse_is_an_upshift() {
+        let written = Shared::default();
+        let mut session = session();
+        session.set_shift_log(Some(ShiftLog::new(
+            Box::new(written.clone()),
+            LogFormat::JsonLines,
+        )));
+        let now = Instant::now();
+        session.set_pedals(Some(0.0), 1.0);
+        session.handle(Action::Neutral, now).unwrap();
+        session.drive(Duration::from_secs(10), now).unwrap();
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Gear(1));
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(800.0));
+        let later = now + Duration::from_secs(1);
+        session.handle(Action::Reverse, later).unwrap();
+        assert_eq!(session.position(), GearPosition::Reverse);
+        session
+            .handle(Action::Upshift, later + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(session.position(), GearPosition::Gear(1));
+
+        let text = String::from_utf8(written.0.borrow().clone()).unwrap();
+        let records: Vec<ShiftRecord> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let shifts: Vec<_> = records
+            .iter()
+            .map(|record| (record.kind, record.from.as_str(), record.to.as_str()))
+            .collect();
+        assert_eq!(
+            shifts,
+            [
+                (ShiftKind::Upshift, "N", "1"),
+                (ShiftKind::Reverse, "1", "R"),
+                (ShiftKind::Upshift, "R", "1"),
+            ]
+        );
+        // A single upshift thump, with no rev-matching blip ahead of it
+        assert_eq!(records[0].stages.len(), 1);
+        assert_eq!(records[2].stages.len(), 1);
+    }
+
+    #[test]
+    fn dropping_the_clutch_at_idle_stalls_until_the_starter_catches() {
+        let mut session = session();
+        let now = Instant::now();
+        session.use_clutch(false);
+        session.car_mut().set_gear(1);
+        let idle = session.car().engine().idle();
+        session.car_mut().engine_mut().set_speed(idle);
+        session.set_pedals(Some(0.0), 0.0);
+        session.set_clutch_down(true);
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert!(!session.stalled());
+
+        // Clutch up with no throttle: it shudders down into silence
+        session.set_clutch_down(false);
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert!(session.stalled());
+        let shudder = session.haptics().patterns[0].stages();
+        assert_eq!(shudder.len(), 2 * STALL_JUDDERS as usize);
+        assert!(shudder[2].strong_magnitude < shudder[0].strong_magnitude);
+
+        // Nothing drives while stalled
+        session.set_pedals(Some(1.0), 0.0);
+        let speed = session.car().road_speed();
+        session.drive(Duration::from_millis(100), now).unwrap();
+        assert!(session.car().road_speed() <= speed);
+
+        // The starter wants the clutch down in gear
+        session.crank_starter(now).unwrap();
+        assert!(session.haptics().repeating.is_empty());
+        session.set_clutch_down(true);
+        session.crank_starter(now).unwrap();
+        assert_eq!(
+            session.haptics().repeating,
+            [(CRANK_PULSE, CRANK_PERIOD_MS)]
+        );
+
+        // Let go too soon and it stays stalled; hold it and the engine catches
+        session.release_starter();
+        session.poll(now + Duration::from_millis(CRANK_MS)).unwrap();
+        assert!(session.stalled());
+        session.crank_starter(now).unwrap();
+        session.poll(now + Duration::from_millis(CRANK_MS)).unwrap();
+        assert!(!session.stalled());
+        assert_eq!(session.haptics().played.last(), Some(&CAUGHT_RUMBLE));
+    }
+
+    #[test]
+    fn turbo_spools_with_the_throttle_and_blows_off_over_the_upshift() {
+        let mut session = session();
+        let now = Instant::now();
+        session.haptics_mut().mixes = true;
+        session.car_mut().set_turbo(true);
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(5000.0));
+        session.set_pedals(Some(1.0), 0.0);
+        for _ in 0..15 {
+            session.drive(Duration::from_millis(100), now).unwrap();
+        }
+        // The weak motor rises with the boost, one step at a time
+        let repeating = &session.haptics().repeating;
+        assert!(repeating.len() >= 2);
+        assert!(
+            repeating
+                .windows(2)
+                .all(|w| w[0].0.weak_magnitude < w[1].0.weak_magnitude)
+        );
+        assert!(
+            repeating
+                .iter()
+                .all(|(spool, _)| spool.strong_magnitude == 0)
+        );
+        let boost = session.car().turbo().unwrap().boost();
+        assert!(boost > 0.5);
+
+        // Mixed over the shift rumble, the blow-off flutters and dies away
+        session.handle(Action::Upshift, now).unwrap();
+        let flutter = session.haptics().patterns.last().unwrap().stages();
+        assert_eq!(flutter.len(), FLUTTER_STAGES as usize);
+        assert!(flutter[0].weak_magnitude > flutter[0].strong_magnitude);
+        assert!(flutter[1].strong_magnitude > flutter[1].weak_magnitude);
+        assert!(flutter[7].strong_magnitude < flutter[1].strong_magnitude);
+        assert_eq!(session.car().turbo().unwrap().boost(), 0.0);
+    }
+
+    #[test]
+    fn money_shift_damages_the_engine() {
+        let mut session = session();
+        let now = Instant::now();
+        // Third at cruise speed takes first far past the redline
+        session.handle(Action::Downshift, now).unwrap();
+        session.handle(Action::Downshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 1);
+        assert_eq!(session.car().money_shifts(), 1);
+        assert!(session.car().engine().damage() > 0.0);
+        assert_eq!(session.haptics().played.last(), Some(&MONEY_SHIFT_RUMBLE));
+
+        // A healthy car rumbles harder on the same shift
+        let mut healthy = self::session();
+        let speed = healthy.car().engine().speed();
+        session.car_mut().set_gear(3);
+        session.car_mut().engine_mut().set_speed(speed);
+        healthy.handle(Action::Upshift, now).unwrap();
+        session.handle(Action::Upshift, now).unwrap();
+        assert!(session.car().engine().torque().nm() < healthy.car().engine().torque().nm());
+        let damaged = session.haptics().played.last().unwrap().strong_magnitude;
+        assert!(damaged < healthy.haptics().played[0].strong_magnitude);
+    }
+
+    #[test]
+    fn h_pattern_money_shift_stays_in() {
+        let mut session = session();
+        session.set_shift_mode(ShiftMode::Direct);
+        let now = Instant::now();
+        session.select(GearPosition::Gear(1), now).unwrap();
+        assert_eq!(session.car().current_gear(), 1);
+        assert_eq!(session.selected(), Some(GearPosition::Gear(1)));
+        assert_eq!(session.car().money_shifts(), 1);
+        assert_eq!(session.haptics().played, vec![MONEY_SHIFT_RUMBLE]);
+        assert!(session.haptics().pulses.is_empty());
+    }
+
+    #[test]
+    fn shifting_without_the_clutch_grinds() {
+        let mut session = session();
+        let now = Instant::now();
+        session.use_clutch(false);
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        assert!(session.haptics().played.is_empty());
+        assert_eq!(session.haptics().pulses, vec![grind_pulses()]);
+
+        session.set_clutch_down(true);
+        session
+            .handle(Action::Upshift, now + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(session.car().current_gear(), 5);
+        assert_eq!(session.haptics().played.len(), 1);
+        assert_eq!(session.haptics().pulses.len(), 1);
+    }
+
+    #[test]
+    fn clutchless_shifts_can_be_refused() {
+        let mut session = session();
+        let now = Instant::now();
+        session.use_clutch(true);
+        session.handle(Action::Downshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 3);
+        assert_eq!(session.haptics().pulses, vec![grind_pulses()]);
+
+        session.select(GearPosition::Gear(4), now).unwrap();
+        assert_eq!(session.selected(), Some(GearPosition::Neutral));
+        assert_eq!(session.car().current_gear(), 3);
+
+        session.set_clutch_down(true);
+        session.select(GearPosition::Gear(4), now).unwrap();
+        assert_eq!(session.selected(), Some(GearPosition::Gear(4)));
+        assert_eq!(session.haptics().played.len(), 1);
+    }
+
+    #[test]
+    fn no_clutch_pedal_means_clean_shifts() {
+        let mut session = session();
+        session.set_clutch_down(false);
+        assert_eq!(session.clutch_down(), None);
+        session.handle(Action::Upshift, Instant::now()).unwrap();
+        assert_eq!(session.haptics().played.len(), 1);
+        assert!(session.haptics().pulses.is_empty());
+    }
+
+    #[test]
+    fn throttle_scales_the_shift_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        session.set_pedals(Some(1.0), 0.0);
+        session.handle(Action::Downshift, now).unwrap();
+        session.set_pedals(Some(0.0), 0.0);
+        session.handle(Action::Upshift, now).unwrap();
+
+        let played = &session.haptics().played;
+        // Flat out is the same as having no throttle pedal, closed is the floor
+        let closed = played[0].strong_magnitude as f32 * THROTTLE_RUMBLE_FLOOR;
+        assert!((played[2].strong_magnitude as f32 - closed).abs() <= 1.0);
+        assert!(played[1].strong_magnitude > played[0].strong_magnitude);
+    }
+
+    #[test]
+    fn pedals_drive_the_engine() {
+        let mut session = session();
+        let cruise = session.car().engine().speed();
+        session
+            .drive(Duration::from_secs(1), Instant::now())
+            .unwrap();
+        assert_eq!(session.car().engine().speed(), cruise);
+
+        session.set_pedals(Some(1.0), 0.0);
+        session
+            .drive(Duration::from_secs(1), Instant::now())
+            .unwrap();
+        let revved = session.car().engine().speed().rpm();
+        assert!(revved > cruise.rpm());
+
+        // Clutch down: the throttle does nothing for the wheels
+        session.use_clutch(false);
+        session.set_clutch_down(true);
+        session
+            .drive(Duration::from_secs(1), Instant::now())
+            .unwrap();
+        assert!(session.car().engine().speed().rpm() < revved);
+    }
+
+    #[test]
+    fn drag_runs_celebrate_a_personal_best() {
+        let mut session = session();
+        session.set_drag(Some(DragRun::new(
+            Strip::ZeroToSixty,
+            MASS_KG,
+            Some(Duration::from_secs(30)),
+        )));
+        assert_eq!(session.car().current_gear(), 1);
+        session.set_pedals(Some(1.0), 0.0);
+        let now = Instant::now();
+        let step = Duration::from_millis(10);
+        for _ in 0..3000 {
+            if session.car().engine().speed().rpm() >= 6500.0 {
+                session.handle(Action::Upshift, now).unwrap();
+            }
+            session.drive(step, now).unwrap();
+            if matches!(session.drag().unwrap().state(), DragState::Finished(_)) {
+                break;
+            }
+        }
+        let DragState::Finished(time) = session.drag().unwrap().state() else {
+            panic!("never finished");
+        };
+        assert_eq!(session.drag().unwrap().best(), Some(time));
+        assert_eq!(session.haptics().patterns.last(), Some(&celebration()));
+
+        session.set_pedals(Some(0.0), 0.0);
+        session.drive(step, now).unwrap();
+        assert_eq!(session.drag().unwrap().state(), DragState::Staged);
+        assert_eq!(session.car().current_gear(), 1);
+    }
+
+    #[test]
+    fn launch_control_stutters_then_drops_the_clutch() {
+        let mut session = session();
+        let now = Instant::now();
+        session.engage_launch_control().unwrap();
+        assert!(!session.launch_control()); // Third gear
+        session.car_mut().set_gear(1);
+        session.set_pedals(Some(1.0), 0.0);
+        session.engage_launch_control().unwrap();
+        assert!(session.launch_control());
+        assert_eq!(
+            session.haptics().repeating,
+            [(LAUNCH_STUTTER, LAUNCH_STUTTER_PERIOD_MS)]
+        );
+
+        // Flat out, the revs stay put
+        for _ in 0..50 {
+            session.drive(Duration::from_millis(10), now).unwrap();
+        }
+        assert_eq!(session.car().engine().speed().rpm(), DEFAULT_LAUNCH_RPM);
+        assert_eq!(session.car().current_gear(), 1);
+
+        session.release_launch_control(now).unwrap();
+        assert!(!session.launch_control());
+        assert_eq!(session.haptics().played, [LAUNCH_RUMBLE]);
+        assert!(session.is_rumbling(now));
+        session.drive(Duration::from_millis(100), now).unwrap();
+        assert!(session.car().engine().speed().rpm() > DEFAULT_LAUNCH_RPM);
+    }
+
+    #[test]
+    fn drag_launches_when_launch_control_lets_go() {
+        let mut session = session();
+        session.set_drag(Some(DragRun::new(Strip::ZeroToSixty, MASS_KG, None)));
+        session.set_launch_rpm(4500.0);
+        session.set_pedals(Some(1.0), 0.0);
+        let now = Instant::now();
+        session.engage_launch_control().unwrap();
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert_eq!(session.drag().unwrap().state(), DragState::Staged);
+        assert_eq!(session.car().engine().speed().rpm(), 4500.0);
+
+        session.release_launch_control(now).unwrap();
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert_eq!(session.drag().unwrap().state(), DragState::Running);
+    }
+
+    #[test]
+    fn automatic_shifts_up_and_down_with_the_revs() {
+        let mut session = session();
+        let now = Instant::now();
+        session.set_pedals(Some(1.0), 0.0);
+        session.handle(Action::ToggleAutomatic, now).unwrap();
+        assert!(session.automatic());
+
+        for _ in 0..100 {
+            session.drive(Duration::from_millis(100), now).unwrap();
+        }
+        assert!(session.car().current_gear() > 3);
+        let upshifts = session.haptics().played.len();
+        assert!(upshifts > 0);
+        assert!(
+            session
+                .haptics()
+                .played
+                .iter()
+                .all(|c| c.duration_ms == 150)
+        );
+
+        session.set_pedals(Some(0.0), 1.0);
+        for _ in 0..100 {
+            session.drive(Duration::from_millis(100), now).unwrap();
+        }
+        assert_eq!(session.car().current_gear(), 1);
+        assert!(
+            session.haptics().played[upshifts..]
+                .iter()
+                .all(|c| c.duration_ms == 200 + BLIP_MS + BLIP_GAP_MS)
+        );
+    }
+
+    #[test]
+    fn manual_mode_never_shifts_by_itself() {
+        let mut session = session();
+        let now = Instant::now();
+        session.set_pedals(Some(1.0), 0.0);
+        session.set_automatic(true);
+        session.handle(Action::ToggleAutomatic, now).unwrap();
+        for _ in 0..100 {
+            session.drive(Duration::from_millis(100), now).unwrap();
+        }
+        assert_eq!(session.car().current_gear(), 3);
+        assert!(session.haptics().played.is_empty());
+
+        // Nor over an H-pattern shifter
+        session.select(GearPosition::Neutral, now).unwrap();
+        session.set_automatic(true);
+        assert!(!session.automatic());
+    }
+
+    #[test]
+    fn muted_shifts_are_silent_until_unmuted() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::ToggleMute, now).unwrap();
+        assert!(session.muted());
+        assert_eq!(session.haptics().stops, 1);
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        assert!(session.haptics().played.is_empty());
+        assert_eq!(session.haptics().clicks, 0);
+        // The limiter is silent too, and comes back with the rumble
+        let later = now + Duration::from_secs(1);
+        session.set_pedals(Some(1.0), 0.0);
+        for _ in 0..200 {
+            session.drive(Duration::from_millis(100), later).unwrap();
+        }
+        assert!(session.haptics().repeating.is_empty());
+
+        session.handle(Action::ToggleMute, later).unwrap();
+        assert!(!session.muted());
+        session.drive(Duration::from_millis(100), later).unwrap();
+        assert_eq!(
+            session.haptics().repeating,
+            vec![(LIMITER_PULSE, LIMITER_PERIOD_MS)]
+        );
+    }
+
+    #[test]
+    fn profiles_switch_the_car_and_rumble_live() {
+        let dir = std::env::temp_dir().join(format!("gear_changer_session_{}", std::process::id()));
+        let mut session = session();
+        session.set_profiles(Some(Profiles::in_dir(dir.clone())));
+        let now = Instant::now();
+        session.handle(Action::SaveProfile, now).unwrap();
+        assert_eq!(session.profiles().unwrap().active(), Some("default"));
+
+        let small = Car::new(
+            Torque::from_lb_ft(120.0),
+            Power::from_hp(110.0),
+            vec![3.5, 2.0, 1.3, 1.0],
+            DEFAULT_FINAL_DRIVE,
+        )
+        .unwrap();
+        session.set_car(small);
+        session.set_intensity(Intensity::new(50, HapticProfile::Standard).unwrap());
+        session.save_profile(Some("small"));
+
+        session.handle(Action::NextProfile, now).unwrap();
+        assert_eq!(session.profiles().unwrap().active(), Some("default"));
+        assert_eq!(session.car().gear_count(), DEFAULT_GEAR_RATIOS.len() as u8);
+        assert_eq!(session.intensity(), Intensity::default());
+
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.car().current_gear(), 4);
+        session.switch_profile("small").unwrap();
+        assert_eq!(session.car().gear_count(), 4);
+        assert_eq!(session.car().current_gear(), 4);
+        assert_eq!(session.intensity().percent(), 50);
+
+        // A profile that isn't there leaves everything as it was
+        session.switch_profile("missing").unwrap();
+        assert_eq!(session.profiles().unwrap().active(), Some("small"));
+        assert_eq!(session.car().gear_count(), 4);
+
+        // Saving again keeps the latency measured for the profile
+        let mut profiles = Profiles::in_dir(dir.clone());
+        let mut measured = profiles.load("small").unwrap();
+        measured.latency_ms = Some(23.5);
+        profiles.save("small", &measured).unwrap();
+        session.handle(Action::SaveProfile, now).unwrap();
+        assert_eq!(profiles.read("small").unwrap().latency_ms, Some(23.5));
+        // and a drag run takes it off the reaction
+        session.set_drag(Some(DragRun::new(Strip::QuarterMile, MASS_KG, None)));
+        session.switch_profile("small").unwrap();
+        let latency = session.drag().unwrap().latency();
+        assert!((latency.as_secs_f32() * 1000.0 - 23.5).abs() < 0.01);
+        session.switch_profile("default").unwrap();
+        assert!(session.drag().unwrap().latency().is_zero());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    struct Hooks;
+
+    const HUM: RumbleCommand = RumbleCommand {
+        strong_magnitude: 0,
+        weak_magnitude: 12000,
+        duration_ms: 40,
+    };
+
+    impl HapticHooks for Hooks {
+        fn on_shift(
+            &mut self,
+            from: GearPosition,
+            to: GearPosition,
+            _rpm: f32,
+        ) -> Option<RumblePattern> {
+            let upshift = matches!((from, to), (GearPosition::Gear(from), GearPosition::Gear(to)) if to > from);
+            upshift.then(|| RumblePattern::single(HUM))
+        }
+
+        fn on_tick(&mut self, state: &TickState) -> Option<RumbleCommand> {
+            (state.throttle > 0.5).then_some(HUM)
+        }
+    }
+
+    struct Hum;
+
+    impl HapticHooks for Hum {
+        fn on_shift(&mut self, _: GearPosition, _: GearPosition, _: f32) -> Option<RumblePattern> {
+            None
+        }
+
+        fn on_tick(&mut self, _: &TickState) -> Option<RumbleCommand> {
+            Some(HUM)
+        }
+    }
+
+    #[test]
+    fn the_limiter_comes_before_the_hum_and_hands_back_to_it() {
+        let mut session = session();
+        session.set_hooks(Some(Box::new(Hum)));
+        let now = Instant::now();
+        let step = Duration::from_millis(10);
+        session.set_pedals(Some(1.0), 0.0);
+        session.drive(step, now).unwrap();
+        assert_eq!(session.haptics().repeating, vec![(HUM, 40)]);
+
+        let redline = session.car().engine().redline();
+        session.car_mut().engine_mut().set_speed(redline);
+        session.drive(step, now).unwrap();
+        session.drive(step, now).unwrap();
+        assert!(session.is_bouncing());
+        assert_eq!(
+            session.haptics().repeating,
+            vec![(HUM, 40), (LIMITER_PULSE, LIMITER_PERIOD_MS)]
+        );
+
+        session.set_pedals(Some(0.0), 0.0);
+        session.drive(step, now).unwrap();
+        assert!(!session.is_bouncing());
+        assert_eq!(session.haptics().repeating.last(), Some(&(HUM, 40)));
+        session.drive(step, now).unwrap();
+        assert_eq!(session.haptics().repeating.len(), 3);
+    }
+
+    #[test]
+    fn hooks_shape_shifts_and_hum_between_them() {
+        let mut session = session();
+        session.set_hooks(Some(Box::new(Hooks)));
+        let now = Instant::now();
+        let step = Duration::from_millis(10);
+        session.set_pedals(Some(1.0), 0.0);
+        session.drive(step, now).unwrap();
+        session.drive(step, now).unwrap();
+        assert_eq!(session.haptics().repeating, vec![(HUM, 40)]);
+
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.haptics().patterns, vec![RumblePattern::single(HUM)]);
+        session.drive(step, now).unwrap();
+        assert_eq!(session.haptics().repeating.len(), 1);
+        session.drive(step, now + Duration::from_secs(1)).unwrap();
+        assert_eq!(session.haptics().repeating, vec![(HUM, 40); 2]);
+
+        session
+            .handle(Action::Downshift, now + Duration::from_secs(2))
+            .unwrap();
+        assert_ne!(session.haptics().patterns[1], RumblePattern::single(HUM));
+        session.set_pedals(Some(0.0), 0.0);
+        session.drive(step, now + Duration::from_secs(3)).unwrap();
+        assert_eq!(session.haptics().repeating.len(), 2);
+    }
+
+    #[test]
+    fn limiter_bounces_until_the_driver_lifts_or_shifts() {
+        let mut session = session();
+        let now = Instant::now();
+        let tick = Duration::from_millis(100);
+        session.set_pedals(Some(1.0), 0.0);
+        for _ in 0..200 {
+            session.drive(tick, now).unwrap();
+        }
+        assert!(session.is_bouncing());
+        assert_eq!(
+            session.haptics().repeating,
+            vec![(LIMITER_PULSE, LIMITER_PERIOD_MS)]
+        );
+
+        // Lifting stops it
+        session.set_pedals(Some(0.0), 0.0);
+        session.drive(tick, now).unwrap();
+        assert!(!session.is_bouncing());
+        assert_eq!(session.haptics().stops, 1);
+
+        // So does shifting up: the shift rumble replaces it
+        session.set_pedals(Some(1.0), 0.0);
+        session.drive(tick, now).unwrap();
+        assert!(session.is_bouncing());
+        session.handle(Action::Upshift, now).unwrap();
+        assert!(!session.is_bouncing());
+        session.drive(tick, now + Duration::from_secs(1)).unwrap();
+        assert!(!session.is_bouncing());
+        assert_eq!(session.haptics().repeating.len(), 2);
+    }
+
+    #[test]
+    fn mixed_limiter_plays_under_shifts() {
+        let mut session = session();
+        session.haptics_mut().mixes = true;
+        let now = Instant::now();
+        session.set_pedals(Some(1.0), 0.0);
+        session.handle(Action::Downshift, now).unwrap();
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(crate::units::AngularSpeed::from_rpm(7000.0));
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert!(session.is_bouncing());
+
+        session.handle(Action::Downshift, now).unwrap();
+        assert!(session.is_bouncing());
+        assert_eq!(session.haptics().repeating.len(), 1);
+        assert_eq!(session.haptics().stops, 0);
+    }
+
+    #[test]
+    fn throttle_trigger_stiffens_towards_the_redline() {
+        let mut session = session();
+        let now = Instant::now();
+        let rpm = crate::units::AngularSpeed::from_rpm;
+        session.car_mut().engine_mut().set_speed(rpm(3000.0));
+        session.poll(now).unwrap();
+        assert_eq!(session.haptics().resistance, 0.0);
+        session.car_mut().engine_mut().set_speed(rpm(7000.0));
+        session.poll(now).unwrap();
+        assert_eq!(session.haptics().resistance, 1.0);
+
+        session.handle(Action::Upshift, now).unwrap();
+        assert_eq!(session.haptics().clicks, 1);
+        session.poll(now).unwrap();
+        assert!(session.haptics().resistance < 1.0);
+    }
+
+    #[test]
+    fn lightbar_follows_the_gear_and_the_redline() {
+        let mut session = session();
+        let now = Instant::now();
+        session.poll(now).unwrap();
+        assert_eq!(
+            session.haptics().lightbar,
+            Some((gear_colour(GearPosition::Gear(3), 6), false))
+        );
+
+        session.select(GearPosition::Reverse, now).unwrap();
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(crate::units::AngularSpeed::from_rpm(7000.0));
+        session.poll(now).unwrap();
+        assert_eq!(
+            session.haptics().lightbar,
+            Some((gear_colour(GearPosition::Neutral, 6), true))
+        );
+    }
+
+    #[test]
+    fn limiter_waits_for_a_shift_rumble() {
+        let mut session = session();
+        let now = Instant::now();
+        session.set_pedals(Some(1.0), 0.0);
+        session.handle(Action::Downshift, now).unwrap();
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(crate::units::AngularSpeed::from_rpm(7000.0));
+        session.drive(Duration::from_millis(10), now).unwrap();
+        assert!(!session.is_bouncing());
+        session
+            .drive(Duration::from_millis(10), now + Duration::from_secs(1))
+            .unwrap();
+        assert!(session.is_bouncing());
+    }
+
+    #[test]
+    fn gear_query_feels_neutral_and_reverse() {
+        let mut session = session();
+        let now = Instant::now();
+        session.select(GearPosition::Neutral, now).unwrap();
+        session.handle(Action::QueryGear, now).unwrap();
+        assert_eq!(
+            session.haptics().pulses,
+            vec![gear_query_pulses(GearPosition::Neutral)]
+        );
+    }
+
+    #[test]
+    fn gear_query_feels_neutral_and_reverse_from_the_buttons() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::Neutral, now).unwrap();
+        session.handle(Action::QueryGear, now).unwrap();
+
+        session.car_mut().set_gear(1);
+        session
+            .car_mut()
+            .engine_mut()
+            .set_speed(AngularSpeed::from_rpm(800.0));
+        session.handle(Action::Reverse, now).unwrap();
+        assert_eq!(session.position(), GearPosition::Reverse);
+        let later = now + Duration::from_secs(5);
+        session.handle(Action::QueryGear, later).unwrap();
+        assert_eq!(
+            session.haptics().pulses,
+            vec![
+                gear_query_pulses(GearPosition::Neutral),
+                gear_query_pulses(GearPosition::Reverse),
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_rumble_clears_playing_and_pending_effects() {
+        let mut session = session();
+        let now = Instant::now();
+        session.handle(Action::Upshift, now).unwrap();
+        session.handle(Action::QueryGear, now).unwrap();
+        session.stop_rumble();
+        assert!(!session.is_rumbling(now));
+        assert_eq!(session.haptics().stops, 1);
+
+        session.poll(now + Duration::from_secs(1)).unwrap();
+        assert!(session.haptics().pulses.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+// `--shift-log`: every shift the session makes, one record per line, for
+// analysing shift timing and tuning the rumble curves offline. A `.csv` path
+// gets CSV with a header row; anything else gets JSON Lines, which also keeps
+// every stage of the rumble as it was sent, so `replay` can play it back.
+
+use crate::car::GearPosition;
+use crate::haptics::{RumbleCommand, RumblePattern};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+const CSV_HEADER: &str =
+    "timestamp_ms,kind,from,to,rpm,torque_lb_ft,intensity,strong,weak,duration_ms";
+
+/// What kind of shift a record is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShiftKind {
+    Upshift,
+    Downshift,
+    MoneyShift,
+    Reverse,
+}
+
+impl ShiftKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ShiftKind::Upshift => "upshift",
+            ShiftKind::Downshift => "downshift",
+            ShiftKind::MoneyShift => "money_shift",
+            ShiftKind::Reverse => "reverse",
+        }
+    }
+}
+
+/// One shift and the rumble it got.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShiftRecord {
+    /// Wall-clock time of the shift, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub kind: ShiftKind,
+    /// Gears as "1".."N", "N" for neutral and "R" for reverse.
+    pub from: String,
+    pub to: String,
+    pub rpm: f32,
+    pub torque_lb_ft: f32,
+    /// The intensity worked out from the torque, for the shifts that have
+    /// one; the fixed rumbles don't.
+    pub intensity: Option<f32>,
+    /// Peak magnitudes and total length of what went to the motors, all
+    /// zero if nothing could rumble.
+    pub strong: u16,
+    pub weak: u16,
+    pub duration_ms: u32,
+    /// Every stage as it was sent, back to back.
+    #[serde(default)]
+    pub stages: Vec<RumbleCommand>,
+}
+
+impl ShiftRecord {
+    /// The rumble as it was sent, `None` if nothing rumbled.
+    pub fn pattern(&self) -> Option<RumblePattern> {
+        (!self.stages.is_empty()).then(|| RumblePattern::new(self.stages.clone()))
+    }
+}
+
+/// How a gear reads in a record.
+pub fn gear_label(position: GearPosition) -> String {
+    position.to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Csv,
+    JsonLines,
+}
+
+/// Where shift records are written as they happen.
+pub struct ShiftLog {
+    out: Box<dyn Write>,
+    format: LogFormat,
+    header_written: bool,
+}
+
+impl ShiftLog {
+    pub fn new(out: Box<dyn Write>, format: LogFormat) -> Self {
+        Self {
+            out,
+            format,
+            header_written: false,
+        }
+    }
+
+    /// Creates (or truncates) `path`, CSV if it ends in `.csv` and JSON
+    /// Lines otherwise.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let is_csv = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let format = if is_csv {
+            LogFormat::Csv
+        } else {
+            LogFormat::JsonLines
+        };
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Self::new(Box::new(file), format))
+    }
+
+    /// Writes `record` out straight away, so a session that crashes keeps
+    /// every shift before it.
+    pub fn record(&mut self, record: &ShiftRecord) -> io::Result<()> {
+        match self.format {
+            LogFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.out, "{}", CSV_HEADER)?;
+                    self.header_written = true;
+                }
+                writeln!(self.out, "{}", csv_row(record))?;
+            }
+            LogFormat::JsonLines => {
+                serde_json::to_writer(&mut self.out, record)?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+/// Reads a JSON Lines shift log back, skipping blank lines.
+pub fn read_json_lines(text: &str) -> Result<Vec<ShiftRecord>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// The shifts that rumbled, each with how long after the first shift in
+/// the log it came.
+pub fn replay_schedule(records: &[ShiftRecord]) -> Vec<(Duration, &ShiftRecord)> {
+    let Some(first) = records.first() else {
+        return Vec::new();
+    };
+    records
+        .iter()
+        .filter(|record| !record.stages.is_empty())
+        .map(|record| {
+            let after_ms = record.timestamp_ms.saturating_sub(first.timestamp_ms);
+            (Duration::from_millis(after_ms), record)
+        })
+        .collect()
+}
+
+fn csv_row(record: &ShiftRecord) -> String {
+    format!(
+        "{},{},{},{},{:.0},{:.1},{},{},{},{}",
+        record.timestamp_ms,
+        record.kind.name(),
+        record.from,
+        record.to,
+        record.rpm,
+        record.torque_lb_ft,
+        record
+            .intensity
+            .map_or(String::new(), |intensity| format!("{:.3}", intensity)),
+        record.strong,
+        record.weak,
+        record.duration_ms
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A writer the test can still read after handing it to the log.
+    #[derive(Clone, Default)]
+    pub(crate) struct Shared(pub(crate) Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn record(kind: ShiftKind, intensity: Option<f32>) -> ShiftRecord {
+        ShiftRecord {
+            timestamp_ms: 1_700_000_000_000,
+            kind,
+            from: "3".to_string(),
+            to: "4".to_string(),
+            rpm: 2819.4,
+            torque_lb_ft: 265.04,
+            intensity,
+            strong: 12206,
+            weak: 8544,
+            duration_ms: 150,
+            stages: vec![RumbleCommand {
+                strong_magnitude: 12206,
+                weak_magnitude: 8544,
+                duration_ms: 150,
+            }],
+        }
+    }
+
+    #[test]
+    fn csv_has_a_header_then_a_row_per_shift() {
+        let written = Shared::default();
+        let mut log = ShiftLog::new(Box::new(written.clone()), LogFormat::Csv);
+        log.record(&record(ShiftKind::Upshift, Some(0.186)))
+            .unwrap();
+        log.record(&record(ShiftKind::Reverse, None)).unwrap();
+        let text = String::from_utf8(written.0.borrow().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                CSV_HEADER,
+                "1700000000000,upshift,3,4,2819,265.0,0.186,12206,8544,150",
+                "1700000000000,reverse,3,4,2819,265.0,,12206,8544,150",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let written = Shared::default();
+        let mut log = ShiftLog::new(Box::new(written.clone()), LogFormat::JsonLines);
+        let shifts = [
+            record(ShiftKind::MoneyShift, None),
+            record(ShiftKind::Downshift, Some(0.5)),
+        ];
+        for shift in &shifts {
+            log.record(shift).unwrap();
+        }
+        let text = String::from_utf8(written.0.borrow().clone()).unwrap();
+        let read: Vec<ShiftRecord> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(read, shifts);
+        assert!(text.contains(r#""kind":"money_shift""#));
+        assert_eq!(read_json_lines(&format!("{}\n", text)), Ok(read));
+        assert!(read_json_lines("{}").unwrap_err().starts_with("line 1:"));
+    }
+
+    #[test]
+    fn replay_keeps_the_timing_of_what_rumbled() {
+        let mut first = record(ShiftKind::Upshift, Some(0.2));
+        first.timestamp_ms = 1000;
+        first.stages.clear();
+        let mut second = record(ShiftKind::Upshift, Some(0.2));
+        second.timestamp_ms = 3500;
+        let mut third = record(ShiftKind::Downshift, Some(0.4));
+        third.timestamp_ms = 4250;
+        let records = [first, second, third];
+
+        let schedule = replay_schedule(&records);
+        let times: Vec<Duration> = schedule.iter().map(|(at, _)| *at).collect();
+        assert_eq!(
+            times,
+            [Duration::from_millis(2500), Duration::from_millis(3250)]
+        );
+        assert_eq!(schedule[1].1.kind, ShiftKind::Downshift);
+        assert_eq!(schedule[1].1.pattern().unwrap().stages(), records[2].stages);
+        assert!(records[0].pattern().is_none());
+        assert!(replay_schedule(&[]).is_empty());
+    }
+}
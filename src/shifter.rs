@@ -0,0 +1,171 @@
+// H-pattern shifters (Logitech Driving Force Shifter, Thrustmaster TH8A and
+// the like) report every gate as a button that stays down while the lever
+// is in it. `[h_pattern]` in gear_changer.toml says which button is which
+// gate, using the same names as `[bindings]`:
+//
+// [h_pattern]
+// 1 = "code:300"
+// 2 = "code:301"
+// reverse = "code:306"
+
+use crate::bindings::Input;
+use crate::car::GearPosition;
+use gilrs::{Button, EventType};
+use std::collections::BTreeMap;
+
+/// Gate buttons and which of them are held.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HPattern {
+    gates: Vec<(Input, GearPosition)>,
+    held: Vec<GearPosition>, // Latest last
+}
+
+impl HPattern {
+    /// Reads `[h_pattern]` entries (gear number or "reverse" → button) for
+    /// a box with `gear_count` gears.
+    pub fn from_config(entries: &BTreeMap<String, String>, gear_count: u8) -> Result<Self, String> {
+        if entries.is_empty() {
+            return Err("[h_pattern] has no gates".to_string());
+        }
+        let mut gates: Vec<(Input, GearPosition)> = Vec::new();
+        for (key, input) in entries {
+            let position = match key.as_str() {
+                "reverse" | "R" => GearPosition::Reverse,
+                gear => match gear.parse::<u8>() {
+                    Ok(n) if (1..=gear_count).contains(&n) => GearPosition::Gear(n),
+                    _ => {
+                        return Err(format!(
+                            "[h_pattern]: '{}' is not reverse or a gear in 1..={}",
+                            key, gear_count
+                        ));
+                    }
+                },
+            };
+            let input: Input = input
+                .parse()
+                .map_err(|e| format!("[h_pattern] {}: {}", key, e))?;
+            if !matches!(input, Input::Button(_) | Input::ButtonCode(_)) {
+                return Err(format!("[h_pattern] {}: gates are buttons, not axes", key));
+            }
+            if gates.iter().any(|&(i, _)| i == input) {
+                return Err(format!("[h_pattern]: {} is used for two gates", input));
+            }
+            gates.push((input, position));
+        }
+        Ok(Self {
+            gates,
+            held: Vec::new(),
+        })
+    }
+
+    pub fn gates(&self) -> &[(Input, GearPosition)] {
+        &self.gates
+    }
+
+    /// Where the lever is: the gate pushed last among the held ones, or
+    /// neutral when none is.
+    pub fn position(&self) -> GearPosition {
+        self.held.last().copied().unwrap_or(GearPosition::Neutral)
+    }
+
+    /// The new lever position if `event` moved it.
+    pub fn update(&mut self, event: &EventType) -> Option<GearPosition> {
+        match *event {
+            EventType::ButtonPressed(button, code) => self.moved(button, code.into_u32(), true),
+            EventType::ButtonReleased(button, code) => self.moved(button, code.into_u32(), false),
+            _ => None,
+        }
+    }
+
+    fn moved(&mut self, button: Button, code: u32, pressed: bool) -> Option<GearPosition> {
+        let before = self.position();
+        let &(_, gate) = self
+            .gates
+            .iter()
+            .find(|(input, _)| input.is_button(button, code))?;
+        self.held.retain(|&held| held != gate);
+        if pressed {
+            self.held.push(gate);
+        }
+        let after = self.position();
+        (after != before).then_some(after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(entries: &[(&str, &str)]) -> Result<HPattern, String> {
+        let entries = entries
+            .iter()
+            .map(|&(key, input)| (key.to_string(), input.to_string()))
+            .collect();
+        HPattern::from_config(&entries, 6)
+    }
+
+    fn h_pattern() -> HPattern {
+        config(&[
+            ("1", "code:300"),
+            ("2", "code:301"),
+            ("3", "code:302"),
+            ("reverse", "code:306"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn held_gate_is_the_gear() {
+        let mut shifter = h_pattern();
+        assert_eq!(shifter.position(), GearPosition::Neutral);
+        assert_eq!(
+            shifter.moved(Button::Unknown, 300, true),
+            Some(GearPosition::Gear(1))
+        );
+        assert_eq!(
+            shifter.moved(Button::Unknown, 300, false),
+            Some(GearPosition::Neutral)
+        );
+        assert_eq!(
+            shifter.moved(Button::Unknown, 306, true),
+            Some(GearPosition::Reverse)
+        );
+        // Not a gate
+        assert_eq!(shifter.moved(Button::Start, 7, true), None);
+        assert_eq!(shifter.position(), GearPosition::Reverse);
+    }
+
+    #[test]
+    fn overlapping_gates_follow_the_latest() {
+        let mut shifter = h_pattern();
+        shifter.moved(Button::Unknown, 301, true);
+        assert_eq!(
+            shifter.moved(Button::Unknown, 302, true),
+            Some(GearPosition::Gear(3))
+        );
+        // Letting go of the old gate late changes nothing
+        assert_eq!(shifter.moved(Button::Unknown, 301, false), None);
+        assert_eq!(
+            shifter.moved(Button::Unknown, 302, false),
+            Some(GearPosition::Neutral)
+        );
+    }
+
+    #[test]
+    fn config_validation() {
+        assert!(config(&[]).is_err());
+        assert!(config(&[("7", "code:300")]).is_err());
+        assert!(config(&[("0", "code:300")]).is_err());
+        assert!(config(&[("first", "code:300")]).is_err());
+        assert!(config(&[("1", "+LeftZ")]).is_err());
+        assert!(config(&[("1", "code:300"), ("2", "code:300")]).is_err());
+        let shifter = config(&[("1", "North"), ("R", "code:306")]).unwrap();
+        assert_eq!(
+            shifter.gates(),
+            [
+                (Input::Button(Button::North), GearPosition::Gear(1)),
+                (Input::ButtonCode(306), GearPosition::Reverse),
+            ]
+        );
+    }
+}
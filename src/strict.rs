@@ -0,0 +1,151 @@
+// `--strict`: haptic errors fail the session instead of being shrugged off.
+
+use crate::event_loop::TICK;
+use crate::say;
+use std::fs;
+use std::time::{Duration, Instant};
+
+// Where --strict writes its error report
+pub const STRICT_REPORT_PATH: &str = "gear_changer_strict_report.txt";
+
+/// How hard `--strict` treats haptic errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictMode {
+    Off,
+    /// Count every error and exit nonzero when the session ends.
+    Report,
+    /// Exit nonzero on the first error.
+    FailFast,
+}
+
+/// Errors collected during a session for `--strict` runs.
+pub struct SessionErrors {
+    mode: StrictMode,
+    started: Instant,
+    errors: Vec<String>,
+}
+
+impl SessionErrors {
+    pub fn new(mode: StrictMode) -> Self {
+        Self {
+            mode,
+            started: Instant::now(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> StrictMode {
+        self.mode
+    }
+
+    /// Records an error and returns true if the session must stop now.
+    pub fn record(&mut self, error: String) -> bool {
+        if self.mode == StrictMode::Off {
+            say!("   ⚠️  {}", error);
+            return false;
+        }
+
+        let entry = format!("[{:>9.3}s] {}", self.started.elapsed().as_secs_f32(), error);
+        say!("   ❌ STRICT: {}", entry);
+        self.errors.push(entry);
+        self.mode == StrictMode::FailFast
+    }
+
+    /// Checks one pass of the main loop against the fixed timestep.
+    pub fn check_tick(&mut self, elapsed: Duration) -> bool {
+        if elapsed > TICK {
+            self.record(format!(
+                "tick overran its {} ms budget by {:.1} ms",
+                TICK.as_millis(),
+                (elapsed - TICK).as_secs_f32() * 1000.0
+            ))
+        } else {
+            false
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.mode != StrictMode::Off && !self.errors.is_empty() {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "gear_changer strict report\nmode: {:?}\nsession length: {:.3}s\nerrors: {}\n",
+            self.mode,
+            self.started.elapsed().as_secs_f32(),
+            self.errors.len()
+        );
+        for error in &self.errors {
+            report.push_str(error);
+            report.push('\n');
+        }
+        report
+    }
+
+    /// Writes the report if anything went wrong and returns the session's
+    /// exit code.
+    pub fn finish(&self) -> i32 {
+        let code = self.exit_code();
+        if code != 0 {
+            match fs::write(STRICT_REPORT_PATH, self.report()) {
+                Ok(()) => println!(
+                    "\n❌ {} haptic error(s), report written to {}",
+                    self.errors.len(),
+                    STRICT_REPORT_PATH
+                ),
+                Err(e) => eprintln!("❌ Failed to write {}: {}", STRICT_REPORT_PATH, e),
+            }
+        }
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::haptics::HapticError;
+
+    #[test]
+    fn strict_report_mode_counts_and_exits_nonzero() {
+        let mut errors = SessionErrors::new(StrictMode::Report);
+        assert_eq!(errors.exit_code(), 0);
+        assert!(!errors.record(format!("rumble failed: {}", HapticError::NoDevice)));
+        assert!(!errors.record(format!(
+            "rumble failed: {}",
+            HapticError::Backend("send failed".to_string())
+        )));
+        assert_eq!(errors.errors.len(), 2);
+        assert_eq!(errors.exit_code(), 1);
+        assert!(errors.report().contains("errors: 2"));
+    }
+
+    #[test]
+    fn strict_fail_fast_stops_on_first_error() {
+        let mut errors = SessionErrors::new(StrictMode::FailFast);
+        assert!(errors.record("rumble failed".to_string()));
+        assert_eq!(errors.exit_code(), 1);
+    }
+
+    #[test]
+    fn non_strict_errors_do_not_fail_the_session() {
+        let mut errors = SessionErrors::new(StrictMode::Off);
+        assert!(!errors.record("rumble failed".to_string()));
+        assert_eq!(errors.exit_code(), 0);
+    }
+
+    #[test]
+    fn slow_tick_is_a_strict_error() {
+        let mut errors = SessionErrors::new(StrictMode::Report);
+        let tick_start = Instant::now();
+        std::thread::sleep(TICK * 2);
+        errors.check_tick(tick_start.elapsed());
+        assert!(!errors.check_tick(TICK / 2));
+        assert_eq!(errors.errors.len(), 1);
+        assert!(errors.errors[0].contains("overran"));
+        assert_eq!(errors.exit_code(), 1);
+    }
+}
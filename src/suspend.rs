@@ -0,0 +1,105 @@
+// Detects that the machine was suspended while the main loop was running.
+//
+// The monotonic clock doesn't advance during suspend on every platform, so
+// this watches the wall clock instead: a jump far larger than one loop
+// iteration means we were asleep.
+
+use std::time::{Duration, SystemTime};
+
+// Any gap between two loop iterations longer than this is a suspend
+pub const SUSPEND_THRESHOLD: Duration = Duration::from_secs(3);
+
+// Input from just before the wake-up is still trusted
+const RESUME_GRACE: Duration = Duration::from_millis(250);
+
+pub struct SuspendDetector {
+    threshold: Duration,
+    last_tick: Option<SystemTime>,
+    discard_before: Option<SystemTime>,
+}
+
+impl SuspendDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            last_tick: None,
+            discard_before: None,
+        }
+    }
+
+    /// Called once per loop iteration. Returns the length of the gap if the
+    /// machine was asleep since the previous call.
+    pub fn tick(&mut self, now: SystemTime) -> Option<Duration> {
+        let previous = self.last_tick.replace(now)?;
+
+        // A clock set backwards isn't a suspend; just start over from `now`
+        let gap = now.duration_since(previous).ok()?;
+        if gap <= self.threshold {
+            return None;
+        }
+
+        self.discard_before = Some(now.checked_sub(RESUME_GRACE).unwrap_or(now));
+        Some(gap)
+    }
+
+    /// Whether an input event timestamped `time` was queued up across the
+    /// suspend and should be ignored.
+    pub fn is_stale(&self, time: SystemTime) -> bool {
+        self.discard_before.is_some_and(|before| time < before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000) + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn regular_ticks_are_not_a_suspend() {
+        let mut detector = SuspendDetector::new(SUSPEND_THRESHOLD);
+        for ms in (0..5000).step_by(10) {
+            assert_eq!(detector.tick(at(ms)), None);
+        }
+        // A long but sub-threshold hiccup is still fine
+        assert_eq!(detector.tick(at(7000)), None);
+        assert!(!detector.is_stale(at(0)));
+    }
+
+    #[test]
+    fn wall_clock_jump_is_a_suspend() {
+        let mut detector = SuspendDetector::new(SUSPEND_THRESHOLD);
+        detector.tick(at(0));
+        detector.tick(at(10));
+        let hours = 2 * 60 * 60 * 1000;
+        assert_eq!(
+            detector.tick(at(10 + hours)),
+            Some(Duration::from_millis(hours))
+        );
+        // Normal ticks after the wake-up
+        assert_eq!(detector.tick(at(20 + hours)), None);
+    }
+
+    #[test]
+    fn events_from_before_the_wake_up_are_stale() {
+        let mut detector = SuspendDetector::new(SUSPEND_THRESHOLD);
+        detector.tick(at(0));
+        detector.tick(at(60_000));
+
+        assert!(detector.is_stale(at(5)));
+        assert!(detector.is_stale(at(59_000)));
+        assert!(!detector.is_stale(at(59_900)));
+        assert!(!detector.is_stale(at(60_010)));
+    }
+
+    #[test]
+    fn clock_set_backwards_is_ignored() {
+        let mut detector = SuspendDetector::new(SUSPEND_THRESHOLD);
+        detector.tick(at(60_000));
+        assert_eq!(detector.tick(at(0)), None);
+        assert_eq!(detector.tick(at(10)), None);
+        assert!(!detector.is_stale(at(0)));
+    }
+}
@@ -0,0 +1,97 @@
+// Live game state from racing sims, so the pad follows the game's gearbox
+// instead of button presses.
+
+pub mod assetto_corsa;
+pub mod dirt;
+pub mod f1;
+pub mod forza;
+pub mod iracing;
+pub mod json;
+pub mod outgauge;
+
+use crate::car::GearPosition;
+use crate::units::{AngularSpeed, Power, Torque};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+/// One snapshot of the player's car.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TelemetryFrame {
+    pub gear: GearPosition,
+    pub engine_speed: AngularSpeed,
+    /// Engine torque if the game reports it; otherwise the car's own torque
+    /// curve is read at `engine_speed`.
+    pub torque: Option<Torque>,
+    /// Engine power output, for games that report it.
+    pub power: Option<Power>,
+    /// How far the shift lights are lit, 0 to 1, for games that report it.
+    pub shift_light: Option<f32>,
+    /// Whether traction control is cutting in, for games that report it.
+    pub traction_control: Option<bool>,
+}
+
+/// A game the session can follow.
+pub trait TelemetrySource {
+    /// The game's name, for messages.
+    fn name(&self) -> &str;
+
+    /// Returns the newest car state since the last call, if any. Never
+    /// blocks: a game that isn't running (yet) is `Ok(None)`.
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>>;
+}
+
+// Big enough for any game's datagram
+const MAX_PACKET_LEN: usize = 2048;
+
+/// A non-blocking socket for games that send telemetry to us.
+pub(crate) fn listen(address: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(address)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Drains `socket` and returns the newest frame that `parse` understood.
+pub(crate) fn latest_frame(
+    socket: &UdpSocket,
+    parse: impl Fn(&[u8]) -> Option<TelemetryFrame>,
+) -> io::Result<Option<TelemetryFrame>> {
+    let mut latest = None;
+    let mut buf = [0; MAX_PACKET_LEN];
+    loop {
+        match socket.recv(&mut buf) {
+            Ok(len) => {
+                if let Some(frame) = parse(&buf[..len]) {
+                    latest = Some(frame);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(latest),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Little-endian field readers for the games' packed structs
+
+pub(crate) fn f32_at(buf: &[u8], offset: usize) -> Option<f32> {
+    Some(f32::from_le_bytes(
+        buf.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+pub(crate) fn u16_at(buf: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        buf.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+pub(crate) fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        buf.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+pub(crate) fn i32_at(buf: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(
+        buf.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
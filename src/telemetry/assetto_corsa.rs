@@ -0,0 +1,212 @@
+// Assetto Corsa's UDP remote telemetry.
+//
+// The client handshakes with the game on port 9996, then subscribes to
+// per-physics-step `RTCarInfo` updates. Gears are reported as 0 = reverse,
+// 1 = neutral, 2 = first. AC doesn't send torque.
+
+use super::{TelemetryFrame, TelemetrySource, f32_at, i32_at};
+use crate::car::GearPosition;
+use crate::units::AngularSpeed;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 9996;
+
+// Handshake operations
+const HANDSHAKE: i32 = 0;
+const SUBSCRIBE_UPDATE: i32 = 1;
+const DISMISS: i32 = 3;
+
+// The game answers any device type; 1 is the iPhone client
+const DEVICE_IDENTIFIER: i32 = 1;
+const PROTOCOL_VERSION: i32 = 1;
+
+const HANDSHAKE_RESPONSE_LEN: usize = 408;
+const CAR_INFO_LEN: usize = 328;
+const ENGINE_RPM_OFFSET: usize = 68;
+const GEAR_OFFSET: usize = 76;
+
+// How often to knock again while the game isn't answering
+const HANDSHAKE_RETRY: Duration = Duration::from_secs(1);
+
+fn handshake(operation: i32) -> [u8; 12] {
+    let mut packet = [0; 12];
+    packet[0..4].copy_from_slice(&DEVICE_IDENTIFIER.to_le_bytes());
+    packet[4..8].copy_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    packet[8..12].copy_from_slice(&operation.to_le_bytes());
+    packet
+}
+
+/// Decodes an `RTCarInfo` packet.
+pub fn parse_car_info(packet: &[u8]) -> Option<TelemetryFrame> {
+    if packet.len() != CAR_INFO_LEN || packet[0] != b'a' {
+        return None;
+    }
+    let rpm = f32_at(packet, ENGINE_RPM_OFFSET)?;
+    let gear = match i32_at(packet, GEAR_OFFSET)? {
+        0 => GearPosition::Reverse,
+        1 => GearPosition::Neutral,
+        n => GearPosition::Gear(u8::try_from(n - 1).ok()?),
+    };
+    Some(TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(rpm),
+        torque: None,
+        power: None,
+        shift_light: None,
+        traction_control: None,
+    })
+}
+
+pub struct AssettoCorsa {
+    socket: UdpSocket,
+    game: SocketAddr,
+    subscribed: bool,
+    last_handshake: Instant,
+}
+
+impl AssettoCorsa {
+    /// Starts the handshake with the game at `game`. The game doesn't have
+    /// to be running yet; the handshake is retried until it answers.
+    pub fn connect(game: SocketAddr) -> io::Result<Self> {
+        let local: SocketAddr = if game.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        socket.send_to(&handshake(HANDSHAKE), game)?;
+        Ok(Self {
+            socket,
+            game,
+            subscribed: false,
+            last_handshake: Instant::now(),
+        })
+    }
+}
+
+impl TelemetrySource for AssettoCorsa {
+    fn name(&self) -> &str {
+        "Assetto Corsa"
+    }
+
+    /// Drains everything the game sent since the last call and returns the
+    /// newest car state, if any.
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        let mut latest = None;
+        let mut buf = [0; 512];
+        loop {
+            let (len, from) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                // Windows reports the game not listening (yet) on the next read
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => break,
+                Err(e) => return Err(e),
+            };
+            if from.ip() != self.game.ip() {
+                continue;
+            }
+            match len {
+                HANDSHAKE_RESPONSE_LEN if !self.subscribed => {
+                    self.socket
+                        .send_to(&handshake(SUBSCRIBE_UPDATE), self.game)?;
+                    self.subscribed = true;
+                }
+                _ => {
+                    if let Some(frame) = parse_car_info(&buf[..len]) {
+                        latest = Some(frame);
+                    }
+                }
+            }
+        }
+
+        if !self.subscribed && self.last_handshake.elapsed() >= HANDSHAKE_RETRY {
+            self.socket.send_to(&handshake(HANDSHAKE), self.game)?;
+            self.last_handshake = Instant::now();
+        }
+        Ok(latest)
+    }
+}
+
+impl Drop for AssettoCorsa {
+    fn drop(&mut self) {
+        if self.subscribed {
+            let _ = self.socket.send_to(&handshake(DISMISS), self.game);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn car_info(rpm: f32, gear: i32) -> Vec<u8> {
+        let mut packet = vec![0; CAR_INFO_LEN];
+        packet[0] = b'a';
+        packet[4..8].copy_from_slice(&(CAR_INFO_LEN as i32).to_le_bytes());
+        packet[ENGINE_RPM_OFFSET..ENGINE_RPM_OFFSET + 4].copy_from_slice(&rpm.to_le_bytes());
+        packet[GEAR_OFFSET..GEAR_OFFSET + 4].copy_from_slice(&gear.to_le_bytes());
+        packet
+    }
+
+    #[test]
+    fn decodes_gear_and_rpm() {
+        let frame = parse_car_info(&car_info(6250.0, 4)).unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(3));
+        assert!((frame.engine_speed.rpm() - 6250.0).abs() < 0.1);
+        assert_eq!(frame.torque, None);
+
+        assert_eq!(
+            parse_car_info(&car_info(900.0, 1)).unwrap().gear,
+            GearPosition::Neutral
+        );
+        assert_eq!(
+            parse_car_info(&car_info(2000.0, 0)).unwrap().gear,
+            GearPosition::Reverse
+        );
+    }
+
+    #[test]
+    fn rejects_other_packets() {
+        assert_eq!(parse_car_info(&[0; HANDSHAKE_RESPONSE_LEN]), None);
+        assert_eq!(parse_car_info(&car_info(6250.0, 4)[..100]), None);
+        let mut wrong_id = car_info(6250.0, 4);
+        wrong_id[0] = b'b';
+        assert_eq!(parse_car_info(&wrong_id), None);
+        assert_eq!(parse_car_info(&car_info(6250.0, -3)), None);
+    }
+
+    #[test]
+    fn handshake_then_subscribe() {
+        let game = UdpSocket::bind("127.0.0.1:0").unwrap();
+        game.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut client = AssettoCorsa::connect(game.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0; 64];
+        let (len, client_addr) = game.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &handshake(HANDSHAKE));
+
+        game.send_to(&[0; HANDSHAKE_RESPONSE_LEN], client_addr)
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !client.subscribed && Instant::now() < deadline {
+            assert_eq!(client.poll().unwrap(), None);
+        }
+        let (len, _) = game.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &handshake(SUBSCRIBE_UPDATE));
+
+        game.send_to(&car_info(3000.0, 2), client_addr).unwrap();
+        game.send_to(&car_info(3100.0, 3), client_addr).unwrap();
+        let mut frame = None;
+        while frame.is_none() && Instant::now() < deadline {
+            frame = client.poll().unwrap();
+        }
+        assert!(frame.is_some());
+
+        drop(client);
+        let (len, _) = game.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &handshake(DISMISS));
+    }
+}
@@ -0,0 +1,110 @@
+// Codemasters' "extradata" UDP telemetry: DiRT Rally 2.0 with
+// `extradata="3"` in its hardware_settings_config.xml, and EA WRC with its
+// DiRT Rally 2.0 compatible output.
+//
+// Every packet is 66 little-endian floats. Gears are 0 = neutral, 1 = first,
+// and reverse is 10 in DiRT Rally 2.0 (-1 in older titles). The engine
+// speed is sent in tens of rpm. The layout has no handbrake or torque.
+
+use super::{TelemetryFrame, TelemetrySource, f32_at, latest_frame, listen};
+use crate::car::GearPosition;
+use crate::units::AngularSpeed;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+pub const DEFAULT_PORT: u16 = 20777;
+
+const FIELD_COUNT: usize = 66;
+const PACKET_LEN: usize = FIELD_COUNT * 4;
+
+// Field indices
+const GEAR_FIELD: usize = 33;
+const ENGINE_RATE_FIELD: usize = 37; // rpm / 10
+
+const REVERSE_GEARS: [f32; 2] = [10.0, -1.0];
+
+fn field(packet: &[u8], index: usize) -> Option<f32> {
+    f32_at(packet, index * 4)
+}
+
+/// Decodes an extradata packet.
+pub fn parse_extradata(packet: &[u8]) -> Option<TelemetryFrame> {
+    if packet.len() != PACKET_LEN {
+        return None;
+    }
+    let gear = field(packet, GEAR_FIELD)?;
+    let gear = if REVERSE_GEARS.contains(&gear) {
+        GearPosition::Reverse
+    } else if gear == 0.0 {
+        GearPosition::Neutral
+    } else if gear.fract() == 0.0 && (1.0..=f32::from(u8::MAX)).contains(&gear) {
+        GearPosition::Gear(gear as u8)
+    } else {
+        return None;
+    };
+    Some(TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(field(packet, ENGINE_RATE_FIELD)? * 10.0),
+        torque: None,
+        power: None,
+        shift_light: None,
+        traction_control: None,
+    })
+}
+
+pub struct DirtRally {
+    socket: UdpSocket,
+}
+
+impl DirtRally {
+    /// Listens on `address` for the game's packets.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: listen(address)?,
+        })
+    }
+}
+
+impl TelemetrySource for DirtRally {
+    fn name(&self) -> &str {
+        "DiRT Rally"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        latest_frame(&self.socket, parse_extradata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extradata(gear: f32, engine_rate: f32) -> Vec<u8> {
+        let mut fields = [0.0f32; FIELD_COUNT];
+        fields[GEAR_FIELD] = gear;
+        fields[ENGINE_RATE_FIELD] = engine_rate;
+        fields.iter().flat_map(|f| f.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_gear_and_rpm() {
+        let frame = parse_extradata(&extradata(3.0, 612.5)).unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(3));
+        assert!((frame.engine_speed.rpm() - 6125.0).abs() < 0.1);
+        assert_eq!(frame.torque, None);
+
+        let gear = |gear| parse_extradata(&extradata(gear, 100.0)).map(|frame| frame.gear);
+        assert_eq!(gear(0.0), Some(GearPosition::Neutral));
+        assert_eq!(gear(10.0), Some(GearPosition::Reverse));
+        assert_eq!(gear(-1.0), Some(GearPosition::Reverse));
+        assert_eq!(gear(2.5), None);
+        assert_eq!(gear(f32::NAN), None);
+    }
+
+    #[test]
+    fn rejects_other_layouts() {
+        // extradata="0" sends only the first 38 fields
+        assert_eq!(parse_extradata(&extradata(3.0, 612.5)[..38 * 4]), None);
+        assert_eq!(parse_extradata(&[]), None);
+    }
+}
@@ -0,0 +1,172 @@
+// Codemasters' F1 23 / F1 24 UDP telemetry.
+//
+// The game sends a stream of packets to port 20777; each starts with a
+// 29-byte header naming the packet type and which car is the player's. Only
+// car telemetry packets (id 6) are read: one 60-byte entry per car, with
+// gears as -1 = reverse, 0 = neutral, 1 = first. F1 doesn't send torque.
+
+use super::{TelemetryFrame, TelemetrySource, latest_frame, listen, u16_at};
+use crate::car::GearPosition;
+use crate::units::AngularSpeed;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+pub const DEFAULT_PORT: u16 = 20777;
+
+// Header
+const PACKET_FORMAT_OFFSET: usize = 0;
+const PACKET_ID_OFFSET: usize = 6;
+const PLAYER_CAR_OFFSET: usize = 27;
+const HEADER_LEN: usize = 29;
+const SUPPORTED_FORMATS: [u16; 2] = [2023, 2024];
+const CAR_TELEMETRY_ID: u8 = 6;
+
+// Car telemetry entries
+const CAR_COUNT: usize = 22;
+const CAR_ENTRY_LEN: usize = 60;
+const GEAR_OFFSET: usize = 15;
+const ENGINE_RPM_OFFSET: usize = 16;
+const REV_LIGHTS_OFFSET: usize = 19; // Percent
+
+// Header, the cars, then MFD panels and the suggested gear
+const CAR_TELEMETRY_LEN: usize = HEADER_LEN + CAR_COUNT * CAR_ENTRY_LEN + 3;
+
+/// Decodes the player's car from a car telemetry packet.
+pub fn parse_car_telemetry(packet: &[u8]) -> Option<TelemetryFrame> {
+    if packet.len() != CAR_TELEMETRY_LEN
+        || !SUPPORTED_FORMATS.contains(&u16_at(packet, PACKET_FORMAT_OFFSET)?)
+        || packet[PACKET_ID_OFFSET] != CAR_TELEMETRY_ID
+    {
+        return None;
+    }
+    let player = packet[PLAYER_CAR_OFFSET] as usize;
+    if player >= CAR_COUNT {
+        return None; // 255 while spectating
+    }
+    let car = &packet[HEADER_LEN + player * CAR_ENTRY_LEN..][..CAR_ENTRY_LEN];
+
+    let gear = match car[GEAR_OFFSET] as i8 {
+        -1 => GearPosition::Reverse,
+        0 => GearPosition::Neutral,
+        n => GearPosition::Gear(u8::try_from(n).ok()?),
+    };
+    Some(TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(u16_at(car, ENGINE_RPM_OFFSET)? as f32),
+        torque: None,
+        power: None,
+        shift_light: Some(car[REV_LIGHTS_OFFSET].min(100) as f32 / 100.0),
+        traction_control: None,
+    })
+}
+
+pub struct F1 {
+    socket: UdpSocket,
+}
+
+impl F1 {
+    /// Listens on `address` for the game's packets. Point the game's UDP
+    /// telemetry setting at this machine.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: listen(address)?,
+        })
+    }
+}
+
+impl TelemetrySource for F1 {
+    fn name(&self) -> &str {
+        "F1"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        latest_frame(&self.socket, parse_car_telemetry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn car_telemetry(format: u16, player: u8, gear: i8, rpm: u16) -> Vec<u8> {
+        let mut packet = vec![0; CAR_TELEMETRY_LEN];
+        packet[PACKET_FORMAT_OFFSET..PACKET_FORMAT_OFFSET + 2]
+            .copy_from_slice(&format.to_le_bytes());
+        packet[PACKET_ID_OFFSET] = CAR_TELEMETRY_ID;
+        packet[PLAYER_CAR_OFFSET] = player;
+        // Every other car sits in neutral at 4000 rpm
+        for car in packet[HEADER_LEN..HEADER_LEN + CAR_COUNT * CAR_ENTRY_LEN]
+            .chunks_exact_mut(CAR_ENTRY_LEN)
+        {
+            car[ENGINE_RPM_OFFSET..ENGINE_RPM_OFFSET + 2].copy_from_slice(&4000u16.to_le_bytes());
+        }
+        if let Some(car) = packet
+            .get_mut(HEADER_LEN + player as usize * CAR_ENTRY_LEN..)
+            .and_then(|rest| rest.get_mut(..CAR_ENTRY_LEN))
+        {
+            car[GEAR_OFFSET] = gear as u8;
+            car[ENGINE_RPM_OFFSET..ENGINE_RPM_OFFSET + 2].copy_from_slice(&rpm.to_le_bytes());
+            car[REV_LIGHTS_OFFSET] = 80;
+        }
+        packet
+    }
+
+    #[test]
+    fn decodes_the_players_car() {
+        let frame = parse_car_telemetry(&car_telemetry(2024, 5, 6, 11200)).unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(6));
+        assert!((frame.engine_speed.rpm() - 11200.0).abs() < 0.1);
+        assert_eq!(frame.torque, None);
+        assert_eq!(frame.shift_light, Some(0.8));
+
+        assert_eq!(
+            parse_car_telemetry(&car_telemetry(2023, 0, 0, 4000))
+                .unwrap()
+                .gear,
+            GearPosition::Neutral
+        );
+        assert_eq!(
+            parse_car_telemetry(&car_telemetry(2023, 21, -1, 4000))
+                .unwrap()
+                .gear,
+            GearPosition::Reverse
+        );
+    }
+
+    #[test]
+    fn ignores_other_packets() {
+        let mut motion = car_telemetry(2024, 0, 3, 9000);
+        motion[PACKET_ID_OFFSET] = 0;
+        assert_eq!(parse_car_telemetry(&motion), None);
+        assert_eq!(parse_car_telemetry(&car_telemetry(2022, 0, 3, 9000)), None);
+        assert_eq!(
+            parse_car_telemetry(&car_telemetry(2024, 255, 3, 9000)),
+            None
+        );
+        assert_eq!(
+            parse_car_telemetry(&car_telemetry(2024, 0, 3, 9000)[..HEADER_LEN]),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_the_newest_packet() {
+        let mut f1 = F1::listen(([127, 0, 0, 1], 0).into()).unwrap();
+        let game = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = f1.socket.local_addr().unwrap();
+        game.send_to(&car_telemetry(2024, 0, 2, 8000), address)
+            .unwrap();
+        game.send_to(&car_telemetry(2024, 0, 3, 9000), address)
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut frame = None;
+        while frame.map(|frame: TelemetryFrame| frame.gear) != Some(GearPosition::Gear(3))
+            && Instant::now() < deadline
+        {
+            frame = f1.poll().unwrap().or(frame);
+        }
+        assert_eq!(frame.unwrap().gear, GearPosition::Gear(3));
+    }
+}
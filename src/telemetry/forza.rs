@@ -0,0 +1,137 @@
+// Forza Motorsport / Forza Horizon "Data Out" UDP telemetry.
+//
+// The game sends one packet per frame to the address and port set in its
+// HUD options. Only the "Dash" format carries the gear: Motorsport 7 sends
+// 311 bytes, Horizon 4/5 insert 12 bytes after the "Sled" section (324),
+// and Motorsport (2023) appends tyre wear and the track (331). Gears are
+// 0 = reverse, 1 = first. Torque and power are live engine output.
+
+use super::{TelemetryFrame, TelemetrySource, f32_at, i32_at, latest_frame, listen};
+use crate::car::GearPosition;
+use crate::units::{AngularSpeed, Power, Torque};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+// Nothing is fixed by the game; this is the port most tools use
+pub const DEFAULT_PORT: u16 = 5300;
+
+const DASH_LEN: usize = 311;
+const HORIZON_LEN: usize = 324;
+const MOTORSPORT_2023_LEN: usize = 331;
+const HORIZON_PADDING: usize = 12;
+
+// Sled section, the first 232 bytes
+const IS_RACE_ON_OFFSET: usize = 0;
+const ENGINE_RPM_OFFSET: usize = 16;
+
+// Dash section, without Horizon's padding
+const POWER_OFFSET: usize = 248; // W
+const TORQUE_OFFSET: usize = 252; // Nm
+const GEAR_OFFSET: usize = 307;
+
+/// Decodes a "Dash" packet. `None` for "Sled" packets, which have no gear,
+/// and while the game is in its menus.
+pub fn parse_dash(packet: &[u8]) -> Option<TelemetryFrame> {
+    let padding = match packet.len() {
+        DASH_LEN | MOTORSPORT_2023_LEN => 0,
+        HORIZON_LEN => HORIZON_PADDING,
+        _ => return None,
+    };
+    if i32_at(packet, IS_RACE_ON_OFFSET)? == 0 {
+        return None;
+    }
+
+    let gear = match *packet.get(GEAR_OFFSET + padding)? {
+        0 => GearPosition::Reverse,
+        n => GearPosition::Gear(n),
+    };
+    // Engine braking shows up as negative torque; a shift is felt either way
+    let torque = f32_at(packet, TORQUE_OFFSET + padding)?.abs();
+    let power = f32_at(packet, POWER_OFFSET + padding)?.abs();
+    Some(TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(f32_at(packet, ENGINE_RPM_OFFSET)?),
+        torque: torque.is_finite().then(|| Torque::from_nm(torque)),
+        power: power.is_finite().then(|| Power::from_kw(power / 1000.0)),
+        shift_light: None,
+        traction_control: None,
+    })
+}
+
+pub struct Forza {
+    socket: UdpSocket,
+}
+
+impl Forza {
+    /// Listens on `address` for the game's packets. Set the game's Data Out
+    /// IP to this machine and its packet format to "Dash".
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: listen(address)?,
+        })
+    }
+}
+
+impl TelemetrySource for Forza {
+    fn name(&self) -> &str {
+        "Forza"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        latest_frame(&self.socket, parse_dash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dash(len: usize, gear: u8, rpm: f32, torque: f32, watts: f32) -> Vec<u8> {
+        let padding = if len == HORIZON_LEN {
+            HORIZON_PADDING
+        } else {
+            0
+        };
+        let mut packet = vec![0; len];
+        packet[IS_RACE_ON_OFFSET..IS_RACE_ON_OFFSET + 4].copy_from_slice(&1i32.to_le_bytes());
+        packet[ENGINE_RPM_OFFSET..ENGINE_RPM_OFFSET + 4].copy_from_slice(&rpm.to_le_bytes());
+        let at = POWER_OFFSET + padding;
+        packet[at..at + 4].copy_from_slice(&watts.to_le_bytes());
+        let at = TORQUE_OFFSET + padding;
+        packet[at..at + 4].copy_from_slice(&torque.to_le_bytes());
+        packet[GEAR_OFFSET + padding] = gear;
+        packet
+    }
+
+    #[test]
+    fn decodes_every_dash_layout() {
+        for len in [DASH_LEN, HORIZON_LEN, MOTORSPORT_2023_LEN] {
+            let frame = parse_dash(&dash(len, 4, 6100.0, 420.0, 250_000.0)).unwrap();
+            assert_eq!(frame.gear, GearPosition::Gear(4), "{} bytes", len);
+            assert!((frame.engine_speed.rpm() - 6100.0).abs() < 0.1);
+            assert!((frame.torque.unwrap().nm() - 420.0).abs() < 1e-3);
+            assert!((frame.power.unwrap().kw() - 250.0).abs() < 1e-3);
+        }
+        assert_eq!(
+            parse_dash(&dash(DASH_LEN, 0, 2000.0, 100.0, 0.0))
+                .unwrap()
+                .gear,
+            GearPosition::Reverse
+        );
+    }
+
+    #[test]
+    fn engine_braking_still_rumbles() {
+        let frame = parse_dash(&dash(HORIZON_LEN, 2, 5000.0, -80.0, -40_000.0)).unwrap();
+        assert!((frame.torque.unwrap().nm() - 80.0).abs() < 1e-3);
+        assert!((frame.power.unwrap().kw() - 40.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ignores_sled_packets_and_menus() {
+        assert_eq!(parse_dash(&[0; 232]), None);
+        let mut menu = dash(DASH_LEN, 3, 900.0, 0.0, 0.0);
+        menu[IS_RACE_ON_OFFSET..IS_RACE_ON_OFFSET + 4].copy_from_slice(&0i32.to_le_bytes());
+        assert_eq!(parse_dash(&menu), None);
+    }
+}
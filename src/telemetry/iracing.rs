@@ -0,0 +1,407 @@
+// iRacing's shared-memory telemetry (Windows only).
+//
+// The sim publishes a memory mapping with a header, a table describing every
+// variable it exports, and up to four rotating buffers of their values; the
+// newest buffer is the one with the highest tick count. Gears are -1 =
+// reverse, 0 = neutral, 1 = first. iRacing doesn't export engine torque.
+
+use super::{TelemetryFrame, TelemetrySource, f32_at, i32_at};
+use crate::car::GearPosition;
+use crate::units::AngularSpeed;
+use mapping::Mapping;
+use std::io;
+use std::time::{Duration, Instant};
+
+pub const MAP_NAME: &str = "Local\\IRSDKMemMapFileName";
+
+// Header
+const STATUS_OFFSET: usize = 4;
+const STATUS_CONNECTED: i32 = 1;
+const NUM_VARS_OFFSET: usize = 24;
+const VAR_TABLE_OFFSET: usize = 28;
+const NUM_BUFFERS_OFFSET: usize = 32;
+const BUFFERS_OFFSET: usize = 48;
+const BUFFER_ENTRY_LEN: usize = 16; // tick count, offset, 2 × padding
+const MAX_BUFFERS: usize = 4;
+
+// Variable table entries
+const VAR_ENTRY_LEN: usize = 144;
+const VAR_OFFSET_OFFSET: usize = 4;
+const VAR_NAME_OFFSET: usize = 16;
+const VAR_NAME_LEN: usize = 32;
+const VAR_TYPE_INT: i32 = 2;
+const VAR_TYPE_FLOAT: i32 = 4;
+
+// How often to look for the mapping while the sim isn't running
+const OPEN_RETRY: Duration = Duration::from_secs(1);
+
+/// Where the variables we read sit inside a value buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarLayout {
+    gear: usize,
+    rpm: usize,
+    shift_light: Option<usize>,
+}
+
+/// True while the sim is in a session.
+pub fn is_connected(mem: &[u8]) -> bool {
+    i32_at(mem, STATUS_OFFSET).is_some_and(|status| status & STATUS_CONNECTED != 0)
+}
+
+/// Looks up `Gear`, `RPM` and `ShiftIndicatorPct` in the variable table.
+/// `None` if the first two are missing or not of the expected type.
+pub fn find_vars(mem: &[u8]) -> Option<VarLayout> {
+    let count = usize::try_from(i32_at(mem, NUM_VARS_OFFSET)?).ok()?;
+    let table = usize::try_from(i32_at(mem, VAR_TABLE_OFFSET)?).ok()?;
+
+    let (mut gear, mut rpm, mut shift_light) = (None, None, None);
+    for entry in mem.get(table..)?.chunks_exact(VAR_ENTRY_LEN).take(count) {
+        let name = &entry[VAR_NAME_OFFSET..VAR_NAME_OFFSET + VAR_NAME_LEN];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(VAR_NAME_LEN)];
+        let offset = usize::try_from(i32_at(entry, VAR_OFFSET_OFFSET)?).ok()?;
+        match (name, i32_at(entry, 0)?) {
+            (b"Gear", VAR_TYPE_INT) => gear = Some(offset),
+            (b"RPM", VAR_TYPE_FLOAT) => rpm = Some(offset),
+            (b"ShiftIndicatorPct", VAR_TYPE_FLOAT) => shift_light = Some(offset),
+            _ => {}
+        }
+    }
+    Some(VarLayout {
+        gear: gear?,
+        rpm: rpm?,
+        shift_light,
+    })
+}
+
+/// Offset of the newest value buffer's entry in the header.
+fn newest_buffer(mem: &[u8]) -> Option<usize> {
+    let count = usize::try_from(i32_at(mem, NUM_BUFFERS_OFFSET)?)
+        .ok()?
+        .min(MAX_BUFFERS);
+    (0..count)
+        .map(|i| BUFFERS_OFFSET + i * BUFFER_ENTRY_LEN)
+        .max_by_key(|&entry| i32_at(mem, entry).unwrap_or(i32::MIN))
+}
+
+/// Decodes the newest value buffer. Returns it with its tick count and the
+/// header offset the tick count was read from.
+pub fn read_frame(mem: &[u8], vars: &VarLayout) -> Option<(usize, i32, TelemetryFrame)> {
+    let entry = newest_buffer(mem)?;
+    let tick = i32_at(mem, entry)?;
+    let values = mem.get(usize::try_from(i32_at(mem, entry + 4)?).ok()?..)?;
+
+    let gear = match i32_at(values, vars.gear)? {
+        -1 => GearPosition::Reverse,
+        0 => GearPosition::Neutral,
+        n => GearPosition::Gear(u8::try_from(n).ok()?),
+    };
+    let frame = TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(f32_at(values, vars.rpm)?),
+        torque: None,
+        power: None,
+        shift_light: vars.shift_light.and_then(|offset| f32_at(values, offset)),
+        traction_control: None,
+    };
+    Some((entry, tick, frame))
+}
+
+pub struct IRacing {
+    mapping: Option<Mapping>,
+    last_open: Instant,
+    vars: Option<VarLayout>, // Looked up again every time the sim connects
+    snapshot: Vec<u8>,
+    last_tick: Option<i32>,
+}
+
+impl IRacing {
+    /// Opens the sim's telemetry. iRacing doesn't have to be running yet;
+    /// the mapping is looked for again until it appears.
+    pub fn open() -> io::Result<Self> {
+        let mapping = match Mapping::open() {
+            Ok(mapping) => Some(mapping),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            mapping,
+            last_open: Instant::now(),
+            vars: None,
+            snapshot: Vec::new(),
+            last_tick: None,
+        })
+    }
+}
+
+impl TelemetrySource for IRacing {
+    fn name(&self) -> &str {
+        "iRacing"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        if self.mapping.is_none() {
+            if self.last_open.elapsed() < OPEN_RETRY {
+                return Ok(None);
+            }
+            self.last_open = Instant::now();
+            match Mapping::open() {
+                Ok(mapping) => self.mapping = Some(mapping),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+        let Some(mapping) = &self.mapping else {
+            return Ok(None);
+        };
+
+        // Work on a copy: the sim keeps writing while we read
+        mapping.copy_into(&mut self.snapshot);
+        if !is_connected(&self.snapshot) {
+            self.vars = None;
+            self.last_tick = None;
+            return Ok(None);
+        }
+        if self.vars.is_none() {
+            self.vars = find_vars(&self.snapshot);
+        }
+        let Some(vars) = self.vars else {
+            return Ok(None);
+        };
+        let Some((entry, tick, frame)) = read_frame(&self.snapshot, &vars) else {
+            return Ok(None);
+        };
+
+        // A buffer the sim moved on to during the copy may be torn
+        if mapping.i32_at(entry) != Some(tick) || self.last_tick == Some(tick) {
+            return Ok(None);
+        }
+        self.last_tick = Some(tick);
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(windows)]
+mod mapping {
+    use super::MAP_NAME;
+    use std::io;
+    use std::mem;
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::Memory::{
+        FILE_MAP_READ, MEMORY_BASIC_INFORMATION, MEMORY_MAPPED_VIEW_ADDRESS, MapViewOfFile,
+        OpenFileMappingW, UnmapViewOfFile, VirtualQuery,
+    };
+
+    /// A read-only view of the sim's shared memory.
+    pub struct Mapping {
+        handle: HANDLE,
+        view: MEMORY_MAPPED_VIEW_ADDRESS,
+        len: usize,
+    }
+
+    impl Mapping {
+        /// Fails with `NotFound` while the sim isn't running.
+        pub fn open() -> io::Result<Self> {
+            let name: Vec<u16> = MAP_NAME.encode_utf16().chain(Some(0)).collect();
+            // SAFETY: `name` is a NUL-terminated UTF-16 string
+            let handle = unsafe { OpenFileMappingW(FILE_MAP_READ, 0, name.as_ptr()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+
+            // SAFETY: `handle` is an open mapping; a length of 0 maps all of it
+            let view = unsafe { MapViewOfFile(handle, FILE_MAP_READ, 0, 0, 0) };
+            if view.Value.is_null() {
+                let error = io::Error::last_os_error();
+                // SAFETY: `handle` is open and not used again
+                unsafe { CloseHandle(handle) };
+                return Err(error);
+            }
+
+            // SAFETY: all-zero is a valid MEMORY_BASIC_INFORMATION, and
+            // VirtualQuery writes at most its size
+            let len = unsafe {
+                let mut info: MEMORY_BASIC_INFORMATION = mem::zeroed();
+                let written = VirtualQuery(
+                    view.Value,
+                    &mut info,
+                    mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                );
+                if written == 0 { 0 } else { info.RegionSize }
+            };
+            Ok(Self { handle, view, len })
+        }
+
+        pub fn copy_into(&self, buf: &mut Vec<u8>) {
+            buf.resize(self.len, 0);
+            // SAFETY: the view is `len` readable bytes while it's mapped
+            unsafe {
+                ptr::copy_nonoverlapping(self.view.Value as *const u8, buf.as_mut_ptr(), self.len)
+            };
+        }
+
+        /// Reads straight from the live mapping.
+        pub fn i32_at(&self, offset: usize) -> Option<i32> {
+            if !offset.is_multiple_of(4) || offset + 4 > self.len {
+                return None;
+            }
+            // SAFETY: in bounds and aligned (the view starts on a page)
+            Some(unsafe {
+                ptr::read_volatile((self.view.Value as *const u8).add(offset) as *const i32)
+            })
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            // SAFETY: both were opened in `open` and aren't used after this
+            unsafe {
+                UnmapViewOfFile(self.view);
+                CloseHandle(self.handle);
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod mapping {
+    use std::io;
+
+    /// iRacing only runs on Windows, so there is never a mapping to open.
+    pub enum Mapping {}
+
+    impl Mapping {
+        pub fn open() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "iRacing telemetry is only available on Windows",
+            ))
+        }
+
+        pub fn copy_into(&self, _buf: &mut Vec<u8>) {
+            match *self {}
+        }
+
+        pub fn i32_at(&self, _offset: usize) -> Option<i32> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES_OFFSET: usize = 1024;
+    const BUFFER_LEN: usize = 64;
+
+    fn put_i32(mem: &mut [u8], offset: usize, value: i32) {
+        mem[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_var(mem: &mut [u8], index: usize, name: &str, kind: i32, offset: i32) {
+        let entry = BUFFERS_OFFSET + MAX_BUFFERS * BUFFER_ENTRY_LEN + index * VAR_ENTRY_LEN;
+        put_i32(mem, entry, kind);
+        put_i32(mem, entry + VAR_OFFSET_OFFSET, offset);
+        mem[entry + VAR_NAME_OFFSET..entry + VAR_NAME_OFFSET + name.len()]
+            .copy_from_slice(name.as_bytes());
+    }
+
+    /// A connected sim with three value buffers; buffer `newest` holds
+    /// `gear` and `rpm` at the highest tick count.
+    fn shared_memory(newest: usize, gear: i32, rpm: f32) -> Vec<u8> {
+        let mut mem = vec![0; VALUES_OFFSET + MAX_BUFFERS * BUFFER_LEN];
+        put_i32(&mut mem, STATUS_OFFSET, STATUS_CONNECTED);
+        put_i32(&mut mem, NUM_VARS_OFFSET, 4);
+        put_i32(
+            &mut mem,
+            VAR_TABLE_OFFSET,
+            (BUFFERS_OFFSET + MAX_BUFFERS * BUFFER_ENTRY_LEN) as i32,
+        );
+        put_var(&mut mem, 0, "SessionTime", 5, 0);
+        put_var(&mut mem, 1, "Gear", VAR_TYPE_INT, 8);
+        put_var(&mut mem, 2, "RPM", VAR_TYPE_FLOAT, 12);
+        put_var(&mut mem, 3, "ShiftIndicatorPct", VAR_TYPE_FLOAT, 16);
+
+        put_i32(&mut mem, NUM_BUFFERS_OFFSET, 3);
+        for i in 0..3 {
+            let entry = BUFFERS_OFFSET + i * BUFFER_ENTRY_LEN;
+            let values = VALUES_OFFSET + i * BUFFER_LEN;
+            put_i32(
+                &mut mem,
+                entry,
+                if i == newest { 100 } else { 90 + i as i32 },
+            );
+            put_i32(&mut mem, entry + 4, values as i32);
+            // Stale buffers hold first gear at idle
+            let (gear, rpm) = if i == newest { (gear, rpm) } else { (1, 800.0) };
+            put_i32(&mut mem, values + 8, gear);
+            mem[values + 12..values + 16].copy_from_slice(&rpm.to_le_bytes());
+            mem[values + 16..values + 20].copy_from_slice(&0.75f32.to_le_bytes());
+        }
+        mem
+    }
+
+    #[test]
+    fn finds_the_variables() {
+        let vars = find_vars(&shared_memory(0, 3, 5000.0)).unwrap();
+        assert_eq!(
+            vars,
+            VarLayout {
+                gear: 8,
+                rpm: 12,
+                shift_light: Some(16)
+            }
+        );
+
+        // RPM exported as an int isn't the variable we know
+        let mut mem = shared_memory(0, 3, 5000.0);
+        put_var(&mut mem, 2, "RPM", VAR_TYPE_INT, 12);
+        assert_eq!(find_vars(&mem), None);
+    }
+
+    #[test]
+    fn reads_the_newest_buffer() {
+        let mem = shared_memory(2, 4, 6800.0);
+        let vars = find_vars(&mem).unwrap();
+        let (entry, tick, frame) = read_frame(&mem, &vars).unwrap();
+        assert_eq!(entry, BUFFERS_OFFSET + 2 * BUFFER_ENTRY_LEN);
+        assert_eq!(tick, 100);
+        assert_eq!(frame.gear, GearPosition::Gear(4));
+        assert!((frame.engine_speed.rpm() - 6800.0).abs() < 0.1);
+        assert_eq!(frame.torque, None);
+        assert_eq!(frame.shift_light, Some(0.75));
+    }
+
+    #[test]
+    fn decodes_neutral_and_reverse() {
+        let gear = |value| {
+            let mem = shared_memory(1, value, 1000.0);
+            read_frame(&mem, &find_vars(&mem).unwrap()).map(|(_, _, frame)| frame.gear)
+        };
+        assert_eq!(gear(0), Some(GearPosition::Neutral));
+        assert_eq!(gear(-1), Some(GearPosition::Reverse));
+        assert_eq!(gear(-2), None);
+    }
+
+    #[test]
+    fn disconnected_and_truncated_memory() {
+        let mut mem = shared_memory(0, 3, 5000.0);
+        assert!(is_connected(&mem));
+        put_i32(&mut mem, STATUS_OFFSET, 0);
+        assert!(!is_connected(&mem));
+        assert!(!is_connected(&[]));
+
+        let mem = shared_memory(2, 3, 5000.0);
+        let vars = find_vars(&mem).unwrap();
+        assert_eq!(read_frame(&mem[..VALUES_OFFSET + 100], &vars), None);
+        assert_eq!(find_vars(&mem[..200]), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn unsupported_off_windows() {
+        let error = IRacing::open().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}
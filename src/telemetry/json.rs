@@ -0,0 +1,211 @@
+// Newline-delimited JSON over UDP, for games without a dedicated parser:
+// a plugin or script sends lines like
+//
+//     {"gear":3,"rpm":5400,"torque":310}
+//
+// and `[json_telemetry]` in gear_changer.toml says which fields to read.
+// Field names may be dotted paths into nested objects ("engine.rpm").
+
+use super::{TelemetryFrame, TelemetrySource, latest_frame, listen};
+use crate::car::GearPosition;
+use crate::units::{AngularSpeed, Torque};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+pub const DEFAULT_PORT: u16 = 5555;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TorqueUnit {
+    #[default]
+    Nm,
+    LbFt,
+}
+
+/// Which JSON fields hold what.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JsonMapping {
+    pub port: u16,
+    pub gear: String,
+    pub rpm: String,
+    pub torque: Option<String>,
+    pub torque_unit: TorqueUnit,
+    /// Gear values that mean reverse and neutral. "R" and "N" always do.
+    pub reverse: i64,
+    pub neutral: i64,
+}
+
+impl Default for JsonMapping {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            gear: "gear".to_string(),
+            rpm: "rpm".to_string(),
+            torque: Some("torque".to_string()),
+            torque_unit: TorqueUnit::Nm,
+            reverse: -1,
+            neutral: 0,
+        }
+    }
+}
+
+fn field<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+impl JsonMapping {
+    fn gear(&self, value: &Value) -> Option<GearPosition> {
+        let number = value.as_i64().or_else(|| {
+            value
+                .as_f64()
+                .filter(|f| f.fract() == 0.0)
+                .map(|f| f as i64)
+        });
+        if let Some(n) = number {
+            return Some(if n == self.reverse {
+                GearPosition::Reverse
+            } else if n == self.neutral {
+                GearPosition::Neutral
+            } else {
+                GearPosition::Gear(u8::try_from(n).ok().filter(|&n| n > 0)?)
+            });
+        }
+        match value.as_str()? {
+            "R" | "r" => Some(GearPosition::Reverse),
+            "N" | "n" => Some(GearPosition::Neutral),
+            digits => self.gear(&Value::from(digits.parse::<i64>().ok()?)),
+        }
+    }
+
+    /// Decodes one JSON object. `None` if the gear or rpm is missing.
+    pub fn parse_line(&self, line: &str) -> Option<TelemetryFrame> {
+        let packet: Value = serde_json::from_str(line).ok()?;
+        let gear = self.gear(field(&packet, &self.gear)?)?;
+        let rpm = field(&packet, &self.rpm)?.as_f64()? as f32;
+        let torque = self
+            .torque
+            .as_deref()
+            .and_then(|path| field(&packet, path)?.as_f64())
+            .map(|torque| match self.torque_unit {
+                TorqueUnit::Nm => Torque::from_nm(torque as f32),
+                TorqueUnit::LbFt => Torque::from_lb_ft(torque as f32),
+            });
+        Some(TelemetryFrame {
+            gear,
+            engine_speed: AngularSpeed::from_rpm(rpm),
+            torque,
+            power: None,
+            shift_light: None,
+            traction_control: None,
+        })
+    }
+
+    /// Decodes a datagram of one or more lines, newest last.
+    pub fn parse_packet(&self, packet: &[u8]) -> Option<TelemetryFrame> {
+        std::str::from_utf8(packet)
+            .ok()?
+            .lines()
+            .rev()
+            .find_map(|line| self.parse_line(line))
+    }
+}
+
+pub struct JsonTelemetry {
+    socket: UdpSocket,
+    mapping: JsonMapping,
+}
+
+impl JsonTelemetry {
+    pub fn listen(address: SocketAddr, mapping: JsonMapping) -> io::Result<Self> {
+        Ok(Self {
+            socket: listen(address)?,
+            mapping,
+        })
+    }
+}
+
+impl TelemetrySource for JsonTelemetry {
+    fn name(&self) -> &str {
+        "JSON"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        latest_frame(&self.socket, |packet| self.mapping.parse_packet(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_mapping_reads_the_documented_packet() {
+        let frame = JsonMapping::default()
+            .parse_line(r#"{"gear":3,"rpm":5400,"torque":310}"#)
+            .unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(3));
+        assert!((frame.engine_speed.rpm() - 5400.0).abs() < 0.1);
+        assert!((frame.torque.unwrap().nm() - 310.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn gear_values() {
+        let mapping = JsonMapping::default();
+        let gear = |json: &str| {
+            mapping
+                .parse_line(&format!(r#"{{"gear":{},"rpm":1000}}"#, json))
+                .map(|frame| frame.gear)
+        };
+        assert_eq!(gear("-1"), Some(GearPosition::Reverse));
+        assert_eq!(gear("0"), Some(GearPosition::Neutral));
+        assert_eq!(gear(r#""R""#), Some(GearPosition::Reverse));
+        assert_eq!(gear(r#""N""#), Some(GearPosition::Neutral));
+        assert_eq!(gear(r#""4""#), Some(GearPosition::Gear(4)));
+        assert_eq!(gear("5.0"), Some(GearPosition::Gear(5)));
+        assert_eq!(gear("-3"), None);
+        assert_eq!(gear("2.5"), None);
+        assert_eq!(gear("null"), None);
+    }
+
+    #[test]
+    fn custom_mapping_with_nested_fields() {
+        let mapping: JsonMapping = toml::from_str(
+            r#"
+            gear = "drivetrain.gear"
+            rpm = "engine.rpm"
+            torque = "engine.torque"
+            torque_unit = "lb-ft"
+            reverse = 0
+            neutral = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(mapping.port, DEFAULT_PORT);
+
+        let frame = mapping
+            .parse_line(r#"{"engine":{"rpm":6100,"torque":300},"drivetrain":{"gear":0}}"#)
+            .unwrap();
+        assert_eq!(frame.gear, GearPosition::Reverse);
+        assert!((frame.torque.unwrap().lb_ft() - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn torque_is_optional() {
+        let frame = JsonMapping::default()
+            .parse_line(r#"{"gear":2,"rpm":3000}"#)
+            .unwrap();
+        assert_eq!(frame.torque, None);
+        assert_eq!(JsonMapping::default().parse_line(r#"{"gear":2}"#), None);
+        assert_eq!(JsonMapping::default().parse_line("not json"), None);
+    }
+
+    #[test]
+    fn newest_line_in_a_packet_wins() {
+        let packet = b"{\"gear\":2,\"rpm\":3000}\n{\"gear\":3,\"rpm\":4000}\ngarbage\n";
+        let frame = JsonMapping::default().parse_packet(packet).unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(3));
+    }
+}
@@ -0,0 +1,121 @@
+// The OutGauge UDP protocol from Live for Speed, as sent by BeamNG.drive
+// (Options → Other → OutGauge support).
+//
+// One 92-byte packet per update, 96 when the sender adds its ID. Gears are
+// 0 = reverse, 1 = neutral, 2 = first. Dashboard lights come as two bit
+// sets: the ones the car has and the ones that are on.
+
+use super::{TelemetryFrame, TelemetrySource, f32_at, latest_frame, listen, u32_at};
+use crate::car::GearPosition;
+use crate::units::AngularSpeed;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+pub const DEFAULT_PORT: u16 = 4444;
+
+const PACKET_LEN: usize = 92;
+const PACKET_WITH_ID_LEN: usize = 96;
+const GEAR_OFFSET: usize = 10;
+const RPM_OFFSET: usize = 16;
+const DASH_LIGHTS_OFFSET: usize = 40; // Available
+const SHOW_LIGHTS_OFFSET: usize = 44; // Lit
+
+// Dashboard light bits
+const DL_SHIFT: u32 = 1 << 0;
+const DL_TC: u32 = 1 << 4;
+
+/// Decodes an OutGauge packet.
+pub fn parse_outgauge(packet: &[u8]) -> Option<TelemetryFrame> {
+    if packet.len() != PACKET_LEN && packet.len() != PACKET_WITH_ID_LEN {
+        return None;
+    }
+    let gear = match packet[GEAR_OFFSET] {
+        0 => GearPosition::Reverse,
+        1 => GearPosition::Neutral,
+        n => GearPosition::Gear(n - 1),
+    };
+    let available = u32_at(packet, DASH_LIGHTS_OFFSET)?;
+    let lit = u32_at(packet, SHOW_LIGHTS_OFFSET)?;
+    // A light the car doesn't have tells us nothing
+    let light = |bit: u32| (available & bit != 0).then_some(lit & bit != 0);
+
+    Some(TelemetryFrame {
+        gear,
+        engine_speed: AngularSpeed::from_rpm(f32_at(packet, RPM_OFFSET)?),
+        torque: None,
+        power: None,
+        shift_light: light(DL_SHIFT).map(|on| if on { 1.0 } else { 0.0 }),
+        traction_control: light(DL_TC),
+    })
+}
+
+pub struct OutGauge {
+    socket: UdpSocket,
+}
+
+impl OutGauge {
+    /// Listens on `address` for OutGauge packets. Point the game's OutGauge
+    /// IP and port at it.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: listen(address)?,
+        })
+    }
+}
+
+impl TelemetrySource for OutGauge {
+    fn name(&self) -> &str {
+        "OutGauge"
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TelemetryFrame>> {
+        latest_frame(&self.socket, parse_outgauge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outgauge(len: usize, gear: u8, rpm: f32, available: u32, lit: u32) -> Vec<u8> {
+        let mut packet = vec![0; len];
+        packet[4..8].copy_from_slice(b"ETK8");
+        packet[GEAR_OFFSET] = gear;
+        packet[RPM_OFFSET..RPM_OFFSET + 4].copy_from_slice(&rpm.to_le_bytes());
+        packet[DASH_LIGHTS_OFFSET..DASH_LIGHTS_OFFSET + 4]
+            .copy_from_slice(&available.to_le_bytes());
+        packet[SHOW_LIGHTS_OFFSET..SHOW_LIGHTS_OFFSET + 4].copy_from_slice(&lit.to_le_bytes());
+        packet
+    }
+
+    #[test]
+    fn decodes_gear_and_rpm() {
+        let frame = parse_outgauge(&outgauge(PACKET_LEN, 4, 5400.0, 0, 0)).unwrap();
+        assert_eq!(frame.gear, GearPosition::Gear(3));
+        assert!((frame.engine_speed.rpm() - 5400.0).abs() < 0.1);
+
+        let gear = |gear| parse_outgauge(&outgauge(PACKET_WITH_ID_LEN, gear, 900.0, 0, 0));
+        assert_eq!(gear(0).unwrap().gear, GearPosition::Reverse);
+        assert_eq!(gear(1).unwrap().gear, GearPosition::Neutral);
+        assert_eq!(gear(2).unwrap().gear, GearPosition::Gear(1));
+    }
+
+    #[test]
+    fn lights_only_count_when_the_car_has_them() {
+        let frame =
+            parse_outgauge(&outgauge(PACKET_LEN, 3, 7000.0, DL_SHIFT | DL_TC, DL_SHIFT)).unwrap();
+        assert_eq!(frame.shift_light, Some(1.0));
+        assert_eq!(frame.traction_control, Some(false));
+
+        let frame =
+            parse_outgauge(&outgauge(PACKET_LEN, 3, 7000.0, DL_TC, DL_SHIFT | DL_TC)).unwrap();
+        assert_eq!(frame.shift_light, None);
+        assert_eq!(frame.traction_control, Some(true));
+    }
+
+    #[test]
+    fn rejects_other_packets() {
+        assert_eq!(parse_outgauge(&[0; 64]), None);
+        assert_eq!(parse_outgauge(&[0; PACKET_LEN + 1]), None);
+    }
+}
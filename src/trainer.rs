@@ -0,0 +1,228 @@
+// `--trainer`: shift-timing practice. Every shift button press is graded by
+// the simulated revs at that moment against a target window: an upshift by
+// where the engine is, a downshift by where the lower gear will put it. Each
+// grade gets its own pulses once the shift rumble has played.
+
+use crate::engine::Engine;
+use crate::haptics::Pulse;
+
+// Default window, as fractions of the rev range above idle
+const DEFAULT_LOW: f32 = 0.8;
+const DEFAULT_HIGH: f32 = 0.92;
+
+// Perfect: one firm knock
+const PERFECT_PULSES: [Pulse; 1] = [Pulse {
+    after_ms: 0,
+    duration_ms: 150,
+}];
+
+// Early: two hurried taps
+const EARLY_PULSES: [Pulse; 2] = [
+    Pulse {
+        after_ms: 0,
+        duration_ms: 40,
+    },
+    Pulse {
+        after_ms: 80,
+        duration_ms: 40,
+    },
+];
+
+// Late: two long, dragging pulses
+const LATE_PULSES: [Pulse; 2] = [
+    Pulse {
+        after_ms: 0,
+        duration_ms: 200,
+    },
+    Pulse {
+        after_ms: 350,
+        duration_ms: 200,
+    },
+];
+
+/// How well a shift was timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Perfect,
+    Early,
+    Late,
+}
+
+impl Grade {
+    pub fn name(self) -> &'static str {
+        match self {
+            Grade::Perfect => "PERFECT",
+            Grade::Early => "EARLY",
+            Grade::Late => "LATE",
+        }
+    }
+
+    /// What the driver feels for this grade.
+    pub fn pulses(self) -> &'static [Pulse] {
+        match self {
+            Grade::Perfect => &PERFECT_PULSES,
+            Grade::Early => &EARLY_PULSES,
+            Grade::Late => &LATE_PULSES,
+        }
+    }
+}
+
+/// The revs to shift at, inclusive at both ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RpmWindow {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl RpmWindow {
+    /// Reads `LOW-HIGH`, e.g. `5800-6400`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (low, high) = spec
+            .split_once('-')
+            .ok_or_else(|| format!("expected <low>-<high> rpm, got '{}'", spec))?;
+        let rpm = |value: &str| {
+            value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|rpm| rpm.is_finite() && *rpm > 0.0)
+                .ok_or_else(|| format!("invalid rpm '{}'", value))
+        };
+        let (low, high) = (rpm(low)?, rpm(high)?);
+        if low >= high {
+            return Err(format!("{} rpm isn't below {} rpm", low, high));
+        }
+        Ok(Self { low, high })
+    }
+
+    /// Just short of the redline, where a shift keeps the engine pulling.
+    pub fn default_for(engine: &Engine) -> Self {
+        let idle = engine.idle().rpm();
+        let range = engine.redline().rpm() - idle;
+        Self {
+            low: idle + range * DEFAULT_LOW,
+            high: idle + range * DEFAULT_HIGH,
+        }
+    }
+
+    /// Grades an upshift pressed with the engine at `rpm`.
+    pub fn grade_upshift(&self, rpm: f32) -> Grade {
+        if rpm < self.low {
+            Grade::Early
+        } else if rpm > self.high {
+            Grade::Late
+        } else {
+            Grade::Perfect
+        }
+    }
+
+    /// Grades a downshift that will put the engine at `landing_rpm`. The
+    /// revs are falling, so landing above the window means it came early.
+    pub fn grade_downshift(&self, landing_rpm: f32) -> Grade {
+        if landing_rpm > self.high {
+            Grade::Early
+        } else if landing_rpm < self.low {
+            Grade::Late
+        } else {
+            Grade::Perfect
+        }
+    }
+}
+
+/// The target window and the running score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trainer {
+    window: RpmWindow,
+    perfect: u32,
+    early: u32,
+    late: u32,
+}
+
+impl Trainer {
+    pub fn new(window: RpmWindow) -> Self {
+        Self {
+            window,
+            perfect: 0,
+            early: 0,
+            late: 0,
+        }
+    }
+
+    pub fn window(&self) -> RpmWindow {
+        self.window
+    }
+
+    pub fn record(&mut self, grade: Grade) {
+        match grade {
+            Grade::Perfect => self.perfect += 1,
+            Grade::Early => self.early += 1,
+            Grade::Late => self.late += 1,
+        }
+    }
+
+    pub fn shifts(&self) -> u32 {
+        self.perfect + self.early + self.late
+    }
+
+    /// The running score, e.g. "3/5 perfect (1 early, 1 late)".
+    pub fn score(&self) -> String {
+        format!(
+            "{}/{} perfect ({} early, {} late)",
+            self.perfect,
+            self.shifts(),
+            self.early,
+            self.late
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::TorqueCurve;
+    use crate::units::{AngularSpeed, Torque};
+
+    #[test]
+    fn windows_parse_and_default_below_the_redline() {
+        assert_eq!(
+            RpmWindow::parse("5800-6400"),
+            Ok(RpmWindow {
+                low: 5800.0,
+                high: 6400.0
+            })
+        );
+        assert!(RpmWindow::parse("6400-5800").is_err());
+        assert!(RpmWindow::parse("6000").is_err());
+        assert!(RpmWindow::parse("low-6000").is_err());
+
+        let engine = Engine::new(
+            AngularSpeed::from_rpm(1000.0),
+            AngularSpeed::from_rpm(7000.0),
+            TorqueCurve::scaled_to(Torque::from_lb_ft(300.0)),
+        );
+        let window = RpmWindow::default_for(&engine);
+        assert_eq!(window.low, 5800.0);
+        assert!((window.high - 6520.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn shifts_are_graded_and_scored() {
+        let window = RpmWindow {
+            low: 5800.0,
+            high: 6400.0,
+        };
+        assert_eq!(window.grade_upshift(5000.0), Grade::Early);
+        assert_eq!(window.grade_upshift(5800.0), Grade::Perfect);
+        assert_eq!(window.grade_upshift(6900.0), Grade::Late);
+        assert_eq!(window.grade_downshift(6900.0), Grade::Early);
+        assert_eq!(window.grade_downshift(6000.0), Grade::Perfect);
+        assert_eq!(window.grade_downshift(4000.0), Grade::Late);
+
+        let mut trainer = Trainer::new(window);
+        for grade in [Grade::Perfect, Grade::Early, Grade::Perfect, Grade::Late] {
+            trainer.record(grade);
+        }
+        assert_eq!(trainer.score(), "2/4 perfect (1 early, 1 late)");
+        assert_ne!(Grade::Early.pulses(), Grade::Late.pulses());
+    }
+}
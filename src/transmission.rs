@@ -0,0 +1,133 @@
+// What kind of gearbox the car has, which sets how long a shift takes and
+// how it feels: a dual-clutch box snaps through in one crisp pulse, a manual
+// goes quiet while the clutch is in and thunks back in, and an old automatic
+// slurs from one gear into the next. A shift still going on holds off the next.
+
+use crate::haptics::{Adsr, RumbleCommand, RumblePattern};
+use serde::{Deserialize, Serialize};
+
+// Dual-clutch: the next gear is already waiting on the other clutch
+const DCT_SHIFT_MS: u32 = 80;
+
+// Manual: a quick lift off as the clutch goes in, the torque interruption
+// while the lever moves, then the clutch biting, as (percent of the shift,
+// scale) in order
+const MANUAL_UPSHIFT_MS: u32 = 300;
+const MANUAL_DOWNSHIFT_MS: u32 = 500;
+const MANUAL_STAGES: [(u32, f32); 3] = [(15, 0.4), (45, 0.0), (40, 1.0)];
+
+// Old automatic: a long, soft swell in and out as the bands slip over
+const AUTO_UPSHIFT_MS: u32 = 600;
+const AUTO_DOWNSHIFT_MS: u32 = 700;
+const AUTO_SCALE: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Transmission {
+    Dct,
+    Manual,
+    #[serde(rename = "auto")]
+    Automatic,
+}
+
+impl Transmission {
+    pub fn name(self) -> &'static str {
+        match self {
+            Transmission::Dct => "dual-clutch",
+            Transmission::Manual => "manual",
+            Transmission::Automatic => "automatic",
+        }
+    }
+
+    /// How long a shift takes, and how long until the box takes another.
+    pub fn shift_ms(self, is_downshift: bool) -> u32 {
+        match (self, is_downshift) {
+            (Transmission::Dct, _) => DCT_SHIFT_MS,
+            (Transmission::Manual, false) => MANUAL_UPSHIFT_MS,
+            (Transmission::Manual, true) => MANUAL_DOWNSHIFT_MS,
+            (Transmission::Automatic, false) => AUTO_UPSHIFT_MS,
+            (Transmission::Automatic, true) => AUTO_DOWNSHIFT_MS,
+        }
+    }
+
+    /// The feel of a shift at `command`'s strength, over `command`'s length.
+    pub fn shift_pattern(self, command: RumbleCommand) -> RumblePattern {
+        let scaled = |scale: f32, duration_ms| RumbleCommand {
+            strong_magnitude: (command.strong_magnitude as f32 * scale) as u16,
+            weak_magnitude: (command.weak_magnitude as f32 * scale) as u16,
+            duration_ms,
+        };
+        match self {
+            Transmission::Dct => RumblePattern::single(command),
+            Transmission::Manual => {
+                let mut left_ms = command.duration_ms;
+                let last = MANUAL_STAGES.len() - 1;
+                let stages = MANUAL_STAGES
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &(percent, scale))| {
+                        // The last stage takes whatever rounding left over
+                        let duration_ms = if i == last {
+                            left_ms
+                        } else {
+                            command.duration_ms * percent / 100
+                        };
+                        left_ms -= duration_ms;
+                        scaled(scale, duration_ms)
+                    })
+                    .collect();
+                RumblePattern::new(stages)
+            }
+            Transmission::Automatic => {
+                let slur = Adsr {
+                    attack_ms: command.duration_ms * 2 / 5,
+                    decay_ms: 0,
+                    sustain_level: 1.0,
+                    release_ms: command.duration_ms / 2,
+                };
+                RumblePattern::enveloped(scaled(AUTO_SCALE, command.duration_ms), &slur)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: RumbleCommand = RumbleCommand {
+        strong_magnitude: 40000,
+        weak_magnitude: 20000,
+        duration_ms: 0,
+    };
+
+    fn pattern(transmission: Transmission, is_downshift: bool) -> RumblePattern {
+        transmission.shift_pattern(RumbleCommand {
+            duration_ms: transmission.shift_ms(is_downshift),
+            ..FULL
+        })
+    }
+
+    #[test]
+    fn each_box_has_its_own_timing_and_feel() {
+        let dct = pattern(Transmission::Dct, false);
+        assert_eq!(dct.stages().len(), 1);
+        assert_eq!(dct.duration_ms(), 80);
+
+        // The manual goes quiet while the clutch is in
+        let manual = pattern(Transmission::Manual, true);
+        assert_eq!(manual.duration_ms(), 500);
+        let gap = manual.stages()[1];
+        assert_eq!((gap.strong_magnitude, gap.weak_magnitude), (0, 0));
+        assert_eq!(manual.stages()[2].strong_magnitude, 40000);
+        assert_eq!(pattern(Transmission::Manual, false).duration_ms(), 300);
+
+        // The automatic swells in and out, never at full strength
+        let auto = pattern(Transmission::Automatic, false);
+        assert_eq!(auto.duration_ms(), 600);
+        let peak = auto.envelope().strong_magnitude;
+        assert!(peak < 40000);
+        assert!(auto.stages()[0].strong_magnitude < peak / 2);
+        assert!(auto.stages().last().unwrap().strong_magnitude < peak / 2);
+    }
+}
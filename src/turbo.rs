@@ -0,0 +1,87 @@
+// A turbocharger. Boost builds while the throttle stays open with the revs
+// up, bleeds away under a part throttle, and vents through the blow-off
+// valve all at once when the driver lifts or shifts up.
+
+use std::time::Duration;
+
+// Where in the rev range (0 idle, 1 redline) it starts making boost
+const SPOOL_FROM: f32 = 0.3;
+// Time constants for boost building and bleeding away
+const SPOOL_SECS: f32 = 1.0;
+const BLEED_SECS: f32 = 2.0;
+// Under this throttle the driver has lifted
+const LIFT_THROTTLE: f32 = 0.1;
+// Less boost than this vents without a blow-off
+const BLOW_OFF_BOOST: f32 = 0.25;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Turbo {
+    boost: f32, // 0 to full boost at 1
+}
+
+impl Turbo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
+
+    /// Builds or bleeds boost over `dt` at `throttle` with the engine at
+    /// `revs`, 0 at idle to 1 at the redline. Returns the boost the blow-off
+    /// valve vented if the driver just lifted.
+    pub fn spool(&mut self, throttle: f32, revs: f32, dt: Duration) -> Option<f32> {
+        if throttle < LIFT_THROTTLE {
+            return self.vent();
+        }
+        let target = throttle.min(1.0) * ((revs - SPOOL_FROM) / (1.0 - SPOOL_FROM)).clamp(0.0, 1.0);
+        let secs = if target > self.boost {
+            SPOOL_SECS
+        } else {
+            BLEED_SECS
+        };
+        self.boost += (target - self.boost) * (1.0 - (-dt.as_secs_f32() / secs).exp());
+        None
+    }
+
+    /// Dumps all the boost, as on an upshift. Returns what vented if there
+    /// was enough for a blow-off.
+    pub fn vent(&mut self) -> Option<f32> {
+        let vented = std::mem::take(&mut self.boost);
+        (vented >= BLOW_OFF_BOOST).then_some(vented)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn boost_builds_with_sustained_throttle_and_blows_off_on_a_lift() {
+        let mut turbo = Turbo::new();
+        // Nothing off boost at low revs
+        for _ in 0..20 {
+            assert_eq!(turbo.spool(1.0, 0.2, STEP), None);
+        }
+        assert_eq!(turbo.boost(), 0.0);
+
+        turbo.spool(1.0, 1.0, STEP);
+        let early = turbo.boost();
+        for _ in 0..30 {
+            turbo.spool(1.0, 1.0, STEP);
+        }
+        assert!(early < turbo.boost());
+        assert!(turbo.boost() > 0.9);
+
+        let vented = turbo.spool(0.0, 1.0, STEP).unwrap();
+        assert!(vented > 0.9);
+        assert_eq!(turbo.boost(), 0.0);
+
+        // A whiff of boost vents quietly
+        turbo.spool(1.0, 1.0, STEP);
+        assert_eq!(turbo.vent(), None);
+    }
+}
@@ -0,0 +1,281 @@
+// Unit-safe quantities.
+//
+// Values are stored in SI units internally and can only be created or read
+// through a constructor/accessor that names the unit, so a number in Nm can't
+// silently end up in a lb-ft code path. Which units the driver types and
+// reads is a `UnitSystem`, which never reaches the arithmetic.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Div;
+
+const NM_PER_LB_FT: f32 = 1.355_818;
+const KW_PER_HP: f32 = 0.745_699_9;
+const KW_PER_PS: f32 = 0.735_498_8;
+const RAD_PER_SEC_PER_RPM: f32 = std::f32::consts::TAU / 60.0;
+const M_PER_S_PER_KMH: f32 = 1.0 / 3.6;
+const M_PER_S_PER_MPH: f32 = 0.447_04;
+
+/// Engine torque. A raw number has to say which unit it is in:
+///
+/// ```compile_fail
+/// use gear_changer::units::Torque;
+/// let nm: f32 = 400.0;
+/// let torque: Torque = nm; // a bare f32 is not a Torque
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Torque(f32); // Nm
+
+impl Torque {
+    pub fn from_nm(nm: f32) -> Self {
+        Self(nm)
+    }
+
+    pub fn from_lb_ft(lb_ft: f32) -> Self {
+        Self(lb_ft * NM_PER_LB_FT)
+    }
+
+    pub fn nm(self) -> f32 {
+        self.0
+    }
+
+    pub fn lb_ft(self) -> f32 {
+        self.0 / NM_PER_LB_FT
+    }
+}
+
+/// The ratio of two torques is a plain number.
+impl Div for Torque {
+    type Output = f32;
+
+    fn div(self, rhs: Torque) -> f32 {
+        self.0 / rhs.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Power(f32); // kW
+
+impl Power {
+    pub fn from_kw(kw: f32) -> Self {
+        Self(kw)
+    }
+
+    /// Mechanical horsepower.
+    pub fn from_hp(hp: f32) -> Self {
+        Self(hp * KW_PER_HP)
+    }
+
+    /// Metric horsepower (PS).
+    pub fn from_ps(ps: f32) -> Self {
+        Self(ps * KW_PER_PS)
+    }
+
+    /// Power delivered by `torque` at `speed`.
+    pub fn from_torque_at(torque: Torque, speed: AngularSpeed) -> Self {
+        Self(torque.nm() * speed.rad_per_sec() / 1000.0)
+    }
+
+    pub fn kw(self) -> f32 {
+        self.0
+    }
+
+    pub fn hp(self) -> f32 {
+        self.0 / KW_PER_HP
+    }
+
+    pub fn ps(self) -> f32 {
+        self.0 / KW_PER_PS
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct AngularSpeed(f32); // rad/s
+
+impl AngularSpeed {
+    pub fn from_rpm(rpm: f32) -> Self {
+        Self(rpm * RAD_PER_SEC_PER_RPM)
+    }
+
+    pub fn from_rad_per_sec(rad_per_sec: f32) -> Self {
+        Self(rad_per_sec)
+    }
+
+    pub fn rpm(self) -> f32 {
+        self.0 / RAD_PER_SEC_PER_RPM
+    }
+
+    pub fn rad_per_sec(self) -> f32 {
+        self.0
+    }
+}
+
+/// Road speed.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Speed(f32); // m/s
+
+impl Speed {
+    pub fn from_m_per_s(m_per_s: f32) -> Self {
+        Self(m_per_s)
+    }
+
+    pub fn from_kmh(kmh: f32) -> Self {
+        Self(kmh * M_PER_S_PER_KMH)
+    }
+
+    pub fn from_mph(mph: f32) -> Self {
+        Self(mph * M_PER_S_PER_MPH)
+    }
+
+    pub fn m_per_s(self) -> f32 {
+        self.0
+    }
+
+    pub fn kmh(self) -> f32 {
+        self.0 / M_PER_S_PER_KMH
+    }
+
+    pub fn mph(self) -> f32 {
+        self.0 / M_PER_S_PER_MPH
+    }
+}
+
+/// The units torque and power are typed in and shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitSystem {
+    /// lb-ft and horsepower.
+    #[default]
+    Imperial,
+    /// Nm and kW.
+    Metric,
+    /// Nm and metric horsepower (PS).
+    MetricPs,
+}
+
+impl UnitSystem {
+    pub fn torque(self, value: f32) -> Torque {
+        match self {
+            UnitSystem::Imperial => Torque::from_lb_ft(value),
+            UnitSystem::Metric | UnitSystem::MetricPs => Torque::from_nm(value),
+        }
+    }
+
+    pub fn power(self, value: f32) -> Power {
+        match self {
+            UnitSystem::Imperial => Power::from_hp(value),
+            UnitSystem::Metric => Power::from_kw(value),
+            UnitSystem::MetricPs => Power::from_ps(value),
+        }
+    }
+
+    pub fn torque_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Imperial => "lb-ft",
+            UnitSystem::Metric | UnitSystem::MetricPs => "Nm",
+        }
+    }
+
+    pub fn power_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Imperial => "hp",
+            UnitSystem::Metric => "kW",
+            UnitSystem::MetricPs => "PS",
+        }
+    }
+
+    /// `torque` as a number in these units.
+    pub fn torque_in(self, torque: Torque) -> f32 {
+        match self {
+            UnitSystem::Imperial => torque.lb_ft(),
+            UnitSystem::Metric | UnitSystem::MetricPs => torque.nm(),
+        }
+    }
+
+    /// `power` as a number in these units.
+    pub fn power_in(self, power: Power) -> f32 {
+        match self {
+            UnitSystem::Imperial => power.hp(),
+            UnitSystem::Metric => power.kw(),
+            UnitSystem::MetricPs => power.ps(),
+        }
+    }
+
+    /// `torque` rounded, with its unit: "300 lb-ft".
+    pub fn format_torque(self, torque: Torque) -> String {
+        format!("{:.0} {}", self.torque_in(torque), self.torque_unit())
+    }
+
+    /// `power` rounded, with its unit: "400 hp".
+    pub fn format_power(self, power: Power) -> String {
+        format!("{:.0} {}", self.power_in(power), self.power_unit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-3 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn torque_conversions() {
+        assert!(close(Torque::from_lb_ft(100.0).nm(), 135.5818));
+        assert!(close(Torque::from_nm(400.0).lb_ft(), 295.0237));
+        assert!(close(Torque::from_lb_ft(300.0).lb_ft(), 300.0));
+    }
+
+    #[test]
+    fn power_conversions() {
+        assert!(close(Power::from_hp(400.0).kw(), 298.28));
+        assert!(close(Power::from_kw(100.0).hp(), 134.102));
+        assert!(close(Power::from_ps(100.0).kw(), 73.5499));
+        assert!(close(Power::from_hp(400.0).hp(), 400.0));
+    }
+
+    #[test]
+    fn angular_speed_conversions() {
+        assert!(close(
+            AngularSpeed::from_rpm(60.0).rad_per_sec(),
+            std::f32::consts::TAU
+        ));
+        assert!(close(AngularSpeed::from_rad_per_sec(100.0).rpm(), 954.93));
+    }
+
+    #[test]
+    fn speed_conversions() {
+        assert!(close(Speed::from_mph(60.0).kmh(), 96.5606));
+        assert!(close(Speed::from_kmh(36.0).m_per_s(), 10.0));
+        assert!(close(Speed::from_m_per_s(10.0).mph(), 22.3694));
+    }
+
+    #[test]
+    fn horsepower_crosses_torque_at_5252_rpm() {
+        let torque = Torque::from_lb_ft(300.0);
+        let power = Power::from_torque_at(torque, AngularSpeed::from_rpm(5252.0));
+        assert!(close(power.hp(), 300.0));
+    }
+
+    #[test]
+    fn unit_systems_read_and_show_their_own_units() {
+        let metric = UnitSystem::Metric;
+        assert!(close(metric.torque(400.0).lb_ft(), 295.0237));
+        assert!(close(metric.power(100.0).hp(), 134.102));
+        assert!(close(UnitSystem::MetricPs.power(100.0).kw(), 73.5499));
+        assert!(close(UnitSystem::Imperial.torque(300.0).lb_ft(), 300.0));
+
+        let torque = Torque::from_lb_ft(300.0);
+        assert_eq!(UnitSystem::Imperial.format_torque(torque), "300 lb-ft");
+        assert_eq!(metric.format_torque(torque), "407 Nm");
+        let power = Power::from_hp(400.0);
+        assert_eq!(metric.format_power(power), "298 kW");
+        assert_eq!(UnitSystem::MetricPs.format_power(power), "406 PS");
+    }
+
+    #[test]
+    fn torque_ratio_is_unitless() {
+        let ratio = Torque::from_lb_ft(250.0) / Torque::from_nm(1000.0 * NM_PER_LB_FT);
+        assert!(close(ratio, 0.25));
+    }
+}